@@ -0,0 +1,120 @@
+use std::env;
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+use std::process::Command;
+
+/// Builds the translated C, C++ or LLVM IR into an executable with an external compiler.
+#[derive(Debug, Clone)]
+pub struct Compiler {
+    pub executable:   String,
+    pub standard:     String,
+    pub optimization: String,
+    pub includes:     Vec<String>,
+    pub libraries:    Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The compiler couldn't be started at all.
+    Spawn(String, io::Error),
+    /// The compiler ran and rejected the code; holds its exit status when it exited normally.
+    Failed(String, Option<i32>),
+}
+
+impl Error {
+    /// The status `helix build` exits with, which is the compiler's own when there is one.
+    pub fn code(&self) -> i32 {
+        match *self {
+            Error::Failed(_, Some(c)) if c != 0 => c,
+            _                                   => 1,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Spawn(ref c, ref e)        => write!(f, "couldn't run the compiler `{}`: {}", c, e),
+            Error::Failed(ref c, Some(code))  => write!(f, "`{}` failed with exit code {}", c, code),
+            Error::Failed(ref c, None)        => write!(f, "`{}` was stopped by a signal", c),
+        }
+    }
+}
+
+impl Compiler {
+    /// The compiler named by `$CXX`, or `g++` when it isn't set.
+    pub fn new() -> Compiler {
+        Compiler {
+            executable:   env::var("CXX").unwrap_or("g++".to_owned()),
+            standard:     "c++14".to_owned(),
+            optimization: "0".to_owned(),
+            includes:     Vec::new(),
+            libraries:    Vec::new(),
+        }
+    }
+
+    /// The C compiler named by `$CC`, or `gcc` when it isn't set.
+    pub fn c() -> Compiler {
+        Compiler {
+            executable: env::var("CC").unwrap_or("gcc".to_owned()),
+            standard:   "c99".to_owned(),
+            ..Compiler::new()
+        }
+    }
+
+    /// The compiler named by `$CLANG`, or `clang` when it isn't set, which takes LLVM IR
+    /// without a language standard.
+    pub fn llvm() -> Compiler {
+        Compiler {
+            executable: env::var("CLANG").unwrap_or("clang".to_owned()),
+            standard:   String::new(),
+            ..Compiler::new()
+        }
+    }
+
+    /// The arguments building `sources` into `destination` takes; libraries come after the
+    /// sources, so the linker sees what uses them first.
+    pub fn arguments(&self, sources: &[String], destination: &str) -> Vec<String> {
+        let mut arguments = Vec::new();
+
+        if !self.standard.is_empty() {
+            arguments.push(format!("-std={}", self.standard))
+        }
+
+        arguments.push(format!("-O{}", self.optimization));
+
+        for i in self.includes.iter() {
+            arguments.push(format!("-I{}", i))
+        }
+
+        arguments.extend(sources.iter().cloned());
+
+        arguments.push("-o".to_owned());
+        arguments.push(destination.to_owned());
+
+        for l in self.libraries.iter() {
+            arguments.push(format!("-l{}", l))
+        }
+
+        arguments
+    }
+
+    /// Compiles and links `sources` into `destination`, waiting for the compiler to finish.
+    /// Whatever it prints is passed on, so its diagnostics reach the user either way.
+    pub fn build(&self, sources: &[String], destination: &str) -> Result<(), Error> {
+        let output = match Command::new(&self.executable).args(&self.arguments(sources, destination)).output() {
+            Ok(o)  => o,
+            Err(e) => return Err(Error::Spawn(self.executable.clone(), e)),
+        };
+
+        io::stdout().write_all(&output.stdout).unwrap();
+        io::stderr().write_all(&output.stderr).unwrap();
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::Failed(self.executable.clone(), output.status.code()))
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use parser;
+use parser::block_tree::{BlockTree, Branch, Chunk, ChunkValue, Trivia};
+use parser::tokenizer::Tokenizer;
+use parser::token::{Token, TokenType};
+use parser::diagnostic::Diagnostic;
+
+/// The indentation of one level of blocks.
+const INDENT: &'static str = "  ";
+
+/// Writes source over in the canonical style: blocks indented by two spaces, one space around
+/// operators and after `:` and `,`, and a blank line around every top-level item with a body.
+/// Comments are kept, as are single blank lines within blocks.
+pub fn format(source: &str) -> Result<String, Vec<Diagnostic>> {
+    // only code that parses is rewritten, so what each token is for is known
+    try!(parser::parse(source));
+
+    let mut tree = BlockTree::new(source, 0);
+
+    let lines = match tree.collect_indents() {
+        Ok(l)  => l,
+        Err(e) => return Err(vec!(e)),
+    };
+
+    let root = try!(Tokenizer::tokenize_branch(&BlockTree::new(source, 0).make_tree(&lines)));
+
+    let mut output = String::new();
+
+    block(&root, 0, &mut output);
+
+    comments(&tree.trailing.leading, "", tree.trailing.blank && !output.is_empty(), &mut output);
+
+    Ok(output)
+}
+
+fn block(branch: &Branch, depth: usize, output: &mut String) {
+    let indent = INDENT.repeat(depth);
+
+    for (i, chunk) in branch.content.iter().enumerate() {
+        let tokens = match chunk.get_value() {
+            ChunkValue::Tokens(t) => t,
+            ChunkValue::Block(b)  => {
+                block(&b, depth + 1, output);
+
+                continue
+            }
+
+            _ => continue,
+        };
+
+        let trivia = chunk.get_trivia();
+
+        // items with a body are set apart from whatever is around them at the top level
+        let blank = i > 0 && (trivia.blank || depth == 0 && (opens(&branch.content, i) || opens(&branch.content, i - 1)));
+
+        line(&tokens, &indent, trivia, blank, output);
+    }
+}
+
+/// Whether the chunk is a block, or a line followed by one.
+fn opens(content: &[Chunk], i: usize) -> bool {
+    let block = |c: Option<&Chunk>| match c.map(|c| c.get_value()) {
+        Some(ChunkValue::Block(_)) => true,
+        _                          => false,
+    };
+
+    block(content.get(i)) || block(content.get(i + 1))
+}
+
+fn line(tokens: &[Token], indent: &str, trivia: &Trivia, blank: bool, output: &mut String) {
+    comments(&trivia.leading, indent, blank, output);
+
+    if blank && trivia.leading.is_empty() {
+        output.push('\n')
+    }
+
+    output.push_str(indent);
+
+    for (i, t) in tokens.iter().enumerate() {
+        if i > 0 && spaced(tokens, i) {
+            output.push(' ')
+        }
+
+        match t.get_type() {
+            // the quotes used are the ones the string doesn't contain
+            TokenType::Text if t.get_content().contains('"') => output.push_str(&format!("'{}'", t.get_content())),
+            TokenType::Text                                  => output.push_str(&format!("\"{}\"", t.get_content())),
+
+            _ => output.push_str(t.get_content()),
+        }
+    }
+
+    if let Some(c) = trivia.trailing {
+        output.push(' ');
+        output.push_str(c)
+    }
+
+    output.push('\n');
+
+    comments(&trivia.after, indent, false, output);
+}
+
+fn comments(comments: &[&str], indent: &str, blank: bool, output: &mut String) {
+    if blank && !comments.is_empty() {
+        output.push('\n')
+    }
+
+    for (i, c) in comments.iter().enumerate() {
+        match *c {
+            "" if blank && i == 0 => (),
+            ""                    => output.push('\n'),
+            c                     => {
+                output.push_str(indent);
+                output.push_str(c);
+                output.push('\n')
+            }
+        }
+    }
+}
+
+/// Whether a space goes between a token and the one before it.
+fn spaced(tokens: &[Token], i: usize) -> bool {
+    let (prev, next) = (tokens[i - 1].get_type(), tokens[i].get_type());
+
+    match (prev, next) {
+        (_, TokenType::Comma)     |
+        (_, TokenType::Semicolon) |
+        (_, TokenType::RParen)    |
+        (_, TokenType::RBracket)  |
+        (_, TokenType::Period)    |
+        (TokenType::Period, _)    |
+        (TokenType::LParen, _)    |
+        (TokenType::LBracket, _)  => false,
+
+        // map types hug their braces, map literals don't
+        (TokenType::LBrace, _) => !typed(tokens, i - 1),
+        (_, TokenType::RBrace) => !typed(tokens, i),
+
+        // the second `:` of a path like `module::name` is followed by its name
+        (TokenType::Colon, _) if i > 1 && tokens[i - 2].get_type() == TokenType::Colon => false,
+        (_, TokenType::Colon) => false,
+
+        // calls and indexing, but a function's parameters are set apart from its name
+        (TokenType::Ident, TokenType::LParen) => i == 2 && tokens[0].get_type() == TokenType::Def,
+
+        // likewise after the type parameters of a generic function, as in `function max[T] (a: T)`
+        (TokenType::RBracket, TokenType::LParen) if tokens[0].get_type() == TokenType::Def &&
+                                                    tokens.get(2).map(|t| t.get_type()) == Some(TokenType::LBracket) &&
+                                                    tokens[..i].iter().all(|t| t.get_type() != TokenType::LParen) => true,
+
+        (TokenType::Ident, TokenType::LBracket)    |
+        (TokenType::RParen, TokenType::LParen)     |
+        (TokenType::RParen, TokenType::LBracket)   |
+        (TokenType::RBracket, TokenType::LParen)   |
+        (TokenType::RBracket, TokenType::LBracket) => false,
+
+        (TokenType::Operator, _) if unary(tokens, i - 1) => tokens[i - 1].get_content() == "not",
+
+        _ => true,
+    }
+}
+
+/// Whether the braces a `{` or `}` belongs to are part of a type, like `{string: int}`,
+/// which follows the `:` of a declaration or the `->` of a function.
+fn typed(tokens: &[Token], i: usize) -> bool {
+    let mut depth = 0;
+    let mut outermost = None;
+
+    for j in (0..i + 1).rev() {
+        match tokens[j].get_type() {
+            TokenType::RBrace if j != i => depth += 1,
+            TokenType::LBrace if depth > 0 => depth -= 1,
+            TokenType::LBrace => outermost = Some(j),
+            _ => (),
+        }
+    }
+
+    match outermost {
+        Some(o) if o > 0 => match tokens[o - 1].get_type() {
+            TokenType::Colon | TokenType::Arrow => true,
+            _                                   => false,
+        },
+
+        _ => false,
+    }
+}
+
+/// Whether an operator applies to the operand after it only, like the `-` in `a * -b`.
+fn unary(tokens: &[Token], i: usize) -> bool {
+    match tokens[i].get_content().as_str() {
+        "not" | "!" | "~" => true,
+        "-"               => i == 0 || match tokens[i - 1].get_type() {
+            TokenType::Ident   | TokenType::Integer  | TokenType::Float  |
+            TokenType::Text    | TokenType::Boolean  | TokenType::Nothing |
+            TokenType::RParen  | TokenType::RBracket | TokenType::RBrace => false,
+
+            _ => true,
+        },
+
+        _ => false,
+    }
+}
@@ -0,0 +1,556 @@
+use std::io;
+use std::io::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use rustc_serialize::json::Json;
+
+use parser;
+use parser::ast::{Expression, Statement, Position};
+use parser::diagnostic::{Diagnostic, Severity};
+use parser::resolver;
+use parser::resolver::index::Index;
+use parser::resolver::scope::{Symbol, Scope};
+use parser::resolver::types::Type;
+
+/// A file the editor has open.
+struct Document {
+    text:  String,
+    /// Definitions of the last version of the file that could be resolved, which is kept
+    /// while the file is edited through versions that don't parse.
+    ast:   Vec<Statement>,
+    index: Option<Index>,
+}
+
+/// A language server speaking the Language Server Protocol over stdin and stdout.
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown:  bool,
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server {
+            documents: HashMap::new(),
+            shutdown:  false,
+        }
+    }
+
+    /// Answers the editor until it says to exit, handing back the status to exit with.
+    pub fn run(&mut self) -> i32 {
+        let stdin = io::stdin();
+        let mut input = stdin.lock();
+
+        while let Some(message) = receive(&mut input) {
+            let message = match Json::from_str(&message) {
+                Ok(m)  => m,
+                Err(e) => {
+                    respond(Json::Null, Err((-32700, format!("invalid message: {}", e))));
+
+                    continue
+                }
+            };
+
+            let method = match message.find("method").and_then(|m| m.as_string()) {
+                Some(m) => m.to_owned(),
+                None    => continue,
+            };
+
+            if method == "exit" {
+                return if self.shutdown { 0 } else { 1 }
+            }
+
+            let params = message.find("params").cloned().unwrap_or(Json::Null);
+            let result = self.handle(&method, &params);
+
+            // notifications don't get an answer, not even an error
+            if let Some(id) = message.find("id") {
+                respond(id.clone(), result)
+            }
+        }
+
+        1
+    }
+
+    fn handle(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        match method {
+            "initialize" => Ok(object(vec!(
+                ("capabilities", object(vec!(
+                    ("textDocumentSync",       Json::I64(1)),
+                    ("hoverProvider",          Json::Boolean(true)),
+                    ("definitionProvider",     Json::Boolean(true)),
+                    ("documentSymbolProvider", Json::Boolean(true)),
+                    ("completionProvider",     object(vec!(
+                        ("triggerCharacters", Json::Array(vec!(Json::String(".".to_owned()), Json::String(":".to_owned())))),
+                    ))),
+                ))),
+                ("serverInfo", object(vec!(
+                    ("name", Json::String("helix".to_owned())),
+                ))),
+            ))),
+
+            "shutdown" => {
+                self.shutdown = true;
+
+                Ok(Json::Null)
+            }
+
+            "textDocument/didOpen" => {
+                let text = string(params, &["textDocument", "text"]);
+
+                self.update(&try!(uri(params)), text);
+
+                Ok(Json::Null)
+            }
+
+            "textDocument/didChange" => {
+                // the whole file is sent on every change
+                let text = match params.find("contentChanges").and_then(|c| c.as_array()).and_then(|c| c.last()) {
+                    Some(c) => string(c, &["text"]),
+                    None    => return Ok(Json::Null),
+                };
+
+                self.update(&try!(uri(params)), text);
+
+                Ok(Json::Null)
+            }
+
+            "textDocument/didClose" => {
+                let uri = try!(uri(params));
+
+                self.documents.remove(&uri);
+
+                publish(&uri, &[]);
+
+                Ok(Json::Null)
+            }
+
+            "textDocument/definition"     => self.definition(params),
+            "textDocument/hover"          => self.hover(params),
+            "textDocument/documentSymbol" => self.symbols(params),
+            "textDocument/completion"     => self.completion(params),
+
+            m => Err((-32601, format!("unsupported method `{}`", m))),
+        }
+    }
+
+    /// Takes the new text of a document and tells the editor what is wrong with it.
+    fn update(&mut self, uri: &str, text: String) {
+        let path = path(uri);
+
+        let (diagnostics, analysis) = analyze(&path, &text);
+
+        publish(uri, &diagnostics);
+
+        let document = self.documents.entry(uri.to_owned()).or_insert(Document {
+            text:  String::new(),
+            ast:   Vec::new(),
+            index: None,
+        });
+
+        document.text = text;
+
+        if let Some((ast, index)) = analysis {
+            document.ast   = ast;
+            document.index = Some(index);
+        }
+    }
+
+    fn document(&self, params: &Json) -> Result<(String, &Document), (i64, String)> {
+        let uri = try!(uri(params));
+
+        match self.documents.get(&uri) {
+            Some(d) => Ok((uri, d)),
+            None    => Err((-32602, format!("`{}` isn't open", uri))),
+        }
+    }
+
+    fn definition(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (uri, document) = try!(self.document(params));
+
+        let reference = match document.index.as_ref().and_then(|i| i.at(position(params))) {
+            Some(r) => r,
+            None    => return Ok(Json::Null),
+        };
+
+        match reference.definition {
+            Some(p) => Ok(object(vec!(
+                ("uri",   Json::String(uri)),
+                ("range", range(p, reference.name.len())),
+            ))),
+
+            None => Ok(Json::Null),
+        }
+    }
+
+    fn hover(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (_, document) = try!(self.document(params));
+
+        match document.index.as_ref().and_then(|i| i.at(position(params))) {
+            Some(r) => Ok(object(vec!(
+                ("contents", object(vec!(
+                    ("kind",  Json::String("markdown".to_owned())),
+                    ("value", Json::String(format!("```helix\n{}\n```", r.describe()))),
+                ))),
+                ("range", range(r.position, r.name.len())),
+            ))),
+
+            None => Ok(Json::Null),
+        }
+    }
+
+    fn symbols(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (_, document) = try!(self.document(params));
+
+        Ok(Json::Array(outline(&document.ast)))
+    }
+
+    /// Offers the members of what comes before a `.` or `::`, or the names of the file
+    /// otherwise.
+    fn completion(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (_, document) = try!(self.document(params));
+
+        let index = match document.index {
+            Some(ref i) => i,
+            None        => return Ok(Json::Array(Vec::new())),
+        };
+
+        let (line, col) = position(params);
+
+        let before: String = match document.text.lines().nth(line as usize - 1) {
+            Some(l) => l.chars().take(col as usize - 1).collect(),
+            None    => String::new(),
+        };
+
+        // whatever of a name has been typed already is left for the editor to filter by
+        let before = before.trim_right_matches(|c: char| c.is_alphanumeric() || c == '_');
+
+        let members = if before.ends_with('.') || before.ends_with("::") {
+            match scope(index, before, (line, col)) {
+                Some(s) => s.members(),
+                None    => Vec::new(),
+            }
+        } else {
+            index.namespace.members()
+        };
+
+        Ok(Json::Array(members.into_iter().map(|(n, s)| completion(n, &s)).collect()))
+    }
+}
+
+/// Parses and resolves a document, giving its diagnostics and, when it could be resolved,
+/// its definitions.
+fn analyze(path: &PathBuf, text: &str) -> (Vec<Diagnostic>, Option<(Vec<Statement>, Index)>) {
+    let file = path.to_string_lossy().into_owned();
+
+    let mut units = match parser::loader::load_source(path, text.to_owned()) {
+        Ok(u)  => u,
+
+        // only what is wrong with this file can be shown in it
+        Err(f) => return (f.diagnostics.into_iter().filter(|d| match d.span {
+            Some(ref s) => s.file == file,
+            None        => true,
+        }).collect(), None),
+    };
+
+    let last = units.len() - 1;
+
+    for i in 0 .. last {
+        let (loaded, rest) = units.split_at_mut(i);
+        let unit = &mut rest[0];
+
+        let imports: Vec<&[Statement]> = unit.imports.iter().map(|&j| &loaded[j].ast[..]).collect();
+
+        resolver::resolve(&mut unit.ast, &imports);
+    }
+
+    let (loaded, rest) = units.split_at_mut(last);
+    let unit = &mut rest[0];
+
+    let imports: Vec<&[Statement]> = unit.imports.iter().map(|&j| &loaded[j].ast[..]).collect();
+
+    let (diagnostics, index) = resolver::analyze(&mut unit.ast, &imports);
+
+    (diagnostics, Some((unit.ast.clone(), index)))
+}
+
+/// The scope whose members can follow `before`, which ends in a `.` or `::`, such as the
+/// class of the variable in `bob.` or the module in `geometry::`.
+fn scope(index: &Index, before: &str, position: Position) -> Option<Rc<Scope>> {
+    let (path, dot) = match before.ends_with('.') {
+        true  => (&before[.. before.len() - 1], true),
+        false => (&before[.. before.len() - 2], false),
+    };
+
+    let start = path.rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == ':'))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+
+    let path = &path[start ..];
+
+    let mut names = path.split(|c| c == '.' || c == ':').filter(|n| !n.is_empty());
+
+    let first = match names.next() {
+        Some(n) => n,
+        None    => return None,
+    };
+
+    let mut symbol = match index.before(first, position) {
+        Some(r) => r.symbol.clone(),
+        None    => match index.namespace.lookup(first) {
+            Some(s) => s,
+            None    => return None,
+        },
+    };
+
+    for name in names {
+        symbol = match members(&symbol).and_then(|s| s.member(name)) {
+            Some(s) => s,
+            None    => return None,
+        }
+    }
+
+    match (symbol, dot) {
+        (Symbol::Variable(Type::Object(c, _)), true) => Some(c),
+        (Symbol::Class(c), false)                 => Some(c),
+        (Symbol::Module(m), false)                => Some(m),
+        (Symbol::Enum(e), false)                  => Some(e),
+        _                                         => None,
+    }
+}
+
+fn members(symbol: &Symbol) -> Option<Rc<Scope>> {
+    match *symbol {
+        Symbol::Variable(Type::Object(ref c, _)) | Symbol::Class(ref c) | Symbol::Module(ref c) => Some(c.clone()),
+        Symbol::Enum(ref e)                                                                  => Some(e.clone()),
+        _                                                                                    => None,
+    }
+}
+
+fn completion(name: String, symbol: &Symbol) -> Json {
+    let (kind, detail) = match *symbol {
+        Symbol::Variable(ref t)                            => (6, t.to_string()),
+        Symbol::Function(ref s) | Symbol::Prototype(ref s) => (3, format!("-> {}", s.retty)),
+        Symbol::Class(_)                                   => (7, "class".to_owned()),
+        Symbol::Module(_)                                  => (9, "module".to_owned()),
+        Symbol::Enum(_)                                    => (13, "enum".to_owned()),
+        Symbol::Variant(ref s)                             => (20, s.retty.to_string()),
+        Symbol::Builtin                                    => (3, "built in".to_owned()),
+    };
+
+    object(vec!(
+        ("label",  Json::String(name)),
+        ("kind",   Json::I64(kind)),
+        ("detail", Json::String(detail)),
+    ))
+}
+
+/// The modules, classes, implementations and functions defined by a list of statements, with
+/// the ones they contain.
+fn outline(ast: &[Statement]) -> Vec<Json> {
+    let mut symbols = Vec::new();
+
+    for s in ast.iter() {
+        let e = match *s {
+            Statement::Expression(ref e, _) => e,
+            _                               => continue,
+        };
+
+        let (name, kind, children, p) = match **e {
+            Expression::Module(ref n, ref c, p)        => (n.clone(), 2, outline(c), p),
+            Expression::Class(ref n, _, ref c, _, p)   => (n.clone(), 5, outline(c), p),
+            Expression::Struct(ref n, ref c, p)        => (n.clone(), 23, outline(c), p),
+            Expression::Implement(ref n, _, ref c, p)  => (format!("implement {}", n), 5, outline(c), p),
+            Expression::Function(ref n, _, _, _, _, p) => (n.clone(), 12, Vec::new(), p),
+            Expression::FunctionDef(ref n, _, _, _, p) => (n.clone(), 12, Vec::new(), p),
+
+            Expression::Enum(ref n, ref v, p) => (n.clone(), 10, v.iter().map(|&(ref n, _, p)| object(vec!(
+                ("name",           Json::String(n.clone())),
+                ("kind",           Json::I64(22)),
+                ("range",          range(p, n.len())),
+                ("selectionRange", range(p, n.len())),
+            ))).collect(), p),
+
+            _ => continue,
+        };
+
+        // the position is the one of the name being implemented
+        let len = name.len() - name.rfind(' ').map(|i| i + 1).unwrap_or(0);
+
+        symbols.push(object(vec!(
+            ("name",           Json::String(name)),
+            ("kind",           Json::I64(kind)),
+            ("range",          range(p, len)),
+            ("selectionRange", range(p, len)),
+            ("children",       Json::Array(children)),
+        )))
+    }
+
+    symbols
+}
+
+/// Reads one message, which comes after headers giving its length.
+fn receive<R: BufRead>(input: &mut R) -> Option<String> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+
+        match input.read_line(&mut line) {
+            Ok(0) | Err(_) => return None,
+            Ok(_)          => (),
+        }
+
+        let line = line.trim();
+
+        if line.is_empty() {
+            if length.is_some() {
+                break
+            }
+
+            continue
+        }
+
+        if line.to_lowercase().starts_with("content-length:") {
+            length = line["content-length:".len() ..].trim().parse::<usize>().ok()
+        }
+    }
+
+    let mut body = vec!(0; length.unwrap());
+
+    match input.read_exact(&mut body) {
+        Ok(_)  => String::from_utf8(body).ok(),
+        Err(_) => None,
+    }
+}
+
+fn send(message: Json) {
+    let body = message.to_string();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    output.flush().unwrap();
+}
+
+fn respond(id: Json, result: Result<Json, (i64, String)>) {
+    let outcome = match result {
+        Ok(r)              => ("result", r),
+        Err((code, error)) => ("error", object(vec!(
+            ("code",    Json::I64(code)),
+            ("message", Json::String(error)),
+        ))),
+    };
+
+    send(object(vec!(
+        ("jsonrpc", Json::String("2.0".to_owned())),
+        ("id",      id),
+        outcome,
+    )))
+}
+
+fn publish(uri: &str, diagnostics: &[Diagnostic]) {
+    let diagnostics = diagnostics.iter().map(|d| {
+        let (position, len) = match d.span {
+            Some(ref s) => ((s.line, s.col), s.len as usize),
+            None        => ((1, 1), 0),
+        };
+
+        let mut message = d.message.clone();
+
+        for n in d.notes.iter() {
+            message.push_str(&format!("\nnote: {}", n))
+        }
+
+        object(vec!(
+            ("range",    range(position, len)),
+            ("severity", Json::I64(match d.severity {
+                Severity::Error   => 1,
+                Severity::Warning => 2,
+                Severity::Note    => 3,
+            })),
+            ("code",     Json::String(d.code.to_owned())),
+            ("source",   Json::String("helix".to_owned())),
+            ("message",  Json::String(message)),
+        ))
+    }).collect();
+
+    send(object(vec!(
+        ("jsonrpc", Json::String("2.0".to_owned())),
+        ("method",  Json::String("textDocument/publishDiagnostics".to_owned())),
+        ("params",  object(vec!(
+            ("uri",         Json::String(uri.to_owned())),
+            ("diagnostics", Json::Array(diagnostics)),
+        ))),
+    )))
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    let mut object = BTreeMap::new();
+
+    for (k, v) in fields {
+        object.insert(k.to_owned(), v);
+    }
+
+    Json::Object(object)
+}
+
+fn string(json: &Json, path: &[&str]) -> String {
+    match json.find_path(path).and_then(|s| s.as_string()) {
+        Some(s) => s.to_owned(),
+        None    => String::new(),
+    }
+}
+
+fn uri(params: &Json) -> Result<String, (i64, String)> {
+    match params.find_path(&["textDocument", "uri"]).and_then(|u| u.as_string()) {
+        Some(u) => Ok(u.to_owned()),
+        None    => Err((-32602, "missing `textDocument.uri`".to_owned())),
+    }
+}
+
+/// The position a request is about; editors count from 0 where Helix counts from 1.
+fn position(params: &Json) -> Position {
+    let number = |k| params.find_path(&["position", k]).and_then(|n| n.as_u64()).unwrap_or(0) as u32;
+
+    (number("line") + 1, number("character") + 1)
+}
+
+fn range(position: Position, len: usize) -> Json {
+    let (line, col) = (position.0.saturating_sub(1) as i64, position.1.saturating_sub(1) as i64);
+
+    let point = |c| object(vec!(
+        ("line",      Json::I64(line)),
+        ("character", Json::I64(c)),
+    ));
+
+    object(vec!(
+        ("start", point(col)),
+        ("end",   point(col + len as i64)),
+    ))
+}
+
+/// The file a `file://` URI names.
+fn path(uri: &str) -> PathBuf {
+    let path = if uri.starts_with("file://") { &uri["file://".len() ..] } else { uri };
+
+    let mut bytes = Vec::new();
+    let mut chars = path.bytes();
+
+    while let Some(b) = chars.next() {
+        if b == b'%' {
+            let hex: Vec<u8> = chars.by_ref().take(2).collect();
+
+            if let Ok(b) = u8::from_str_radix(&String::from_utf8_lossy(&hex), 16) {
+                bytes.push(b);
+
+                continue
+            }
+        }
+
+        bytes.push(b)
+    }
+
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
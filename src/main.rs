@@ -1,89 +1,282 @@
 extern crate docopt;
+extern crate rustc_serialize;
 use docopt::Docopt;
 
-use std::process::Command;
-
 use std::io;
 use std::io::prelude::*;
 use std::error::Error;
 
+use std::fs;
 use std::fs::File;
 use std::env;
+use std::thread;
 use std::path::Path;
 
 pub mod parser;
+pub mod driver;
+pub mod repl;
+pub mod lsp;
+pub mod formatter;
 use parser::ast::Statement;
+use parser::diagnostic::{Diagnostic, Severity};
+use parser::loader::Unit;
+use parser::resolver::index::Index;
+use parser::ir::lower::Lowering;
+use parser::translater::{Translater, Target};
+use parser::bytecode::Program;
+use parser::interpreter::value::Value;
+
+/// The stack programs are run on, which holds as many calls as the VM and the interpreter
+/// let a program make.
+const STACK: usize = 1 << 30;
 
 const USAGE: &'static str = "
 helix language
 
 usage:
     helix run <source>
-    helix build <source> <destination>
-    helix translate <source> <destination>
+    helix compile <source> <destination>
+    helix disasm <source>
+    helix repl
+    helix lsp
+    helix fmt [--check] <file>...
+    helix build [--target=<target>] [--cxx=<compiler>] [--std=<standard>] [-O <level>] [-I <path>]... [-l <library>]... <source> <destination>
+    helix translate [--target=<target>] [--library=<mapping>]... <source> <destination>
     helix (-h | --help)
     helix --version
 
 options:
-    -h --help   display this message
-    --version   display version
+    -h --help            display this message
+    --version            display version
+    --target=<target>    language to translate to, cpp, c, js or llvm [default: cpp]
+    --library=<mapping>  map an imported library to a JavaScript module, like `fs=node:fs`
+    --cxx=<compiler>     compiler to build with, `$CXX` or g++ when not given, `$CC` or gcc for C,
+                         `$CLANG` or clang for LLVM
+    --std=<standard>     standard to compile against, c++14 or c99 when not given
+    -O <level>           optimization level [default: 0]
+    -I <path>            add a directory to search for headers
+    -l <library>         link against a library
+    --check              list the files that aren't formatted instead of formatting them
 ";
 
-#[allow(dead_code)]
-fn repl() {
-    loop {
-        print!(">>> ");
-        io::stdout().flush().unwrap();
+/// Loads the program starting at `path` along with the Helix files it imports, and checks
+/// each of them against the files it imports, giving the index of what each file's names
+/// refer to along with it.
+fn program(path: &str) -> (Vec<Unit>, Vec<Index>) {
+    let mut units = match parser::loader::load(Path::new(path)) {
+        Ok(u)  => u,
+        Err(f) => fail(&f.diagnostics, &f.source),
+    };
+
+    let mut indices = Vec::new();
 
-        let mut input_line = String::new();
+    for i in 0 .. units.len() {
+        let (loaded, rest) = units.split_at_mut(i);
+        let unit = &mut rest[0];
 
-        match io::stdin().read_line(&mut input_line) {
-            Ok(_) => {
-                if input_line.trim() == String::from(":quit") ||
-                   input_line.trim() == String::from(":q") {
-                    println!("=> bye bb <3");
+        let imports: Vec<&[Statement]> = unit.imports.iter().map(|&j| &loaded[j].ast[..]).collect();
 
-                    std::process::exit(0)
-                }
+        indices.push(check(&mut unit.ast, &imports, &unit.source, &unit.path.to_string_lossy()));
+    }
+
+    (units, indices)
+}
+
+fn check(ast: &mut [Statement], imports: &[&[Statement]], source: &str, path: &str) -> Index {
+    let (diagnostics, index) = parser::resolver::analyze(ast, imports);
+
+    let (errors, warnings): (Vec<Diagnostic>, Vec<Diagnostic>) = diagnostics
+        .into_iter()
+        .map(|d| d.in_file(path))
+        .partition(|d| d.severity == Severity::Error);
+
+    for w in warnings.iter() {
+        writeln!(io::stderr(), "{}", w.render(source)).unwrap();
+    }
+
+    if !errors.is_empty() {
+        fail(&errors, source)
+    }
+
+    index
+}
+
+fn fail(diagnostics: &[Diagnostic], source: &str) -> ! {
+    for d in diagnostics.iter() {
+        writeln!(io::stderr(), "{}", d.render(source)).unwrap();
+    }
+
+    match diagnostics.len() {
+        1 => writeln!(io::stderr(), "error: aborting due to previous error").unwrap(),
+        n => writeln!(io::stderr(), "error: aborting due to {} previous errors", n).unwrap(),
+    }
+
+    std::process::exit(1)
+}
+
+fn translate(unit: &Unit, index: &Index, imports: &[(&[Statement], &Index)], target: Target,
+             libraries: &[(String, String)], title: &str) -> Translater {
+    let file = unit.path.to_string_lossy().into_owned();
+
+    let mut transpiler = Translater::to(target, title.to_owned(), file.clone());
+
+    for &(ast, index) in imports.iter() {
+        transpiler.import(ast, index)
+    }
+
+    for &(ref name, ref module) in libraries.iter() {
+        transpiler.library(name, module)
+    }
+
+    if let Err(e) = transpiler.make_environment(unit.ast.clone(), index) {
+        fail(&[e.in_file(&file)], &unit.source)
+    }
+
+    transpiler
+}
 
-                println!("=>\n{:#?}", parse(&input_line));
+/// Translates every file of a program, the one it starts from to `destination` and the
+/// files it imports next to it, and hands back the sources written.
+fn emit(units: Vec<Unit>, indices: Vec<Index>, destination: &str, target: Target,
+        libraries: &[(String, String)]) -> Vec<String> {
+    let directory = Path::new(destination).parent().unwrap_or(Path::new("")).to_owned();
+    let root      = units.len() - 1;
+
+    let mut sources = Vec::new();
+
+    for (i, unit) in units.iter().enumerate() {
+        let title = if i == root {
+            destination.to_owned()
+        } else {
+            directory.join(&unit.name).to_string_lossy().into_owned()
+        };
+
+        if let Some(parent) = Path::new(&title).parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).unwrap()
             }
+        }
+
+        let imports: Vec<(&[Statement], &Index)> = unit.imports.iter().map(|&j| (&units[j].ast[..], &indices[j])).collect();
+
+        let mut transpiler       = translate(unit, &indices[i], &imports, target, libraries, &title);
+        let (source, header)     = transpiler.translate();
+        let (extension, headers) = transpiler.extensions();
 
-            Err(e) => panic!(e),
+        write(&source, &format!("{}.{}", title, extension));
+        if !header.is_empty() {
+            write(&header, &format!("{}.{}", title, headers));
         }
+
+        sources.push(format!("{}.{}", title, extension))
     }
+
+    sources
 }
 
-fn parse(source: &str) -> Vec<Statement> {
-    use parser::block_tree::BlockTree;
+/// Compiles a program to bytecode, giving the mistake the compiler found along with the
+/// file it is in otherwise.
+fn compile(units: &[Unit], indices: &[Index]) -> Result<Program, (usize, Diagnostic)> {
+    let mut compiler = parser::bytecode::compiler::Compiler::new();
+
+    for (i, unit) in units.iter().enumerate() {
+        let items = try!(Lowering::new(&indices[i]).lower(&unit.ast).map_err(|d| (i, d)));
+
+        compiler.load(&items)
+    }
+
+    compiler.compile()
+}
+
+/// Compiles a program to bytecode, or reads it back when it already is, failing on
+/// anything the compiler turns down.
+fn bytecode(source: &str) -> Program {
+    if source.ends_with(".hxc") {
+        let mut bytes = Vec::new();
+
+        if let Err(e) = File::open(source).and_then(|mut f| f.read_to_end(&mut bytes)) {
+            writeln!(io::stderr(), "error: couldn't read `{}`: {}", source, e).unwrap();
+
+            std::process::exit(1)
+        }
+
+        return match parser::bytecode::file::read(&bytes) {
+            Ok(p)  => p,
+            Err(e) => {
+                writeln!(io::stderr(), "error: `{}` can't be run: {}", source, e).unwrap();
 
-    let mut tree = BlockTree::new(source, 0);
-    let indents = tree.collect_indents();
+                std::process::exit(1)
+            }
+        }
+    }
 
-    let root = tree.make_tree(&indents);
+    let (units, indices) = program(source);
 
-    let mut parser = parser::ast::Parser::from(
-             parser::tokenizer::Tokenizer::from(
-                    parser::tokenizer::flatten_tree(
-                            &parser::tokenizer::Tokenizer::tokenize_branch(&root)
-                        ),
-                ),
-        );
+    match compile(&units, &indices) {
+        Ok(p)       => p,
+        Err((i, d)) => {
+            let unit = &units[i];
 
-    match parser.parse() {
-        Ok(c) => c,
-        Err(e) => panic!(e),
+            fail(&[d.in_file(&unit.path.to_string_lossy())], &unit.source)
+        }
     }
 }
 
-fn translate(ast: Vec<Statement>, title: &str) -> (String, String) {
-    use parser::translater::Translater;
+/// Runs a program on the bytecode VM, or on the interpreter when it can't be compiled.
+fn run(source: &str) -> i32 {
+    use parser::bytecode::vm::Vm;
+    use parser::interpreter::Interpreter;
+
+    if source.ends_with(".hxc") {
+        return status(Vm::new(bytecode(source)).run())
+    }
+
+    let (units, indices) = program(source);
+
+    // what the compiler turns down is said, since it is run more slowly by the interpreter
+    match compile(&units, &indices) {
+        Ok(p) => return status(Vm::new(p).run()),
+
+        Err((i, mut d)) => {
+            let unit = &units[i];
+
+            d.severity = Severity::Warning;
+
+            let d = d.in_file(&unit.path.to_string_lossy()).note("running it on the interpreter instead".to_owned());
+
+            writeln!(io::stderr(), "{}", d.render(&unit.source)).unwrap();
+        }
+    }
+
+    let mut interpreter = Interpreter::new();
+
+    let mut result = Ok(());
 
-    let mut transpiler = Translater::new(title.to_owned());
+    for unit in units.iter() {
+        result = interpreter.load(&unit.ast);
 
-    transpiler.make_environment(ast);
+        if result.is_err() {
+            break
+        }
+    }
 
-    transpiler.translate()
+    status(match result {
+        Ok(_)  => interpreter.run(),
+        Err(e) => Err(e),
+    })
+}
+
+/// The status a program exits with, which is what `main` returns when it's an integer.
+fn status(result: Result<Value, String>) -> i32 {
+    match result {
+        Ok(Value::Integer(code)) => code as i32,
+        Ok(_)                    => 0,
+        Err(e)                   => {
+            writeln!(io::stderr(), "runtime error: {}", e).unwrap();
+
+            1
+        }
+    }
 }
 
 #[allow(unused_must_use)]
@@ -102,24 +295,80 @@ fn write(content: &str, destination: &str) {
     file.write_all(content.as_bytes());
 }
 
-#[allow(unused_must_use)]
-fn file<'a>(source: &str) -> String {
-    let mut source_file = match File::open(source) {
-        Ok(f) => f,
-        Err(_) => panic!("failed to open path: {}", source),
-    };
+/// Rewrites files in the canonical style, or only lists the ones that aren't in it when
+/// checking, and gives the status to exit with.
+fn format(files: &[&str], check: bool) -> i32 {
+    let mut status = 0;
+
+    for path in files.iter() {
+        let mut source = String::new();
+
+        if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut source)) {
+            writeln!(io::stderr(), "error: couldn't read `{}`: {}", path, e).unwrap();
+
+            status = 1;
+
+            continue
+        }
+
+        let formatted = match formatter::format(&source) {
+            Ok(f)  => f,
+            Err(e) => {
+                for d in e.into_iter() {
+                    writeln!(io::stderr(), "{}", d.in_file(path).render(&source)).unwrap();
+                }
+
+                status = 1;
+
+                continue
+            }
+        };
+
+        if formatted == source {
+            continue
+        }
+
+        if check {
+            println!("{}", path);
+
+            status = 1
+        } else {
+            write(&formatted, path)
+        }
+    }
+
+    status
+}
 
-    let mut source_buffer = String::new();
-    source_file.read_to_string(&mut source_buffer).unwrap();
+fn target(name: &str) -> Target {
+    match Target::named(name) {
+        Some(t) => t,
+        None    => {
+            writeln!(io::stderr(), "error: there's no target named `{}`, only `cpp`, `c`, `js` and `llvm`", name).unwrap();
 
-    source_buffer
+            std::process::exit(1)
+        }
+    }
+}
+
+/// The libraries named on the command line along with the modules they are mapped to.
+fn libraries(mappings: &[&str]) -> Vec<(String, String)> {
+    mappings.iter().map(|m| match m.find('=') {
+        Some(i) => (m[..i].to_owned(), m[i + 1 ..].to_owned()),
+        None    => {
+            writeln!(io::stderr(), "error: `{}` doesn't map a library to a module, like `fs=node:fs`", m).unwrap();
+
+            std::process::exit(1)
+        }
+    }).collect()
 }
 
-fn binary(source: &str, destination: &str) {
-    Command::new("g++")
-        .args(&[source, "-o", destination])
-        .spawn()
-        .expect("failed to compile binary!");
+fn binary(compiler: &driver::Compiler, sources: &[String], destination: &str) {
+    if let Err(e) = compiler.build(sources, destination) {
+        writeln!(io::stderr(), "error: {}", e).unwrap();
+
+        std::process::exit(e.code())
+    }
 }
 
 fn main() {
@@ -129,24 +378,78 @@ fn main() {
         .and_then(|d| d.argv(argv.into_iter()).parse())
         .unwrap_or_else(|e| e.exit());
 
-    if args.get_bool("translate") {
+    if args.get_bool("repl") {
+        repl::Repl::new().run()
+
+    } else if args.get_bool("lsp") {
+        std::process::exit(lsp::Server::new().run())
+
+    } else if args.get_bool("fmt") {
+        std::process::exit(format(&args.get_vec("<file>"), args.get_bool("--check")))
+
+    } else if args.get_bool("run") {
+        let source = args.get_str("<source>").to_owned();
+
+        let running = thread::Builder::new().stack_size(STACK).spawn(move || run(&source));
+
+        std::process::exit(running.unwrap().join().unwrap_or(101));
+
+    } else if args.get_bool("compile") {
+        let program = bytecode(args.get_str("<source>"));
+
+        let destination = args.get_str("<destination>");
+
+        if let Err(e) = File::create(destination).and_then(|mut f| f.write_all(&parser::bytecode::file::write(&program))) {
+            writeln!(io::stderr(), "error: couldn't write `{}`: {}", destination, e).unwrap();
+
+            std::process::exit(1)
+        }
+
+    } else if args.get_bool("disasm") {
+        print!("{}", parser::bytecode::disassemble(&bytecode(args.get_str("<source>"))));
+
+    } else if args.get_bool("translate") {
         let source = args.get_str("<source>");
         let destination = args.get_str("<destination>");
 
-        let (source, header) = translate(parse(&file(source)), destination);
+        let libraries = libraries(&args.get_vec("--library"));
 
-        write(&source, &format!("{}.cpp", destination));
-        write(&header, &format!("{}.hpp", destination));
+        let (units, indices) = program(source);
+
+        emit(units, indices, destination, target(args.get_str("--target")), &libraries);
 
     } else if args.get_bool("build") {
         let source = args.get_str("<source>");
         let destination = args.get_str("<destination>");
 
-        let (source, header) = translate(parse(&file(source)), destination);
+        let target = target(args.get_str("--target"));
+
+        let mut compiler = match target {
+            Target::Cpp  => driver::Compiler::new(),
+            Target::C    => driver::Compiler::c(),
+            Target::Llvm => driver::Compiler::llvm(),
+            Target::Js   => {
+                writeln!(io::stderr(), "error: JavaScript isn't built, only translated with `helix translate --target=js`").unwrap();
+
+                std::process::exit(1)
+            }
+        };
+
+        if !args.get_str("--cxx").is_empty() {
+            compiler.executable = args.get_str("--cxx").to_owned()
+        }
+
+        if !args.get_str("--std").is_empty() {
+            compiler.standard = args.get_str("--std").to_owned()
+        }
+
+        compiler.optimization = args.get_str("-O").to_owned();
+        compiler.includes     = args.get_vec("-I").iter().map(|s| s.to_string()).collect();
+        compiler.libraries    = args.get_vec("-l").iter().map(|s| s.to_string()).collect();
 
-        write(&source, &format!("{}.cpp", destination));
-        write(&header, &format!("{}.hpp", destination));
+        let (units, indices) = program(source);
+        let sources          = emit(units, indices, destination, target, &[]);
 
-        binary(&format!("{}.cpp", destination), "out");
+        binary(&compiler, &sources, destination);
     }
 }
\ No newline at end of file
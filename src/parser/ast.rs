@@ -1,5 +1,19 @@
+use std::cmp;
+
 use super::tokenizer::Tokenizer;
 use super::token::{TokenType, Operator};
+use super::diagnostic::{Diagnostic, code};
+
+/// The line and column a name was written at, both starting at 1.
+pub type Position = (u32, u32);
+
+/// A variant of an enum: its name, the fields it carries as `(type, name)` pairs like the
+/// parameters of a function, and where it was written.
+pub type Variant = (String, Vec<(String, String)>, Position);
+
+/// An arm of a `match`: the variant it is for, or `_` for every other one, the names the
+/// variant's fields are bound to and the block run for it.
+pub type Arm = (String, Vec<String>, Statement, Position);
 
 #[derive(Debug, Clone)]
 pub enum Expression {
@@ -8,29 +22,33 @@ pub enum Expression {
     Text(String),
     Boolean(bool),
 
-    Ident(String),
+    Ident(String, Position),
 
-    Operation(Box<Expression>, Operator, Box<Expression>),
+    Operation(Box<Expression>, Operator, Box<Expression>, Position),
+
+    Unary(Operator, Box<Expression>, Position),
 
     Call(Box<Expression>, Box<Vec<Expression>>),
 
     Use(Box<Expression>),
 
-    Module(String, Box<Vec<Statement>>),
+    Module(String, Box<Vec<Statement>>, Position),
+
+    Implement(String, Vec<String>, Box<Vec<Statement>>, Position),
 
-    Implement(String, Box<Vec<Statement>>),
+    Class(String, Vec<String>, Box<Vec<Statement>>, Option<Box<Expression>>, Position),
 
-    Class(String, Box<Vec<Statement>>, Option<Box<Expression>>),
+    Struct(String, Box<Vec<Statement>>, Position),
 
-    Struct(String, Box<Vec<Statement>>),
+    Enum(String, Vec<Variant>, Position),
 
     Typed(Box<Expression>, Box<Expression>),
 
-    Import(String, bool),
+    Import(String, bool, Position),
 
-    Function(String, Vec<(String, String)>, Box<Vec<Statement>>, Option<Box<Expression>>),
+    Function(String, Vec<String>, Vec<(String, String)>, Box<Vec<Statement>>, Option<Box<Expression>>, Position),
 
-    FunctionDef(String, Vec<(String, String)>, Box<Expression>),
+    FunctionDef(String, Vec<String>, Vec<(String, String)>, Box<Expression>, Position),
 
     IndexDot(Box<Expression>, Box<Expression>),
 
@@ -38,39 +56,106 @@ pub enum Expression {
 
     IndexArray(Box<Expression>, Box<Expression>),
 
-    Return(Box<Expression>),
+    /// A generic class or function given the types its parameters stand for, like
+    /// `Stack[int]`.
+    Generic(Box<Expression>, Vec<Expression>),
+
+    /// A list like `[1, 2, 3]`, or with a single type in it, like `[int]`, the type of one.
+    List(Vec<Expression>, Position),
+
+    /// A map like `{ "a": 1 }`, or the type of one, like `{string: int}`.
+    Map(Vec<(Expression, Expression)>, Position),
+
+    /// An anonymous function, like `function (x: int): x * 2`, with the parameters, body and
+    /// return type, if one is written, of a named one.
+    Lambda(Vec<(String, String)>, Box<Vec<Statement>>, Option<Box<Expression>>, Position),
+
+    /// The type of a function, like `(int, int) -> int`.
+    FunctionType(Vec<Expression>, Box<Expression>, Position),
+
+    Return(Box<Expression>, Position),
 }
 
 #[derive(Debug, Clone)]
 pub enum Statement {
-    Assignment(String, Box<Expression>),
+    Assignment(Box<Expression>, Box<Expression>, Position),
+
+    CompoundAssignment(Box<Expression>, Operator, Box<Expression>, Position),
 
-    Declaration(String, Box<Expression>),
+    Declaration(String, Option<Box<Expression>>, Box<Expression>, Position),
 
     Block(Box<Vec<Statement>>),
 
-    If(Box<Expression>, Box<Statement>),
+    If(Vec<(Expression, Statement)>, Option<Box<Statement>>, Position),
+
+    While(Box<Expression>, Box<Statement>, Position),
 
-    IfElse(Box<Expression>, Box<Statement>, Box<Statement>),
+    For(String, Box<Expression>, Box<Statement>, Position),
 
-    Expression(Box<Expression>),
+    ForRange(String, Box<Expression>, Box<Expression>, Box<Statement>, Position),
+
+    Match(Box<Expression>, Vec<Arm>, Position),
+
+    Break(Position),
+
+    Continue(Position),
+
+    Expression(Box<Expression>, Position),
+}
+
+impl Statement {
+    /// Where the statement starts; blocks only group other statements and have none.
+    pub fn position(&self) -> Option<Position> {
+        match *self {
+            Statement::Assignment(_, _, p)            |
+            Statement::CompoundAssignment(_, _, _, p) |
+            Statement::Declaration(_, _, _, p)        |
+            Statement::If(_, _, p)                    |
+            Statement::While(_, _, p)                 |
+            Statement::For(_, _, _, p)                |
+            Statement::ForRange(_, _, _, _, p)        |
+            Statement::Match(_, _, p)                 |
+            Statement::Break(p)                       |
+            Statement::Continue(p)                    |
+            Statement::Expression(_, p)               => Some(p),
+            Statement::Block(_)                       => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Parser {
     tokenizer: Tokenizer,
+    errors: Vec<Diagnostic>,
 }
 
 impl Parser {
     pub fn new() -> Parser {
-        Parser { tokenizer: Tokenizer::new() }
+        Parser {
+            tokenizer: Tokenizer::new(),
+            errors: Vec::new(),
+        }
     }
 
     pub fn from(tokenizer: Tokenizer) -> Parser {
-        Parser { tokenizer: tokenizer }
+        Parser {
+            tokenizer: tokenizer,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Statement>, Vec<Diagnostic>> {
+        let stack = self.parse_partial();
+
+        match self.errors.len() {
+            0 => Ok(stack),
+            _ => Err(self.errors.clone()),
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Statement>, String> {
+    /// Parses as much as possible, recovering from syntax errors at statement boundaries.
+    /// Every error is kept in `get_errors` and the statements that did parse are returned.
+    pub fn parse_partial(&mut self) -> Vec<Statement> {
         let mut stack = Vec::new();
 
         loop {
@@ -78,162 +163,267 @@ impl Parser {
                 break;
             }
 
-            stack.push(try!(self.statement()));
+            let start = self.tokenizer.get_top();
 
-            self.tokenizer.next_token();
+            match self.statement() {
+                Ok(s) => {
+                    stack.push(s);
+
+                    self.tokenizer.next_token();
+                }
+
+                Err(e) => {
+                    let row = e.span.as_ref().map_or(0, |s| s.line);
+
+                    self.errors.push(e);
+
+                    self.synchronize(start, row)
+                }
+            }
         }
 
-        Ok(stack)
+        stack
     }
 
-    fn operation(&mut self, expression: Expression) -> Result<Expression, String> {
+    pub fn get_errors(&self) -> &Vec<Diagnostic> {
+        &self.errors
+    }
 
-        let mut ex_stack = vec![expression];
-        let mut op_stack: Vec<(Operator, u8)> = Vec::new();
+    /// Skips the rest of the statement starting at `start` that failed on line `failed`: the
+    /// remainder of its lines up to that one, the indented blocks that belong to it and any
+    /// `else` lines continuing it.
+    fn synchronize(&mut self, start: usize, failed: u32) {
+        self.tokenizer.set_top(start);
 
-        op_stack.push(super::tokenizer::operator(&self.tokenizer.current_content()).unwrap());
+        let mut row = cmp::max(*self.tokenizer.current().get_position().0, failed);
 
         self.tokenizer.next_token();
 
-        ex_stack.push(try!(self.term()));
+        while self.tokenizer.remaining() > 0 {
+            let current = self.tokenizer.current().clone();
 
-        let mut done = false;
-        while ex_stack.len() > 1 {
+            match current.get_type() {
+                TokenType::Block(_) => (),
+                TokenType::Else => row = *current.get_position().0,
+                _ if *current.get_position().0 <= row => (),
+                _ => break,
+            }
 
-            if !done && self.tokenizer.next_token() {
-                if self.tokenizer.current().get_type() != TokenType::Operator {
-                    self.tokenizer.prev_token();
+            self.tokenizer.next_token();
+        }
+    }
 
-                    done = true;
+    /// Where the current token was written.
+    fn position(&self) -> Position {
+        let (row, col) = self.tokenizer.current().get_position();
 
-                    continue;
-                }
+        (*row, *col)
+    }
 
-                let (op, prec) = super::tokenizer::operator(&self.tokenizer.current_content())
-                    .unwrap();
+    /// The type of the token `offset` places after the current one, without moving.
+    fn peek(&self, offset: usize) -> Option<TokenType> {
+        self.tokenizer
+            .get_tokens()
+            .get(self.tokenizer.get_top() + offset)
+            .map(|t| t.get_type())
+    }
 
-                if prec > op_stack.last().unwrap().1 {
-                    let left = ex_stack.pop().unwrap();
-                    let right = ex_stack.pop().unwrap();
+    /// Precedence climbing over the binary operators; every operator is left associative.
+    fn binary(&mut self, precedence: u8) -> Result<Expression, Diagnostic> {
+        let mut left = try!(self.unary());
 
-                    ex_stack.push(Expression::Operation(Box::new(left),
-                                                        op_stack.pop().unwrap().0,
-                                                        Box::new(right)));
+        loop {
+            if self.peek(1) != Some(TokenType::Operator) {
+                break;
+            }
 
-                    self.tokenizer.next_token();
+            self.tokenizer.next_token();
 
-                    ex_stack.push(try!(self.term()));
-                    op_stack.push((op, prec));
+            let position = self.position();
 
-                    continue;
+            let (op, prec) = match super::tokenizer::operator(&self.tokenizer.current_content()) {
+                Some(o) => o,
+                None => {
+                    return Err(self.tokenizer.error(code::UNEXPECTED,
+                                                    format!("{} is not a binary operator",
+                                                            self.tokenizer.current())))
                 }
+            };
 
-                self.tokenizer.next_token();
+            if prec < precedence {
+                self.tokenizer.prev_token();
 
-                ex_stack.push(try!(self.term()));
-                op_stack.push((op, prec));
+                break;
             }
 
-            let left = ex_stack.pop().unwrap();
-            let right = ex_stack.pop().unwrap();
+            self.tokenizer.next_token();
 
-            ex_stack.push(Expression::Operation(Box::new(left),
-                                                op_stack.pop().unwrap().0,
-                                                Box::new(right)));
+            let right = try!(self.binary(prec + 1));
+
+            left = Expression::Operation(Box::new(left), op, Box::new(right), position);
         }
 
-        Ok(ex_stack.pop().unwrap())
+        Ok(left)
     }
 
-    fn call(&mut self, caller: Expression) -> Result<Expression, String> {
-        let mut stack = Vec::new();
+    fn unary(&mut self) -> Result<Expression, Diagnostic> {
+        if self.tokenizer.current().get_type() != TokenType::Operator {
+            return self.postfix();
+        }
 
-        self.tokenizer.next_token();
+        let position = self.position();
 
-        while self.tokenizer.current().get_type() != TokenType::RParen {
-            stack.push(try!(self.expression()));
+        let op = match super::tokenizer::unary(&self.tokenizer.current_content()) {
+            Some(o) => o,
+            None => {
+                return Err(self.tokenizer.error(code::UNEXPECTED,
+                                                format!("{} is not a prefix operator",
+                                                        self.tokenizer.current())))
+            }
+        };
 
-            self.tokenizer.next_token();
+        self.tokenizer.next_token();
 
-            if self.tokenizer.current().get_type() == TokenType::Comma {
-                self.tokenizer.next_token();
-            }
-        }
+        let operand = try!(self.unary());
 
-        Ok(Expression::Call(Box::new(caller), Box::new(stack)))
+        // negative literals stay literals
+        Ok(match (op, operand) {
+            (Operator::Minus, Expression::Integer(i)) => Expression::Integer(-i),
+            (Operator::Minus, Expression::Float(f)) => Expression::Float(-f),
+            (op, operand) => Expression::Unary(op, Box::new(operand), position),
+        })
     }
 
-    fn dot(&mut self, id: Expression) -> Result<Expression, String> {
-        self.tokenizer.next_token();
+    /// A term followed by any number of calls, indexes, `.` and `::` accesses.
+    fn postfix(&mut self) -> Result<Expression, Diagnostic> {
+        let mut expression = try!(self.term());
+
+        loop {
+            match self.peek(1) {
+                Some(TokenType::LParen) => {
+                    self.tokenizer.next_token();
 
-        try!(self.tokenizer.match_current(TokenType::Ident));
+                    expression = try!(self.call(expression));
+                }
 
-        let index = try!(self.expression());
+                Some(TokenType::Period) => {
+                    self.tokenizer.next_token();
+                    self.tokenizer.next_token();
 
-        Ok(Expression::IndexDot(Box::new(id), Box::new(index)))
-    }
+                    try!(self.tokenizer.match_current(TokenType::Ident));
 
-    fn colon(&mut self, id: Expression) -> Result<Expression, String> {
-        self.tokenizer.next_token();
+                    let index = Expression::Ident(self.tokenizer.current_content(), self.position());
 
-        try!(self.tokenizer.match_current(TokenType::Ident));
+                    expression = Expression::IndexDot(Box::new(expression), Box::new(index));
+                }
 
-        let index = try!(self.expression());
+                Some(TokenType::Colon) if self.peek(2) == Some(TokenType::Colon) => {
+                    self.tokenizer.next_token();
+                    self.tokenizer.next_token();
+                    self.tokenizer.next_token();
 
-        Ok(Expression::IndexColon(Box::new(id), Box::new(index)))
-    }
+                    try!(self.tokenizer.match_current(TokenType::Ident));
 
-    fn index(&mut self, id: Expression) -> Result<Expression, String> {
-        self.tokenizer.next_token();
+                    let index = Expression::Ident(self.tokenizer.current_content(), self.position());
 
-        let index = try!(self.expression());
+                    expression = Expression::IndexColon(Box::new(expression), Box::new(index));
+                }
 
-        self.tokenizer.next_token();
+                Some(TokenType::LBracket) => {
+                    self.tokenizer.next_token();
+                    self.tokenizer.next_token();
 
-        try!(self.tokenizer.match_current(TokenType::RBracket));
+                    let mut indexes = vec!(try!(self.expression()));
 
-        self.tokenizer.next_token();
+                    self.tokenizer.next_token();
 
-        let expression = Expression::IndexArray(Box::new(id), Box::new(index));
+                    while self.tokenizer.current().get_type() == TokenType::Comma {
+                        self.tokenizer.next_token();
 
-        match self.tokenizer.current().get_type() {
-            TokenType::Period => Ok(try!(self.dot(expression))),
-            TokenType::Colon => {
-                self.tokenizer.next_token();
+                        indexes.push(try!(self.expression()));
 
-                if self.tokenizer.current().get_type() == TokenType::Colon {
-                    self.colon(expression)
-                } else {
-                    self.typed(expression)
+                        self.tokenizer.next_token();
+                    }
+
+                    try!(self.tokenizer.match_current(TokenType::RBracket));
+
+                    // arrays are indexed by one number and hold no functions, so anything else
+                    // is a generic given its type arguments
+                    expression = if indexes.len() > 1 || self.peek(1) == Some(TokenType::LParen) {
+                        Expression::Generic(Box::new(expression), indexes.into_iter().map(applied).collect())
+                    } else {
+                        Expression::IndexArray(Box::new(expression), Box::new(indexes.remove(0)))
+                    };
                 }
+
+                _ => break,
             }
-            TokenType::LParen => Ok(try!(self.call(expression))),            
-            _ => Ok(expression),
         }
+
+        Ok(expression)
     }
 
-    fn typed(&mut self, id: Expression) -> Result<Expression, String> {
-        let ident = try!(self.term());
+    fn call(&mut self, caller: Expression) -> Result<Expression, Diagnostic> {
+        let mut stack = Vec::new();
+
+        self.tokenizer.next_token();
+
+        if self.tokenizer.current().get_type() != TokenType::RParen {
+            loop {
+                stack.push(try!(self.expression()));
+
+                if !self.tokenizer.next_token() || self.tokenizer.remaining() < 1 {
+                    self.tokenizer.prev_token();
 
-        Ok(Expression::Typed(Box::new(id), Box::new(ident)))
+                    return Err(self.tokenizer.error(code::EXPECTED,
+                                                    format!("expected `)`, found end of line")));
+                }
+
+                match self.tokenizer.current().get_type() {
+                    TokenType::Comma => {
+                        self.tokenizer.next_token();
+                    }
+
+                    TokenType::RParen => break,
+
+                    _ => {
+                        return Err(self.tokenizer.error(code::EXPECTED,
+                                                        format!("expected `,` or `)`, found {}",
+                                                                self.tokenizer.current())))
+                    }
+                }
+            }
+        }
+
+        Ok(Expression::Call(Box::new(caller), Box::new(stack)))
     }
 
-    fn term(&mut self) -> Result<Expression, String> {
+    fn term(&mut self) -> Result<Expression, Diagnostic> {
+        // past the last token the current one is still the last, which may start a term again
+        if self.tokenizer.remaining() < 1 {
+            self.tokenizer.prev_token();
+
+            return Err(self.tokenizer.error(code::EXPECTED,
+                                            format!("expected an expression, found end of line")));
+        }
+
         let token_type = self.tokenizer.current().get_type();
 
         match token_type {
             TokenType::Integer => {
-                Ok(Expression::Integer(self.tokenizer
-                                           .current_content()
-                                           .parse::<i64>()
-                                           .unwrap()))
+                match self.tokenizer.current_content().parse::<i64>() {
+                    Ok(i) => Ok(Expression::Integer(i)),
+                    Err(e) => Err(self.tokenizer.error(code::LITERAL,
+                                                       format!("invalid integer literal: {}", e))),
+                }
             }
 
             TokenType::Float => {
-                Ok(Expression::Float(self.tokenizer
-                                         .current_content()
-                                         .parse::<f64>()
-                                         .unwrap()))
+                match self.tokenizer.current_content().parse::<f64>() {
+                    Ok(f) => Ok(Expression::Float(f)),
+                    Err(e) => Err(self.tokenizer.error(code::LITERAL,
+                                                       format!("invalid float literal: {}", e))),
+                }
             }
 
             TokenType::Boolean => {
@@ -242,112 +432,192 @@ impl Parser {
 
             TokenType::Text => Ok(Expression::Text(self.tokenizer.current_content())),
 
-            TokenType::Nothing => Ok(Expression::Ident("void".to_owned())),
+            TokenType::Nothing => Ok(Expression::Ident("void".to_owned(), self.position())),
 
-            TokenType::Ident => {
-                let ident = Expression::Ident(self.tokenizer.current_content());
+            TokenType::Ident => Ok(Expression::Ident(self.tokenizer.current_content(), self.position())),
 
-                if self.tokenizer.next_token() {
-                    return match self.tokenizer.current().get_type() {
-                               TokenType::Operator => self.operation(ident),
-                               TokenType::Period => self.dot(ident),
-                               TokenType::LBracket => self.index(ident),
-                               TokenType::Colon => {
-                        self.tokenizer.next_token();
-                        if self.tokenizer.current().get_type() == TokenType::Colon {
-                            self.colon(ident)
-                        } else {
-                            self.typed(ident)
-                        }
-                    }
-                               TokenType::LParen => self.call(ident),
-                               _ => {
-                        self.tokenizer.prev_token();
-                        Ok(ident)
-                    }
-                           };
+            TokenType::LBracket => {
+                let position = self.position();
+                let mut elements = Vec::new();
+
+                self.tokenizer.next_token();
+
+                while self.tokenizer.current().get_type() != TokenType::RBracket {
+                    elements.push(try!(self.expression()));
+
+                    self.tokenizer.next_token();
+
+                    match self.tokenizer.current().get_type() {
+                        TokenType::Comma    => self.tokenizer.next_token(),
+                        TokenType::RBracket => break,
+
+                        _ => return Err(self.tokenizer.error(code::EXPECTED,
+                                                             format!("expected `,` or `]`, found {}",
+                                                                     self.tokenizer.current()))),
+                    };
                 }
 
-                Ok(ident)
+                Ok(Expression::List(elements, position))
             }
 
-            TokenType::LParen => {
+            TokenType::LBrace => {
+                let position = self.position();
+                let mut entries = Vec::new();
 
                 self.tokenizer.next_token();
 
-                let expression = try!(self.expression());
+                while self.tokenizer.current().get_type() != TokenType::RBrace {
+                    let key = try!(self.expression());
 
-                self.tokenizer.next_token();
+                    self.tokenizer.next_token();
+
+                    try!(self.tokenizer.match_current(TokenType::Colon));
+
+                    self.tokenizer.next_token();
+
+                    entries.push((key, try!(self.expression())));
+
+                    self.tokenizer.next_token();
+
+                    match self.tokenizer.current().get_type() {
+                        TokenType::Comma  => self.tokenizer.next_token(),
+                        TokenType::RBrace => break,
+
+                        _ => return Err(self.tokenizer.error(code::EXPECTED,
+                                                             format!("expected `,` or `}}`, found {}",
+                                                                     self.tokenizer.current()))),
+                    };
+                }
+
+                Ok(Expression::Map(entries, position))
+            }
 
-                try!(self.tokenizer.match_current(TokenType::RParen));
+            TokenType::LParen => {
+                let position = self.position();
+                let mut items = Vec::new();
 
                 self.tokenizer.next_token();
 
-                if self.tokenizer.current().get_type() == TokenType::LParen {
-                    return self.call(expression);
+                while self.tokenizer.current().get_type() != TokenType::RParen {
+                    items.push(try!(self.expression()));
+
+                    self.tokenizer.next_token();
+
+                    match self.tokenizer.current().get_type() {
+                        TokenType::Comma  => self.tokenizer.next_token(),
+                        TokenType::RParen => break,
+
+                        _ => return Err(self.tokenizer.error(code::EXPECTED,
+                                                             format!("expected `,` or `)`, found {}",
+                                                                     self.tokenizer.current()))),
+                    };
                 }
 
-                self.tokenizer.prev_token();
+                // only the parameters of a function type come in a list, or not at all
+                if self.peek(1) == Some(TokenType::Arrow) {
+                    self.tokenizer.next_token();
+                    self.tokenizer.next_token();
+
+                    let retty = try!(self.kind());
+
+                    return Ok(Expression::FunctionType(items.into_iter().map(applied).collect(), Box::new(retty), position))
+                }
 
-                Ok(expression)
+                if items.len() != 1 {
+                    return Err(self.tokenizer.error(code::EXPECTED,
+                                                    format!("expected `->` after the parameter types of a function type")));
+                }
+
+                Ok(items.remove(0))
             }
 
             TokenType::Module => {
                 self.tokenizer.next_token();
 
+                try!(self.tokenizer.match_current(TokenType::Ident));
+
                 let ident = self.tokenizer.current_content();
+                let position = self.position();
 
                 self.tokenizer.next_token();
 
                 let body = try!(self.block());
 
-                Ok(Expression::Module(ident, Box::new(body)))
+                Ok(Expression::Module(ident, Box::new(body), position))
             }
 
             TokenType::Class => {
                 self.tokenizer.next_token();
 
+                try!(self.tokenizer.match_current(TokenType::Ident));
+
                 let ident = self.tokenizer.current_content();
+                let position = self.position();
 
                 self.tokenizer.next_token();
 
+                let generics = try!(self.generics());
+
                 let mut parent = None;
 
                 if self.tokenizer.current().get_type() == TokenType::NArrow {
                     self.tokenizer.next_token();
 
-                    parent = Some(Box::new(try!(self.term())));
+                    parent = Some(Box::new(try!(self.kind())));
 
                     self.tokenizer.next_token();
                 }
 
                 let body = try!(self.block());
 
-                Ok(Expression::Class(ident, Box::new(body), parent))
+                Ok(Expression::Class(ident, generics, Box::new(body), parent, position))
             }
 
             TokenType::Implement => {
                 self.tokenizer.next_token();
 
+                try!(self.tokenizer.match_current(TokenType::Ident));
+
                 let ident = self.tokenizer.current_content();
+                let position = self.position();
 
                 self.tokenizer.next_token();
 
+                let generics = try!(self.generics());
+
                 let body = try!(self.block());
 
-                Ok(Expression::Implement(ident, Box::new(body)))
+                Ok(Expression::Implement(ident, generics, Box::new(body), position))
             }
 
             TokenType::Struct => {
                 self.tokenizer.next_token();
 
+                try!(self.tokenizer.match_current(TokenType::Ident));
+
                 let ident = self.tokenizer.current_content();
+                let position = self.position();
 
                 self.tokenizer.next_token();
 
                 let body = try!(self.block());
 
-                Ok(Expression::Struct(ident, Box::new(body)))
+                Ok(Expression::Struct(ident, Box::new(body), position))
+            }
+
+            TokenType::Enum => {
+                self.tokenizer.next_token();
+
+                try!(self.tokenizer.match_current(TokenType::Ident));
+
+                let ident = self.tokenizer.current_content();
+                let position = self.position();
+
+                self.tokenizer.next_token();
+
+                let variants = try!(self.variants());
+
+                Ok(Expression::Enum(ident, variants, position))
             }
 
             TokenType::Import => {
@@ -355,58 +625,81 @@ impl Parser {
 
                 try!(self.tokenizer.match_current(TokenType::Text));
 
-                let ident = self.tokenizer.current_content();
+                let ident    = self.tokenizer.current_content();
+                let position = self.position();
 
                 self.tokenizer.next_token();
 
                 if self.tokenizer.current().get_type() == TokenType::Library {
-                    return Ok(Expression::Import(ident, true));
+                    return Ok(Expression::Import(ident, true, position));
                 }
 
                 self.tokenizer.prev_token();
 
-                Ok(Expression::Import(ident, false))
+                Ok(Expression::Import(ident, false, position))
             }
 
             TokenType::Use => {
                 self.tokenizer.next_token();
 
-                Ok(Expression::Use(Box::new(try!(self.term()))))
+                Ok(Expression::Use(Box::new(try!(self.postfix()))))
             }
 
-            TokenType::Def => {
+            TokenType::Def if self.peek(1) == Some(TokenType::LParen) => {
+                let position = self.position();
+
                 self.tokenizer.next_token();
 
-                let name = self.tokenizer.current_content();
+                let args = try!(self.parameters());
 
                 self.tokenizer.next_token();
 
-                let mut args = Vec::new();
+                let mut retty = None;
 
-                if self.tokenizer.current().get_type() == TokenType::LParen {
+                if self.tokenizer.current().get_type() == TokenType::Arrow {
                     self.tokenizer.next_token();
 
-                    while self.tokenizer.current().get_type() == TokenType::Ident {
-                        let n = self.tokenizer.current_content();
+                    retty = Some(Box::new(try!(self.kind())));
 
-                        self.tokenizer.next_token();
+                    self.tokenizer.next_token();
+                }
 
-                        try!(self.tokenizer.match_current(TokenType::Colon));
+                let body = match self.tokenizer.current().get_type() {
+                    TokenType::Block(_) => try!(self.block()),
 
+                    TokenType::Colon => {
                         self.tokenizer.next_token();
 
-                        let t = self.tokenizer.current_content();
+                        let start = self.position();
+                        let value = try!(self.expression());
 
-                        self.tokenizer.next_token();
+                        vec!(Statement::Expression(Box::new(Expression::Return(Box::new(value), start)), start))
+                    }
 
-                        if self.tokenizer.current().get_type() == TokenType::Comma {
-                            self.tokenizer.next_token();                      
-                        }
+                    _ => return Err(self.tokenizer.error(code::EXPECTED,
+                                                         format!("expected `:` or an indented block, found {}",
+                                                                 self.tokenizer.current()))),
+                };
 
-                        args.push((t, n));     
-                    }
+                Ok(Expression::Lambda(args, Box::new(body), retty, position))
+            }
+
+            TokenType::Def => {
+                self.tokenizer.next_token();
+
+                try!(self.tokenizer.match_current(TokenType::Ident));
+
+                let name = self.tokenizer.current_content();
+                let position = self.position();
+
+                self.tokenizer.next_token();
+
+                let generics = try!(self.generics());
 
-                    try!(self.tokenizer.match_current(TokenType::RParen));
+                let mut args = Vec::new();
+
+                if self.tokenizer.current().get_type() == TokenType::LParen {
+                    args = try!(self.parameters());
 
                     self.tokenizer.next_token();
                 }
@@ -414,97 +707,245 @@ impl Parser {
                 match self.tokenizer.current().get_type() {
                     TokenType::Block(_) => {
                         let body = try!(self.block());
-                        Ok(Expression::Function(name, args, Box::new(body), None))
+                        Ok(Expression::Function(name, generics, args, Box::new(body), None, position))
                     },
 
                     _ => {
                         if self.tokenizer.current().get_type() == TokenType::Arrow {
                             self.tokenizer.next_token();
-                            let retty = try!(self.term());
+                            let retty = try!(self.kind());
 
                             self.tokenizer.next_token();
 
                             match self.tokenizer.current().get_type() {
                                 TokenType::Block(_) => {
                                     let body = try!(self.block());
-                                    return Ok(Expression::Function(name, args, Box::new(body), Some(Box::new(retty))))
+                                    return Ok(Expression::Function(name, generics, args, Box::new(body), Some(Box::new(retty)), position))
                                 }
                                 _ => (),
                             }
 
                             self.tokenizer.prev_token();
 
-                            Ok(Expression::FunctionDef(name, args, Box::new(retty)))
+                            Ok(Expression::FunctionDef(name, generics, args, Box::new(retty), position))
                         } else {
                             self.tokenizer.prev_token();
-                            Ok(Expression::FunctionDef(name, args, Box::new(Expression::Ident("void".to_owned()))))
+                            Ok(Expression::FunctionDef(name, generics, args, Box::new(Expression::Ident("void".to_owned(), position)), position))
                         }
                     }
                 }
             }
 
             TokenType::Return => {
+                let position = self.position();
+
                 self.tokenizer.next_token();
 
-                Ok(Expression::Return(Box::new(try!(self.expression()))))
+                Ok(Expression::Return(Box::new(try!(self.expression())), position))
             }
 
-            _ => Err(format!("unexpected token: {:#?}", token_type)),
+            _ => Err(self.tokenizer.error(code::UNEXPECTED,
+                                          format!("unexpected {}", self.tokenizer.current()))),
         }
     }
 
-    fn block(&mut self) -> Result<Vec<Statement>, String> {
-        match self.tokenizer.current().get_type() {
-            TokenType::Block(v) => {
-                let mut p = Parser::from(Tokenizer::from(v));
+    /// The `(name: type, ...)` list of a function's parameters or a variant's fields, starting
+    /// at the `(` and ending at the `)`.
+    fn parameters(&mut self) -> Result<Vec<(String, String)>, Diagnostic> {
+        let mut args = Vec::new();
+
+        self.tokenizer.next_token();
+
+        while self.tokenizer.current().get_type() == TokenType::Ident {
+            let n = self.tokenizer.current_content();
 
-                p.parse()
+            self.tokenizer.next_token();
+
+            try!(self.tokenizer.match_current(TokenType::Colon));
+
+            self.tokenizer.next_token();
+
+            let t = written(&try!(self.kind()));
+
+            self.tokenizer.next_token();
+
+            if self.tokenizer.current().get_type() == TokenType::Comma {
+                self.tokenizer.next_token();
             }
+
+            args.push((t, n));
+        }
+
+        try!(self.tokenizer.match_current(TokenType::RParen));
+
+        Ok(args)
+    }
+
+    /// The `[T, ...]` type parameters of a generic class or function, if it has any, leaving
+    /// the token after them current.
+    fn generics(&mut self) -> Result<Vec<String>, Diagnostic> {
+        let mut generics = Vec::new();
+
+        if self.tokenizer.current().get_type() != TokenType::LBracket {
+            return Ok(generics)
+        }
+
+        self.tokenizer.next_token();
+
+        loop {
+            try!(self.tokenizer.match_current(TokenType::Ident));
+
+            generics.push(self.tokenizer.current_content());
+
+            self.tokenizer.next_token();
+
+            match self.tokenizer.current().get_type() {
+                TokenType::Comma    => self.tokenizer.next_token(),
+                TokenType::RBracket => break,
+
+                _ => return Err(self.tokenizer.error(code::EXPECTED,
+                                                     format!("expected `,` or `]`, found {}",
+                                                             self.tokenizer.current()))),
+            };
+        }
+
+        self.tokenizer.next_token();
+
+        Ok(generics)
+    }
+
+    /// A type, such as `int`, `geometry::Point` or `Stack[int]`.
+    fn kind(&mut self) -> Result<Expression, Diagnostic> {
+        Ok(applied(try!(self.postfix())))
+    }
+
+    /// A parser for the contents of the indented block at the current token.
+    fn inner(&self) -> Result<Parser, Diagnostic> {
+        match self.tokenizer.current().get_type() {
+            TokenType::Block(v) => Ok(Parser::from(Tokenizer::from(v))),
             _ => {
-                Err(format!(
-                            "expected block, found: {:#?}",
-                            self.tokenizer.current(),
-                        ))
+                Err(self.tokenizer.error(code::BLOCK,
+                                         format!("expected indented block, found {}",
+                                                 self.tokenizer.current())))
             }
         }
     }
 
-    fn statement(&mut self) -> Result<Statement, String> {
-        match self.tokenizer.current().get_type() {
-            TokenType::Ident => {
-                let ident = self.tokenizer.current_content();
+    fn block(&mut self) -> Result<Vec<Statement>, Diagnostic> {
+        let mut p = try!(self.inner());
 
-                self.tokenizer.next_token();
+        let body = p.parse_partial();
 
-                if self.tokenizer.current().get_type() != TokenType::Assign {
-                    self.tokenizer.prev_token();
+        self.errors.append(&mut p.errors);
 
-                    let expression = try!(self.expression());
+        Ok(body)
+    }
 
-                    return Ok(Statement::Expression(Box::new(expression)));
-                }
+    /// The variants in the block of an `enum`, each a name with its fields, if any, in
+    /// parentheses.
+    fn variants(&mut self) -> Result<Vec<Variant>, Diagnostic> {
+        let mut p = try!(self.inner());
 
-                self.tokenizer.next_token();
+        let mut variants = Vec::new();
 
-                let expression = try!(self.expression());
+        while p.tokenizer.remaining() > 0 {
+            try!(p.tokenizer.match_current(TokenType::Ident));
+
+            let name = p.tokenizer.current_content();
+            let position = p.position();
+
+            let mut fields = Vec::new();
+
+            if p.peek(1) == Some(TokenType::LParen) {
+                p.tokenizer.next_token();
+
+                fields = try!(p.parameters());
+            }
+
+            variants.push((name, fields, position));
+
+            p.tokenizer.next_token();
+        }
+
+        Ok(variants)
+    }
+
+    /// The arms in the block of a `match`, each a variant with the names its fields are bound
+    /// to, followed by a block of its own.
+    fn arms(&mut self) -> Result<Vec<Arm>, Diagnostic> {
+        let mut p = try!(self.inner());
+
+        let mut arms = Vec::new();
+
+        while p.tokenizer.remaining() > 0 {
+            try!(p.tokenizer.match_current(TokenType::Ident));
+
+            let variant = p.tokenizer.current_content();
+            let position = p.position();
+
+            p.tokenizer.next_token();
+
+            let mut bindings = Vec::new();
+
+            if p.tokenizer.current().get_type() == TokenType::LParen {
+                p.tokenizer.next_token();
+
+                while p.tokenizer.current().get_type() == TokenType::Ident {
+                    bindings.push(p.tokenizer.current_content());
+
+                    p.tokenizer.next_token();
 
-                Ok(Statement::Assignment(ident, Box::new(expression)))
+                    if p.tokenizer.current().get_type() == TokenType::Comma {
+                        p.tokenizer.next_token();
+                    }
+                }
+
+                try!(p.tokenizer.match_current(TokenType::RParen));
+
+                p.tokenizer.next_token();
             }
 
+            let body = try!(p.block());
+
+            arms.push((variant, bindings, Statement::Block(Box::new(body)), position));
+
+            p.tokenizer.next_token();
+        }
+
+        self.errors.append(&mut p.errors);
+
+        Ok(arms)
+    }
+
+    fn statement(&mut self) -> Result<Statement, Diagnostic> {
+        let start = self.position();
+
+        match self.tokenizer.current().get_type() {
             TokenType::Let => {
                 self.tokenizer.next_token();
 
                 let ident = self.tokenizer.current_content();
+                let position = self.position();
 
                 self.tokenizer.next_token();
 
+                let mut t = None;
+
+                if self.tokenizer.current().get_type() == TokenType::Colon {
+                    self.tokenizer.next_token();
+
+                    t = Some(Box::new(try!(self.kind())));
+
+                    self.tokenizer.next_token();
+                }
+
                 try!(self.tokenizer.match_current(TokenType::Assign));
 
                 self.tokenizer.next_token();
 
                 let expression = try!(self.expression());
 
-                Ok(Statement::Declaration(ident, Box::new(expression)))
+                Ok(Statement::Declaration(ident, t, Box::new(expression), position))
             }
 
             TokenType::If => {
@@ -516,46 +957,233 @@ impl Parser {
 
                 let body = try!(self.block());
 
+                let mut branches = vec!((condition, Statement::Block(Box::new(body))));
+
+                loop {
+                    self.tokenizer.next_token();
+
+                    if self.tokenizer.current().get_type() != TokenType::Else {
+                        self.tokenizer.prev_token();
+
+                        return Ok(Statement::If(branches, None, start));
+                    }
+
+                    self.tokenizer.next_token();
+
+                    if self.tokenizer.current().get_type() != TokenType::If {
+                        let else_body = try!(self.block());
+
+                        return Ok(Statement::If(branches,
+                                                Some(Box::new(Statement::Block(Box::new(else_body)))),
+                                                start));
+                    }
+
+                    self.tokenizer.next_token();
+
+                    let condition = try!(self.expression());
+
+                    self.tokenizer.next_token();
+
+                    let body = try!(self.block());
+
+                    branches.push((condition, Statement::Block(Box::new(body))));
+                }
+            }
+
+            TokenType::While => {
+                self.tokenizer.next_token();
+
+                let condition = try!(self.expression());
+
+                self.tokenizer.next_token();
+
+                let body = try!(self.block());
+
+                Ok(Statement::While(Box::new(condition),
+                                    Box::new(Statement::Block(Box::new(body))),
+                                    start))
+            }
+
+            TokenType::For => {
+                self.tokenizer.next_token();
+
+                try!(self.tokenizer.match_current(TokenType::Ident));
+
+                let ident = self.tokenizer.current_content();
+
+                self.tokenizer.next_token();
+
+                try!(self.tokenizer.match_current(TokenType::In));
+
+                self.tokenizer.next_token();
+
+                let iterable = try!(self.expression());
+
                 self.tokenizer.next_token();
 
-                if self.tokenizer.current().get_type() == TokenType::Else {
+                if self.tokenizer.current().get_type() == TokenType::Range {
                     self.tokenizer.next_token();
 
-                    let else_body = try!(self.block());
+                    let end = try!(self.expression());
 
-                    return Ok(Statement::IfElse(Box::new(condition),
-                                                Box::new(Statement::Block(Box::new(body))),
+                    self.tokenizer.next_token();
+
+                    let body = try!(self.block());
 
-                                                Box::new(Statement::Block(Box::new(else_body)))));
+                    return Ok(Statement::ForRange(ident,
+                                                  Box::new(iterable),
+                                                  Box::new(end),
+                                                  Box::new(Statement::Block(Box::new(body))),
+                                                  start));
                 }
 
-                self.tokenizer.prev_token();
+                let body = try!(self.block());
 
-                Ok(Statement::If(Box::new(condition),
-                                 Box::new(Statement::Block(Box::new(body)))))
+                Ok(Statement::For(ident,
+                                  Box::new(iterable),
+                                  Box::new(Statement::Block(Box::new(body))),
+                                  start))
             }
 
-            _ => {
-                let expression = try!(self.expression());
+            TokenType::Match => {
+                self.tokenizer.next_token();
+
+                let subject = try!(self.expression());
+
+                self.tokenizer.next_token();
+
+                let arms = try!(self.arms());
 
-                Ok(Statement::Expression(Box::new(expression)))
+                Ok(Statement::Match(Box::new(subject), arms, start))
             }
+
+            TokenType::Break => Ok(Statement::Break(start)),
+
+            TokenType::Continue => Ok(Statement::Continue(start)),
+
+            _ => self.expression_statement(),
         }
     }
 
-    fn expression(&mut self) -> Result<Expression, String> {
-        let expr = try!(self.term());
+    /// An expression on its own line, which may be a `name: type` declaration or the target
+    /// of an assignment.
+    fn expression_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let start = self.position();
 
-        self.tokenizer.next_token();
+        let mut expression = try!(self.expression());
+
+        match self.peek(1) {
+            Some(TokenType::Assign) | Some(TokenType::CompoundAssign) => {
+                match expression {
+                    Expression::Ident(_, _) |
+                    Expression::IndexDot(_, _) |
+                    Expression::IndexColon(_, _) |
+                    Expression::IndexArray(_, _) => (),
+
+                    _ => {
+                        self.tokenizer.next_token();
+
+                        return Err(self.tokenizer.error(code::ASSIGNMENT,
+                                                        "invalid left-hand side of assignment".to_owned())
+                            .note("only names, members and indexes can be assigned to".to_owned()))
+                    }
+                }
+
+                self.tokenizer.next_token();
+
+                let op = super::tokenizer::compound(&self.tokenizer.current_content());
 
-        if self.tokenizer.remaining() > 0 {
-            if self.tokenizer.current().get_type() == TokenType::Operator {
-                return self.operation(expr);
+                self.tokenizer.next_token();
+
+                let value = try!(self.expression());
+
+                return Ok(match op {
+                    Some(op) => Statement::CompoundAssignment(Box::new(expression), op, Box::new(value), start),
+                    None => Statement::Assignment(Box::new(expression), Box::new(value), start),
+                });
             }
 
-            self.tokenizer.prev_token();
+            _ => (),
+        }
+
+        if self.peek(1) == Some(TokenType::Colon) {
+            self.tokenizer.next_token();
+            self.tokenizer.next_token();
+
+            let t = try!(self.kind());
+
+            expression = Expression::Typed(Box::new(expression), Box::new(t));
+        }
+
+        Ok(Statement::Expression(Box::new(expression), start))
+    }
+
+    fn expression(&mut self) -> Result<Expression, Diagnostic> {
+        self.binary(0)
+    }
+}
+
+/// A type read like an expression, with the indexes in it taken as type arguments.
+fn applied(t: Expression) -> Expression {
+    match t {
+        Expression::IndexArray(a, b)      => Expression::Generic(Box::new(applied(*a)), vec!(applied(*b))),
+        Expression::Generic(a, b)         => Expression::Generic(Box::new(applied(*a)), b.into_iter().map(applied).collect()),
+        Expression::IndexColon(a, b)      => Expression::IndexColon(Box::new(applied(*a)), b),
+        Expression::List(t, p)            => Expression::List(t.into_iter().map(applied).collect(), p),
+        Expression::Map(t, p)             => Expression::Map(t.into_iter().map(|(k, v)| (applied(k), applied(v))).collect(), p),
+        Expression::FunctionType(a, r, p) => Expression::FunctionType(a.into_iter().map(applied).collect(), Box::new(applied(*r)), p),
+        t                                 => t,
+    }
+}
+
+/// A type written out the way it reads in Helix, as the types of parameters are kept.
+pub fn written(t: &Expression) -> String {
+    match *t {
+        Expression::Ident(ref n, _)          => n.clone(),
+        Expression::IndexColon(ref a, ref b) => format!("{}::{}", written(&**a), written(&**b)),
+
+        Expression::Generic(ref a, ref b) => {
+            let arguments: Vec<String> = b.iter().map(written).collect();
+
+            format!("{}[{}]", written(&**a), arguments.join(", "))
         }
 
-        Ok(expr)
+        Expression::List(ref t, _) if t.len() == 1 => format!("[{}]", written(&t[0])),
+        Expression::Map(ref t, _) if t.len() == 1  => format!("{{{}: {}}}", written(&t[0].0), written(&t[0].1)),
+
+        Expression::FunctionType(ref a, ref r, _) => {
+            let parameters: Vec<String> = a.iter().map(written).collect();
+
+            format!("({}) -> {}", parameters.join(", "), written(&**r))
+        }
+
+        _ => String::new(),
+    }
+}
+
+/// The parts of a written type separated by `separator`, like the type arguments in the
+/// brackets of a generic, leaving the ones nested in brackets, braces or parentheses alone.
+pub fn split<'a>(list: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in list.char_indices() {
+        match c {
+            '[' | '{' | '(' => depth += 1,
+            ']' | '}' | ')' => depth -= 1,
+
+            _ if depth == 0 && i >= start && list[i..].starts_with(separator) => {
+                parts.push(list[start..i].trim());
+
+                start = i + separator.len()
+            }
+
+            _ => (),
+        }
     }
+
+    parts.push(list[start..].trim());
+
+    parts
 }
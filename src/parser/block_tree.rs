@@ -1,4 +1,5 @@
 use super::token::Token;
+use super::diagnostic::{Diagnostic, code};
 
 #[derive(Debug, Clone)]
 pub enum ChunkValue<'a> {
@@ -7,20 +8,73 @@ pub enum ChunkValue<'a> {
     Block(Branch<'a>),
 }
 
+/// What surrounds a line without being part of its code: comments and blank lines, which
+/// only matter for rewriting the source.
+#[derive(Debug, Clone, Default)]
+pub struct Trivia<'a> {
+    /// Whether blank lines came before the line, or before the comments leading up to it.
+    pub blank: bool,
+    /// Comments on their own lines right before the line, with an empty one for every run of
+    /// blank lines between them.
+    pub leading: Vec<&'a str>,
+    /// A comment after the code on the line itself.
+    pub trailing: Option<&'a str>,
+    /// Comments on their own lines after the line, indented past the line that follows them.
+    pub after: Vec<&'a str>,
+}
+
+/// A non-empty line of code, with its indentation and line number.
+#[derive(Debug, Clone)]
+pub struct Line<'a> {
+    pub indent: usize,
+    pub number: usize,
+    pub content: &'a str,
+    pub trivia: Trivia<'a>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Chunk<'a> {
     value: ChunkValue<'a>,
+    position: (u32, u32),
+    trivia: Trivia<'a>,
 }
 
 impl<'a> Chunk<'a> {
     pub fn new(value: ChunkValue<'a>) -> Chunk<'a> {
 
-        Chunk { value: value }
+        Chunk {
+            value: value,
+            position: (0, 0),
+            trivia: Trivia::default(),
+        }
+    }
+
+    pub fn at(value: ChunkValue<'a>, row: u32, col: u32) -> Chunk<'a> {
+
+        Chunk {
+            value: value,
+            position: (row, col),
+            trivia: Trivia::default(),
+        }
+    }
+
+    pub fn with_trivia(mut self, trivia: Trivia<'a>) -> Chunk<'a> {
+        self.trivia = trivia;
+
+        self
     }
 
     pub fn get_value(&self) -> ChunkValue<'a> {
         self.value.clone()
     }
+
+    pub fn get_position(&self) -> (u32, u32) {
+        self.position
+    }
+
+    pub fn get_trivia(&self) -> &Trivia<'a> {
+        &self.trivia
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +94,8 @@ pub struct BlockTree<'a> {
     source: &'a str,
     line: usize,
     method: Option<char>,
+    /// Comments after the last line of code that aren't indented into it.
+    pub trailing: Trivia<'a>,
 }
 
 impl<'a> BlockTree<'a> {
@@ -49,36 +105,79 @@ impl<'a> BlockTree<'a> {
             source: source,
             line: line,
             method: None,
+            trailing: Trivia::default(),
         }
     }
 
-    pub fn collect_indents(&mut self) -> Vec<(usize, &'a str)> {
-        let mut indents: Vec<(usize, &'a str)> = Vec::new();
+    /// Collects the indentation, line number and content of every line with code on it. The
+    /// comments and blank lines between them are attached to the lines as trivia.
+    pub fn collect_indents(&mut self) -> Result<Vec<Line<'a>>, Diagnostic> {
+        let mut indents: Vec<Line<'a>> = Vec::new();
 
-        let mut lines = self.source.lines();
+        let mut trivia = Trivia::default();
+        // comments waiting for the next line of code, with their indentation
+        let mut comments: Vec<(usize, &'a str)> = Vec::new();
 
-        while let Some(line) = lines.next() {
+        let mut lines = self.source.lines().enumerate();
 
-            let parts: Vec<&str> = line.split("#").collect();
-            let ln = parts.get(0).unwrap().trim();
+        while let Some((number, line)) = lines.next() {
+            let (code, comment) = split_comment(line);
+            let ln = code.trim();
 
             if ln.len() > 0 {
-                let indent = self.get_indent(&line);
-                indents.push((indent, ln))
+                let indent = try!(self.get_indent(&line, number + 1));
+
+                attach(&mut indents, &mut trivia, &mut comments, indent);
+
+                trivia.trailing = comment;
+
+                indents.push(Line {
+                    indent: indent,
+                    number: number + 1,
+                    content: ln,
+                    trivia: trivia,
+                });
+
+                trivia = Trivia::default()
+
+            } else if let Some(c) = comment {
+                comments.push((line.len() - line.trim_left().len(), c))
+
+            } else if !comments.is_empty() {
+                // blank lines between comments are kept as empty ones
+                if comments.last() != Some(&(0, "")) {
+                    comments.push((0, ""))
+                }
+
+            } else if !indents.is_empty() {
+                trivia.blank = true
             }
         }
 
-        indents
+        attach(&mut indents, &mut trivia, &mut comments, 0);
+
+        self.trailing = trivia;
+
+        Ok(indents)
     }
 
-    pub fn get_indent(&mut self, line: &str) -> usize {
+    pub fn get_indent(&mut self, line: &str, number: usize) -> Result<usize, Diagnostic> {
         let mut pos: usize = 0;
 
         for c in line.chars() {
             match c {
                 ' ' | '\t' => {
                     match self.method {
-                        Some(m) => assert!(m == c, "use of inconsistent indentation"),
+                        Some(m) => if m != c {
+                            return Err(
+                                Diagnostic::error(
+                                    code::INDENTATION,
+                                    "use of inconsistent indentation".to_owned(),
+                                )
+                                .at(number as u32, pos as u32 + 1, 1)
+                                .note(format!("this file is indented with {}", describe_indent(m)))
+                            )
+                        },
                         None => self.method = Some(c),
                     }
 
@@ -89,26 +188,31 @@ impl<'a> BlockTree<'a> {
             }
         }
 
-        pos
+        Ok(pos)
     }
 
-    pub fn make_tree(&mut self, indents: &Vec<(usize, &'a str)>) -> Branch<'a> {
+    pub fn make_tree(&mut self, indents: &Vec<Line<'a>>) -> Branch<'a> {
         let mut branch = Branch::new(Vec::new());
         let base_line = indents.get(self.line);
 
-        let &(base_indent, _) = match base_line {
-            Some(i) => i,
+        let base_indent = match base_line {
+            Some(l) => l.indent,
             None => return branch,
         };
 
         while self.line < indents.len() {
-            let &(indent, line) = match indents.get(self.line) {
-                Some(i) => i,
+            let line = match indents.get(self.line) {
+                Some(l) => l,
                 None => panic!("branching nothing!?"),
             };
 
+            let indent = line.indent;
+
             if indent == base_indent {
-                branch.content.push(Chunk::new(ChunkValue::Text(line)))
+                branch.content.push(
+                    Chunk::at(ChunkValue::Text(line.content), line.number as u32, indent as u32 + 1)
+                        .with_trivia(line.trivia.clone())
+                )
             } else if indent < base_indent {
                 self.line -= 1;
                 return branch;
@@ -124,3 +228,38 @@ impl<'a> BlockTree<'a> {
         branch
     }
 }
+
+/// Gives out the comments collected since the last line of code: the ones indented past the
+/// next line belong after the last line, the rest before the next one.
+fn attach<'a>(indents: &mut Vec<Line<'a>>, trivia: &mut Trivia<'a>, comments: &mut Vec<(usize, &'a str)>, next: usize) {
+    for (indent, c) in comments.drain(..) {
+        match indents.last_mut() {
+            Some(l) if indent > next && trivia.leading.is_empty() => l.trivia.after.push(c),
+            _                                                     => trivia.leading.push(c),
+        }
+    }
+}
+
+/// Splits a line into its code and the comment after it, if any; a `#` within a string
+/// doesn't start a comment.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut quote = None;
+
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'')   => quote = Some(c),
+            (Some(q), _) if q == c       => quote = None,
+            (None, '#')                  => return (&line[.. i], Some(line[i ..].trim_right())),
+            _                            => (),
+        }
+    }
+
+    (line, None)
+}
+
+fn describe_indent(c: char) -> &'static str {
+    match c {
+        '\t' => "tabs",
+        _ => "spaces",
+    }
+}
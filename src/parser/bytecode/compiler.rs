@@ -0,0 +1,1325 @@
+use std::mem;
+use std::collections::HashMap;
+
+use super::{Op, Constant, Type, Chunk, Function, Class, Program};
+use super::super::ast::Position;
+use super::super::ir::{self, Path, Expression, Node, Statement, Arm, Item, Field};
+use super::super::token::Operator;
+use super::super::diagnostic::{Diagnostic, code};
+use super::super::interpreter::builtin;
+use super::super::interpreter::value::Enum;
+
+/// What the path of something a program defines stands for.
+#[derive(Debug, Clone, Copy)]
+enum Symbol {
+    Global(u32),
+    Function(u32),
+    Class(u32),
+    Enum(u32),
+}
+
+/// A function waiting to be compiled once every name of the program is known.
+struct Job {
+    index:    u32,
+    name:     String,
+    params:   Vec<(String, ir::Type)>,
+    body:     Vec<Statement>,
+    /// The fields of a class, given to the object being constructed before the body runs.
+    fields:   Vec<Field>,
+    /// Whether it is the `main` of the program, which gives back what it returns as it is,
+    /// like the interpreter.
+    main:     bool,
+    /// Where the function is defined, which is where its parameters are converted.
+    position: Position,
+}
+
+/// The function being compiled.
+struct State {
+    name:   String,
+    chunk:  Chunk,
+    /// The locals in sight, each with the slot it lives in.
+    scopes: Vec<HashMap<String, u32>>,
+    locals: u32,
+    main:   bool,
+    /// The jumps out of the loops around, for `break` and `continue`, to point once the
+    /// loops are compiled.
+    loops:  Vec<(Vec<usize>, Vec<usize>)>,
+}
+
+/// Compiles the lowered files of a program into one `Program`, resolving every path when
+/// compiling so the VM only deals with slots.
+pub struct Compiler {
+    constants:  Vec<Constant>,
+    types:      Vec<Type>,
+    functions:  Vec<Option<Function>>,
+    classes:    Vec<Class>,
+    /// The methods of each class by name, sorted into it once every implementation is known.
+    methods:    Vec<HashMap<String, u32>>,
+    enums:      Vec<Enum>,
+    /// The types of the fields of the first variant of each enum, which a variable of it
+    /// starts out as.
+    firsts:     Vec<Vec<ir::Type>>,
+    globals:    Vec<String>,
+    symbols:    HashMap<Path, Symbol>,
+
+    jobs:       Vec<(usize, Job)>,
+    /// The globals given their values, in the order they are run, each with the file it is
+    /// in.
+    init:       Vec<(usize, Path, ir::Type, Option<Expression>, Position)>,
+    parents:    Vec<(usize, u32, Path, Position)>,
+    implements: Vec<(usize, Path, Vec<ir::Function>, Position)>,
+
+    states:     Vec<State>,
+    /// The file being gone through, as the number of files loaded before it.
+    unit:       usize,
+    position:   Position,
+}
+
+impl Compiler {
+    pub fn new() -> Compiler {
+        Compiler {
+            constants:  Vec::new(),
+            types:      Vec::new(),
+            functions:  Vec::new(),
+            classes:    Vec::new(),
+            methods:    Vec::new(),
+            enums:      Vec::new(),
+            firsts:     Vec::new(),
+            globals:    Vec::new(),
+            symbols:    HashMap::new(),
+
+            jobs:       Vec::new(),
+            init:       Vec::new(),
+            parents:    Vec::new(),
+            implements: Vec::new(),
+
+            states:     Vec::new(),
+            unit:       0,
+            position:   (0, 0),
+        }
+    }
+
+    /// Makes the definitions of a lowered file known; files are loaded after the files they
+    /// import, like the interpreter loads them.
+    pub fn load(&mut self, items: &[Item]) {
+        self.define(items);
+
+        self.unit += 1
+    }
+
+    /// Compiles every file loaded, giving the file a mistake is in, as the number of files
+    /// loaded before it, along with the mistake.
+    pub fn compile(mut self) -> Result<Program, (usize, Diagnostic)> {
+        match self.program() {
+            Ok(p)  => Ok(p),
+            Err(d) => Err((self.unit, d)),
+        }
+    }
+
+    fn unsupported(&self, message: String) -> Diagnostic {
+        let (line, col) = self.position;
+
+        Diagnostic::error(code::UNSUPPORTED, message).at(line, col, 0)
+    }
+
+    fn undefined(&self, name: &str) -> Diagnostic {
+        let (line, col) = self.position;
+
+        Diagnostic::error(code::UNDEFINED, format!("undefined: {}", name)).at(line, col, 0)
+    }
+
+    fn state(&mut self) -> &mut State {
+        self.states.last_mut().unwrap()
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        let line = self.position.0;
+
+        self.state().chunk.write(op, line)
+    }
+
+    fn here(&mut self) -> u32 {
+        self.state().chunk.code.len() as u32
+    }
+
+    /// Points a jump at the instruction written next.
+    fn patch(&mut self, at: usize) {
+        let here = self.here();
+
+        match self.state().chunk.code[at] {
+            Op::Jump(ref mut to) | Op::JumpUnless(ref mut to) => *to = here,
+            _                                                 => (),
+        }
+    }
+
+    fn constant(&mut self, c: Constant) -> u32 {
+        // floats are told apart by their bits, so `-0.0` isn't taken for `0.0`
+        let found = self.constants.iter().position(|k| match (k, &c) {
+            (&Constant::Float(a), &Constant::Float(b)) => a.to_bits() == b.to_bits(),
+            (k, c)                                     => k == c,
+        });
+
+        match found {
+            Some(i) => i as u32,
+            None    => {
+                self.constants.push(c);
+
+                self.constants.len() as u32 - 1
+            }
+        }
+    }
+
+    fn text(&mut self, name: &str) -> u32 {
+        self.constant(Constant::Text(name.to_owned()))
+    }
+
+    /// A function to be compiled later, for calls to it to be compiled before.
+    fn slot(&mut self) -> u32 {
+        self.functions.push(None);
+
+        self.functions.len() as u32 - 1
+    }
+
+    /// Makes the paths a file defines known, like the interpreter defines them.
+    fn define(&mut self, items: &[Item]) {
+        for i in items.iter() {
+            match *i {
+                Item::Global(ref p, ref t, ref e, position) => {
+                    if let None = self.symbols.get(p) {
+                        self.globals.push(p.to_string());
+
+                        let g = self.globals.len() as u32 - 1;
+
+                        self.symbols.insert(p.clone(), Symbol::Global(g));
+                    }
+
+                    self.init.push((self.unit, p.clone(), t.clone(), e.clone(), position))
+                }
+
+                Item::Function(ref f) => {
+                    if let Some(ref body) = f.body {
+                        let index = self.slot();
+
+                        self.symbols.insert(f.path.clone(), Symbol::Function(index));
+
+                        let job = Job {
+                            index:    index,
+                            name:     f.name().to_owned(),
+                            params:   f.params.clone(),
+                            body:     body.clone(),
+                            fields:   Vec::new(),
+                            main:     f.path == Path::new(&["main"]),
+                            position: f.position,
+                        };
+
+                        self.jobs.push((self.unit, job))
+                    }
+                }
+
+                Item::Module(_, ref c, _) => self.define(c),
+
+                Item::Class(ref c) => {
+                    let class = self.classes.len() as u32;
+
+                    let mut methods = HashMap::new();
+
+                    for m in c.methods.iter() {
+                        if let Some(index) = self.method(m) {
+                            methods.insert(m.name().to_owned(), index);
+                        }
+                    }
+
+                    // the fields are given their values by a function of their own, run on
+                    // the object being constructed
+                    let initializer = if c.fields.is_empty() {
+                        None
+                    } else {
+                        let index = self.slot();
+
+                        let job = Job {
+                            index:    index,
+                            name:     format!("<fields of {}>", c.path.name()),
+                            params:   Vec::new(),
+                            body:     Vec::new(),
+                            fields:   c.fields.clone(),
+                            main:     false,
+                            position: c.position,
+                        };
+
+                        self.jobs.push((self.unit, job));
+
+                        Some(index)
+                    };
+
+                    self.classes.push(Class {
+                        name:    c.path.name().to_owned(),
+                        parent:  None,
+                        fields:  initializer,
+                        methods: Vec::new(),
+                    });
+
+                    self.methods.push(methods);
+
+                    self.symbols.insert(c.path.clone(), Symbol::Class(class));
+
+                    if let Some(ir::Type::Object(ref p, _)) = c.parent {
+                        self.parents.push((self.unit, class, p.path(), c.position))
+                    }
+                }
+
+                Item::Enum(ref p, ref v, _) => {
+                    self.enums.push(Enum {
+                        name:     p.name().to_owned(),
+                        variants: v.iter().map(|v| {
+                            (v.name.clone(), v.fields.iter().map(|&(ref f, ref t)| (t.to_string(), f.clone())).collect())
+                        }).collect(),
+                    });
+
+                    self.firsts.push(v.first().map_or(Vec::new(), |v| v.fields.iter().map(|&(_, ref t)| t.clone()).collect()));
+
+                    let e = self.enums.len() as u32 - 1;
+
+                    self.symbols.insert(p.clone(), Symbol::Enum(e));
+                }
+
+                Item::Implement(ref p, _, ref c, position) => {
+                    self.implements.push((self.unit, p.clone(), c.clone(), position))
+                }
+
+                // every name is already resolved to what it refers to, and imports are
+                // resolved by the loader
+                Item::Use(_, _) | Item::Import(_, _, _) => (),
+            }
+        }
+    }
+
+    /// A method to be compiled later, or none for one only declared.
+    fn method(&mut self, m: &ir::Function) -> Option<u32> {
+        let body = match m.body {
+            Some(ref b) => b.clone(),
+            None        => return None,
+        };
+
+        let index = self.slot();
+
+        let job = Job {
+            index:    index,
+            name:     m.name().to_owned(),
+            params:   m.params.clone(),
+            body:     body,
+            fields:   Vec::new(),
+            main:     false,
+            position: m.position,
+        };
+
+        self.jobs.push((self.unit, job));
+
+        Some(index)
+    }
+
+    /// Resolves what the definitions of the program refer to once all of them are known:
+    /// the classes inherited from and the classes implemented.
+    fn link(&mut self) -> Result<(), Diagnostic> {
+        for (unit, class, p, position) in mem::replace(&mut self.parents, Vec::new()).into_iter() {
+            self.unit     = unit;
+            self.position = position;
+
+            match self.symbols.get(&p) {
+                Some(&Symbol::Class(parent)) => self.classes[class as usize].parent = Some(parent),
+
+                _ => return Err(self.unsupported(format!(
+                        "class {} inherits from {}, which is not a class",
+                        self.classes[class as usize].name, p,
+                    ))),
+            }
+        }
+
+        for (unit, p, methods, position) in mem::replace(&mut self.implements, Vec::new()).into_iter() {
+            self.unit     = unit;
+            self.position = position;
+
+            let class = match self.symbols.get(&p) {
+                Some(&Symbol::Class(c)) => c,
+                _                       => return Err(self.unsupported(format!("implementation of undeclared class: {}", p))),
+            };
+
+            for m in methods.iter() {
+                if let Some(index) = self.method(m) {
+                    self.methods[class as usize].insert(m.name().to_owned(), index);
+                }
+            }
+        }
+
+        for (c, methods) in self.methods.iter().enumerate() {
+            let mut methods: Vec<(String, u32)> = methods.iter().map(|(n, &f)| (n.clone(), f)).collect();
+
+            methods.sort();
+
+            self.classes[c].methods = methods
+        }
+
+        Ok(())
+    }
+
+    fn program(&mut self) -> Result<Program, Diagnostic> {
+        try!(self.link());
+
+        let init = self.slot();
+
+        try!(self.globals(init));
+
+        for (unit, job) in mem::replace(&mut self.jobs, Vec::new()).into_iter() {
+            self.unit = unit;
+
+            try!(self.function(job, &[]))
+        }
+
+        let main = match self.symbols.get(&Path::new(&["main"])) {
+            Some(&Symbol::Function(f)) => Some(f),
+            _                          => None,
+        };
+
+        Ok(Program {
+            constants: mem::replace(&mut self.constants, Vec::new()),
+            types:     mem::replace(&mut self.types, Vec::new()),
+            functions: self.functions.drain(..).map(Option::unwrap).collect(),
+            classes:   mem::replace(&mut self.classes, Vec::new()),
+            enums:     mem::replace(&mut self.enums, Vec::new()),
+            globals:   mem::replace(&mut self.globals, Vec::new()),
+            init:      init,
+            main:      main,
+        })
+    }
+
+    /// The function giving every global its value, in the order they were declared.
+    fn globals(&mut self, index: u32) -> Result<(), Diagnostic> {
+        self.states.push(State::new("<globals>".to_owned(), false));
+
+        for (unit, p, t, e, position) in mem::replace(&mut self.init, Vec::new()).into_iter() {
+            self.unit     = unit;
+            self.position = position;
+
+            try!(self.initial(&t, e.as_ref()));
+
+            if let Some(&Symbol::Global(g)) = self.symbols.get(&p) {
+                self.emit(Op::SetGlobal(g));
+            }
+        }
+
+        self.emit(Op::Void);
+        self.emit(Op::Return);
+
+        let state = self.states.pop().unwrap();
+
+        self.functions[index as usize] = Some(state.finish(0, 0));
+
+        Ok(())
+    }
+
+    /// Compiles a function; a lambda has the locals it captures in its first slots.
+    fn function(&mut self, job: Job, captured: &[String]) -> Result<(), Diagnostic> {
+        let mut state = State::new(job.name.clone(), job.main);
+
+        for n in captured.iter().chain(job.params.iter().map(|&(ref n, _)| n)) {
+            let slot = state.locals;
+
+            state.scopes[0].insert(n.clone(), slot);
+            state.locals += 1;
+        }
+
+        self.states.push(state);
+
+        self.position = job.position;
+
+        // arguments are converted to the types of their parameters
+        for (i, &(_, ref t)) in job.params.iter().enumerate() {
+            if let Some(t) = converted(t) {
+                let slot = (captured.len() + i) as u32;
+                let t    = self.text(&t);
+
+                self.emit(Op::Local(slot));
+                self.emit(Op::Convert(t));
+                self.emit(Op::SetLocal(slot));
+            }
+        }
+
+        let mut result = Ok(());
+
+        for f in job.fields.iter() {
+            result = self.initialize(f);
+
+            if result.is_err() {
+                break
+            }
+        }
+
+        if result.is_ok() {
+            result = self.statements(&job.body);
+        }
+
+        self.emit(Op::Void);
+        self.emit(Op::Return);
+
+        let state = self.states.pop().unwrap();
+
+        try!(result);
+
+        self.functions[job.index as usize] = Some(state.finish(job.params.len() as u32, captured.len() as u32));
+
+        Ok(())
+    }
+
+    /// Gives the object being constructed a field, with the value it starts out with.
+    fn initialize(&mut self, f: &Field) -> Result<(), Diagnostic> {
+        self.position = f.position;
+
+        try!(self.initial(&f.ty, f.value.as_ref()));
+
+        let name = self.text(&f.name);
+
+        self.emit(Op::Field(name));
+
+        Ok(())
+    }
+
+    /// Pushes the value a variable starts out with: a copy of the one given, or the one a
+    /// variable of its type is made with.
+    fn initial(&mut self, t: &ir::Type, value: Option<&Expression>) -> Result<(), Diagnostic> {
+        let value = match value {
+            Some(v) => v,
+
+            None => {
+                let t = try!(self.kind(t));
+
+                self.emit(Op::Default(t));
+
+                return Ok(())
+            }
+        };
+
+        try!(self.expression(value));
+
+        self.emit(Op::Copy);
+
+        // an empty map learns what its missing keys read as from its type
+        if let ir::Type::Map(_, ref v) = *t {
+            if let Ok(zero) = self.kind(v) {
+                self.emit(Op::Zero(zero));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The type a type of the IR stands for, as an index into the types of the program.
+    fn kind(&mut self, t: &ir::Type) -> Result<u32, Diagnostic> {
+        let t = try!(self.type_of(t));
+
+        match self.types.iter().position(|k| *k == t) {
+            Some(i) => Ok(i as u32),
+            None    => {
+                self.types.push(t);
+
+                Ok(self.types.len() as u32 - 1)
+            }
+        }
+    }
+
+    fn type_of(&mut self, t: &ir::Type) -> Result<Type, Diagnostic> {
+        match *t {
+            ir::Type::Int | ir::Type::Char | ir::Type::Long => Ok(Type::Int),
+            ir::Type::Float | ir::Type::Double              => Ok(Type::Float),
+            ir::Type::Bool                                  => Ok(Type::Bool),
+            ir::Type::Text                                  => Ok(Type::Text),
+            ir::Type::List(_)                               => Ok(Type::List),
+            ir::Type::Function(_, _) | ir::Type::Void       => Ok(Type::Void),
+
+            ir::Type::Map(_, ref v) => Ok(Type::Map(Box::new(try!(self.type_of(v))))),
+
+            ir::Type::Object(ref c, _) => match self.symbols.get(&c.path()) {
+                Some(&Symbol::Class(c)) => Ok(Type::Object(c)),
+                _                       => Err(self.undefined(&c.path().to_string())),
+            },
+
+            ir::Type::Enum(ref e) => {
+                let e = match self.symbols.get(&e.path()) {
+                    Some(&Symbol::Enum(e)) => e,
+                    _                      => return Err(self.undefined(&e.path().to_string())),
+                };
+
+                let fields = self.firsts[e as usize].clone();
+
+                let mut types = Vec::new();
+
+                for t in fields.iter() {
+                    types.push(try!(self.type_of(t)))
+                }
+
+                Ok(Type::Variant(e, types))
+            }
+
+            ir::Type::Parameter(ref n) => Err(self.unsupported(format!("the type parameter `{}` is only known when running", n))),
+
+            ref t => Err(Diagnostic::error(code::TYPE, format!("{} is not a type", t)).at(self.position.0, self.position.1, 0)),
+        }
+    }
+
+    /// Compiles statements nested in another, with locals of their own.
+    fn nested(&mut self, body: &[Statement]) -> Result<(), Diagnostic> {
+        self.state().scopes.push(HashMap::new());
+
+        let result = self.statements(body);
+
+        self.state().scopes.pop();
+
+        result
+    }
+
+    fn statements(&mut self, body: &[Statement]) -> Result<(), Diagnostic> {
+        for s in body.iter() {
+            try!(self.statement(s))
+        }
+
+        Ok(())
+    }
+
+    fn declare(&mut self, name: &str) -> u32 {
+        let slot = self.hidden();
+
+        self.state().scopes.last_mut().unwrap().insert(name.to_owned(), slot);
+
+        slot
+    }
+
+    /// A local without a name, which holds what a loop or a `match` keeps track of.
+    fn hidden(&mut self) -> u32 {
+        let state = self.state();
+
+        state.locals += 1;
+        state.locals - 1
+    }
+
+    fn statement(&mut self, s: &Statement) -> Result<(), Diagnostic> {
+        self.position = s.position();
+
+        match *s {
+            Statement::Let(ref n, ref t, ref e, _) => {
+                try!(self.initial(t, e.as_ref()));
+
+                let slot = self.declare(n);
+
+                self.emit(Op::SetLocal(slot));
+            }
+
+            Statement::Assign(ref n, None, ref e, _) => {
+                try!(self.expression(e));
+
+                self.emit(Op::Copy);
+
+                try!(self.store(n))
+            }
+
+            Statement::Assign(ref n, Some(ref o), ref e, _) => {
+                try!(self.expression(n));
+                try!(self.expression(e));
+
+                self.emit(Op::Binary(o.clone()));
+
+                try!(self.store(n))
+            }
+
+            Statement::Expression(ref e, _) => {
+                try!(self.expression(e));
+
+                self.emit(Op::Pop);
+            }
+
+            Statement::Return(ref v, _) => {
+                match *v {
+                    // what `main` returns is given back as it is, without the conversion to
+                    // the status C++ exits with
+                    Some(ref v) if self.states.last().unwrap().main => try!(self.expression(v.unconverted())),
+                    Some(ref v)                                     => try!(self.expression(v)),
+
+                    None => {
+                        self.emit(Op::Void);
+                    }
+                }
+
+                self.emit(Op::Copy);
+                self.emit(Op::Return);
+            }
+
+            Statement::If(ref e, ref c, ref otherwise, _) => {
+                try!(self.expression(e));
+
+                let next = self.emit(Op::JumpUnless(0));
+
+                try!(self.nested(c));
+
+                let end = self.emit(Op::Jump(0));
+
+                self.patch(next);
+
+                try!(self.nested(otherwise));
+
+                self.patch(end)
+            }
+
+            // what comes before the loop is a local of the loop alone
+            Statement::Loop(ref init, ref e, ref step, ref c, _) => {
+                self.state().scopes.push(HashMap::new());
+
+                let result = self.looped(init, e, step, c);
+
+                self.state().scopes.pop();
+
+                try!(result)
+            }
+
+            Statement::Each(ref i, _, ref e, ref c, _) => {
+                try!(self.expression(e));
+
+                self.emit(Op::Elements);
+
+                let items = self.hidden();
+                let index = self.hidden();
+                let zero  = self.constant(Constant::Integer(0));
+
+                self.emit(Op::SetLocal(items));
+                self.emit(Op::Constant(zero));
+                self.emit(Op::SetLocal(index));
+
+                let start = self.here();
+
+                self.emit(Op::Local(index));
+                self.emit(Op::Local(items));
+                self.emit(Op::Length);
+                self.emit(Op::Binary(Operator::Lt));
+
+                let exit = self.emit(Op::JumpUnless(0));
+
+                // each element is a copy, like C++ binds it with `auto`
+                let element = vec!(Op::Local(items), Op::Local(index), Op::Index, Op::Copy);
+
+                let (breaks, continues) = try!(self.body(Some((i, element)), c));
+
+                let step = self.here();
+
+                self.step(index);
+
+                self.emit(Op::Jump(start));
+
+                self.patch(exit);
+                self.exits(breaks, continues, step)
+            }
+
+            Statement::Match(ref e, ref arms, _) => try!(self.arms(e, arms)),
+
+            Statement::Break(_) | Statement::Continue(_) => {
+                let jump = self.emit(Op::Jump(0));
+
+                let breaking = match *s {
+                    Statement::Break(_) => true,
+                    _                   => false,
+                };
+
+                match self.state().loops.last_mut() {
+                    Some(&mut (ref mut b, _)) if breaking => b.push(jump),
+                    Some(&mut (_, ref mut c))             => c.push(jump),
+                    None                                  => return Err(self.unsupported("`break` or `continue` outside of a loop".to_owned())),
+                }
+            }
+
+            Statement::Use(_, _) => return Err(self.unsupported("`use` inside a function".to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// A loop run while its condition holds, with what comes before it and what is run after
+    /// each time through; a `continue` goes on with the latter.
+    fn looped(&mut self, init: &Option<Box<Statement>>, condition: &Expression, step: &Option<Box<Statement>>,
+              body: &[Statement]) -> Result<(), Diagnostic> {
+        if let Some(ref i) = *init {
+            try!(self.statement(i))
+        }
+
+        let start = self.here();
+
+        try!(self.expression(condition));
+
+        let exit = self.emit(Op::JumpUnless(0));
+
+        let (breaks, continues) = try!(self.body(None, body));
+
+        let next = self.here();
+
+        if let Some(ref s) = *step {
+            try!(self.statement(s))
+        }
+
+        self.emit(Op::Jump(start));
+
+        self.patch(exit);
+        self.exits(breaks, continues, next);
+
+        Ok(())
+    }
+
+    /// A `match`, as a cascade of tests on the variant matched; a variant no arm takes is a
+    /// mistake when running.
+    fn arms(&mut self, e: &Expression, arms: &[Arm]) -> Result<(), Diagnostic> {
+        try!(self.expression(e));
+
+        self.emit(Op::Subject);
+
+        let subject = self.hidden();
+
+        self.emit(Op::SetLocal(subject));
+
+        let mut ends     = Vec::new();
+        let mut wildcard = false;
+
+        for arm in arms.iter() {
+            self.position = arm.position;
+
+            let v = match arm.variant {
+                Some(ref v) => v,
+
+                None => {
+                    try!(self.nested(&arm.body));
+
+                    wildcard = true;
+
+                    break
+                }
+            };
+
+            let name = self.text(v);
+
+            self.emit(Op::Local(subject));
+            self.emit(Op::Is(name));
+
+            let next = self.emit(Op::JumpUnless(0));
+
+            self.state().scopes.push(HashMap::new());
+
+            // fields bound to `_` are taken out too, into locals without a name
+            let count = arm.bindings.iter().map(|&(i, _, _)| i + 1).max().unwrap_or(0);
+
+            let slots: Vec<u32> = (0 .. count).map(|i| match arm.bindings.iter().find(|&&(j, _, _)| j == i) {
+                Some(&(_, ref n, _)) => self.declare(n),
+                None                 => self.hidden(),
+            }).collect();
+
+            self.emit(Op::Local(subject));
+            self.emit(Op::Unpack(slots.len() as u32));
+
+            for &s in slots.iter().rev() {
+                self.emit(Op::SetLocal(s));
+            }
+
+            let result = self.statements(&arm.body);
+
+            self.state().scopes.pop();
+
+            try!(result);
+
+            ends.push(self.emit(Op::Jump(0)));
+
+            self.patch(next)
+        }
+
+        if !wildcard {
+            self.emit(Op::Local(subject));
+            self.emit(Op::Unmatched);
+        }
+
+        for j in ends.into_iter() {
+            self.patch(j)
+        }
+
+        Ok(())
+    }
+
+    /// Compiles the body of a loop, with the loop variable, if it has one, given the value the
+    /// instructions along with it push. Gives the jumps of the `break`s and `continue`s in it.
+    fn body(&mut self, variable: Option<(&String, Vec<Op>)>, body: &[Statement]) -> Result<(Vec<usize>, Vec<usize>), Diagnostic> {
+        self.state().scopes.push(HashMap::new());
+        self.state().loops.push((Vec::new(), Vec::new()));
+
+        if let Some((name, value)) = variable {
+            let slot = self.declare(name);
+
+            for op in value.into_iter() {
+                self.emit(op);
+            }
+
+            self.emit(Op::SetLocal(slot));
+        }
+
+        let result = self.statements(body);
+
+        let jumps = self.state().loops.pop().unwrap();
+
+        self.state().scopes.pop();
+
+        try!(result);
+
+        Ok(jumps)
+    }
+
+    /// Adds one to the counter of a loop.
+    fn step(&mut self, counter: u32) {
+        let one = self.constant(Constant::Integer(1));
+
+        self.emit(Op::Local(counter));
+        self.emit(Op::Constant(one));
+        self.emit(Op::Binary(Operator::Plus));
+        self.emit(Op::SetLocal(counter));
+    }
+
+    /// Points the `break`s of a loop after it, and its `continue`s at where it goes on.
+    fn exits(&mut self, breaks: Vec<usize>, continues: Vec<usize>, next: u32) {
+        for b in breaks.into_iter() {
+            self.patch(b)
+        }
+
+        for c in continues.into_iter() {
+            self.state().chunk.code[c] = Op::Jump(next)
+        }
+    }
+
+    /// The slot of a local in sight.
+    fn local(&self, name: &str) -> Option<u32> {
+        let state = self.states.last().unwrap();
+
+        for scope in state.scopes.iter().rev() {
+            if let Some(&s) = scope.get(name) {
+                return Some(s)
+            }
+        }
+
+        None
+    }
+
+    /// Assigns the value on top to what an assignment target denotes.
+    fn store(&mut self, target: &Expression) -> Result<(), Diagnostic> {
+        match target.node {
+            Node::Local(ref n) => match self.local(n) {
+                Some(s) => {
+                    self.emit(Op::SetLocal(s));
+                }
+
+                None => return Err(self.unsupported(format!("assignment to undeclared variable: {}", n))),
+            },
+
+            Node::Global(ref p) => match self.symbols.get(p).cloned() {
+                Some(Symbol::Global(g)) => {
+                    self.emit(Op::SetGlobal(g));
+                }
+
+                _ => return Err(self.unsupported(format!("assignment to undeclared variable: {}", p))),
+            },
+
+            Node::Field(ref o, _, ref n) | Node::Member(ref o, ref n) => {
+                let name = self.text(n);
+
+                try!(self.expression(o));
+
+                self.emit(Op::SetMember(name));
+            }
+
+            Node::Index(ref a, ref i) => {
+                try!(self.expression(a));
+                try!(self.expression(i));
+
+                self.emit(Op::SetIndex);
+            }
+
+            // converting the place stored to changes nothing of where it is
+            Node::Convert(ref e) => try!(self.store(e)),
+
+            ref n => return Err(self.unsupported(format!("invalid assignment target: {:?}", n))),
+        }
+
+        Ok(())
+    }
+
+    /// Pushes a function by its path, or a function of the VM itself for one only declared,
+    /// like `puts`.
+    fn callee(&mut self, path: &Path) -> Result<(), Diagnostic> {
+        match self.symbols.get(path).cloned() {
+            Some(Symbol::Function(f)) => {
+                self.emit(Op::Function(f));
+            }
+
+            _ => try!(self.foreign(path.name())),
+        }
+
+        Ok(())
+    }
+
+    /// Pushes a name nothing in the program defines, which is a function of the VM itself.
+    fn foreign(&mut self, name: &str) -> Result<(), Diagnostic> {
+        if name == "void" {
+            self.emit(Op::Void);
+
+            return Ok(())
+        }
+
+        match builtin::lookup(name) {
+            Some(_) => {
+                let c = self.constant(Constant::Builtin(name.to_owned()));
+
+                self.emit(Op::Constant(c));
+
+                Ok(())
+            }
+
+            None => Err(self.undefined(name)),
+        }
+    }
+
+    fn expression(&mut self, ex: &Expression) -> Result<(), Diagnostic> {
+        match ex.node {
+            Node::Integer(i) => {
+                let c = self.constant(Constant::Integer(i));
+
+                self.emit(Op::Constant(c));
+            }
+
+            Node::Float(f) => {
+                let c = self.constant(Constant::Float(f));
+
+                self.emit(Op::Constant(c));
+            }
+
+            Node::Boolean(b) => {
+                let c = self.constant(Constant::Boolean(b));
+
+                self.emit(Op::Constant(c));
+            }
+
+            Node::Text(ref t) => {
+                let c = self.constant(Constant::Text(builtin::unescape(t)));
+
+                self.emit(Op::Constant(c));
+            }
+
+            Node::Local(ref n) => match self.local(n) {
+                Some(s) => {
+                    self.emit(Op::Local(s));
+                }
+
+                None => return Err(self.undefined(n)),
+            },
+
+            Node::Global(ref p) => match self.symbols.get(p).cloned() {
+                Some(Symbol::Global(g)) => {
+                    self.emit(Op::Global(g));
+                }
+
+                _ => return Err(self.undefined(&p.to_string())),
+            },
+
+            Node::Function(ref p) => try!(self.callee(p)),
+
+            Node::Foreign(ref n) => try!(self.foreign(n)),
+
+            Node::This => {
+                self.emit(Op::This);
+            }
+
+            Node::Field(ref o, _, ref n) | Node::Member(ref o, ref n) => {
+                let name = self.text(n);
+
+                try!(self.expression(o));
+
+                self.emit(Op::Member(name));
+            }
+
+            Node::Variant(ref p, ref v, ref a) => {
+                let (e, v) = try!(self.variant(p, v));
+                let n      = try!(self.arguments(a));
+
+                self.emit(Op::Variant(e, v, n));
+            }
+
+            Node::Binary(ref l, Operator::And, ref r) => {
+                try!(self.expression(l));
+
+                let short = self.emit(Op::JumpUnless(0));
+
+                try!(self.expression(r));
+
+                self.emit(Op::Truth);
+
+                let end    = self.emit(Op::Jump(0));
+                let false_ = self.constant(Constant::Boolean(false));
+
+                self.patch(short);
+                self.emit(Op::Constant(false_));
+                self.patch(end)
+            }
+
+            Node::Binary(ref l, Operator::Or, ref r) => {
+                try!(self.expression(l));
+
+                let rest  = self.emit(Op::JumpUnless(0));
+                let true_ = self.constant(Constant::Boolean(true));
+
+                self.emit(Op::Constant(true_));
+
+                let end = self.emit(Op::Jump(0));
+
+                self.patch(rest);
+
+                try!(self.expression(r));
+
+                self.emit(Op::Truth);
+                self.patch(end)
+            }
+
+            Node::Binary(ref l, ref o, ref r) => {
+                try!(self.expression(l));
+                try!(self.expression(r));
+
+                self.emit(Op::Binary(o.clone()));
+            }
+
+            Node::Unary(Operator::Not, ref e) => {
+                try!(self.expression(e));
+
+                self.emit(Op::Not);
+            }
+
+            Node::Unary(ref o, ref e) => {
+                try!(self.expression(e));
+
+                self.emit(Op::Unary(o.clone()));
+            }
+
+            Node::Convert(ref e) => {
+                try!(self.expression(e));
+
+                if let Some(t) = converted(&ex.ty) {
+                    let t = self.text(&t);
+
+                    self.emit(Op::Convert(t));
+                }
+            }
+
+            Node::Index(ref a, ref i) => {
+                try!(self.expression(a));
+                try!(self.expression(i));
+
+                self.emit(Op::Index);
+            }
+
+            Node::Call(ref p, ref g, ref a) => {
+                if !g.is_empty() {
+                    return Err(self.unsupported(format!("`{}` is given its type arguments, which only the interpreter binds", p)))
+                }
+
+                match self.symbols.get(p).cloned() {
+                    Some(Symbol::Function(f)) => {
+                        let n = try!(self.arguments(a));
+
+                        self.emit(Op::CallFunction(f, n));
+                    }
+
+                    _ => {
+                        try!(self.callee(p));
+
+                        let n = try!(self.arguments(a));
+
+                        self.emit(Op::Call(n));
+                    }
+                }
+            }
+
+            Node::New(ref p, _, ref a) => match self.symbols.get(p).cloned() {
+                Some(Symbol::Class(c)) => {
+                    let n = try!(self.arguments(a));
+
+                    self.emit(Op::New(c, n));
+                }
+
+                _ => return Err(self.undefined(&p.to_string())),
+            },
+
+            Node::Method(ref o, _, ref m, ref a) | Node::Invoke(ref o, ref m, ref a) => {
+                let name = self.text(m);
+
+                try!(self.expression(o));
+
+                let n = try!(self.arguments(a));
+
+                self.emit(Op::Invoke(name, n));
+            }
+
+            Node::Apply(ref f, ref a) => {
+                try!(self.expression(f));
+
+                let n = try!(self.arguments(a));
+
+                self.emit(Op::Call(n));
+            }
+
+            Node::List(ref e) => {
+                let n = try!(self.arguments(e));
+
+                self.emit(Op::List(n));
+            }
+
+            Node::Map(ref e) => {
+                for &(ref k, ref v) in e.iter() {
+                    try!(self.expression(k));
+                    try!(self.expression(v));
+                }
+
+                self.emit(Op::Map(e.len() as u32));
+            }
+
+            // the arrays are made by their sizes in the order written, like the interpreter
+            // makes them, out of copies of the value a variable of the type of their elements
+            // starts out with
+            Node::Array(ref sizes) => {
+                let mut element = &ex.ty;
+
+                for _ in sizes.iter() {
+                    if let ir::Type::Array(ref t) = *element {
+                        element = t
+                    }
+                }
+
+                let t = try!(self.kind(element));
+
+                self.emit(Op::Default(t));
+
+                // each array holds copies of the one made before it, so they are made from
+                // the last size written, the innermost one, outward
+                for s in sizes.iter().rev() {
+                    try!(self.expression(s));
+
+                    self.emit(Op::Array);
+                }
+            }
+
+            Node::Lambda(ref a, ref c) => try!(self.lambda(a, c)),
+        }
+
+        Ok(())
+    }
+
+    fn arguments(&mut self, args: &[Expression]) -> Result<u32, Diagnostic> {
+        for a in args.iter() {
+            try!(self.expression(a))
+        }
+
+        Ok(args.len() as u32)
+    }
+
+    /// The enum of a path and the place of one of its variants.
+    fn variant(&self, path: &Path, name: &str) -> Result<(u32, u32), Diagnostic> {
+        let e = match self.symbols.get(path) {
+            Some(&Symbol::Enum(e)) => e,
+            _                      => return Err(self.undefined(&path.to_string())),
+        };
+
+        match self.enums[e as usize].variants.iter().position(|&(ref v, _)| v == name) {
+            Some(v) => Ok((e, v as u32)),
+            None    => Err(self.undefined(&path.child(name).to_string())),
+        }
+    }
+
+    /// Compiles a lambda, which captures copies of every local in sight, like `[=]`.
+    fn lambda(&mut self, params: &[(String, ir::Type)], body: &[Statement]) -> Result<(), Diagnostic> {
+        let captured = {
+            let state = self.states.last().unwrap();
+
+            let mut visible = HashMap::new();
+
+            for scope in state.scopes.iter() {
+                for (n, &s) in scope.iter() {
+                    visible.insert(n.clone(), s);
+                }
+            }
+
+            let mut captured: Vec<(String, u32)> = visible.into_iter().collect();
+
+            captured.sort_by_key(|&(_, s)| s);
+
+            captured
+        };
+
+        let index = self.slot();
+
+        let job = Job {
+            index:    index,
+            name:     "lambda".to_owned(),
+            params:   params.to_vec(),
+            body:     body.to_vec(),
+            fields:   Vec::new(),
+            main:     false,
+            position: self.position,
+        };
+
+        let names: Vec<String> = captured.iter().map(|&(ref n, _)| n.clone()).collect();
+
+        let position = self.position;
+
+        try!(self.function(job, &names));
+
+        self.position = position;
+
+        for &(_, s) in captured.iter() {
+            self.emit(Op::Local(s));
+        }
+
+        self.emit(Op::Closure(index, captured.len() as u32));
+
+        Ok(())
+    }
+}
+
+impl State {
+    fn new(name: String, main: bool) -> State {
+        State {
+            name:   name,
+            chunk:  Chunk::new(),
+            scopes: vec!(HashMap::new()),
+            locals: 0,
+            main:   main,
+            loops:  Vec::new(),
+        }
+    }
+
+    fn finish(self, arity: u32, captures: u32) -> Function {
+        Function {
+            name:     self.name,
+            arity:    arity,
+            captures: captures,
+            locals:   self.locals,
+            chunk:    self.chunk,
+        }
+    }
+}
+
+/// The name values are converted to a type by, for types C++ converts values to when
+/// binding them: numbers, `bool` and classes, which objects are sliced to.
+fn converted(t: &ir::Type) -> Option<String> {
+    match *t {
+        ir::Type::Int    => Some("int".to_owned()),
+        ir::Type::Long   => Some("long".to_owned()),
+        ir::Type::Float  => Some("float".to_owned()),
+        ir::Type::Double => Some("double".to_owned()),
+        ir::Type::Bool   => Some("bool".to_owned()),
+
+        ir::Type::Object(ref c, _) => Some(c.path().to_string()),
+
+        _ => None,
+    }
+}
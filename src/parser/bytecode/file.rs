@@ -0,0 +1,734 @@
+use super::{Op, Constant, Type, Chunk, Function, Class, Program};
+use super::super::token::Operator;
+use super::super::interpreter::value::Enum;
+
+/// What every `.hxc` file starts with, followed by the version of the format.
+const MAGIC:   &'static [u8] = b"HXC\0";
+const VERSION: u32           = 1;
+
+/// The operators in the order `operator` numbers them.
+const OPERATORS: [Operator; 20] = [
+    Operator::Mul, Operator::Div, Operator::Mod, Operator::Plus, Operator::Minus,
+    Operator::Equal, Operator::NEqual, Operator::Lt, Operator::LtEqual, Operator::Gt,
+    Operator::GtEqual, Operator::And, Operator::Or, Operator::Not, Operator::BitAnd,
+    Operator::BitOr, Operator::BitXor, Operator::BitNot, Operator::Shl, Operator::Shr,
+];
+
+/// Writes a program the way a `.hxc` file holds it, with every number little-endian.
+pub fn write(program: &Program) -> Vec<u8> {
+    let mut out = Writer { bytes: MAGIC.to_vec() };
+
+    out.u32(VERSION);
+
+    out.u32(program.constants.len() as u32);
+
+    for c in program.constants.iter() {
+        match *c {
+            Constant::Integer(i)     => { out.u8(0); out.u64(i as u64) }
+            Constant::Float(f)       => { out.u8(1); out.u64(f.to_bits()) }
+            Constant::Boolean(b)     => { out.u8(2); out.u8(b as u8) }
+            Constant::Text(ref t)    => { out.u8(3); out.text(t) }
+            Constant::Builtin(ref n) => { out.u8(4); out.text(n) }
+        }
+    }
+
+    out.u32(program.types.len() as u32);
+
+    for t in program.types.iter() {
+        out.kind(t)
+    }
+
+    out.u32(program.functions.len() as u32);
+
+    for f in program.functions.iter() {
+        out.text(&f.name);
+        out.u32(f.arity);
+        out.u32(f.captures);
+        out.u32(f.locals);
+
+        out.u32(f.chunk.code.len() as u32);
+
+        for (op, &line) in f.chunk.code.iter().zip(f.chunk.lines.iter()) {
+            out.u32(line);
+            out.op(op)
+        }
+    }
+
+    out.u32(program.classes.len() as u32);
+
+    for c in program.classes.iter() {
+        out.text(&c.name);
+        out.option(c.parent);
+        out.option(c.fields);
+
+        out.u32(c.methods.len() as u32);
+
+        for &(ref n, f) in c.methods.iter() {
+            out.text(n);
+            out.u32(f)
+        }
+    }
+
+    out.u32(program.enums.len() as u32);
+
+    for e in program.enums.iter() {
+        out.text(&e.name);
+
+        out.u32(e.variants.len() as u32);
+
+        for &(ref n, ref f) in e.variants.iter() {
+            out.text(n);
+
+            out.u32(f.len() as u32);
+
+            for &(ref t, ref n) in f.iter() {
+                out.text(t);
+                out.text(n)
+            }
+        }
+    }
+
+    out.u32(program.globals.len() as u32);
+
+    for g in program.globals.iter() {
+        out.text(g)
+    }
+
+    out.u32(program.init);
+    out.option(program.main);
+
+    out.bytes
+}
+
+/// Reads a program back from what `write` gave.
+pub fn read(bytes: &[u8]) -> Result<Program, String> {
+    let mut r = Reader { bytes: bytes, at: 0 };
+
+    if bytes.len() < MAGIC.len() || &bytes[.. MAGIC.len()] != MAGIC {
+        return Err("not a compiled helix program".to_owned())
+    }
+
+    r.at = MAGIC.len();
+
+    let version = try!(r.u32());
+
+    if version != VERSION {
+        return Err(format!("compiled with version {} of the bytecode, but this is version {}", version, VERSION))
+    }
+
+    let mut constants = Vec::new();
+
+    for _ in 0 .. try!(r.u32()) {
+        constants.push(match try!(r.u8()) {
+            0 => Constant::Integer(try!(r.u64()) as i64),
+            1 => Constant::Float(f64::from_bits(try!(r.u64()))),
+            2 => Constant::Boolean(try!(r.u8()) != 0),
+            3 => Constant::Text(try!(r.text())),
+            4 => Constant::Builtin(try!(r.text())),
+            t => return Err(format!("unknown constant tag {}", t)),
+        })
+    }
+
+    let mut types = Vec::new();
+
+    for _ in 0 .. try!(r.u32()) {
+        types.push(try!(r.kind()))
+    }
+
+    let mut functions = Vec::new();
+
+    for _ in 0 .. try!(r.u32()) {
+        let name     = try!(r.text());
+        let arity    = try!(r.u32());
+        let captures = try!(r.u32());
+        let locals   = try!(r.u32());
+
+        let mut chunk = Chunk::new();
+
+        for _ in 0 .. try!(r.u32()) {
+            let line = try!(r.u32());
+            let op   = try!(r.op());
+
+            chunk.write(op, line);
+        }
+
+        functions.push(Function {
+            name:     name,
+            arity:    arity,
+            captures: captures,
+            locals:   locals,
+            chunk:    chunk,
+        })
+    }
+
+    let mut classes = Vec::new();
+
+    for _ in 0 .. try!(r.u32()) {
+        let name   = try!(r.text());
+        let parent = try!(r.option());
+        let fields = try!(r.option());
+
+        let mut methods = Vec::new();
+
+        for _ in 0 .. try!(r.u32()) {
+            methods.push((try!(r.text()), try!(r.u32())))
+        }
+
+        classes.push(Class {
+            name:    name,
+            parent:  parent,
+            fields:  fields,
+            methods: methods,
+        })
+    }
+
+    let mut enums = Vec::new();
+
+    for _ in 0 .. try!(r.u32()) {
+        let name = try!(r.text());
+
+        let mut variants = Vec::new();
+
+        for _ in 0 .. try!(r.u32()) {
+            let n = try!(r.text());
+
+            let mut fields = Vec::new();
+
+            for _ in 0 .. try!(r.u32()) {
+                fields.push((try!(r.text()), try!(r.text())))
+            }
+
+            variants.push((n, fields))
+        }
+
+        enums.push(Enum {
+            name:     name,
+            variants: variants,
+        })
+    }
+
+    let mut globals = Vec::new();
+
+    for _ in 0 .. try!(r.u32()) {
+        globals.push(try!(r.text()))
+    }
+
+    let init = try!(r.u32());
+    let main = try!(r.option());
+
+    let program = Program {
+        constants: constants,
+        types:     types,
+        functions: functions,
+        classes:   classes,
+        enums:     enums,
+        globals:   globals,
+        init:      init,
+        main:      main,
+    };
+
+    try!(check(&program));
+
+    Ok(program)
+}
+
+/// Makes sure every index a program holds is in range and that its functions keep their
+/// stacks in order, so a damaged file is turned down instead of crashing the VM.
+fn check(program: &Program) -> Result<(), String> {
+    let constants = program.constants.len() as u32;
+    let types     = program.types.len() as u32;
+    let functions = program.functions.len() as u32;
+    let classes   = program.classes.len() as u32;
+    let globals   = program.globals.len() as u32;
+
+    let within = |i: u32, n: u32, what: &str| if i < n {
+        Ok(())
+    } else {
+        Err(format!("{} {} out of range", what, i))
+    };
+
+    try!(within(program.init, functions, "function"));
+
+    if let Some(m) = program.main {
+        try!(within(m, functions, "function"))
+    }
+
+    for c in program.classes.iter() {
+        if let Some(p) = c.parent {
+            try!(within(p, classes, "class"))
+        }
+
+        if let Some(f) = c.fields {
+            try!(within(f, functions, "function"))
+        }
+
+        for &(_, f) in c.methods.iter() {
+            try!(within(f, functions, "function"))
+        }
+    }
+
+    for t in program.types.iter() {
+        try!(valid(program, t))
+    }
+
+    for f in program.functions.iter() {
+        let length = f.chunk.code.len() as u32;
+
+        // every local is a capture, a parameter or stored by an instruction of its own
+        let least = f.arity as u64 + f.captures as u64;
+
+        if (f.locals as u64) < least || f.locals as u64 > least + length as u64 {
+            return Err(format!("{} has {} locals for {} parameters", f.name, f.locals, f.arity))
+        }
+
+        for op in f.chunk.code.iter() {
+            match *op {
+                Op::Constant(i) | Op::Member(i) | Op::SetMember(i) | Op::Field(i) | Op::Convert(i) |
+                Op::Is(i) | Op::Invoke(i, _) => try!(within(i, constants, "constant")),
+
+                Op::Local(i) | Op::SetLocal(i) => try!(within(i, f.locals, "local")),
+                Op::Global(i) | Op::SetGlobal(i) => try!(within(i, globals, "global")),
+                Op::Jump(i) | Op::JumpUnless(i) => try!(within(i, length + 1, "jump")),
+                Op::Zero(i) | Op::Default(i) => try!(within(i, types, "type")),
+
+                Op::Function(i) => try!(within(i, functions, "function")),
+
+                Op::Closure(i, n) => {
+                    try!(within(i, functions, "function"));
+
+                    if program.functions[i as usize].captures != n {
+                        return Err(format!("{} captures {} values, not {}", program.functions[i as usize].name,
+                                           program.functions[i as usize].captures, n))
+                    }
+                }
+
+                Op::CallFunction(i, n) => {
+                    try!(within(i, functions, "function"));
+
+                    if program.functions[i as usize].arity != n {
+                        return Err(format!("{} takes {} arguments, not {}", program.functions[i as usize].name,
+                                           program.functions[i as usize].arity, n))
+                    }
+                }
+
+                Op::New(i, _) => try!(within(i, classes, "class")),
+
+                Op::Variant(e, v, _) => match program.enums.get(e as usize) {
+                    Some(e) => try!(within(v, e.variants.len() as u32, "variant")),
+                    None    => return Err(format!("enum {} out of range", e)),
+                },
+
+                _ => (),
+            }
+        }
+
+        try!(stack(f))
+    }
+
+    Ok(())
+}
+
+/// The most values a function's stack is let to hold.
+const DEPTH: u64 = 1 << 16;
+
+/// Follows every path through a function, making sure no instruction takes more values than
+/// are on the stack, that paths meeting agree on how many there are and that none runs past
+/// the end.
+fn stack(f: &Function) -> Result<(), String> {
+    let code = &f.chunk.code;
+
+    let mut depths = vec!(None; code.len());
+    let mut next   = vec!((0, 0));
+
+    while let Some((ip, depth)) = next.pop() {
+        if ip == code.len() {
+            return Err(format!("{} runs past its end", f.name))
+        }
+
+        match depths[ip] {
+            Some(d) if d == depth => continue,
+            Some(_)               => return Err(format!("{} reaches instruction {} with stacks of two sizes", f.name, ip)),
+            None                  => depths[ip] = Some(depth),
+        }
+
+        let (takes, gives) = effect(&code[ip]);
+
+        if takes > depth {
+            return Err(format!("instruction {} of {} takes more values than are on the stack", ip, f.name))
+        }
+
+        let after = depth - takes + gives;
+
+        if after > DEPTH {
+            return Err(format!("the stack of {} grows too big", f.name))
+        }
+
+        match code[ip] {
+            Op::Return | Op::Unmatched => (),
+
+            Op::Jump(to) => next.push((to as usize, after)),
+
+            Op::JumpUnless(to) => {
+                next.push((to as usize, after));
+                next.push((ip + 1, after))
+            }
+
+            _ => next.push((ip + 1, after)),
+        }
+    }
+
+    Ok(())
+}
+
+/// How many values an instruction takes off the stack and how many it puts on it.
+fn effect(op: &Op) -> (u64, u64) {
+    match *op {
+        Op::Constant(_) | Op::Void | Op::Local(_) | Op::Global(_) | Op::This | Op::Default(_) |
+        Op::Function(_) => (0, 1),
+
+        Op::Pop | Op::SetLocal(_) | Op::SetGlobal(_) | Op::Field(_) | Op::JumpUnless(_) |
+        Op::Unmatched => (1, 0),
+
+        Op::Copy | Op::Member(_) | Op::Truth | Op::Not | Op::Unary(_) | Op::Convert(_) | Op::Zero(_) |
+        Op::Subject | Op::Is(_) | Op::Elements | Op::Length => (1, 1),
+
+        Op::List(n) | Op::Closure(_, n) | Op::CallFunction(_, n) | Op::New(_, n) |
+        Op::Variant(_, _, n) => (n as u64, 1),
+
+        Op::Jump(_) | Op::Return              => (0, 0),
+        Op::SetMember(_)                      => (2, 0),
+        Op::Index | Op::Binary(_) | Op::Array => (2, 1),
+        Op::Range                             => (2, 2),
+        Op::SetIndex                          => (3, 0),
+        Op::Unpack(n)                         => (1, n as u64),
+        Op::Map(n)                            => (2 * n as u64, 1),
+        Op::Call(n) | Op::Invoke(_, n)        => (n as u64 + 1, 1),
+    }
+}
+
+fn valid(program: &Program, t: &Type) -> Result<(), String> {
+    match *t {
+        Type::Map(ref v) => valid(program, v),
+
+        Type::Object(c) if c as usize >= program.classes.len() => Err(format!("class {} out of range", c)),
+
+        Type::Variant(e, ref f) => {
+            if e as usize >= program.enums.len() {
+                return Err(format!("enum {} out of range", e))
+            }
+
+            for t in f.iter() {
+                try!(valid(program, t))
+            }
+
+            Ok(())
+        }
+
+        _ => Ok(()),
+    }
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, b: u8) {
+        self.bytes.push(b)
+    }
+
+    fn u32(&mut self, n: u32) {
+        for i in 0 .. 4 {
+            self.bytes.push((n >> (i * 8)) as u8)
+        }
+    }
+
+    fn u64(&mut self, n: u64) {
+        for i in 0 .. 8 {
+            self.bytes.push((n >> (i * 8)) as u8)
+        }
+    }
+
+    fn text(&mut self, t: &str) {
+        self.u32(t.len() as u32);
+        self.bytes.extend_from_slice(t.as_bytes())
+    }
+
+    fn option(&mut self, o: Option<u32>) {
+        match o {
+            Some(n) => { self.u8(1); self.u32(n) }
+            None    => self.u8(0),
+        }
+    }
+
+    fn kind(&mut self, t: &Type) {
+        match *t {
+            Type::Int   => self.u8(0),
+            Type::Float => self.u8(1),
+            Type::Bool  => self.u8(2),
+            Type::Text  => self.u8(3),
+            Type::Void  => self.u8(4),
+            Type::List  => self.u8(5),
+
+            Type::Map(ref v) => {
+                self.u8(6);
+                self.kind(v)
+            }
+
+            Type::Object(c) => {
+                self.u8(7);
+                self.u32(c)
+            }
+
+            Type::Variant(e, ref f) => {
+                self.u8(8);
+                self.u32(e);
+                self.u32(f.len() as u32);
+
+                for t in f.iter() {
+                    self.kind(t)
+                }
+            }
+        }
+    }
+
+    fn op(&mut self, op: &Op) {
+        let (tag, operands): (u8, &[u32]) = match *op {
+            Op::Constant(ref i)      => (0, ref_slice(i)),
+            Op::Void                 => (1, &[]),
+            Op::Pop                  => (2, &[]),
+            Op::Copy                 => (3, &[]),
+            Op::Local(ref i)         => (4, ref_slice(i)),
+            Op::SetLocal(ref i)      => (5, ref_slice(i)),
+            Op::Global(ref i)        => (6, ref_slice(i)),
+            Op::SetGlobal(ref i)     => (7, ref_slice(i)),
+            Op::This                 => (8, &[]),
+            Op::Member(ref i)        => (9, ref_slice(i)),
+            Op::SetMember(ref i)     => (10, ref_slice(i)),
+            Op::Field(ref i)         => (11, ref_slice(i)),
+            Op::Index                => (12, &[]),
+            Op::SetIndex             => (13, &[]),
+            Op::Jump(ref i)          => (14, ref_slice(i)),
+            Op::JumpUnless(ref i)    => (15, ref_slice(i)),
+            Op::Truth                => (16, &[]),
+            Op::Not                  => (17, &[]),
+            Op::Convert(ref i)       => (20, ref_slice(i)),
+            Op::Zero(ref i)          => (21, ref_slice(i)),
+            Op::Default(ref i)       => (22, ref_slice(i)),
+            Op::Array                => (23, &[]),
+            Op::List(ref n)          => (24, ref_slice(n)),
+            Op::Map(ref n)           => (25, ref_slice(n)),
+            Op::Function(ref f)      => (26, ref_slice(f)),
+            Op::Call(ref n)          => (28, ref_slice(n)),
+            Op::Subject              => (33, &[]),
+            Op::Is(ref i)            => (34, ref_slice(i)),
+            Op::Unpack(ref n)        => (35, ref_slice(n)),
+            Op::Unmatched            => (36, &[]),
+            Op::Elements             => (37, &[]),
+            Op::Length               => (38, &[]),
+            Op::Range                => (39, &[]),
+            Op::Return               => (40, &[]),
+
+            Op::Binary(ref o) | Op::Unary(ref o) => {
+                let tag = match *op {
+                    Op::Binary(_) => 18,
+                    _             => 19,
+                };
+
+                self.u8(tag);
+                self.u8(operator(o));
+
+                return
+            }
+
+            Op::Closure(f, n) | Op::CallFunction(f, n) | Op::Invoke(f, n) | Op::New(f, n) => {
+                let tag = match *op {
+                    Op::Closure(_, _)      => 27,
+                    Op::CallFunction(_, _) => 29,
+                    Op::Invoke(_, _)       => 30,
+                    _                      => 31,
+                };
+
+                self.u8(tag);
+                self.u32(f);
+                self.u32(n);
+
+                return
+            }
+
+            Op::Variant(e, v, n) => {
+                self.u8(32);
+                self.u32(e);
+                self.u32(v);
+                self.u32(n);
+
+                return
+            }
+        };
+
+        self.u8(tag);
+
+        for &o in operands.iter() {
+            self.u32(o)
+        }
+    }
+}
+
+fn ref_slice(n: &u32) -> &[u32] {
+    ::std::slice::from_ref(n)
+}
+
+fn operator(o: &Operator) -> u8 {
+    match *o {
+        Operator::Mul     => 0,
+        Operator::Div     => 1,
+        Operator::Mod     => 2,
+        Operator::Plus    => 3,
+        Operator::Minus   => 4,
+        Operator::Equal   => 5,
+        Operator::NEqual  => 6,
+        Operator::Lt      => 7,
+        Operator::LtEqual => 8,
+        Operator::Gt      => 9,
+        Operator::GtEqual => 10,
+        Operator::And     => 11,
+        Operator::Or      => 12,
+        Operator::Not     => 13,
+        Operator::BitAnd  => 14,
+        Operator::BitOr   => 15,
+        Operator::BitXor  => 16,
+        Operator::BitNot  => 17,
+        Operator::Shl     => 18,
+        Operator::Shr     => 19,
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at:    usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.at < n {
+            return Err("the file ends too soon".to_owned())
+        }
+
+        self.at += n;
+
+        Ok(&self.bytes[self.at - n .. self.at])
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(try!(self.take(1))[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = try!(self.take(4));
+
+        Ok(b.iter().rev().fold(0, |n, &b| (n << 8) | b as u32))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let b = try!(self.take(8));
+
+        Ok(b.iter().rev().fold(0, |n, &b| (n << 8) | b as u64))
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        let n = try!(self.u32()) as usize;
+
+        match String::from_utf8(try!(self.take(n)).to_vec()) {
+            Ok(t)  => Ok(t),
+            Err(_) => Err("a string isn't valid UTF-8".to_owned()),
+        }
+    }
+
+    fn option(&mut self) -> Result<Option<u32>, String> {
+        match try!(self.u8()) {
+            0 => Ok(None),
+            _ => Ok(Some(try!(self.u32()))),
+        }
+    }
+
+    fn kind(&mut self) -> Result<Type, String> {
+        Ok(match try!(self.u8()) {
+            0 => Type::Int,
+            1 => Type::Float,
+            2 => Type::Bool,
+            3 => Type::Text,
+            4 => Type::Void,
+            5 => Type::List,
+            6 => Type::Map(Box::new(try!(self.kind()))),
+            7 => Type::Object(try!(self.u32())),
+
+            8 => {
+                let e = try!(self.u32());
+
+                let mut fields = Vec::new();
+
+                for _ in 0 .. try!(self.u32()) {
+                    fields.push(try!(self.kind()))
+                }
+
+                Type::Variant(e, fields)
+            }
+
+            t => return Err(format!("unknown type tag {}", t)),
+        })
+    }
+
+    fn operator(&mut self) -> Result<Operator, String> {
+        match OPERATORS.get(try!(self.u8()) as usize) {
+            Some(o) => Ok(o.clone()),
+            None    => Err("unknown operator".to_owned()),
+        }
+    }
+
+    fn op(&mut self) -> Result<Op, String> {
+        Ok(match try!(self.u8()) {
+            0  => Op::Constant(try!(self.u32())),
+            1  => Op::Void,
+            2  => Op::Pop,
+            3  => Op::Copy,
+            4  => Op::Local(try!(self.u32())),
+            5  => Op::SetLocal(try!(self.u32())),
+            6  => Op::Global(try!(self.u32())),
+            7  => Op::SetGlobal(try!(self.u32())),
+            8  => Op::This,
+            9  => Op::Member(try!(self.u32())),
+            10 => Op::SetMember(try!(self.u32())),
+            11 => Op::Field(try!(self.u32())),
+            12 => Op::Index,
+            13 => Op::SetIndex,
+            14 => Op::Jump(try!(self.u32())),
+            15 => Op::JumpUnless(try!(self.u32())),
+            16 => Op::Truth,
+            17 => Op::Not,
+            18 => Op::Binary(try!(self.operator())),
+            19 => Op::Unary(try!(self.operator())),
+            20 => Op::Convert(try!(self.u32())),
+            21 => Op::Zero(try!(self.u32())),
+            22 => Op::Default(try!(self.u32())),
+            23 => Op::Array,
+            24 => Op::List(try!(self.u32())),
+            25 => Op::Map(try!(self.u32())),
+            26 => Op::Function(try!(self.u32())),
+            27 => Op::Closure(try!(self.u32()), try!(self.u32())),
+            28 => Op::Call(try!(self.u32())),
+            29 => Op::CallFunction(try!(self.u32()), try!(self.u32())),
+            30 => Op::Invoke(try!(self.u32()), try!(self.u32())),
+            31 => Op::New(try!(self.u32()), try!(self.u32())),
+            32 => Op::Variant(try!(self.u32()), try!(self.u32()), try!(self.u32())),
+            33 => Op::Subject,
+            34 => Op::Is(try!(self.u32())),
+            35 => Op::Unpack(try!(self.u32())),
+            36 => Op::Unmatched,
+            37 => Op::Elements,
+            38 => Op::Length,
+            39 => Op::Range,
+            40 => Op::Return,
+            t  => return Err(format!("unknown instruction {}", t)),
+        })
+    }
+}
@@ -0,0 +1,298 @@
+pub mod compiler;
+pub mod vm;
+pub mod file;
+
+use super::token::Operator;
+use super::interpreter::value::Enum;
+
+/// An instruction of the stack VM. Operands index into the tables of the `Program`, or
+/// into the locals of the function running; jumps go to an instruction of the same chunk.
+#[derive(Debug, Clone)]
+pub enum Op {
+    Constant(u32),
+    Void,
+    Pop,
+    /// Replaces the value on top with a copy, as C++ copies values bound to a name.
+    Copy,
+
+    Local(u32),
+    SetLocal(u32),
+    Global(u32),
+    SetGlobal(u32),
+
+    /// Pushes the object the method running was called on.
+    This,
+    /// Takes the member named by a constant from the value on top.
+    Member(u32),
+    /// Assigns the value below the object on top to a member of it.
+    SetMember(u32),
+    /// Gives the object being constructed a field.
+    Field(u32),
+    Index,
+    /// Assigns the value below the list or map and the index on top to an element.
+    SetIndex,
+
+    Jump(u32),
+    /// Jumps when the value popped is false as a condition.
+    JumpUnless(u32),
+
+    /// Turns the value on top into a `bool` the way conditions read it.
+    Truth,
+    Not,
+    Binary(Operator),
+    Unary(Operator),
+    /// Converts the value on top to the type named by a constant, as C++ does implicitly.
+    Convert(u32),
+    /// Tells an empty map what its missing keys read as, with the type of its values.
+    Zero(u32),
+    /// Pushes the value a variable of a type starts out with.
+    Default(u32),
+    /// Makes an array of copies of a value, as long as the integer on top.
+    Array,
+    List(u32),
+    Map(u32),
+
+    Function(u32),
+    /// Makes a lambda of a function, capturing the values on top along with the object the
+    /// method running was called on.
+    Closure(u32, u32),
+    /// Calls the value below the arguments on top.
+    Call(u32),
+    /// Calls a function known when compiling, without making a value of it first.
+    CallFunction(u32, u32),
+    /// Calls the method named by a constant of the value below the arguments.
+    Invoke(u32, u32),
+    /// Makes an object of a class, passing the arguments on top to its constructor.
+    New(u32, u32),
+    /// Makes a value of the variant of an enum out of the fields on top.
+    Variant(u32, u32, u32),
+
+    /// Fails unless the value on top is a variant, which is all a `match` takes apart.
+    Subject,
+    /// Whether the variant popped has the name of a constant.
+    Is(u32),
+    /// Pushes as many fields of the variant popped.
+    Unpack(u32),
+    /// Fails for a variant no arm of a `match` took.
+    Unmatched,
+    /// Takes what a `for` goes through out of a list or a string.
+    Elements,
+    Length,
+    /// Fails unless the bounds of a range on top are integers.
+    Range,
+
+    Return,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Text(String),
+    /// A function of the VM itself, like `printf`.
+    Builtin(String),
+}
+
+/// A type as far as making the value a variable of it starts out with goes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    Text,
+    Void,
+    List,
+    /// A map, with the type of its values.
+    Map(Box<Type>),
+    /// An object of a class.
+    Object(u32),
+    /// An enum, along with the types of the fields of its first variant.
+    Variant(u32, Vec<Type>),
+}
+
+/// The instructions of a function, along with the line of the source each came from.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub code:  Vec<Op>,
+    pub lines: Vec<u32>,
+}
+
+impl Chunk {
+    pub fn new() -> Chunk {
+        Chunk {
+            code:  Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Adds an instruction and gives where it is, for jumps to be pointed at it later.
+    pub fn write(&mut self, op: Op, line: u32) -> usize {
+        self.code.push(op);
+        self.lines.push(line);
+
+        self.code.len() - 1
+    }
+}
+
+/// A compiled function. Its locals start with what it captured and its parameters.
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name:     String,
+    pub arity:    u32,
+    pub captures: u32,
+    pub locals:   u32,
+    pub chunk:    Chunk,
+}
+
+#[derive(Debug, Clone)]
+pub struct Class {
+    pub name:    String,
+    pub parent:  Option<u32>,
+    /// The function giving an object its fields, run before the constructor.
+    pub fields:  Option<u32>,
+    pub methods: Vec<(String, u32)>,
+}
+
+/// A whole program compiled to bytecode, which is what a `.hxc` file holds.
+#[derive(Debug)]
+pub struct Program {
+    pub constants: Vec<Constant>,
+    pub types:     Vec<Type>,
+    pub functions: Vec<Function>,
+    pub classes:   Vec<Class>,
+    pub enums:     Vec<Enum>,
+    /// The names of the variables of modules and of the program itself.
+    pub globals:   Vec<String>,
+    /// The function giving the globals their values, run before `main`.
+    pub init:      u32,
+    pub main:      Option<u32>,
+}
+
+/// Lists the instructions of every function of a program, one per line along with where
+/// it is, the line it came from and what its operands stand for.
+pub fn disassemble(program: &Program) -> String {
+    let mut out = String::new();
+
+    for (i, c) in program.constants.iter().enumerate() {
+        if i == 0 {
+            out.push_str("== constants ==\n")
+        }
+
+        out.push_str(&format!("{:04} {}\n", i, constant(c)))
+    }
+
+    for (i, g) in program.globals.iter().enumerate() {
+        if i == 0 {
+            out.push_str("\n== globals ==\n")
+        }
+
+        out.push_str(&format!("{:04} {}\n", i, g))
+    }
+
+    for c in program.classes.iter() {
+        out.push_str(&format!("\n== class {} ==\n", c.name));
+
+        if let Some(p) = c.parent {
+            out.push_str(&format!("parent {}\n", program.classes[p as usize].name))
+        }
+
+        for &(ref n, f) in c.methods.iter() {
+            out.push_str(&format!("method {} -> {:04}\n", n, f))
+        }
+    }
+
+    for (i, f) in program.functions.iter().enumerate() {
+        out.push_str(&format!("\n== {:04} {} ({} parameters, {} captured, {} locals) ==\n",
+                              i, f.name, f.arity, f.captures, f.locals));
+
+        let mut line = 0;
+
+        for (j, op) in f.chunk.code.iter().enumerate() {
+            let l = f.chunk.lines[j];
+
+            let at = if l == line {
+                "   |".to_owned()
+            } else {
+                format!("{:4}", l)
+            };
+
+            line = l;
+
+            out.push_str(&format!("{:04} {} {}\n", j, at, instruction(program, op)))
+        }
+    }
+
+    out
+}
+
+fn constant(c: &Constant) -> String {
+    match *c {
+        Constant::Integer(i)     => i.to_string(),
+        Constant::Float(f)       => format!("{:?}", f),
+        Constant::Boolean(b)     => b.to_string(),
+        Constant::Text(ref t)    => format!("{:?}", t),
+        Constant::Builtin(ref n) => format!("<builtin {}>", n),
+    }
+}
+
+/// An instruction with its operands spelled out.
+fn instruction(program: &Program, op: &Op) -> String {
+    let name = |i: u32| match program.constants.get(i as usize) {
+        Some(&Constant::Text(ref t)) => t.clone(),
+        Some(c)                      => constant(c),
+        None                         => "?".to_owned(),
+    };
+
+    let function = |i: u32| program.functions.get(i as usize).map(|f| f.name.clone()).unwrap_or_default();
+
+    match *op {
+        Op::Constant(i)         => format!("{:<14} {:4} {}", "CONSTANT", i, name(i)),
+        Op::Local(i)            => format!("{:<14} {:4}", "LOCAL", i),
+        Op::SetLocal(i)         => format!("{:<14} {:4}", "SET_LOCAL", i),
+        Op::Global(i)           => format!("{:<14} {:4} {}", "GLOBAL", i, program.globals[i as usize]),
+        Op::SetGlobal(i)        => format!("{:<14} {:4} {}", "SET_GLOBAL", i, program.globals[i as usize]),
+        Op::Member(i)           => format!("{:<14} {:4} {}", "MEMBER", i, name(i)),
+        Op::SetMember(i)        => format!("{:<14} {:4} {}", "SET_MEMBER", i, name(i)),
+        Op::Field(i)            => format!("{:<14} {:4} {}", "FIELD", i, name(i)),
+        Op::Jump(i)             => format!("{:<14} {:4}", "JUMP", i),
+        Op::JumpUnless(i)       => format!("{:<14} {:4}", "JUMP_UNLESS", i),
+        Op::Binary(ref o)       => format!("{:<14} {}", "BINARY", o),
+        Op::Unary(ref o)        => format!("{:<14} {}", "UNARY", o),
+        Op::Convert(i)          => format!("{:<14} {:4} {}", "CONVERT", i, name(i)),
+        Op::Zero(i)             => format!("{:<14} {:4} {:?}", "ZERO", i, program.types[i as usize]),
+        Op::Default(i)          => format!("{:<14} {:4} {:?}", "DEFAULT", i, program.types[i as usize]),
+        Op::List(n)             => format!("{:<14} {:4}", "LIST", n),
+        Op::Map(n)              => format!("{:<14} {:4}", "MAP", n),
+        Op::Function(f)         => format!("{:<14} {:4} {}", "FUNCTION", f, function(f)),
+        Op::Closure(f, n)       => format!("{:<14} {:4} {} capturing {}", "CLOSURE", f, function(f), n),
+        Op::Call(n)             => format!("{:<14} {:4}", "CALL", n),
+        Op::CallFunction(f, n)  => format!("{:<14} {:4} {} with {}", "CALL_FUNCTION", f, function(f), n),
+        Op::Invoke(i, n)        => format!("{:<14} {:4} {} with {}", "INVOKE", i, name(i), n),
+        Op::New(c, n)           => format!("{:<14} {:4} {} with {}", "NEW", c, program.classes[c as usize].name, n),
+        Op::Is(i)               => format!("{:<14} {:4} {}", "IS", i, name(i)),
+        Op::Unpack(n)           => format!("{:<14} {:4}", "UNPACK", n),
+
+        Op::Variant(e, v, n) => {
+            let e = &program.enums[e as usize];
+
+            format!("{:<14} {}::{} with {}", "VARIANT", e.name, e.variants[v as usize].0, n)
+        }
+
+        Op::Void      => "VOID".to_owned(),
+        Op::Pop       => "POP".to_owned(),
+        Op::Copy      => "COPY".to_owned(),
+        Op::This      => "THIS".to_owned(),
+        Op::Index     => "INDEX".to_owned(),
+        Op::SetIndex  => "SET_INDEX".to_owned(),
+        Op::Truth     => "TRUTH".to_owned(),
+        Op::Not       => "NOT".to_owned(),
+        Op::Array     => "ARRAY".to_owned(),
+        Op::Subject   => "SUBJECT".to_owned(),
+        Op::Unmatched => "UNMATCHED".to_owned(),
+        Op::Elements  => "ELEMENTS".to_owned(),
+        Op::Length    => "LENGTH".to_owned(),
+        Op::Range     => "RANGE".to_owned(),
+        Op::Return    => "RETURN".to_owned(),
+    }
+}
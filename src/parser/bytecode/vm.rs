@@ -0,0 +1,691 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use super::{Op, Constant, Type, Function, Program};
+use super::super::interpreter::{self, builtin};
+use super::super::interpreter::value::{Value, Enum, Map};
+
+/// A function along with the values it captured and, for a method or a lambda made in one,
+/// the object it is called on.
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub captured: Vec<Value>,
+    pub this:     Option<Rc<RefCell<Instance>>>,
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name:    String,
+    pub parent:  Option<Rc<Class>>,
+    pub fields:  Option<Rc<Function>>,
+    pub methods: HashMap<String, Rc<Function>>,
+}
+
+impl Class {
+    pub fn method(&self, name: &str) -> Option<Rc<Function>> {
+        if let Some(m) = self.methods.get(name) {
+            return Some(m.clone())
+        }
+
+        match self.parent {
+            Some(ref p) => p.method(name),
+            None        => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class:  Rc<Class>,
+    pub fields: HashMap<String, Value>,
+}
+
+/// Runs a compiled program on a stack of values, with the semantics of the interpreter.
+pub struct Vm {
+    constants: Vec<Value>,
+    types:     Vec<Type>,
+    functions: Vec<Rc<Function>>,
+    classes:   Vec<Rc<Class>>,
+    enums:     Vec<Rc<Enum>>,
+    globals:   Vec<Value>,
+    init:      u32,
+    main:      Option<u32>,
+    /// How many calls are running.
+    depth:     usize,
+}
+
+impl Vm {
+    pub fn new(program: Program) -> Vm {
+        let functions: Vec<Rc<Function>> = program.functions.into_iter().map(Rc::new).collect();
+
+        let mut classes: Vec<Option<Rc<Class>>> = vec!(None; program.classes.len());
+
+        for i in 0 .. program.classes.len() {
+            class(i, &program.classes, &functions, &mut classes);
+        }
+
+        Vm {
+            constants: program.constants.into_iter().map(|c| match c {
+                Constant::Integer(i) => Value::Integer(i),
+                Constant::Float(f)   => Value::Float(f),
+                Constant::Boolean(b) => Value::Boolean(b),
+                Constant::Text(t)    => Value::Text(t),
+                Constant::Builtin(n) => Value::Builtin(n),
+            }).collect(),
+
+            types:     program.types,
+            functions: functions,
+            classes:   classes.into_iter().map(Option::unwrap).collect(),
+            enums:     program.enums.into_iter().map(Rc::new).collect(),
+            globals:   vec!(Value::Void; program.globals.len()),
+            init:      program.init,
+            main:      program.main,
+            depth:     0,
+        }
+    }
+
+    /// Gives the globals their values, then calls `main` and hands back whatever it returned.
+    pub fn run(&mut self) -> Result<Value, String> {
+        let init = self.functions[self.init as usize].clone();
+
+        try!(self.invoke(&init, None, &[], Vec::new()));
+
+        match self.main {
+            Some(main) => {
+                let main = self.functions[main as usize].clone();
+
+                self.invoke(&main, None, &[], Vec::new())
+            }
+
+            None => Err("no `main` function to run".to_owned()),
+        }
+    }
+
+    fn invoke(&mut self, function: &Rc<Function>, this: Option<Rc<RefCell<Instance>>>, captured: &[Value], args: Vec<Value>) -> Result<Value, String> {
+        if args.len() != function.arity as usize {
+            return Err(format!(
+                    "{} takes {} argument(s) but {} were given",
+                    function.name, function.arity, args.len(),
+                ))
+        }
+
+        let mut locals = Vec::with_capacity(function.locals as usize);
+
+        locals.extend(captured.iter().cloned());
+        locals.extend(args.into_iter().map(|v| v.copied()));
+        locals.resize(function.locals as usize, Value::Void);
+
+        if self.depth == interpreter::CALLS {
+            return Err(format!("calls nested more than {} deep, in {}", interpreter::CALLS, function.name))
+        }
+
+        self.depth += 1;
+
+        let result = self.execute(function, this, locals);
+
+        self.depth -= 1;
+
+        result
+    }
+
+    pub fn call(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, String> {
+        match callee {
+            Value::Closure(ref c) => {
+                // a class's name in its own methods makes another object rather than calling
+                // the constructor again
+                if let Some(ref o) = c.this {
+                    let class = o.borrow().class.clone();
+
+                    if class.name == c.function.name {
+                        return self.instantiate(&class, args)
+                    }
+                }
+
+                self.invoke(&c.function, c.this.clone(), &c.captured, args)
+            }
+
+            Value::Builtin(ref n)            => builtin::call(n, args),
+            Value::Member(ref r, ref n)      => builtin::member(r, n, args),
+            Value::Constructor(ref e, ref n) => interpreter::variant(e, n, args),
+            v                                => Err(format!("{} is not callable", v)),
+        }
+    }
+
+    /// Creates an object, giving it the fields of its classes from the base class down and
+    /// then running the constructor, which is the method named after the class.
+    fn instantiate(&mut self, class: &Rc<Class>, args: Vec<Value>) -> Result<Value, String> {
+        let object = Rc::new(RefCell::new(Instance {
+            class:  class.clone(),
+            fields: HashMap::new(),
+        }));
+
+        try!(self.construct(class, &object, args));
+
+        Ok(Value::Instance(object))
+    }
+
+    fn construct(&mut self, class: &Rc<Class>, object: &Rc<RefCell<Instance>>, args: Vec<Value>) -> Result<(), String> {
+        if let Some(ref p) = class.parent {
+            try!(self.construct(p, object, Vec::new()))
+        }
+
+        if let Some(ref f) = class.fields {
+            try!(self.invoke(f, Some(object.clone()), &[], Vec::new()));
+        }
+
+        match class.methods.get(&class.name) {
+            Some(c) => {
+                try!(self.invoke(c, Some(object.clone()), &[], args));
+            }
+
+            None => if !args.is_empty() {
+                return Err(format!("class {} has no constructor taking {} argument(s)", class.name, args.len()))
+            },
+        }
+
+        Ok(())
+    }
+
+    fn default(&mut self, t: &Type) -> Result<Value, String> {
+        match *t {
+            Type::Int   => Ok(Value::Integer(0)),
+            Type::Float => Ok(Value::Float(0.0)),
+            Type::Bool  => Ok(Value::Boolean(false)),
+            Type::Text  => Ok(Value::Text(String::new())),
+            Type::Void  => Ok(Value::Void),
+            Type::List  => Ok(Value::Array(Rc::new(RefCell::new(Vec::new())))),
+
+            Type::Map(ref t) => Ok(Value::Map(Rc::new(RefCell::new(Map {
+                    entries: Vec::new(),
+                    zero:    Some(try!(self.default(t))),
+                })))),
+
+            Type::Object(c) => {
+                let class = self.classes[c as usize].clone();
+
+                self.instantiate(&class, Vec::new())
+            }
+
+            // like its C++ translation, an enum starts out as its first variant
+            Type::Variant(e, ref fields) => {
+                let enumeration = self.enums[e as usize].clone();
+
+                let name = match enumeration.variants.first() {
+                    Some(&(ref n, _)) => n.clone(),
+                    None              => return Err(format!("enum {} has no variants", enumeration.name)),
+                };
+
+                let mut values = Vec::new();
+
+                for t in fields.iter() {
+                    values.push(try!(self.default(t)))
+                }
+
+                Ok(Value::Variant(enumeration, name, values))
+            }
+        }
+    }
+
+    /// The member of a value, which for an object is a field or a method bound to it.
+    fn member(&self, base: &Value, name: &str) -> Result<Value, String> {
+        match *base {
+            Value::Instance(ref o) => {
+                let object = o.borrow();
+
+                if let Some(v) = object.fields.get(name) {
+                    return Ok(v.clone())
+                }
+
+                match object.class.method(name) {
+                    Some(m) => Ok(Value::Closure(Rc::new(Closure {
+                        function: m,
+                        captured: Vec::new(),
+                        this:     Some(o.clone()),
+                    }))),
+
+                    None => Err(format!("{} has no member named {}", object.class.name, name)),
+                }
+            }
+
+            ref v => interpreter::get(v, name),
+        }
+    }
+
+    fn name(&self, i: u32) -> &str {
+        match self.constants[i as usize] {
+            Value::Text(ref t) => t,
+            _                  => "",
+        }
+    }
+
+    fn execute(&mut self, function: &Function, this: Option<Rc<RefCell<Instance>>>, mut locals: Vec<Value>) -> Result<Value, String> {
+        let code = &function.chunk.code;
+
+        let mut stack: Vec<Value> = Vec::new();
+        let mut ip = 0;
+
+        loop {
+            let op = &code[ip];
+
+            ip += 1;
+
+            match *op {
+                Op::Constant(i) => stack.push(self.constants[i as usize].clone()),
+                Op::Void        => stack.push(Value::Void),
+
+                Op::Pop => {
+                    stack.pop();
+                }
+
+                Op::Copy => {
+                    let v = stack.pop().unwrap().copied();
+
+                    stack.push(v)
+                }
+
+                Op::Local(i)     => stack.push(locals[i as usize].clone()),
+                Op::SetLocal(i)  => locals[i as usize] = stack.pop().unwrap(),
+                Op::Global(i)    => stack.push(self.globals[i as usize].clone()),
+                Op::SetGlobal(i) => self.globals[i as usize] = stack.pop().unwrap(),
+
+                Op::This => match this {
+                    Some(ref o) => stack.push(Value::Instance(o.clone())),
+                    None        => return Err("`this` outside of a method".to_owned()),
+                },
+
+                Op::Member(i) => {
+                    let base = stack.pop().unwrap();
+
+                    stack.push(try!(self.member(&base, self.name(i))))
+                }
+
+                Op::SetMember(i) => {
+                    let base  = stack.pop().unwrap();
+                    let value = stack.pop().unwrap();
+                    let name  = self.name(i);
+
+                    match base {
+                        Value::Instance(ref o) => {
+                            let mut object = o.borrow_mut();
+
+                            match object.fields.get_mut(name) {
+                                Some(v) => *v = value,
+                                None    => return Err(format!("{} has no field named {}", object.class.name, name)),
+                            }
+                        }
+
+                        v => return Err(format!("can't assign to a member of {}", v.type_name())),
+                    }
+                }
+
+                Op::Field(i) => {
+                    let value = stack.pop().unwrap();
+
+                    if let Some(ref o) = this {
+                        o.borrow_mut().fields.insert(self.name(i).to_owned(), value);
+                    }
+                }
+
+                Op::Index => {
+                    let index = stack.pop().unwrap();
+                    let base  = stack.pop().unwrap();
+
+                    stack.push(try!(interpreter::index_array(&base, &index)))
+                }
+
+                Op::SetIndex => {
+                    let index = stack.pop().unwrap();
+                    let base  = stack.pop().unwrap();
+                    let value = stack.pop().unwrap();
+
+                    try!(store(base, index, value))
+                }
+
+                Op::Jump(to) => ip = to as usize,
+
+                Op::JumpUnless(to) => if !try!(truth(&stack.pop().unwrap())) {
+                    ip = to as usize
+                },
+
+                Op::Truth => {
+                    let b = try!(truth(&stack.pop().unwrap()));
+
+                    stack.push(Value::Boolean(b))
+                }
+
+                Op::Not => {
+                    let b = try!(truth(&stack.pop().unwrap()));
+
+                    stack.push(Value::Boolean(!b))
+                }
+
+                Op::Binary(ref o) => {
+                    let right = stack.pop().unwrap();
+                    let left  = stack.pop().unwrap();
+
+                    stack.push(try!(interpreter::operation(left, o, right)))
+                }
+
+                Op::Unary(ref o) => {
+                    let v = stack.pop().unwrap();
+
+                    stack.push(try!(interpreter::unary(o, v)))
+                }
+
+                Op::Convert(i) => {
+                    let v = match stack.pop().unwrap() {
+                        Value::Instance(o) => Value::Instance(sliced(o, self.name(i))),
+                        v                  => try!(interpreter::convert(v, self.name(i))),
+                    };
+
+                    stack.push(v)
+                }
+
+                Op::Zero(t) => {
+                    let learns = match stack.last() {
+                        Some(&Value::Map(ref m)) => m.borrow().zero.is_none(),
+                        _                        => false,
+                    };
+
+                    if learns {
+                        let t    = self.types[t as usize].clone();
+                        let zero = try!(self.default(&t));
+
+                        if let Some(&Value::Map(ref m)) = stack.last() {
+                            m.borrow_mut().zero = Some(zero)
+                        }
+                    }
+                }
+
+                Op::Default(t) => {
+                    let t = self.types[t as usize].clone();
+
+                    stack.push(try!(self.default(&t)))
+                }
+
+                Op::Array => {
+                    let size = match stack.pop().unwrap() {
+                        Value::Integer(s) if s >= 0 => s as usize,
+                        v                           => return Err(format!("invalid array size: {}", v)),
+                    };
+
+                    let value = stack.pop().unwrap();
+
+                    let elements = (0 .. size).map(|_| value.copied()).collect();
+
+                    stack.push(Value::Array(Rc::new(RefCell::new(elements))))
+                }
+
+                Op::List(n) => {
+                    let elements = try!(take(&mut stack, n as usize)).iter().map(|v| v.copied()).collect();
+
+                    stack.push(Value::Array(Rc::new(RefCell::new(elements))))
+                }
+
+                Op::Map(n) => {
+                    let entries = try!(take(&mut stack, 2 * n as usize));
+
+                    let mut map = Map {
+                        entries: Vec::new(),
+                        zero:    None,
+                    };
+
+                    for pair in entries.chunks(2) {
+                        let (k, v) = (pair[0].copied(), pair[1].copied());
+
+                        // the first of two equal keys is the one kept, as in C++
+                        if map.get(&k).is_none() {
+                            map.zero = Some(v.zeroed());
+                            map.insert(k, v)
+                        }
+                    }
+
+                    stack.push(Value::Map(Rc::new(RefCell::new(map))))
+                }
+
+                Op::Function(f) => stack.push(Value::Closure(Rc::new(Closure {
+                    function: self.functions[f as usize].clone(),
+                    captured: Vec::new(),
+                    this:     None,
+                }))),
+
+                Op::Closure(f, n) => {
+                    let captured = try!(take(&mut stack, n as usize)).iter().map(|v| v.copied()).collect();
+
+                    stack.push(Value::Closure(Rc::new(Closure {
+                        function: self.functions[f as usize].clone(),
+                        captured: captured,
+                        this:     this.clone(),
+                    })))
+                }
+
+                Op::Call(n) => {
+                    let args   = try!(take(&mut stack, n as usize));
+                    let callee = stack.pop().unwrap();
+
+                    stack.push(try!(self.call(callee, args)))
+                }
+
+                Op::CallFunction(f, n) => {
+                    let args = try!(take(&mut stack, n as usize));
+                    let f    = self.functions[f as usize].clone();
+
+                    stack.push(try!(self.invoke(&f, None, &[], args)))
+                }
+
+                Op::Invoke(i, n) => {
+                    let args = try!(take(&mut stack, n as usize));
+                    let base = stack.pop().unwrap();
+
+                    let value = try!(self.method(base, i, args));
+
+                    stack.push(value)
+                }
+
+                Op::New(c, n) => {
+                    let args  = try!(take(&mut stack, n as usize));
+                    let class = self.classes[c as usize].clone();
+
+                    stack.push(try!(self.instantiate(&class, args)))
+                }
+
+                Op::Variant(e, v, n) => {
+                    let args        = try!(take(&mut stack, n as usize));
+                    let enumeration = self.enums[e as usize].clone();
+                    let name        = enumeration.variants[v as usize].0.clone();
+
+                    stack.push(try!(interpreter::variant(&enumeration, &name, args)))
+                }
+
+                Op::Subject => match stack.last() {
+                    Some(&Value::Variant(_, _, _)) => (),
+                    Some(v)                        => return Err(format!("{} can't be matched on", v.type_name())),
+                    None                           => (),
+                },
+
+                Op::Is(i) => {
+                    let is = match stack.pop().unwrap() {
+                        Value::Variant(_, ref n, _) => n == self.name(i),
+                        _                           => false,
+                    };
+
+                    stack.push(Value::Boolean(is))
+                }
+
+                Op::Unpack(n) => {
+                    let fields = match stack.pop().unwrap() {
+                        Value::Variant(_, _, f) => f,
+                        _                       => Vec::new(),
+                    };
+
+                    let mut fields = fields.into_iter();
+
+                    for _ in 0 .. n {
+                        stack.push(fields.next().unwrap_or(Value::Void))
+                    }
+                }
+
+                Op::Unmatched => match stack.pop().unwrap() {
+                    Value::Variant(_, n, _) => return Err(format!("no arm matches {}", n)),
+                    v                       => return Err(format!("no arm matches {}", v)),
+                },
+
+                Op::Elements => {
+                    let elements = match stack.pop().unwrap() {
+                        Value::Array(a) => a.borrow().clone(),
+                        Value::Text(t)  => t.chars().map(|c| Value::Integer(c as i64)).collect(),
+                        v               => return Err(format!("{} is not iterable", v.type_name())),
+                    };
+
+                    stack.push(Value::Array(Rc::new(RefCell::new(elements))))
+                }
+
+                Op::Length => {
+                    let length = match stack.pop().unwrap() {
+                        Value::Array(a) => a.borrow().len(),
+                        _               => 0,
+                    };
+
+                    stack.push(Value::Integer(length as i64))
+                }
+
+                Op::Range => match stack[stack.len().saturating_sub(2) ..] {
+                    [Value::Integer(_), Value::Integer(_)] => (),
+
+                    [ref a, ref b] => return Err(format!(
+                            "range bounds must be integers, found {} and {}",
+                            a.type_name(), b.type_name(),
+                        )),
+
+                    _ => return Err(SHORT.to_owned()),
+                },
+
+                Op::Return => return Ok(stack.pop().unwrap_or(Value::Void)),
+            }
+        }
+    }
+
+    /// Calls a method of a value; methods of objects are called without binding them first.
+    fn method(&mut self, base: Value, name: u32, args: Vec<Value>) -> Result<Value, String> {
+        if let Value::Instance(ref o) = base {
+            let (field, class) = {
+                let object = o.borrow();
+
+                (object.fields.get(self.name(name)).cloned(), object.class.clone())
+            };
+
+            if let Some(f) = field {
+                return self.call(f, args)
+            }
+
+            if let Some(m) = class.method(self.name(name)) {
+                if m.name == class.name {
+                    return self.instantiate(&class, args)
+                }
+
+                return self.invoke(&m, Some(o.clone()), &[], args)
+            }
+        }
+
+        let callee = try!(self.member(&base, self.name(name)));
+
+        self.call(callee, args)
+    }
+}
+
+/// What the VM says when an instruction takes more values than the stack holds, which only
+/// a damaged program makes it do.
+const SHORT: &'static str = "an instruction takes more values than are on the stack";
+
+/// Takes the `n` values on top of the stack, in the order they were pushed.
+fn take(stack: &mut Vec<Value>, n: usize) -> Result<Vec<Value>, String> {
+    match stack.len().checked_sub(n) {
+        Some(at) => Ok(stack.split_off(at)),
+        None     => Err(SHORT.to_owned()),
+    }
+}
+
+/// Builds the class at `i` of a program, after the class it inherits from.
+fn class(i: usize, program: &[super::Class], functions: &[Rc<Function>], classes: &mut Vec<Option<Rc<Class>>>) -> Rc<Class> {
+    if let Some(ref c) = classes[i] {
+        return c.clone()
+    }
+
+    let c = &program[i];
+
+    let parent = c.parent.map(|p| class(p as usize, program, functions, classes));
+
+    let built = Rc::new(Class {
+        name:    c.name.clone(),
+        parent:  parent,
+        fields:  c.fields.map(|f| functions[f as usize].clone()),
+        methods: c.methods.iter().map(|&(ref n, f)| (n.clone(), functions[f as usize].clone())).collect(),
+    });
+
+    classes[i] = Some(built.clone());
+
+    built
+}
+
+fn truth(v: &Value) -> Result<bool, String> {
+    match *v {
+        Value::Boolean(b) => Ok(b),
+        Value::Integer(i) => Ok(i != 0),
+        Value::Float(f)   => Ok(f != 0.0),
+        ref v             => Err(format!("{} can't be used as a condition", v.type_name())),
+    }
+}
+
+/// Writes a value to an element of a list or a map.
+fn store(base: Value, index: Value, value: Value) -> Result<(), String> {
+    match (base, index) {
+        (Value::Array(ref a), Value::Integer(i)) => match a.borrow_mut().get_mut(i as usize) {
+            Some(v) if i >= 0 => {
+                *v = value;
+
+                Ok(())
+            }
+
+            _ => Err(format!("array index out of bounds: {}", i)),
+        },
+
+        (Value::Array(_), i) => Err(format!("array index must be an integer, found {}", i.type_name())),
+
+        (Value::Map(ref m), k) => {
+            m.borrow_mut().insert(k.copied(), value);
+
+            Ok(())
+        }
+
+        (v, _) => Err(format!("can't assign to an element of {}", v.type_name())),
+    }
+}
+
+/// An object bound to one of its base classes, sliced to it like the interpreter does.
+fn sliced(object: Rc<RefCell<Instance>>, t: &str) -> Rc<RefCell<Instance>> {
+    let name = t.rsplit("::").next().unwrap_or(t);
+
+    let mut base = object.borrow().class.parent.clone();
+
+    while let Some(c) = base.clone() {
+        if c.name == name {
+            break
+        }
+
+        base = c.parent.clone()
+    }
+
+    let class = match base {
+        Some(c) => c,
+        None    => return object,
+    };
+
+    let copy = Instance {
+        class:  class,
+        fields: object.borrow().fields.iter().map(|(k, v)| (k.clone(), v.copied())).collect(),
+    };
+
+    Rc::new(RefCell::new(copy))
+}
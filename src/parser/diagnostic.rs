@@ -0,0 +1,176 @@
+use std::fmt;
+
+/// Error codes reported by the front end.
+///
+/// E0001 inconsistent indentation
+/// E0002 unexpected symbol
+/// E0003 unterminated string literal
+/// E0004 unexpected token
+/// E0005 expected a specific token
+/// E0006 expected an indented block
+/// E0007 invalid literal
+/// E0008 invalid member of an `implement` block
+/// E0009 invalid assignment target
+/// E0010 undefined name
+/// E0011 name defined multiple times
+/// E0012 wrong number of arguments
+/// E0013 implementation of an undeclared class
+/// E0014 mismatched types
+/// E0015 operator applied to the wrong types
+/// E0016 Helix module that can't be loaded
+/// E0017 cyclic import
+/// E0018 `match` that misses variants or repeats them
+/// E0019 wrong number of type arguments
+/// E0020 construct the chosen target can't express
+/// E0021 `break` or `continue` outside of a loop
+pub mod code {
+    pub const INDENTATION:     &'static str = "E0001";
+    pub const SYMBOL:          &'static str = "E0002";
+    pub const UNTERMINATED:    &'static str = "E0003";
+    pub const UNEXPECTED:      &'static str = "E0004";
+    pub const EXPECTED:        &'static str = "E0005";
+    pub const BLOCK:           &'static str = "E0006";
+    pub const LITERAL:         &'static str = "E0007";
+    pub const IMPLEMENTATION:  &'static str = "E0008";
+    pub const ASSIGNMENT:      &'static str = "E0009";
+    pub const UNDEFINED:       &'static str = "E0010";
+    pub const DUPLICATE:       &'static str = "E0011";
+    pub const ARGUMENTS:       &'static str = "E0012";
+    pub const UNDECLARED:      &'static str = "E0013";
+    pub const TYPE:            &'static str = "E0014";
+    pub const OPERATOR:        &'static str = "E0015";
+    pub const MODULE:          &'static str = "E0016";
+    pub const CYCLE:           &'static str = "E0017";
+    pub const MATCH:           &'static str = "E0018";
+    pub const GENERICS:        &'static str = "E0019";
+    pub const UNSUPPORTED:     &'static str = "E0020";
+    pub const LOOP:            &'static str = "E0021";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error   => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note    => write!(f, "note"),
+        }
+    }
+}
+
+/// A region of a source file; lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub file: String,
+    pub line: u32,
+    pub col:  u32,
+    pub len:  u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code:     &'static str,
+    pub message:  String,
+    pub span:     Option<Span>,
+    pub notes:    Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: severity,
+            code:     code,
+            message:  message,
+            span:     None,
+            notes:    Vec::new(),
+        }
+    }
+
+    pub fn error(code: &'static str, message: String) -> Diagnostic {
+        Diagnostic::new(Severity::Error, code, message)
+    }
+
+    pub fn warning(code: &'static str, message: String) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, code, message)
+    }
+
+    pub fn at(mut self, line: u32, col: u32, len: u32) -> Diagnostic {
+        let file = match self.span {
+            Some(ref s) => s.file.clone(),
+            None        => String::new(),
+        };
+
+        self.span = Some(Span {
+            file: file,
+            line: line,
+            col:  col,
+            len:  len,
+        });
+
+        self
+    }
+
+    pub fn note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+
+    /// Names the file the span points into, which the front end itself doesn't know.
+    pub fn in_file(mut self, file: &str) -> Diagnostic {
+        if let Some(ref mut s) = self.span {
+            s.file = file.to_owned()
+        }
+
+        self
+    }
+
+    /// Renders the diagnostic like rustc does: a header, the location, the offending source
+    /// line with a caret underline and any notes.
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("{}\n", self);
+
+        let span = match self.span {
+            Some(ref s) => s,
+            None        => {
+                for n in self.notes.iter() {
+                    out.push_str(&format!(" = note: {}\n", n))
+                }
+
+                return out
+            }
+        };
+
+        let gutter = " ".repeat(span.line.to_string().len());
+
+        out.push_str(&format!("{}--> {}:{}:{}\n", gutter, span.file, span.line, span.col));
+
+        if let Some(line) = source.lines().nth(span.line.saturating_sub(1) as usize) {
+            let line   = line.replace('\t', " ");
+            let indent = " ".repeat(span.col.saturating_sub(1) as usize);
+            let carets = "^".repeat(::std::cmp::max(span.len, 1) as usize);
+
+            out.push_str(&format!("{} |\n", gutter));
+            out.push_str(&format!("{} | {}\n", span.line, line));
+            out.push_str(&format!("{} | {}{}\n", gutter, indent, carets));
+        }
+
+        for n in self.notes.iter() {
+            out.push_str(&format!("{} = note: {}\n", gutter, n))
+        }
+
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
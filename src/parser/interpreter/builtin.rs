@@ -0,0 +1,325 @@
+use std::io;
+use std::io::prelude::*;
+
+use super::value::Value;
+
+pub fn lookup(name: &str) -> Option<Value> {
+    match name {
+        "printf" | "puts" => Some(Value::Builtin(name.to_owned())),
+        _                 => None,
+    }
+}
+
+pub fn call(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    match name {
+        "printf" => {
+            if args.is_empty() {
+                return Err("printf expects a format string".to_owned())
+            }
+
+            let text = match args[0] {
+                Value::Text(ref t) => try!(format(t, &args[1..])),
+                ref v              => return Err(format!("printf expects a format string, found {}", v.type_name())),
+            };
+
+            print!("{}", text);
+            io::stdout().flush().unwrap();
+
+            Ok(Value::Integer(text.len() as i64))
+        }
+
+        "puts" => {
+            match args.first() {
+                Some(v) => println!("{}", v),
+                None    => println!(),
+            }
+
+            Ok(Value::Integer(0))
+        }
+
+        _ => Err(format!("unknown builtin: {}", name)),
+    }
+}
+
+/// Whether a list or map has a method of that name.
+pub fn is_member(receiver: &Value, name: &str) -> bool {
+    match (receiver, name) {
+        (_, "size") | (_, "clear") => true,
+
+        (&Value::Array(_), "push_back") |
+        (&Value::Array(_), "pop_back")  |
+        (&Value::Array(_), "back")      => true,
+
+        (&Value::Map(_), "count") |
+        (&Value::Map(_), "erase") => true,
+
+        _ => false,
+    }
+}
+
+/// Calls a method of a list or map, with the behavior of its `std::vector` or
+/// `std::unordered_map` counterpart.
+pub fn member(receiver: &Value, name: &str, args: Vec<Value>) -> Result<Value, String> {
+    let mut args = args.into_iter();
+
+    let (argument, extra) = (args.next(), args.next());
+
+    if extra.is_some() {
+        return Err(format!("{} takes at most 1 argument", name))
+    }
+
+    match (receiver, name, argument) {
+        (&Value::Array(ref a), "size", None) => Ok(Value::Integer(a.borrow().len() as i64)),
+        (&Value::Map(ref m), "size", None)   => Ok(Value::Integer(m.borrow().entries.len() as i64)),
+
+        (&Value::Array(ref a), "clear", None) => {
+            a.borrow_mut().clear();
+
+            Ok(Value::Void)
+        }
+
+        (&Value::Map(ref m), "clear", None) => {
+            m.borrow_mut().entries.clear();
+
+            Ok(Value::Void)
+        }
+
+        (&Value::Array(ref a), "push_back", Some(v)) => {
+            a.borrow_mut().push(v.copied());
+
+            Ok(Value::Void)
+        }
+
+        (&Value::Array(ref a), "pop_back", None) => match a.borrow_mut().pop() {
+            Some(_) => Ok(Value::Void),
+            None    => Err("pop_back on an empty list".to_owned()),
+        },
+
+        (&Value::Array(ref a), "back", None) => match a.borrow().last() {
+            Some(v) => Ok(v.clone()),
+            None    => Err("back of an empty list".to_owned()),
+        },
+
+        (&Value::Map(ref m), "count", Some(k)) => Ok(Value::Integer(m.borrow().get(&k).is_some() as i64)),
+
+        (&Value::Map(ref m), "erase", Some(k)) => {
+            let mut map = m.borrow_mut();
+            let before  = map.entries.len();
+
+            map.entries.retain(|&(ref e, _)| !e.same(&k));
+
+            Ok(Value::Integer((before - map.entries.len()) as i64))
+        }
+
+        (v, n, _) => Err(format!("wrong arguments to {} of {}", n, v.type_name())),
+    }
+}
+
+/// Expands a C `printf` format string: flags, width and precision are honored for the
+/// `d i u x X o c s f e g` conversions.
+pub fn format(fmt: &str, args: &[Value]) -> Result<String, String> {
+    let mut out  = String::new();
+    let mut args = args.iter();
+
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue
+        }
+
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            out.push('%');
+            continue
+        }
+
+        let mut left  = false;
+        let mut zero  = false;
+        let mut plus  = false;
+
+        while let Some(&f) = chars.peek() {
+            match f {
+                '-' => left = true,
+                '0' => zero = true,
+                '+' => plus = true,
+                ' ' | '#' => (),
+                _ => break,
+            }
+
+            chars.next();
+        }
+
+        let mut width = String::new();
+
+        while let Some(&d) = chars.peek() {
+            if !d.is_ascii_digit() {
+                break
+            }
+
+            width.push(d);
+            chars.next();
+        }
+
+        let mut precision = None;
+
+        if chars.peek() == Some(&'.') {
+            chars.next();
+
+            let mut p = String::new();
+
+            while let Some(&d) = chars.peek() {
+                if !d.is_ascii_digit() {
+                    break
+                }
+
+                p.push(d);
+                chars.next();
+            }
+
+            precision = Some(p.parse::<usize>().unwrap_or(0));
+        }
+
+        // length modifiers carry no meaning for dynamically typed values
+        while let Some(&l) = chars.peek() {
+            match l {
+                'l' | 'h' | 'z' | 'j' | 't' | 'L' => { chars.next(); },
+                _ => break,
+            }
+        }
+
+        let conversion = match chars.next() {
+            Some(c) => c,
+            None    => return Err("incomplete format specifier at end of string".to_owned()),
+        };
+
+        let arg = match args.next() {
+            Some(a) => a,
+            None    => return Err(format!("missing argument for format specifier %{}", conversion)),
+        };
+
+        let mut text = match conversion {
+            'd' | 'i' | 'u' => {
+                let i = try!(integer(arg, conversion));
+
+                if plus && i >= 0 {
+                    format!("+{}", i)
+                } else {
+                    i.to_string()
+                }
+            }
+
+            'x' => format!("{:x}", try!(integer(arg, conversion))),
+            'X' => format!("{:X}", try!(integer(arg, conversion))),
+            'o' => format!("{:o}", try!(integer(arg, conversion))),
+
+            'c' => match *arg {
+                Value::Integer(i) => match ::std::char::from_u32(i as u32) {
+                    Some(c) => c.to_string(),
+                    None    => return Err(format!("invalid character code: {}", i)),
+                },
+                Value::Text(ref t) => t.chars().take(1).collect(),
+                ref v => return Err(format!("%c expects a char, found {}", v.type_name())),
+            },
+
+            's' => {
+                let s = arg.to_string();
+
+                match precision {
+                    Some(p) => s.chars().take(p).collect(),
+                    None    => s,
+                }
+            }
+
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let f = match *arg {
+                    Value::Float(f)   => f,
+                    Value::Integer(i) => i as f64,
+                    ref v => return Err(format!("%{} expects a number, found {}", conversion, v.type_name())),
+                };
+
+                let p = precision.unwrap_or(6);
+
+                let s = match conversion {
+                    'e' => format!("{:.*e}", p, f),
+                    'E' => format!("{:.*E}", p, f),
+                    'g' | 'G' => format!("{}", f),
+                    _   => format!("{:.*}", p, f),
+                };
+
+                if plus && f >= 0.0 {
+                    format!("+{}", s)
+                } else {
+                    s
+                }
+            }
+
+            c => return Err(format!("unsupported format specifier: %{}", c)),
+        };
+
+        if let Ok(w) = width.parse::<usize>() {
+            let len = text.chars().count();
+
+            if len < w {
+                let pad = w - len;
+
+                if left {
+                    text.push_str(&" ".repeat(pad))
+                } else if zero && conversion != 's' && conversion != 'c' {
+                    let (sign, digits) = if text.starts_with('-') || text.starts_with('+') {
+                        text.split_at(1)
+                    } else {
+                        ("", text.as_str())
+                    };
+
+                    text = format!("{}{}{}", sign, "0".repeat(pad), digits)
+                } else {
+                    text = format!("{}{}", " ".repeat(pad), text)
+                }
+            }
+        }
+
+        out.push_str(&text)
+    }
+
+    Ok(out)
+}
+
+fn integer(v: &Value, conversion: char) -> Result<i64, String> {
+    match *v {
+        Value::Integer(i) => Ok(i),
+        Value::Boolean(b) => Ok(b as i64),
+        ref v             => Err(format!("%{} expects an integer, found {}", conversion, v.type_name())),
+    }
+}
+
+/// Resolves the backslash escapes of a string literal the way the C++ compiler would.
+pub fn unescape(text: &str) -> String {
+    let mut out   = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue
+        }
+
+        match chars.next() {
+            Some('n')  => out.push('\n'),
+            Some('t')  => out.push('\t'),
+            Some('r')  => out.push('\r'),
+            Some('0')  => out.push('\0'),
+            Some('\\') => out.push('\\'),
+            Some('"')  => out.push('"'),
+            Some('\'') => out.push('\''),
+            Some(c)    => {
+                out.push('\\');
+                out.push(c)
+            }
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
@@ -0,0 +1,1408 @@
+pub mod value;
+pub mod builtin;
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use super::ast::{self, Expression, Statement};
+use super::token::Operator;
+
+use self::value::{Value, Function, Class, Object, Enum, Map, Namespace};
+
+/// How deep calls can go before a program is stopped, rather than running it until the
+/// stack runs out.
+pub const CALLS: usize = 10000;
+
+#[derive(Debug, Clone)]
+pub enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+#[derive(Debug)]
+struct Frame {
+    scopes:    Vec<HashMap<String, Value>>,
+    /// The modules pulled in by a `use` in the function, each with the number of scopes
+    /// there were when it was run.
+    uses:      Vec<(usize, Rc<Namespace>)>,
+    this:      Option<Rc<RefCell<Object>>>,
+    namespace: Rc<Namespace>,
+    types:     HashMap<String, Expression>,
+}
+
+impl Frame {
+    fn new(namespace: Rc<Namespace>, this: Option<Rc<RefCell<Object>>>) -> Frame {
+        Frame {
+            scopes:    vec!(HashMap::new()),
+            uses:      Vec::new(),
+            this:      this,
+            namespace: namespace,
+            types:     HashMap::new(),
+        }
+    }
+}
+
+/// Runs a parsed program directly, giving `helix run` the semantics the C++ translation is
+/// expected to have.
+#[derive(Debug)]
+pub struct Interpreter {
+    global: Rc<Namespace>,
+    frames: Vec<Frame>,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        let global = Namespace::new("".to_owned(), None);
+
+        Interpreter {
+            global: global.clone(),
+            frames: vec!(Frame::new(global, None)),
+        }
+    }
+
+    pub fn get_global(&self) -> &Rc<Namespace> {
+        &self.global
+    }
+
+    /// Registers every top-level definition of the program.
+    pub fn load(&mut self, ast: &[Statement]) -> Result<(), String> {
+        for s in ast.iter() {
+            try!(self.define(s))
+        }
+
+        Ok(())
+    }
+
+    /// Calls `main` and hands back whatever it returned.
+    pub fn run(&mut self) -> Result<Value, String> {
+        match self.global.member("main") {
+            Some(main) => self.call(main, Vec::new()),
+            None       => Err("no `main` function to run".to_owned()),
+        }
+    }
+
+    /// Runs a statement entered at the top level of the REPL. Definitions go into the global
+    /// namespace so later entries can use them, and bare expressions hand back their value.
+    pub fn interact(&mut self, st: &Statement) -> Result<Option<Value>, String> {
+        match *st {
+            Statement::Declaration(_, _, _, _) => self.define(st).map(|_| None),
+
+            Statement::Expression(ref e, _) => match **e {
+                Expression::Function(_, _, _, _, _, _) |
+                Expression::FunctionDef(_, _, _, _, _) |
+                Expression::Module(_, _, _)            |
+                Expression::Class(_, _, _, _, _)       |
+                Expression::Struct(_, _, _)            |
+                Expression::Enum(_, _, _)              |
+                Expression::Implement(_, _, _, _)      |
+                Expression::Import(_, _, _)            |
+                Expression::Use(_)                     |
+                Expression::Typed(_, _)                => self.define(st).map(|_| None),
+
+                Expression::Return(ref e, _) => self.evaluate(&**e).map(Some),
+
+                ref e => self.evaluate(e).map(Some),
+            },
+
+            _ => match try!(self.execute(st)) {
+                Flow::Normal    => Ok(None),
+                Flow::Return(v) => Ok(Some(v)),
+                Flow::Break     => Err("`break` outside of a loop".to_owned()),
+                Flow::Continue  => Err("`continue` outside of a loop".to_owned()),
+            },
+        }
+    }
+
+    fn namespace(&self) -> Rc<Namespace> {
+        self.frames.last().unwrap().namespace.clone()
+    }
+
+    fn define(&mut self, st: &Statement) -> Result<(), String> {
+        let namespace = self.namespace();
+
+        match *st {
+            Statement::Declaration(ref n, ref t, ref e, _) => {
+                let value = try!(self.initial(t, &**e));
+
+                namespace.define(n.clone(), value);
+
+                Ok(())
+            }
+
+            Statement::Expression(ref e, _) => match **e {
+                Expression::Function(ref n, ref g, ref a, ref c, ref t, _) => {
+                    let function = try!(self.function(n, g, &[], a, c, t));
+
+                    namespace.define(n.clone(), Value::Function(function));
+
+                    Ok(())
+                }
+
+                Expression::Module(ref n, ref c, _) => {
+                    let module = match namespace.members.borrow().get(n) {
+                        Some(&Value::Module(ref m)) => Some(m.clone()),
+                        _                           => None,
+                    };
+
+                    let module = match module {
+                        Some(m) => m,
+                        None    => {
+                            let m = Namespace::new(n.clone(), Some(namespace.clone()));
+
+                            namespace.define(n.clone(), Value::Module(m.clone()));
+
+                            m
+                        }
+                    };
+
+                    self.frames.push(Frame::new(module, None));
+
+                    let mut result = Ok(());
+
+                    for s in c.iter() {
+                        result = self.define(s);
+
+                        if result.is_err() {
+                            break
+                        }
+                    }
+
+                    self.frames.pop();
+
+                    result
+                }
+
+                Expression::Class(ref n, ref g, ref c, ref p, _) => {
+                    let parent = match *p {
+                        Some(ref p) => match try!(self.evaluate(&**p)) {
+                            Value::Class(c) => Some(c),
+                            v               => return Err(format!("class {} inherits from {}, which is not a class", n, v)),
+                        },
+
+                        None => None,
+                    };
+
+                    let class = try!(self.class(n, g, c, parent));
+
+                    namespace.define(n.clone(), Value::Class(class));
+
+                    Ok(())
+                }
+
+                Expression::Struct(ref n, ref c, _) => {
+                    let class = try!(self.class(n, &[], c, None));
+
+                    namespace.define(n.clone(), Value::Class(class));
+
+                    Ok(())
+                }
+
+                Expression::Enum(ref n, ref v, _) => {
+                    let enumeration = Enum {
+                        name:     n.clone(),
+                        variants: v.iter().map(|&(ref n, ref f, _)| (n.clone(), f.clone())).collect(),
+                    };
+
+                    namespace.define(n.clone(), Value::Enum(Rc::new(enumeration)));
+
+                    Ok(())
+                }
+
+                Expression::Implement(ref n, ref g, ref c, _) => {
+                    let class = match namespace.lookup(n) {
+                        Some(Value::Class(c)) => c,
+                        _                     => return Err(format!("implementation of undeclared class: {}", n)),
+                    };
+
+                    for s in c.iter() {
+                        if let Statement::Expression(ref e, _) = *s {
+                            match **e {
+                                Expression::Function(ref n, ref f, ref a, ref c, ref t, _) => {
+                                    let method = try!(self.function(n, f, g, a, c, t));
+
+                                    class.methods.borrow_mut().insert(n.clone(), method);
+
+                                    continue
+                                }
+
+                                Expression::FunctionDef(_, _, _, _, _) => continue,
+
+                                _ => (),
+                            }
+                        }
+
+                        return Err(format!("unexpected implementation of: {:?}", s))
+                    }
+
+                    Ok(())
+                }
+
+                Expression::Use(ref e) => {
+                    match self.evaluate(&**e) {
+                        Ok(Value::Module(m)) => namespace.uses.borrow_mut().push(m),
+                        Ok(v)                => return Err(format!("can't use {}, it is not a module", v)),
+
+                        // the standard library lives in the interpreter itself
+                        Err(_) => if let Expression::Ident(ref n, _) = **e {
+                            if n != "std" {
+                                return Err(format!("use of undefined module: {}", n))
+                            }
+                        },
+                    }
+
+                    Ok(())
+                }
+
+                Expression::Typed(ref i, ref t) => {
+                    let (name, value) = try!(self.typed(&**i, &**t));
+
+                    namespace.define(name, value);
+
+                    Ok(())
+                }
+
+                // imports are resolved by the C++ compiler and declarations carry no behavior
+                _ => Ok(()),
+            },
+
+            _ => Ok(()),
+        }
+    }
+
+    fn function(&mut self, name: &str, generics: &[String], outer: &[String], args: &[(String, String)], body: &[Statement], retty: &Option<Box<Expression>>) -> Result<Rc<Function>, String> {
+        let retty = match *retty {
+            Some(ref t) => Some(try!(type_name(&**t))),
+            None        => None,
+        };
+
+        Ok(Rc::new(Function {
+            name:      name.to_owned(),
+            generics:  generics.to_vec(),
+            outer:     outer.to_vec(),
+            args:      args.to_vec(),
+            body:      body.to_vec(),
+            retty:     retty,
+            namespace: self.namespace(),
+            captured:  HashMap::new(),
+        }))
+    }
+
+    fn class(&mut self, name: &str, generics: &[String], body: &[Statement], parent: Option<Rc<Class>>) -> Result<Rc<Class>, String> {
+        let mut fields  = Vec::new();
+        let mut methods = HashMap::new();
+
+        for s in body.iter() {
+            match *s {
+                Statement::Declaration(_, _, _, _) => fields.push(s.clone()),
+
+                Statement::Expression(ref e, _) => match **e {
+                    Expression::Typed(_, _) => fields.push(s.clone()),
+
+                    Expression::Function(ref n, ref g, ref a, ref c, ref t, _) => {
+                        methods.insert(n.clone(), try!(self.function(n, g, generics, a, c, t)));
+                    }
+
+                    Expression::FunctionDef(_, _, _, _, _) => (),
+
+                    _ => return Err(format!("unexpected member of class {}: {:?}", name, e)),
+                },
+
+                _ => return Err(format!("unexpected member of class {}: {:?}", name, s)),
+            }
+        }
+
+        Ok(Rc::new(Class {
+            name:      name.to_owned(),
+            generics:  generics.to_vec(),
+            parent:    parent,
+            fields:    fields,
+            methods:   RefCell::new(methods),
+            namespace: self.namespace(),
+        }))
+    }
+
+    /// Creates an object, initializing fields from the base class down and then running the
+    /// constructor, which is the method named after the class.
+    fn instantiate(&mut self, class: &Rc<Class>, types: Vec<Expression>, args: Vec<Value>) -> Result<Value, String> {
+        let object = Rc::new(RefCell::new(Object {
+            class:  class.clone(),
+            types:  types,
+            fields: HashMap::new(),
+        }));
+
+        try!(self.construct(class, &object, args));
+
+        Ok(Value::Object(object))
+    }
+
+    fn construct(&mut self, class: &Rc<Class>, object: &Rc<RefCell<Object>>, args: Vec<Value>) -> Result<(), String> {
+        if let Some(ref p) = class.parent {
+            try!(self.construct(p, object, Vec::new()))
+        }
+
+        let mut frame = Frame::new(class.namespace.clone(), None);
+
+        frame.types = class.generics.iter().cloned().zip(object.borrow().types.iter().cloned()).collect();
+
+        self.frames.push(frame);
+
+        let mut result = Ok(());
+
+        for s in class.fields.iter() {
+            let field = match *s {
+                Statement::Declaration(ref n, ref t, ref e, _) => self.initial(t, &**e).map(|v| (n.clone(), v)),
+
+                Statement::Expression(ref e, _) => match **e {
+                    Expression::Typed(ref i, ref t) => self.typed(&**i, &**t),
+                    _                               => continue,
+                },
+
+                _ => continue,
+            };
+
+            match field {
+                Ok((n, v)) => {
+                    object.borrow_mut().fields.insert(n, v);
+                }
+
+                Err(e) => {
+                    result = Err(e);
+
+                    break
+                }
+            }
+        }
+
+        self.frames.pop();
+
+        try!(result);
+
+        let constructor = class.methods.borrow().get(&class.name).cloned();
+
+        match constructor {
+            Some(c) => {
+                try!(self.invoke(&c, Some(object.clone()), HashMap::new(), args));
+            }
+
+            None => if !args.is_empty() {
+                return Err(format!("class {} has no constructor taking {} argument(s)", class.name, args.len()))
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates a `name: type` declaration into the name and its default value.
+    fn typed(&mut self, i: &Expression, t: &Expression) -> Result<(String, Value), String> {
+        match *i {
+            Expression::Ident(ref n, _) => Ok((n.clone(), try!(self.default(t)))),
+
+            // like in C++, `grid[3][2]` is 3 arrays of 2, so the arrays are made from the
+            // last size written, the innermost one, outward
+            Expression::IndexArray(..) => {
+                let mut sizes  = Vec::new();
+                let mut target = i;
+
+                while let Expression::IndexArray(ref a, ref s) = *target {
+                    match try!(self.evaluate(&**s)) {
+                        Value::Integer(s) if s >= 0 => sizes.push(s as usize),
+                        v                           => return Err(format!("invalid array size: {}", v)),
+                    }
+
+                    target = &**a;
+                }
+
+                let (name, mut value) = try!(self.typed(target, t));
+
+                for size in sizes.into_iter() {
+                    let mut elements = Vec::with_capacity(size);
+
+                    for _ in 0..size {
+                        elements.push(value.copied())
+                    }
+
+                    value = Value::Array(Rc::new(RefCell::new(elements)));
+                }
+
+                Ok((name, value))
+            }
+
+            _ => Err(format!("can't declare {:?}", i)),
+        }
+    }
+
+    /// The value a `var` declaration starts out with, converted to its type if it has one.
+    fn initial(&mut self, t: &Option<Box<Expression>>, e: &Expression) -> Result<Value, String> {
+        let value = try!(self.evaluate(e)).copied();
+
+        let t = match *t {
+            Some(ref t) => self.concrete(&**t),
+            None        => return Ok(value),
+        };
+
+        // an empty map learns what its missing keys read as from its type
+        if let (&Value::Map(ref m), &Expression::Map(ref e, _)) = (&value, &t) {
+            if m.borrow().zero.is_none() && e.len() == 1 {
+                m.borrow_mut().zero = Some(try!(self.default(&e[0].1)));
+            }
+        }
+
+        convert(value, &try!(type_name(&t)))
+    }
+
+    /// A type with the type parameters in scope replaced by what they stand for.
+    fn concrete(&self, t: &Expression) -> Expression {
+        match *t {
+            Expression::Ident(ref n, _) => match self.frames.last().unwrap().types.get(n) {
+                Some(t) => t.clone(),
+                None    => t.clone(),
+            },
+
+            Expression::Generic(ref a, ref b) => Expression::Generic(
+                    Box::new(self.concrete(&**a)),
+                    b.iter().map(|t| self.concrete(t)).collect(),
+                ),
+
+            ref t => t.clone(),
+        }
+    }
+
+    fn default(&mut self, t: &Expression) -> Result<Value, String> {
+        let t = &self.concrete(t);
+
+        match *t {
+            Expression::List(_, _) => return Ok(Value::Array(Rc::new(RefCell::new(Vec::new())))),
+
+            // an empty `std::function`, which can't be called
+            Expression::FunctionType(_, _, _) => return Ok(Value::Void),
+
+            Expression::Map(ref e, _) if e.len() == 1 => return Ok(Value::Map(Rc::new(RefCell::new(Map {
+                    entries: Vec::new(),
+                    zero:    Some(try!(self.default(&e[0].1))),
+                })))),
+
+            _ => (),
+        }
+
+        if let Expression::Generic(ref a, ref b) = *t {
+            return match try!(self.evaluate(&**a)) {
+                Value::Class(c) => self.instantiate(&c, b.clone(), Vec::new()),
+                v               => Err(format!("{} is not a generic type", v)),
+            }
+        }
+
+        if let Expression::Ident(ref t, _) = *t {
+            match t.as_str() {
+                "int" | "char" | "short" | "long" | "unsigned" | "size_t" => return Ok(Value::Integer(0)),
+                "float" | "double" => return Ok(Value::Float(0.0)),
+                "bool"             => return Ok(Value::Boolean(false)),
+                "string"           => return Ok(Value::Text("".to_owned())),
+                _                  => (),
+            }
+        }
+
+        match try!(self.evaluate(t)) {
+            Value::Class(c) => self.instantiate(&c, Vec::new(), Vec::new()),
+
+            // like its C++ translation, an enum starts out as its first variant
+            Value::Enum(e) => {
+                let (name, fields) = match e.variants.first() {
+                    Some(&(ref n, ref f)) => (n.clone(), f.clone()),
+                    None                  => return Err(format!("enum {} has no variants", e.name)),
+                };
+
+                let mut values = Vec::new();
+
+                for &(ref t, _) in fields.iter() {
+                    values.push(try!(self.default(&Expression::Ident(t.clone(), (0, 0)))))
+                }
+
+                Ok(Value::Variant(e, name, values))
+            }
+
+            v => Err(format!("{} is not a type", v)),
+        }
+    }
+
+    pub fn call(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, String> {
+        self.instance(callee, Vec::new(), args)
+    }
+
+    /// Calls a value with the type arguments written at the call, like `max[int](a, b)`.
+    /// Without them, type parameters stand for the types of the arguments passed for them.
+    fn instance(&mut self, callee: Value, types: Vec<Expression>, args: Vec<Value>) -> Result<Value, String> {
+        match callee {
+            Value::Function(ref f) => {
+                let types = bindings(&f.generics, &f.args, types, &args);
+
+                self.invoke(f, None, types, args)
+            }
+
+            // a class's name in its own methods makes another object rather than calling the
+            // constructor again
+            Value::Method(ref o, ref f) if f.name == o.borrow().class.name => {
+                let class = o.borrow().class.clone();
+
+                self.instance(Value::Class(class), types, args)
+            }
+
+            Value::Method(ref o, ref f) => {
+                let types = bindings(&f.generics, &f.args, types, &args);
+
+                self.invoke(f, Some(o.clone()), types, args)
+            }
+
+            Value::Class(ref c) => {
+                let constructor = c.methods.borrow().get(&c.name).cloned();
+
+                let types = match constructor {
+                    Some(ref f) if types.is_empty() => {
+                        let types = bindings(&f.outer, &f.args, types, &args);
+
+                        f.outer.iter().filter_map(|g| types.get(g).cloned()).collect()
+                    }
+
+                    _ => types,
+                };
+
+                self.instantiate(c, types, args)
+            }
+
+            Value::Builtin(ref n)            => builtin::call(n, args),
+            Value::Member(ref r, ref n)      => builtin::member(r, n, args),
+            Value::Constructor(ref e, ref n) => variant(e, n, args),
+            v                                => Err(format!("{} is not callable", v)),
+        }
+    }
+
+    fn invoke(&mut self, function: &Rc<Function>, this: Option<Rc<RefCell<Object>>>, mut types: HashMap<String, Expression>, args: Vec<Value>) -> Result<Value, String> {
+        if args.len() != function.args.len() {
+            return Err(format!(
+                    "{} takes {} argument(s) but {} were given",
+                    function.name, function.args.len(), args.len(),
+                ))
+        }
+
+        // methods also see the type arguments of the object they are called on
+        if let Some(ref o) = this {
+            types.extend(function.outer.iter().cloned().zip(o.borrow().types.iter().cloned()));
+        }
+
+        let mut frame = Frame::new(function.namespace.clone(), this);
+
+        frame.types     = types;
+        frame.scopes[0] = function.captured.clone();
+
+        for (&(ref t, ref n), v) in function.args.iter().zip(args.into_iter()) {
+            frame.scopes[0].insert(n.clone(), try!(convert(v.copied(), &bound(&frame.types, t))));
+        }
+
+        let retty = function.retty.as_ref().map(|t| bound(&frame.types, t));
+
+        // the first frame is the program's own
+        if self.frames.len() > CALLS {
+            return Err(format!("calls nested more than {} deep, in {}", CALLS, function.name))
+        }
+
+        self.frames.push(frame);
+
+        let mut result = Ok(Value::Void);
+
+        for s in function.body.iter() {
+            match self.execute(s) {
+                Ok(Flow::Normal)    => continue,
+                Ok(Flow::Return(v)) => result = Ok(v),
+                Ok(_)               => result = Err("`break` or `continue` outside of a loop".to_owned()),
+                Err(e)              => result = Err(e),
+            }
+
+            break
+        }
+
+        self.frames.pop();
+
+        let value = try!(result);
+
+        match retty {
+            Some(ref t) if t != "void" && t != &function.name => convert(value, t),
+            _                                                  => Ok(value),
+        }
+    }
+
+    pub fn execute(&mut self, st: &Statement) -> Result<Flow, String> {
+        match *st {
+            Statement::Expression(ref e, _) => match **e {
+                Expression::Return(ref e, _) => Ok(Flow::Return(try!(self.evaluate(&**e)).copied())),
+
+                Expression::Typed(ref i, ref t) => {
+                    let (name, value) = try!(self.typed(&**i, &**t));
+
+                    self.declare(name, value);
+
+                    Ok(Flow::Normal)
+                }
+
+                // the names of the module are in sight until the end of the block
+                Expression::Use(ref e) => {
+                    match self.evaluate(&**e) {
+                        Ok(Value::Module(m)) => {
+                            let frame = self.frames.last_mut().unwrap();
+                            let depth = frame.scopes.len();
+
+                            frame.uses.push((depth, m))
+                        }
+
+                        Ok(v) => return Err(format!("can't use {}, it is not a module", v)),
+
+                        Err(_) => if let Expression::Ident(ref n, _) = **e {
+                            if n != "std" {
+                                return Err(format!("use of undefined module: {}", n))
+                            }
+                        },
+                    }
+
+                    Ok(Flow::Normal)
+                }
+
+                ref e => {
+                    try!(self.evaluate(e));
+
+                    Ok(Flow::Normal)
+                }
+            },
+
+            Statement::Declaration(ref n, ref t, ref e, _) => {
+                let value = try!(self.initial(t, &**e));
+
+                self.declare(n.clone(), value);
+
+                Ok(Flow::Normal)
+            }
+
+            Statement::Assignment(ref n, ref e, _) => {
+                let value = try!(self.evaluate(&**e)).copied();
+
+                try!(self.store(&**n, value));
+
+                Ok(Flow::Normal)
+            }
+
+            Statement::CompoundAssignment(ref n, ref o, ref e, _) => {
+                let current = try!(self.evaluate(&**n));
+                let value   = try!(self.evaluate(&**e));
+
+                let result = try!(operation(current, o, value));
+
+                try!(self.store(&**n, result));
+
+                Ok(Flow::Normal)
+            }
+
+            Statement::Block(ref c) => {
+                self.frames.last_mut().unwrap().scopes.push(HashMap::new());
+
+                let mut result = Ok(Flow::Normal);
+
+                for s in c.iter() {
+                    result = self.execute(s);
+
+                    match result {
+                        Ok(Flow::Normal) => continue,
+                        _                => break,
+                    }
+                }
+
+                let frame = self.frames.last_mut().unwrap();
+
+                frame.scopes.pop();
+
+                let depth = frame.scopes.len();
+
+                frame.uses.retain(|&(d, _)| d <= depth);
+
+                result
+            }
+
+            Statement::If(ref b, ref c1, _) => {
+                for &(ref e, ref c) in b.iter() {
+                    if try!(self.condition(e)) {
+                        return self.execute(c)
+                    }
+                }
+
+                match *c1 {
+                    Some(ref c1) => self.execute(&**c1),
+                    None         => Ok(Flow::Normal),
+                }
+            }
+
+            Statement::While(ref e, ref c, _) => {
+                while try!(self.condition(&**e)) {
+                    if let Some(flow) = try!(self.iteration(&**c)) {
+                        return Ok(flow)
+                    }
+                }
+
+                Ok(Flow::Normal)
+            }
+
+            Statement::ForRange(ref i, ref a, ref b, ref c, _) => {
+                let (start, end) = match (try!(self.evaluate(&**a)), try!(self.evaluate(&**b))) {
+                    (Value::Integer(a), Value::Integer(b)) => (a, b),
+                    (a, b) => return Err(format!(
+                            "range bounds must be integers, found {} and {}",
+                            a.type_name(), b.type_name(),
+                        )),
+                };
+
+                for n in start..end {
+                    if let Some(flow) = try!(self.iteration_with(i, Value::Integer(n), &**c)) {
+                        return Ok(flow)
+                    }
+                }
+
+                Ok(Flow::Normal)
+            }
+
+            Statement::For(ref i, ref e, ref c, _) => {
+                let elements = match try!(self.evaluate(&**e)) {
+                    Value::Array(a)  => a.borrow().clone(),
+                    Value::Text(t)   => t.chars().map(|c| Value::Integer(c as i64)).collect(),
+                    v                => return Err(format!("{} is not iterable", v.type_name())),
+                };
+
+                // each element is a copy, like C++ binds it with `auto`
+                for v in elements.into_iter() {
+                    if let Some(flow) = try!(self.iteration_with(i, v.copied(), &**c)) {
+                        return Ok(flow)
+                    }
+                }
+
+                Ok(Flow::Normal)
+            }
+
+            Statement::Match(ref e, ref a, _) => {
+                let (name, fields) = match try!(self.evaluate(&**e)) {
+                    Value::Variant(_, n, f) => (n, f),
+                    v                       => return Err(format!("{} can't be matched on", v.type_name())),
+                };
+
+                let arm = a.iter().find(|&&(ref v, _, _, _)| *v == name || v == "_");
+
+                let (bindings, body) = match arm {
+                    Some(&(ref v, _, ref c, _)) if v == "_" => (HashMap::new(), c),
+                    Some(&(_, ref b, ref c, _))             => (b.iter().cloned().zip(fields.into_iter()).collect(), c),
+                    None                                    => return Err(format!("no arm matches {}", name)),
+                };
+
+                self.frames.last_mut().unwrap().scopes.push(bindings);
+
+                let result = self.execute(body);
+
+                self.frames.last_mut().unwrap().scopes.pop();
+
+                result
+            }
+
+            Statement::Break(_)    => Ok(Flow::Break),
+            Statement::Continue(_) => Ok(Flow::Continue),
+        }
+    }
+
+    /// Runs a loop body, telling the caller whether to keep looping; `Some` carries the
+    /// result to hand up when the loop ends early.
+    fn iteration(&mut self, body: &Statement) -> Result<Option<Flow>, String> {
+        match try!(self.execute(body)) {
+            Flow::Normal | Flow::Continue => Ok(None),
+            Flow::Break                   => Ok(Some(Flow::Normal)),
+            flow                          => Ok(Some(flow)),
+        }
+    }
+
+    /// Runs a loop body with the loop variable bound in a scope of its own.
+    fn iteration_with(&mut self, name: &str, value: Value, body: &Statement) -> Result<Option<Flow>, String> {
+        let mut scope = HashMap::new();
+
+        scope.insert(name.to_owned(), value);
+
+        self.frames.last_mut().unwrap().scopes.push(scope);
+
+        let result = self.iteration(body);
+
+        self.frames.last_mut().unwrap().scopes.pop();
+
+        result
+    }
+
+    fn condition(&mut self, e: &Expression) -> Result<bool, String> {
+        match try!(self.evaluate(e)) {
+            Value::Boolean(b) => Ok(b),
+            Value::Integer(i) => Ok(i != 0),
+            Value::Float(f)   => Ok(f != 0.0),
+            v                 => Err(format!("{} can't be used as a condition", v.type_name())),
+        }
+    }
+
+    fn declare(&mut self, name: String, value: Value) {
+        self.frames.last_mut().unwrap().scopes.last_mut().unwrap().insert(name, value);
+    }
+
+    /// Writes a value to whatever the assignment target expression denotes.
+    fn store(&mut self, target: &Expression, value: Value) -> Result<(), String> {
+        match *target {
+            Expression::Ident(ref n, _) => self.assign(n, value),
+
+            Expression::IndexDot(ref a, ref b) | Expression::IndexColon(ref a, ref b) => {
+                let name = match **b {
+                    Expression::Ident(ref n, _) => n,
+                    _                           => return Err(format!("invalid member access: {:?}", b)),
+                };
+
+                match try!(self.evaluate(&**a)) {
+                    Value::Object(ref o) => {
+                        let mut object = o.borrow_mut();
+
+                        match object.fields.get_mut(name) {
+                            Some(v) => {
+                                *v = value;
+
+                                Ok(())
+                            }
+
+                            None => Err(format!("{} has no field named {}", object.class.name, name)),
+                        }
+                    }
+
+                    Value::Module(ref m) => if m.assign(name, value) {
+                        Ok(())
+                    } else {
+                        Err(format!("module {} has no member named {}", m.name, name))
+                    },
+
+                    v => Err(format!("can't assign to a member of {}", v.type_name())),
+                }
+            }
+
+            Expression::IndexArray(ref a, ref i) => {
+                let base  = try!(self.evaluate(&**a));
+                let index = try!(self.evaluate(&**i));
+
+                match (base, index) {
+                    (Value::Array(ref a), Value::Integer(i)) => match a.borrow_mut().get_mut(i as usize) {
+                        Some(v) if i >= 0 => {
+                            *v = value;
+
+                            Ok(())
+                        }
+
+                        _ => Err(format!("array index out of bounds: {}", i)),
+                    },
+
+                    (Value::Array(_), i) => Err(format!("array index must be an integer, found {}", i.type_name())),
+
+                    (Value::Map(ref m), k) => {
+                        m.borrow_mut().insert(k.copied(), value);
+
+                        Ok(())
+                    }
+
+                    (v, _) => Err(format!("can't assign to an element of {}", v.type_name())),
+                }
+            }
+
+            _ => Err(format!("invalid assignment target: {:?}", target)),
+        }
+    }
+
+    fn assign(&mut self, name: &str, value: Value) -> Result<(), String> {
+        let frame = self.frames.last_mut().unwrap();
+
+        for scope in frame.scopes.iter_mut().rev() {
+            if let Some(v) = scope.get_mut(name) {
+                *v = value;
+
+                return Ok(())
+            }
+        }
+
+        for &(_, ref m) in frame.uses.iter().rev() {
+            if let Some(v) = m.members.borrow_mut().get_mut(name) {
+                *v = value;
+
+                return Ok(())
+            }
+        }
+
+        if let Some(ref o) = frame.this {
+            if let Some(v) = o.borrow_mut().fields.get_mut(name) {
+                *v = value;
+
+                return Ok(())
+            }
+        }
+
+        if frame.namespace.assign(name, value) {
+            return Ok(())
+        }
+
+        Err(format!("assignment to undeclared variable: {}", name))
+    }
+
+    fn lookup(&self, name: &str) -> Option<Value> {
+        let frame = self.frames.last().unwrap();
+
+        for scope in frame.scopes.iter().rev() {
+            if let Some(v) = scope.get(name) {
+                return Some(v.clone())
+            }
+        }
+
+        for &(_, ref m) in frame.uses.iter().rev() {
+            if let Some(v) = m.member(name) {
+                return Some(v)
+            }
+        }
+
+        if let Some(ref o) = frame.this {
+            if let Some(v) = o.borrow().fields.get(name) {
+                return Some(v.clone())
+            }
+
+            let method = o.borrow().class.method(name);
+
+            if let Some(m) = method {
+                return Some(Value::Method(o.clone(), m))
+            }
+        }
+
+        match frame.namespace.lookup(name) {
+            Some(v) => Some(v),
+            None    => builtin::lookup(name),
+        }
+    }
+
+    pub fn evaluate(&mut self, ex: &Expression) -> Result<Value, String> {
+        match *ex {
+            Expression::Integer(ref i) => Ok(Value::Integer(*i)),
+            Expression::Float(ref f)   => Ok(Value::Float(*f)),
+            Expression::Boolean(ref b) => Ok(Value::Boolean(*b)),
+            Expression::Text(ref t)    => Ok(Value::Text(builtin::unescape(t))),
+
+            Expression::Ident(ref n, _) => {
+                if n == "void" {
+                    return Ok(Value::Void)
+                }
+
+                match self.lookup(n) {
+                    Some(v) => Ok(v),
+                    None    => Err(format!("undefined: {}", n)),
+                }
+            }
+
+            Expression::Operation(ref l, Operator::And, ref r, _) => {
+                if !try!(self.condition(&**l)) {
+                    return Ok(Value::Boolean(false))
+                }
+
+                Ok(Value::Boolean(try!(self.condition(&**r))))
+            }
+
+            Expression::Operation(ref l, Operator::Or, ref r, _) => {
+                if try!(self.condition(&**l)) {
+                    return Ok(Value::Boolean(true))
+                }
+
+                Ok(Value::Boolean(try!(self.condition(&**r))))
+            }
+
+            Expression::Unary(Operator::Not, ref e, _) => Ok(Value::Boolean(!try!(self.condition(&**e)))),
+
+            Expression::Unary(ref o, ref e, _) => {
+                let value = try!(self.evaluate(&**e));
+
+                unary(o, value)
+            }
+
+            Expression::Operation(ref l, ref o, ref r, _) => {
+                let left  = try!(self.evaluate(&**l));
+                let right = try!(self.evaluate(&**r));
+
+                operation(left, o, right)
+            }
+
+            Expression::Call(ref c, ref a) => {
+                let (callee, types) = match **c {
+                    Expression::Generic(ref c, ref t) => (c, t.iter().map(|t| self.concrete(t)).collect()),
+                    _                                 => (c, Vec::new()),
+                };
+
+                let callee = try!(self.evaluate(&**callee));
+                let args   = try!(self.arguments(a));
+
+                self.instance(callee, types, args)
+            }
+
+            // type arguments only matter when calling or declaring
+            Expression::Generic(ref a, _) => self.evaluate(&**a),
+
+            Expression::List(ref e, _) => {
+                let mut elements = Vec::with_capacity(e.len());
+
+                for e in e.iter() {
+                    elements.push(try!(self.evaluate(e)).copied())
+                }
+
+                Ok(Value::Array(Rc::new(RefCell::new(elements))))
+            }
+
+            Expression::Map(ref e, _) => {
+                let mut map = Map {
+                    entries: Vec::new(),
+                    zero:    None,
+                };
+
+                for &(ref k, ref v) in e.iter() {
+                    let k = try!(self.evaluate(k)).copied();
+                    let v = try!(self.evaluate(v)).copied();
+
+                    // the first of two equal keys is the one kept, as in C++
+                    if map.get(&k).is_none() {
+                        map.zero = Some(v.zeroed());
+                        map.insert(k, v)
+                    }
+                }
+
+                Ok(Value::Map(Rc::new(RefCell::new(map))))
+            }
+
+            Expression::IndexDot(ref a, ref b) | Expression::IndexColon(ref a, ref b) => {
+                let base = try!(self.evaluate(&**a));
+
+                match **b {
+                    Expression::Ident(ref n, _) => get(&base, n),
+                    _                           => Err(format!("invalid member access: {:?}", b)),
+                }
+            }
+
+            Expression::IndexArray(ref a, ref i) => {
+                let base  = try!(self.evaluate(&**a));
+                let index = try!(self.evaluate(&**i));
+
+                index_array(&base, &index)
+            }
+
+            Expression::Lambda(ref a, ref c, ref t, _) => {
+                let mut function = try!(self.function("lambda", &[], &[], a, c, t));
+                let frame        = self.frames.last().unwrap();
+
+                // like `[=]`, every local in sight is copied as it is now
+                let captured = &mut Rc::get_mut(&mut function).unwrap().captured;
+
+                for scope in frame.scopes.iter() {
+                    for (n, v) in scope.iter() {
+                        captured.insert(n.clone(), v.copied());
+                    }
+                }
+
+                match frame.this {
+                    Some(ref o) => Ok(Value::Method(o.clone(), function)),
+                    None        => Ok(Value::Function(function)),
+                }
+            }
+
+            Expression::Return(_, _) => Err("return outside of a function body".to_owned()),
+
+            _ => Err(format!("can't evaluate {:?}", ex)),
+        }
+    }
+
+    fn arguments(&mut self, args: &[Expression]) -> Result<Vec<Value>, String> {
+        let mut values = Vec::new();
+
+        for a in args.iter() {
+            values.push(try!(self.evaluate(a)))
+        }
+
+        Ok(values)
+    }
+}
+
+pub fn get(base: &Value, name: &str) -> Result<Value, String> {
+    let member = match *base {
+        Value::Module(ref m) => m.member(name),
+
+        Value::Class(ref c) => c.method(name).map(Value::Function),
+
+        Value::Array(_) | Value::Map(_) if builtin::is_member(base, name) => Some(Value::Member(Box::new(base.clone()), name.to_owned())),
+
+        Value::Enum(ref e) => e.fields(name).map(|f| match f.len() {
+            0 => Value::Variant(e.clone(), name.to_owned(), Vec::new()),
+            _ => Value::Constructor(e.clone(), name.to_owned()),
+        }),
+
+        Value::Object(ref o) => {
+            let field = o.borrow().fields.get(name).cloned();
+
+            match field {
+                Some(v) => Some(v),
+                None    => {
+                    let method = o.borrow().class.method(name);
+
+                    method.map(|m| Value::Method(o.clone(), m))
+                }
+            }
+        }
+
+        ref v => return Err(format!("{} has no members", v.type_name())),
+    };
+
+    match member {
+        Some(v) => Ok(v),
+        None    => Err(format!("{} has no member named {}", base.type_name(), name)),
+    }
+}
+
+/// Makes a value of a variant that carries fields.
+pub fn variant(enumeration: &Rc<Enum>, name: &str, args: Vec<Value>) -> Result<Value, String> {
+    let fields = enumeration.fields(name).unwrap_or(&[]);
+
+    if args.len() != fields.len() {
+        return Err(format!("{}::{} takes {} field(s) but {} were given", enumeration.name, name, fields.len(), args.len()))
+    }
+
+    let mut values = Vec::new();
+
+    for (&(ref t, _), v) in fields.iter().zip(args.into_iter()) {
+        values.push(try!(convert(v.copied(), t)))
+    }
+
+    Ok(Value::Variant(enumeration.clone(), name.to_owned(), values))
+}
+
+pub fn index_array(base: &Value, index: &Value) -> Result<Value, String> {
+    // like `std::unordered_map`, reading a missing key adds it
+    if let Value::Map(ref m) = *base {
+        let mut map = m.borrow_mut();
+
+        if let Some(v) = map.get(index) {
+            return Ok(v.clone())
+        }
+
+        let zero = match map.zero.clone().or_else(|| map.entries.first().map(|&(_, ref v)| v.zeroed())) {
+            Some(z) => z,
+            None    => return Err(format!("no entry for {} in the map", index)),
+        };
+
+        map.insert(index.copied(), zero.clone());
+
+        return Ok(zero)
+    }
+
+    let i = match *index {
+        Value::Integer(i) => i,
+        ref v             => return Err(format!("array index must be an integer, found {}", v.type_name())),
+    };
+
+    match *base {
+        Value::Array(ref a) => match a.borrow().get(i as usize) {
+            Some(v) if i >= 0 => Ok(v.clone()),
+            _                 => Err(format!("array index out of bounds: {}", i)),
+        },
+
+        Value::Text(ref t) => match t.chars().nth(i as usize) {
+            Some(c) if i >= 0 => Ok(Value::Integer(c as i64)),
+            _                 => Err(format!("string index out of bounds: {}", i)),
+        },
+
+        ref v => Err(format!("{} can't be indexed", v.type_name())),
+    }
+}
+
+fn type_name(t: &Expression) -> Result<String, String> {
+    match *t {
+        Expression::Ident(ref n, _)          => Ok(n.clone()),
+        Expression::IndexColon(ref a, ref b) => Ok(format!("{}::{}", try!(type_name(&**a)), try!(type_name(&**b)))),
+        Expression::Generic(_, _)            |
+        Expression::List(_, _)               |
+        Expression::Map(_, _)                |
+        Expression::FunctionType(_, _, _)    => Ok(ast::written(t)),
+        _                                    => Err(format!("invalid type: {:?}", t)),
+    }
+}
+
+/// What the type parameters of a call stand for, either given explicitly or taken from
+/// the arguments passed for parameters declared with them.
+fn bindings(generics: &[String], params: &[(String, String)], types: Vec<Expression>, args: &[Value]) -> HashMap<String, Expression> {
+    if !types.is_empty() {
+        return generics.iter().cloned().zip(types.into_iter()).collect()
+    }
+
+    let mut bindings = HashMap::new();
+
+    for (&(ref t, _), v) in params.iter().zip(args.iter()) {
+        if generics.contains(t) && !bindings.contains_key(t) {
+            bindings.insert(t.clone(), Expression::Ident(v.type_name(), (0, 0)));
+        }
+    }
+
+    bindings
+}
+
+/// The name of a type once the type parameters it may be are bound.
+fn bound(types: &HashMap<String, Expression>, t: &str) -> String {
+    match types.get(t) {
+        Some(t) => type_name(t).unwrap_or_else(|_| ast::written(t)),
+        None    => t.to_owned(),
+    }
+}
+
+/// Applies the implicit conversion C++ performs when a value is bound to a declared type.
+pub fn convert(value: Value, t: &str) -> Result<Value, String> {
+    match (t, value) {
+        ("int", Value::Float(f))   |
+        ("long", Value::Float(f))  => Ok(Value::Integer(f as i64)),
+        ("int", Value::Boolean(b)) => Ok(Value::Integer(b as i64)),
+
+        ("float", Value::Integer(i))  |
+        ("double", Value::Integer(i)) => Ok(Value::Float(i as f64)),
+
+        ("bool", Value::Integer(i)) => Ok(Value::Boolean(i != 0)),
+
+        ("int", v @ Value::Text(_)) |
+        ("float", v @ Value::Text(_)) |
+        ("bool", v @ Value::Text(_)) => Err(format!("can't convert {} to {}", v.type_name(), t)),
+
+        (_, Value::Object(o)) => Ok(Value::Object(sliced(o, t))),
+
+        (_, v) => Ok(v),
+    }
+}
+
+/// An object bound to one of its base classes, which becomes an object of the base class
+/// like C++ slices it, so its methods are the ones of the base class from then on.
+fn sliced(object: Rc<RefCell<Object>>, t: &str) -> Rc<RefCell<Object>> {
+    let name = t.rsplit("::").next().unwrap_or(t);
+
+    let mut base = object.borrow().class.parent.clone();
+
+    while let Some(c) = base.clone() {
+        if c.name == name {
+            break
+        }
+
+        base = c.parent.clone()
+    }
+
+    let class = match base {
+        Some(c) => c,
+        None    => return object,
+    };
+
+    let copy = {
+        let o = object.borrow();
+
+        Object {
+            class:  class,
+            types:  o.types.clone(),
+            fields: o.fields.iter().map(|(k, v)| (k.clone(), v.copied())).collect(),
+        }
+    };
+
+    Rc::new(RefCell::new(copy))
+}
+
+pub fn unary(op: &Operator, value: Value) -> Result<Value, String> {
+    match (op, value) {
+        (&Operator::Minus, Value::Integer(i))  => Ok(Value::Integer(i.wrapping_neg())),
+        (&Operator::Minus, Value::Float(f))    => Ok(Value::Float(-f)),
+        (&Operator::Minus, Value::Boolean(b))  => Ok(Value::Integer(-(b as i64))),
+        (&Operator::BitNot, Value::Integer(i)) => Ok(Value::Integer(!i)),
+        (&Operator::BitNot, Value::Boolean(b)) => Ok(Value::Integer(!(b as i64))),
+
+        (op, v) => Err(format!("invalid operand to {}: {}", op, v.type_name())),
+    }
+}
+
+pub fn operation(left: Value, op: &Operator, right: Value) -> Result<Value, String> {
+    match (left, right) {
+        (Value::Integer(a), Value::Integer(b)) => match *op {
+            Operator::Plus  => Ok(Value::Integer(a.wrapping_add(b))),
+            Operator::Minus => Ok(Value::Integer(a.wrapping_sub(b))),
+            Operator::Mul   => Ok(Value::Integer(a.wrapping_mul(b))),
+
+            Operator::Div | Operator::Mod if b == 0 => Err("division by zero".to_owned()),
+
+            // both truncate toward zero, exactly like C++
+            Operator::Div => Ok(Value::Integer(a.wrapping_div(b))),
+            Operator::Mod => Ok(Value::Integer(a.wrapping_rem(b))),
+
+            Operator::BitAnd => Ok(Value::Integer(a & b)),
+            Operator::BitOr  => Ok(Value::Integer(a | b)),
+            Operator::BitXor => Ok(Value::Integer(a ^ b)),
+
+            Operator::Shl | Operator::Shr if b < 0 || b >= 64 => {
+                Err(format!("shift amount out of range: {}", b))
+            }
+
+            Operator::Shl => Ok(Value::Integer(a << b)),
+            Operator::Shr => Ok(Value::Integer(a >> b)),
+
+            ref o => compare(o, &a, &b),
+        },
+
+        (Value::Integer(a), Value::Float(b)) => operation(Value::Float(a as f64), op, Value::Float(b)),
+        (Value::Float(a), Value::Integer(b)) => operation(Value::Float(a), op, Value::Float(b as f64)),
+
+        (Value::Float(a), Value::Float(b)) => match *op {
+            Operator::Plus  => Ok(Value::Float(a + b)),
+            Operator::Minus => Ok(Value::Float(a - b)),
+            Operator::Mul   => Ok(Value::Float(a * b)),
+            Operator::Div   => Ok(Value::Float(a / b)),
+
+            ref o => compare(o, &a, &b),
+        },
+
+        (Value::Text(a), Value::Text(b)) => match *op {
+            Operator::Plus => Ok(Value::Text(a + &b)),
+
+            ref o => compare(o, &a, &b),
+        },
+
+        (Value::Boolean(a), Value::Boolean(b)) => match *op {
+            Operator::Equal  => Ok(Value::Boolean(a == b)),
+            Operator::NEqual => Ok(Value::Boolean(a != b)),
+
+            _ => operation(Value::Integer(a as i64), op, Value::Integer(b as i64)),
+        },
+
+        (Value::Variant(a, x, _), Value::Variant(b, y, _)) => match *op {
+            Operator::Equal  => Ok(Value::Boolean(Rc::ptr_eq(&a, &b) && x == y)),
+            Operator::NEqual => Ok(Value::Boolean(!(Rc::ptr_eq(&a, &b) && x == y))),
+
+            ref o => Err(format!("invalid operands to {}: {} and {}", o, a.name, b.name)),
+        },
+
+        (Value::Boolean(a), b) => operation(Value::Integer(a as i64), op, b),
+        (a, Value::Boolean(b)) => operation(a, op, Value::Integer(b as i64)),
+
+        (a, b) => Err(format!(
+                "invalid operands to {}: {} and {}",
+                op, a.type_name(), b.type_name(),
+            )),
+    }
+}
+
+fn compare<T: PartialOrd + ::std::fmt::Debug>(op: &Operator, a: &T, b: &T) -> Result<Value, String> {
+    match *op {
+        Operator::Equal   => Ok(Value::Boolean(a == b)),
+        Operator::NEqual  => Ok(Value::Boolean(a != b)),
+        Operator::Lt      => Ok(Value::Boolean(a < b)),
+        Operator::Gt      => Ok(Value::Boolean(a > b)),
+        Operator::LtEqual => Ok(Value::Boolean(a <= b)),
+        Operator::GtEqual => Ok(Value::Boolean(a >= b)),
+        ref o             => Err(format!("invalid operands to {}: {:?} and {:?}", o, a, b)),
+    }
+}
@@ -0,0 +1,363 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+
+use super::super::ast::{Expression, Statement};
+use super::super::bytecode::vm::{Closure, Instance};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Void,
+
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Text(String),
+
+    Array(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<Map>>),
+
+    Function(Rc<Function>),
+    Method(Rc<RefCell<Object>>, Rc<Function>),
+    Builtin(String),
+    /// A method of a list or map, along with the one it was taken from.
+    Member(Box<Value>, String),
+
+    Class(Rc<Class>),
+    Object(Rc<RefCell<Object>>),
+    Module(Rc<Namespace>),
+
+    Enum(Rc<Enum>),
+    /// A value of an enum: the variant it is, along with the values of its fields.
+    Variant(Rc<Enum>, String, Vec<Value>),
+    /// A variant that carries fields, waiting to be called with them.
+    Constructor(Rc<Enum>, String),
+
+    /// A function compiled to bytecode, along with what it captured.
+    Closure(Rc<Closure>),
+    /// An object made by the bytecode VM.
+    Instance(Rc<RefCell<Instance>>),
+}
+
+impl Value {
+    pub fn type_name(&self) -> String {
+        match *self {
+            Value::Void                 => "void".to_owned(),
+            Value::Integer(_)           => "int".to_owned(),
+            Value::Float(_)             => "float".to_owned(),
+            Value::Boolean(_)           => "bool".to_owned(),
+            Value::Text(_)              => "string".to_owned(),
+            Value::Array(_)             => "array".to_owned(),
+            Value::Map(_)               => "map".to_owned(),
+            Value::Function(_)          |
+            Value::Method(_, _)         |
+            Value::Builtin(_)           |
+            Value::Member(_, _)         |
+            Value::Constructor(_, _)    |
+            Value::Closure(_)           => "function".to_owned(),
+            Value::Class(ref c)         => format!("class {}", c.name),
+            Value::Object(ref o)        => o.borrow().class.name.clone(),
+            Value::Module(ref m)        => format!("module {}", m.name),
+            Value::Enum(ref e)          => format!("enum {}", e.name),
+            Value::Variant(ref e, _, _) => e.name.clone(),
+            Value::Instance(ref o)      => o.borrow().class.name.clone(),
+        }
+    }
+
+    /// C++ copies objects and arrays on assignment and when passing them by value, so
+    /// the interpreter does the same whenever a value is bound to a name.
+    pub fn copied(&self) -> Value {
+        match *self {
+            Value::Array(ref a) => Value::Array(
+                    Rc::new(RefCell::new(a.borrow().iter().map(|v| v.copied()).collect())),
+                ),
+
+            Value::Object(ref o) => {
+                let object = o.borrow();
+
+                let mut fields = HashMap::new();
+
+                for (k, v) in object.fields.iter() {
+                    fields.insert(k.clone(), v.copied());
+                }
+
+                Value::Object(Rc::new(RefCell::new(Object {
+                    class:  object.class.clone(),
+                    types:  object.types.clone(),
+                    fields: fields,
+                })))
+            }
+
+            Value::Instance(ref o) => {
+                let object = o.borrow();
+
+                Value::Instance(Rc::new(RefCell::new(Instance {
+                    class:  object.class.clone(),
+                    fields: object.fields.iter().map(|(k, v)| (k.clone(), v.copied())).collect(),
+                })))
+            }
+
+            Value::Variant(ref e, ref n, ref f) => Value::Variant(e.clone(), n.clone(), f.iter().map(|v| v.copied()).collect()),
+
+            Value::Map(ref m) => {
+                let map = m.borrow();
+
+                Value::Map(Rc::new(RefCell::new(Map {
+                    entries: map.entries.iter().map(|&(ref k, ref v)| (k.copied(), v.copied())).collect(),
+                    zero:    map.zero.clone(),
+                })))
+            }
+
+            ref v => v.clone(),
+        }
+    }
+
+    /// What a value of the same type starts out as when C++ value-initializes it, which is
+    /// what a map hands back for a key it doesn't have yet.
+    pub fn zeroed(&self) -> Value {
+        match *self {
+            Value::Integer(_) => Value::Integer(0),
+            Value::Float(_)   => Value::Float(0.0),
+            Value::Boolean(_) => Value::Boolean(false),
+            Value::Text(_)    => Value::Text(String::new()),
+            Value::Array(_)   => Value::Array(Rc::new(RefCell::new(Vec::new()))),
+
+            Value::Map(ref m) => Value::Map(Rc::new(RefCell::new(Map {
+                    entries: Vec::new(),
+                    zero:    m.borrow().zero.clone(),
+                }))),
+
+            ref v => v.copied(),
+        }
+    }
+
+    /// Whether two values are the same key of a map.
+    pub fn same(&self, other: &Value) -> bool {
+        match (self, other) {
+            (&Value::Integer(a), &Value::Integer(b)) => a == b,
+            (&Value::Float(a), &Value::Float(b))     => a == b,
+            (&Value::Boolean(a), &Value::Boolean(b)) => a == b,
+            (&Value::Text(ref a), &Value::Text(ref b)) => a == b,
+
+            (&Value::Variant(ref a, ref x, _), &Value::Variant(ref b, ref y, _)) => Rc::ptr_eq(a, b) && x == y,
+
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Void           => write!(f, "void"),
+            Value::Integer(ref i) => write!(f, "{}", i),
+            Value::Float(ref i)   => write!(f, "{}", i),
+            Value::Boolean(ref i) => write!(f, "{}", i),
+            Value::Text(ref i)    => write!(f, "{}", i),
+
+            Value::Array(ref a) => {
+                try!(write!(f, "["));
+
+                for (i, v) in a.borrow().iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(f, ", "))
+                    }
+
+                    try!(write!(f, "{}", v))
+                }
+
+                write!(f, "]")
+            }
+
+            Value::Map(ref m) => {
+                try!(write!(f, "{{"));
+
+                for (i, &(ref k, ref v)) in m.borrow().entries.iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(f, ", "))
+                    }
+
+                    try!(write!(f, "{}: {}", k, v))
+                }
+
+                write!(f, "}}")
+            }
+
+            Value::Member(_, ref n) => write!(f, "<method {}>", n),
+            Value::Function(ref c)  => write!(f, "<function {}>", c.name),
+            Value::Method(_, ref c) => write!(f, "<method {}>", c.name),
+            Value::Builtin(ref n)   => write!(f, "<builtin {}>", n),
+            Value::Class(ref c)     => write!(f, "<class {}>", c.name),
+            Value::Object(ref o)    => write!(f, "<{} object>", o.borrow().class.name),
+            Value::Module(ref m)    => write!(f, "<module {}>", m.name),
+            Value::Enum(ref e)      => write!(f, "<enum {}>", e.name),
+            Value::Closure(ref c)   => write!(f, "<function {}>", c.function.name),
+            Value::Instance(ref o)  => write!(f, "<{} object>", o.borrow().class.name),
+
+            Value::Constructor(ref e, ref n) => write!(f, "<variant {}::{}>", e.name, n),
+
+            Value::Variant(ref e, ref n, ref v) => {
+                try!(write!(f, "{}::{}", e.name, n));
+
+                if v.is_empty() {
+                    return Ok(())
+                }
+
+                try!(write!(f, "("));
+
+                for (i, v) in v.iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(f, ", "))
+                    }
+
+                    try!(write!(f, "{}", v))
+                }
+
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name:      String,
+    pub generics:  Vec<String>,
+    /// The type parameters of the class a method belongs to, as its `implement` names them.
+    pub outer:     Vec<String>,
+    pub args:      Vec<(String, String)>,
+    pub body:      Vec<Statement>,
+    pub retty:     Option<String>,
+    pub namespace: Rc<Namespace>,
+    /// Copies of the locals around a lambda, taken when it was made.
+    pub captured:  HashMap<String, Value>,
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name:      String,
+    pub generics:  Vec<String>,
+    pub parent:    Option<Rc<Class>>,
+    pub fields:    Vec<Statement>,
+    pub methods:   RefCell<HashMap<String, Rc<Function>>>,
+    pub namespace: Rc<Namespace>,
+}
+
+impl Class {
+    pub fn method(&self, name: &str) -> Option<Rc<Function>> {
+        if let Some(m) = self.methods.borrow().get(name) {
+            return Some(m.clone())
+        }
+
+        match self.parent {
+            Some(ref p) => p.method(name),
+            None        => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Enum {
+    pub name:     String,
+    pub variants: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Enum {
+    /// The `(type, name)` fields of a variant, if the enum has one of that name.
+    pub fn fields(&self, variant: &str) -> Option<&[(String, String)]> {
+        self.variants.iter().find(|&&(ref n, _)| n == variant).map(|&(_, ref f)| &f[..])
+    }
+}
+
+/// The entries of a map in the order they were added.
+#[derive(Debug)]
+pub struct Map {
+    pub entries: Vec<(Value, Value)>,
+    /// What a missing key reads as, once the type of the values is known.
+    pub zero:    Option<Value>,
+}
+
+impl Map {
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        self.entries.iter().find(|&&(ref k, _)| k.same(key)).map(|&(_, ref v)| v)
+    }
+
+    pub fn insert(&mut self, key: Value, value: Value) {
+        match self.entries.iter_mut().find(|&&mut (ref k, _)| k.same(&key)) {
+            Some(e) => e.1 = value,
+            None    => self.entries.push((key, value)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Object {
+    pub class:  Rc<Class>,
+    /// The type arguments of a generic class, in the order of its type parameters.
+    pub types:  Vec<Expression>,
+    pub fields: HashMap<String, Value>,
+}
+
+#[derive(Debug)]
+pub struct Namespace {
+    pub name:    String,
+    pub members: RefCell<HashMap<String, Value>>,
+    pub uses:    RefCell<Vec<Rc<Namespace>>>,
+    pub parent:  Option<Rc<Namespace>>,
+}
+
+impl Namespace {
+    pub fn new(name: String, parent: Option<Rc<Namespace>>) -> Rc<Namespace> {
+        Rc::new(Namespace {
+            name:    name,
+            members: RefCell::new(HashMap::new()),
+            uses:    RefCell::new(Vec::new()),
+            parent:  parent,
+        })
+    }
+
+    /// Looks a name up in this namespace alone, including the namespaces pulled in by `use`.
+    pub fn member(&self, name: &str) -> Option<Value> {
+        if let Some(v) = self.members.borrow().get(name) {
+            return Some(v.clone())
+        }
+
+        for u in self.uses.borrow().iter() {
+            if let Some(v) = u.member(name) {
+                return Some(v)
+            }
+        }
+
+        None
+    }
+
+    /// Looks a name up in this namespace and then in every enclosing one.
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        match self.member(name) {
+            Some(v) => Some(v),
+            None    => match self.parent {
+                Some(ref p) => p.lookup(name),
+                None        => None,
+            },
+        }
+    }
+
+    pub fn define(&self, name: String, value: Value) {
+        self.members.borrow_mut().insert(name, value);
+    }
+
+    /// Overwrites an existing member of this namespace or of an enclosing one.
+    pub fn assign(&self, name: &str, value: Value) -> bool {
+        if let Some(v) = self.members.borrow_mut().get_mut(name) {
+            *v = value;
+
+            return true
+        }
+
+        match self.parent {
+            Some(ref p) => p.assign(name, value),
+            None        => false,
+        }
+    }
+}
@@ -0,0 +1,1035 @@
+use std::mem;
+use std::rc::Rc;
+use std::collections::HashMap;
+
+use super::super::ast::{self, Position};
+use super::super::token::Operator;
+use super::super::diagnostic::{Diagnostic, code};
+use super::super::resolver::index::Index;
+use super::super::resolver::scope::{self, Symbol, Signature, Scope};
+use super::super::resolver::types::{self, Type};
+
+use super::{Path, Expression, Node, Statement, Arm, Item, Function, Class, Field, Variant};
+
+/// Lowers a resolved file into the IR: every name is given what the resolver found it to
+/// refer to, every expression its type, implicit conversions and returns are written out and
+/// loops are taken down to `Loop` and `Each`.
+///
+/// Names nothing defines are kept as they are written, since they may come from a library;
+/// the backends decide what to make of them.
+pub struct Lowering {
+    /// What the names of the file refer to, by where and how they were written.
+    references: HashMap<(Position, String), (Symbol, Option<Rc<Scope>>)>,
+    types:      HashMap<Position, Type>,
+
+    /// The module whose definitions are being gone through.
+    namespace:  Rc<Scope>,
+    /// The class whose members are being gone through.
+    this:       Option<Rc<Scope>>,
+    generics:   Vec<String>,
+    retty:      Option<Type>,
+}
+
+impl Lowering {
+    /// Starts lowering the file the index was made from.
+    pub fn new(index: &Index) -> Lowering {
+        Lowering {
+            references: index.references.iter()
+                                        .map(|r| ((r.position, r.name.clone()), (r.symbol.clone(), r.scope.clone())))
+                                        .collect(),
+            types:      index.types.clone(),
+
+            namespace:  index.namespace.clone(),
+            this:       None,
+            generics:   Vec::new(),
+            retty:      None,
+        }
+    }
+
+    pub fn lower(&mut self, ast: &[ast::Statement]) -> Result<Vec<Item>, Diagnostic> {
+        let mut items = Vec::new();
+
+        for s in ast.iter() {
+            if let Some(i) = try!(self.item(s)) {
+                items.push(i)
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// What a name written at a position refers to, along with the scope defining it.
+    fn reference(&self, name: &str, position: Position) -> Option<(Symbol, Option<Rc<Scope>>)> {
+        self.references.get(&(position, name.to_owned())).cloned()
+    }
+
+    /// The type the resolver gave a variable declared at a position.
+    fn declared(&self, name: &str, position: Position) -> Option<Type> {
+        match self.reference(name, position) {
+            Some((Symbol::Variable(t), _)) => Some(t),
+            _                              => None,
+        }
+    }
+
+    /// The type a type name such as a parameter's refers to.
+    fn named(&self, name: &str) -> Type {
+        types::named(&self.namespace, &self.generics, name)
+    }
+
+    fn type_of(&self, t: &ast::Expression) -> Type {
+        self.named(&ast::written(t))
+    }
+
+    /// The path of a module pulled in by `use`, as it is written when nothing defines it, like
+    /// `std`.
+    fn module(&self, ex: &ast::Expression) -> Path {
+        let written = ast::written(ex);
+
+        match types::defined(&self.namespace, &written) {
+            Some(Symbol::Module(m)) => m.path(),
+            _                       => Path(ast::split(&written, "::").into_iter().map(|s| s.to_owned()).collect()),
+        }
+    }
+
+    /// Lowers what a statement of a module or file defines, or nothing for statements that
+    /// only belong in functions.
+    pub fn item(&mut self, st: &ast::Statement) -> Result<Option<Item>, Diagnostic> {
+        let namespace = self.namespace.clone();
+
+        let e = match *st {
+            ast::Statement::Declaration(ref n, _, ref e, p) => {
+                let value = self.expression(&**e);
+                let t     = variable(&namespace, n).unwrap_or_else(|| value.ty.clone());
+                let value = self.convert(value, &t);
+
+                return Ok(Some(Item::Global(namespace.path().child(n), t, Some(value), p)))
+            }
+
+            ast::Statement::Expression(ref e, _) => e,
+
+            _ => return Ok(None),
+        };
+
+        Ok(Some(match **e {
+            ast::Expression::Function(ref n, ref g, ref a, ref c, _, p) => {
+                Item::Function(self.function(&namespace, n, g, a, Some(c), p))
+            }
+
+            ast::Expression::FunctionDef(ref n, ref g, ref a, _, p) => {
+                Item::Function(self.function(&namespace, n, g, a, None, p))
+            }
+
+            ast::Expression::Module(ref n, ref c, p) => {
+                let module = match namespace.member(n) {
+                    Some(Symbol::Module(m)) => m,
+                    _                       => Scope::new(n.clone(), Some(namespace.clone())),
+                };
+
+                self.namespace = module.clone();
+
+                let items = self.lower(c);
+
+                self.namespace = namespace;
+
+                Item::Module(module.path(), try!(items), p)
+            }
+
+            ast::Expression::Class(ref n, _, ref c, ref b, p) => Item::Class(self.class(n, c, b, false, p)),
+            ast::Expression::Struct(ref n, ref c, p)         => Item::Class(self.class(n, c, &None, true, p)),
+
+            ast::Expression::Enum(ref n, ref v, p) => {
+                let enumeration = match namespace.member(n) {
+                    Some(Symbol::Enum(e)) => e,
+                    _                     => Scope::new(n.clone(), Some(namespace.clone())),
+                };
+
+                let variants = v.iter().map(|&(ref name, ref fields, position)| {
+                    let types = match enumeration.member(name) {
+                        Some(Symbol::Variant(s)) => s.params,
+                        _                        => fields.iter().map(|&(ref t, _)| self.named(t)).collect(),
+                    };
+
+                    Variant {
+                        name:     name.clone(),
+                        fields:   fields.iter().map(|&(_, ref f)| f.clone()).zip(types.into_iter()).collect(),
+                        position: position,
+                    }
+                }).collect();
+
+                Item::Enum(enumeration.path(), variants, p)
+            }
+
+            ast::Expression::Implement(ref n, ref g, ref c, p) => {
+                for s in c.iter() {
+                    if let ast::Statement::Expression(ref e, _) = *s {
+                        if let ast::Expression::Function(_, _, _, _, _, _) = **e {
+                            continue
+                        }
+                    }
+
+                    return Err(Diagnostic::error(code::IMPLEMENTATION, format!("unexpected member in implementation of {}", n))
+                        .at(p.0, p.1, n.len() as u32)
+                        .note("`implement` blocks may only contain functions".to_owned()))
+                }
+
+                let class = match namespace.lookup(n) {
+                    Some(Symbol::Class(c)) => c,
+                    _                      => Scope::new(n.clone(), Some(namespace.clone())),
+                };
+
+                self.this     = Some(class.clone());
+                self.generics = g.clone();
+
+                let mut methods = Vec::new();
+
+                for s in c.iter() {
+                    if let ast::Statement::Expression(ref e, _) = *s {
+                        if let ast::Expression::Function(ref n, ref g, ref a, ref c, _, p) = **e {
+                            methods.push(self.function(&class, n, g, a, Some(c), p))
+                        }
+                    }
+                }
+
+                self.this = None;
+                self.generics.clear();
+
+                Item::Implement(class.path(), g.clone(), methods, p)
+            }
+
+            ast::Expression::Typed(ref i, _) => {
+                let (n, position) = declaration(&**i);
+                let t             = variable(&namespace, &n).unwrap_or(Type::Unknown);
+                let value         = self.sizes(&**i, &t);
+
+                Item::Global(namespace.path().child(&n), t, value, position)
+            }
+
+            ast::Expression::Import(ref path, library, p) => Item::Import(path.clone(), library, p),
+
+            ast::Expression::Use(ref m) => Item::Use(self.module(&**m), located(&**m)),
+
+            _ => return Ok(None),
+        }))
+    }
+
+    /// Lowers a function, method or constructor defined in `scope`, with the returns it
+    /// leaves out written out.
+    fn function(&mut self, scope: &Rc<Scope>, name: &str, generics: &[String], args: &[(String, String)],
+                body: Option<&[ast::Statement]>, position: Position) -> Function {
+        let outer = self.generics.len();
+
+        self.generics.extend(generics.iter().cloned());
+
+        let constructor = is_class(scope) && scope.name == name;
+
+        let signature = match scope.symbols.borrow().get(name) {
+            Some(&(Symbol::Function(ref s), _)) | Some(&(Symbol::Prototype(ref s), _)) => Some(s.clone()),
+            _                                                                         => None,
+        };
+
+        let params: Vec<(String, Type)> = match signature {
+            Some(ref s) if s.params.len() == args.len() => {
+                args.iter().map(|&(_, ref n)| n.clone()).zip(s.params.iter().cloned()).collect()
+            }
+
+            _ => args.iter().map(|&(ref t, ref n)| (n.clone(), self.named(t))).collect(),
+        };
+
+        // `main` gives back the status the program exits with
+        let main = scope.parent.is_none() && name == "main";
+
+        let retty = match signature {
+            _ if main   => Type::Int,
+            Some(s)     => s.retty,
+            None        => Type::Unknown,
+        };
+
+        let body = body.map(|b| {
+            self.retty = Some(retty.clone());
+
+            let mut body = self.statements(b);
+
+            self.retty = None;
+
+            if !terminates(&body) {
+                match retty {
+                    _ if main        => body.push(Statement::Return(Some(Expression::new(Node::Integer(0), Type::Int)), position)),
+                    _ if constructor => body.push(Statement::Return(None, position)),
+                    Type::Void       => body.push(Statement::Return(None, position)),
+                    _                => (),
+                }
+            }
+
+            body
+        });
+
+        self.generics.truncate(outer);
+
+        Function {
+            path:        scope.path().child(name),
+            generics:    generics.to_vec(),
+            params:      params,
+            retty:       retty,
+            body:        body,
+            constructor: constructor,
+            position:    position,
+        }
+    }
+
+    fn class(&mut self, name: &str, body: &[ast::Statement], parent: &Option<Box<ast::Expression>>, plain: bool,
+             position: Position) -> Class {
+        let class = match self.namespace.member(name) {
+            Some(Symbol::Class(c)) => c,
+            _                      => Scope::new(name.to_owned(), Some(self.namespace.clone())),
+        };
+
+        let generics = class.generics.borrow().clone();
+
+        self.this     = Some(class.clone());
+        self.generics = generics.clone();
+
+        let parent = parent.as_ref().map(|p| self.type_of(&**p));
+
+        let mut fields  = Vec::new();
+        let mut methods = Vec::new();
+
+        for s in body.iter() {
+            match *s {
+                ast::Statement::Declaration(ref n, _, ref e, p) => {
+                    let value = self.expression(&**e);
+                    let t     = variable(&class, n).unwrap_or_else(|| value.ty.clone());
+
+                    fields.push(Field {
+                        name:     n.clone(),
+                        value:    Some(self.convert(value, &t)),
+                        ty:       t,
+                        position: p,
+                    })
+                }
+
+                ast::Statement::Expression(ref e, _) => match **e {
+                    ast::Expression::Typed(ref i, _) => {
+                        let (n, position) = declaration(&**i);
+                        let t             = variable(&class, &n).unwrap_or(Type::Unknown);
+
+                        fields.push(Field {
+                            name:     n,
+                            value:    self.sizes(&**i, &t),
+                            ty:       t,
+                            position: position,
+                        })
+                    }
+
+                    ast::Expression::Function(ref n, ref g, ref a, ref c, _, p) => {
+                        methods.push(self.function(&class, n, g, a, Some(c), p))
+                    }
+
+                    ast::Expression::FunctionDef(ref n, ref g, ref a, _, p) => {
+                        methods.push(self.function(&class, n, g, a, None, p))
+                    }
+
+                    _ => (),
+                },
+
+                _ => (),
+            }
+        }
+
+        self.this = None;
+        self.generics.clear();
+
+        Class {
+            path:     class.path(),
+            generics: generics,
+            parent:   parent,
+            plain:    plain,
+            fields:   fields,
+            methods:  methods,
+            position: position,
+        }
+    }
+
+    /// The value of a `name[size]: type` declaration, which makes the arrays of the sizes
+    /// written, or none for a `name: type` one.
+    fn sizes(&mut self, ex: &ast::Expression, t: &Type) -> Option<Expression> {
+        let mut sizes = Vec::new();
+        let mut ex    = ex;
+
+        while let ast::Expression::IndexArray(ref a, ref s) = *ex {
+            sizes.push(self.expression(&**s));
+
+            ex = &**a
+        }
+
+        if sizes.is_empty() {
+            return None
+        }
+
+        sizes.reverse();
+
+        Some(Expression::new(Node::Array(sizes), t.clone()))
+    }
+
+    fn statements(&mut self, body: &[ast::Statement]) -> Vec<Statement> {
+        let mut lowered = Vec::new();
+
+        for s in body.iter() {
+            lowered.extend(self.statement(s))
+        }
+
+        lowered
+    }
+
+    /// Lowers a statement of a function, which blocks and `else if` chains may make several
+    /// of or nest.
+    pub fn statement(&mut self, st: &ast::Statement) -> Vec<Statement> {
+        let p = st.position().unwrap_or((0, 0));
+
+        let lowered = match *st {
+            ast::Statement::Block(ref c) => return self.statements(c),
+
+            ast::Statement::Expression(ref e, _) => match **e {
+                ast::Expression::Return(ref e, _) => self.ret(&**e, p),
+
+                ast::Expression::Typed(ref i, ref t) => {
+                    let (n, position) = declaration(&**i);
+
+                    let t = match self.declared(&n, position) {
+                        Some(t) => t,
+                        None    => self.type_of(&**t),
+                    };
+
+                    let value = self.sizes(&**i, &t);
+
+                    Statement::Let(n, t, value, p)
+                }
+
+                ast::Expression::Use(ref m) => Statement::Use(self.module(&**m), p),
+
+                ref e => Statement::Expression(self.expression(e), p),
+            },
+
+            ast::Statement::Declaration(ref n, _, ref e, position) => {
+                let value = self.expression(&**e);
+                let t     = self.declared(n, position).unwrap_or_else(|| value.ty.clone());
+                let value = self.convert(value, &t);
+
+                Statement::Let(n.clone(), t, Some(value), p)
+            }
+
+            ast::Statement::Assignment(ref n, ref e, _) => {
+                let place = self.expression(&**n);
+                let value = self.expression(&**e);
+                let value = self.convert(value, &place.ty);
+
+                Statement::Assign(place, None, value, p)
+            }
+
+            // the value is combined with the one stored the way the operator combines its
+            // operands, and the result converted back like C++ does
+            ast::Statement::CompoundAssignment(ref n, ref o, ref e, _) => {
+                let place = self.expression(&**n);
+                let value = self.expression(&**e);
+
+                Statement::Assign(place, Some(o.clone()), value, p)
+            }
+
+            ast::Statement::If(ref b, ref c, _) => {
+                let mut branches = Vec::new();
+
+                for &(ref e, ref s) in b.iter() {
+                    let condition = self.expression(e);
+
+                    branches.push((condition, self.statement(s)))
+                }
+
+                let mut otherwise = match *c {
+                    Some(ref c) => self.statement(&**c),
+                    None        => Vec::new(),
+                };
+
+                for (condition, body) in branches.into_iter().rev() {
+                    otherwise = vec!(Statement::If(condition, body, otherwise, p))
+                }
+
+                return otherwise
+            }
+
+            ast::Statement::While(ref e, ref c, _) => {
+                let condition = self.expression(&**e);
+
+                Statement::Loop(None, condition, None, self.statement(&**c), p)
+            }
+
+            ast::Statement::For(ref i, ref e, ref c, _) => {
+                let collection = self.expression(&**e);
+                let element    = collection.ty.iterated().unwrap_or(Type::Unknown);
+
+                Statement::Each(i.clone(), element, collection, self.statement(&**c), p)
+            }
+
+            // a range is counted through like `for (int i = a; i < b; i += 1)` does
+            ast::Statement::ForRange(ref i, ref a, ref b, ref c, _) => {
+                let start = self.expression(&**a);
+                let start = self.convert(start, &Type::Int);
+                let end   = self.expression(&**b);
+                let body  = self.statement(&**c);
+
+                let counter   = Expression::new(Node::Local(i.clone()), Type::Int);
+                let condition = self.operation(counter.clone(), Operator::Lt, end);
+                let step      = Statement::Assign(counter, Some(Operator::Plus), Expression::new(Node::Integer(1), Type::Int), p);
+
+                Statement::Loop(Some(Box::new(Statement::Let(i.clone(), Type::Int, Some(start), p))), condition,
+                                Some(Box::new(step)), body, p)
+            }
+
+            ast::Statement::Match(ref e, ref arms, _) => {
+                let subject = self.expression(&**e);
+
+                let enumeration = match subject.ty {
+                    Type::Enum(ref e) => Some(e.clone()),
+                    _                 => None,
+                };
+
+                let mut lowered = Vec::new();
+
+                for &(ref v, ref b, ref body, position) in arms.iter() {
+                    let fields = match enumeration.as_ref().and_then(|e| e.member(v)) {
+                        Some(Symbol::Variant(s)) if v != "_" => s.params,
+                        _                                    => Vec::new(),
+                    };
+
+                    let bindings = b.iter().enumerate()
+                        .filter(|&(_, n)| n != "_")
+                        .map(|(i, n)| (i, n.clone(), fields.get(i).cloned().unwrap_or(Type::Unknown)))
+                        .collect();
+
+                    lowered.push(Arm {
+                        variant:  if v == "_" { None } else { Some(v.clone()) },
+                        bindings: bindings,
+                        body:     self.statement(body),
+                        position: position,
+                    });
+
+                    // the `_` arm matches every variant left, so arms after it are never reached
+                    if v == "_" {
+                        break
+                    }
+                }
+
+                Statement::Match(subject, lowered, p)
+            }
+
+            ast::Statement::Break(_)    => Statement::Break(p),
+            ast::Statement::Continue(_) => Statement::Continue(p),
+        };
+
+        vec!(lowered)
+    }
+
+    /// A return, which gives back nothing for a bare `return` and otherwise a value of the
+    /// type the function returns.
+    fn ret(&mut self, value: &ast::Expression, position: Position) -> Statement {
+        if let ast::Expression::Ident(ref n, _) = *value {
+            if n == "void" {
+                return Statement::Return(None, position)
+            }
+        }
+
+        let value = self.expression(value);
+
+        let value = match self.retty.clone() {
+            Some(ref r) => self.convert(value, r),
+            None        => value,
+        };
+
+        Statement::Return(Some(value), position)
+    }
+
+    /// Lowers an expression, giving it its type.
+    pub fn expression(&mut self, ex: &ast::Expression) -> Expression {
+        match *ex {
+            ast::Expression::Integer(i)     => Expression::new(Node::Integer(i), types::integer(i)),
+            ast::Expression::Float(f)       => Expression::new(Node::Float(f), Type::Double),
+            ast::Expression::Boolean(b)     => Expression::new(Node::Boolean(b), Type::Bool),
+            ast::Expression::Text(ref s)    => Expression::new(Node::Text(s.clone()), Type::Text),
+            ast::Expression::Ident(ref n, p) => self.name(n, p),
+
+            ast::Expression::Operation(ref l, ref o, ref r, _) => {
+                let left  = self.expression(&**l);
+                let right = self.expression(&**r);
+
+                self.operation(left, o.clone(), right)
+            }
+
+            ast::Expression::Unary(ref o, ref e, _) => {
+                let operand = self.expression(&**e);
+
+                let t = types::unary(o, &operand.ty).unwrap_or(Type::Unknown);
+
+                let operand = match *o {
+                    Operator::Not => operand,
+                    _             => self.convert(operand, &t),
+                };
+
+                Expression::new(Node::Unary(o.clone(), Box::new(operand)), t)
+            }
+
+            ast::Expression::Call(ref c, ref a) => self.call(&**c, a),
+
+            ast::Expression::IndexDot(ref a, ref b) => {
+                let object = self.expression(&**a);
+
+                self.member(object, &ast::written(&**b))
+            }
+
+            ast::Expression::IndexColon(_, ref b) => match self.path(&**b) {
+                Some((n, symbol, scope)) => self.referred(&n, symbol, scope),
+                None                     => Expression::new(Node::Foreign(ast::written(ex)), Type::Unknown),
+            },
+
+            ast::Expression::IndexArray(ref a, ref i) => {
+                let base  = self.expression(&**a);
+                let index = self.expression(&**i);
+
+                let index = match base.ty.clone() {
+                    Type::Map(k, _) => self.convert(index, &k),
+                    _               => index,
+                };
+
+                let t = base.ty.indexed().unwrap_or(Type::Unknown);
+
+                Expression::new(Node::Index(Box::new(base), Box::new(index)), t)
+            }
+
+            // a generic function named on its own is left for the target to instantiate
+            ast::Expression::Generic(ref b, _) => self.expression(&**b),
+
+            ast::Expression::List(ref e, _) => {
+                let elements: Vec<Expression> = e.iter().map(|e| self.expression(e)).collect();
+                let element                   = common(elements.iter().map(|e| &e.ty));
+                let elements                  = elements.into_iter().map(|e| self.convert(e, &element)).collect();
+
+                Expression::new(Node::List(elements), Type::List(Box::new(element)))
+            }
+
+            ast::Expression::Map(ref e, _) => {
+                let entries: Vec<(Expression, Expression)> = e.iter().map(|&(ref k, ref v)| (self.expression(k), self.expression(v))).collect();
+
+                let key   = common(entries.iter().map(|&(ref k, _)| &k.ty));
+                let value = common(entries.iter().map(|&(_, ref v)| &v.ty));
+
+                let entries = entries.into_iter().map(|(k, v)| (self.convert(k, &key), self.convert(v, &value))).collect();
+
+                Expression::new(Node::Map(entries), Type::Map(Box::new(key), Box::new(value)))
+            }
+
+            ast::Expression::Lambda(ref a, ref b, _, p) => self.lambda(a, b, p),
+
+            ast::Expression::Return(ref e, _) => self.expression(&**e),
+
+            ref e => Expression::new(Node::Foreign(ast::written(e)), Type::Unknown),
+        }
+    }
+
+    /// The value a name stands for where it is used.
+    fn name(&mut self, name: &str, position: Position) -> Expression {
+        match self.reference(name, position) {
+            Some((symbol, scope)) => self.referred(name, symbol, scope),
+            None                  => Expression::new(Node::Foreign(name.to_owned()), Type::Unknown),
+        }
+    }
+
+    /// The name a path like `a::b` ends in, along with what it refers to.
+    fn path(&self, last: &ast::Expression) -> Option<(String, Symbol, Option<Rc<Scope>>)> {
+        match *last {
+            ast::Expression::Ident(ref n, p) => self.reference(n, p).map(|(s, scope)| (n.clone(), s, scope)),
+            _                                => None,
+        }
+    }
+
+    /// The object the methods being gone through are called on.
+    fn this(&self) -> Expression {
+        match self.this {
+            Some(ref c) => Expression::new(Node::This, types::instance(c)),
+            None        => Expression::new(Node::This, Type::Unknown),
+        }
+    }
+
+    /// The value of what a name refers to, named on its own; `scope` defines it, or is none
+    /// for locals and what is built in.
+    fn referred(&mut self, name: &str, symbol: Symbol, scope: Option<Rc<Scope>>) -> Expression {
+        let scope = match scope {
+            Some(s) => s,
+
+            None => return match symbol {
+                Symbol::Variable(t) => Expression::new(Node::Local(name.to_owned()), t),
+                _                   => Expression::new(Node::Foreign(name.to_owned()), Type::Unknown),
+            },
+        };
+
+        match symbol {
+            // the fields of the class being gone through are named without an object
+            Symbol::Variable(_) if is_class(&scope) => {
+                let this = self.this();
+
+                self.member(this, name)
+            }
+
+            Symbol::Variable(t) => Expression::new(Node::Global(scope.path().child(name)), t),
+
+            // a function named on its own is passed around like a lambda
+            Symbol::Function(ref s) | Symbol::Prototype(ref s) if !is_class(&scope) && s.generics.is_empty() => {
+                Expression::new(Node::Function(scope.path().child(name)), Type::Function(s.params.clone(), Box::new(s.retty.clone())))
+            }
+
+            Symbol::Function(_) | Symbol::Prototype(_) => Expression::new(Node::Foreign(name.to_owned()), Type::Unknown),
+
+            // a variant without fields is a value of its own
+            Symbol::Variant(ref s) if s.params.is_empty() => {
+                Expression::new(Node::Variant(scope.path(), name.to_owned(), Vec::new()), s.retty.clone())
+            }
+
+            _ => Expression::new(Node::Foreign(scope.path().child(name).to_string()), Type::Unknown),
+        }
+    }
+
+    /// A field of an object.
+    fn member(&mut self, object: Expression, name: &str) -> Expression {
+        if let Type::Object(ref c, ref a) = object.ty {
+            if let Some(Symbol::Variable(t)) = c.member(name) {
+                let t = t.substitute(&bindings(c, a));
+
+                return Expression::new(Node::Field(Box::new(object.clone()), declaring(c, name), name.to_owned()), t)
+            }
+        }
+
+        Expression::new(Node::Member(Box::new(object), name.to_owned()), Type::Unknown)
+    }
+
+    fn call(&mut self, callee: &ast::Expression, args: &[ast::Expression]) -> Expression {
+        // the type arguments of a generic are given in brackets after its name
+        let (callee, explicit) = match *callee {
+            ast::Expression::Generic(ref b, ref t) => (&**b, Some(t.iter().map(|t| self.type_of(t)).collect::<Vec<Type>>())),
+            ref c                                  => (c, None),
+        };
+
+        let args: Vec<Expression> = args.iter().map(|a| self.expression(a)).collect();
+
+        let referred = match *callee {
+            ast::Expression::Ident(ref n, p) => self.reference(n, p).map(|(s, scope)| (n.clone(), s, scope)),
+
+            ast::Expression::IndexColon(_, ref b) => self.path(&**b),
+
+            ast::Expression::IndexDot(ref a, ref b) => {
+                let object = self.expression(&**a);
+
+                return self.method(object, &ast::written(&**b), args)
+            }
+
+            ref c => {
+                let f = self.expression(c);
+
+                return self.apply(f, args)
+            }
+        };
+
+        match referred {
+            Some((n, symbol, scope)) => self.invoke(&n, symbol, scope, explicit, args),
+            None                     => self.apply(Expression::new(Node::Foreign(ast::written(callee)), Type::Unknown), args),
+        }
+    }
+
+    /// A call to what a name refers to.
+    fn invoke(&mut self, name: &str, symbol: Symbol, scope: Option<Rc<Scope>>, explicit: Option<Vec<Type>>,
+              args: Vec<Expression>) -> Expression {
+        match (symbol, scope) {
+            (Symbol::Function(s), Some(scope)) | (Symbol::Prototype(s), Some(scope)) => {
+                if is_class(&scope) {
+                    // `Pair[B, A](..)` in a method of `Pair` makes another object of the class
+                    if scope.name == name {
+                        return self.construct(scope, explicit, args)
+                    }
+
+                    let this = self.this();
+
+                    return self.method(this, name, args)
+                }
+
+                let (args, retty) = self.arguments(&s, explicit.clone(), args);
+
+                Expression::new(Node::Call(scope.path().child(name), explicit.unwrap_or_default(), args), retty)
+            }
+
+            (Symbol::Class(c), _) => self.construct(c, explicit, args),
+
+            (Symbol::Variant(s), Some(scope)) => {
+                let args = args.into_iter().zip(s.params.iter()).map(|(a, t)| self.convert(a, t)).collect();
+
+                Expression::new(Node::Variant(scope.path(), name.to_owned(), args), s.retty)
+            }
+
+            // printf and puts give back a count
+            (Symbol::Builtin, _) => {
+                let f = Expression::new(Node::Foreign(name.to_owned()), Type::Unknown);
+
+                Expression::new(Node::Apply(Box::new(f), args), Type::Int)
+            }
+
+            (symbol, scope) => {
+                let f = self.referred(name, symbol, scope);
+
+                self.apply(f, args)
+            }
+        }
+    }
+
+    /// An object made by its class, whose type arguments are given or told by the arguments
+    /// of its constructor.
+    fn construct(&mut self, class: Rc<Scope>, explicit: Option<Vec<Type>>, args: Vec<Expression>) -> Expression {
+        let params = match class.symbols.borrow().get(&class.name) {
+            Some(&(Symbol::Function(ref s), _)) | Some(&(Symbol::Prototype(ref s), _)) => s.params.clone(),
+            _                                                                         => args.iter().map(|_| Type::Unknown).collect(),
+        };
+
+        let constructor = Signature {
+            generics: class.generics.borrow().clone(),
+            params:   params,
+            retty:    types::instance(&class),
+        };
+
+        let (args, t) = self.arguments(&constructor, explicit.clone(), args);
+
+        Expression::new(Node::New(class.path(), explicit.unwrap_or_default(), args), t)
+    }
+
+    /// A call to a method of an object.
+    fn method(&mut self, object: Expression, name: &str, args: Vec<Expression>) -> Expression {
+        let signature = match object.ty.clone() {
+            Type::Object(c, a) => match c.member(name) {
+                Some(Symbol::Function(s)) | Some(Symbol::Prototype(s)) => {
+                    let s             = s.substitute(&bindings(&c, &a));
+                    let (args, retty) = self.arguments(&s, None, args);
+
+                    return Expression::new(Node::Method(Box::new(object), declaring(&c, name), name.to_owned(), args), retty)
+                }
+
+                // a field holding a function
+                Some(Symbol::Variable(_)) => {
+                    let f = self.member(object, name);
+
+                    return self.apply(f, args)
+                }
+
+                _ => None,
+            },
+
+            // the methods of `std::vector`, `std::unordered_map` and `std::string`
+            t @ Type::List(_) | t @ Type::Map(_, _) | t @ Type::Text => types::method(&t, name),
+
+            _ => None,
+        };
+
+        match signature {
+            Some(s) => {
+                let (args, retty) = self.arguments(&s, None, args);
+
+                Expression::new(Node::Invoke(Box::new(object), name.to_owned(), args), retty)
+            }
+
+            None => Expression::new(Node::Invoke(Box::new(object), name.to_owned(), args), Type::Unknown),
+        }
+    }
+
+    /// A call to a function value.
+    fn apply(&mut self, f: Expression, args: Vec<Expression>) -> Expression {
+        match f.ty.clone() {
+            Type::Function(params, retty) => {
+                let args = args.into_iter().zip(params.iter()).map(|(a, t)| self.convert(a, t)).collect();
+
+                Expression::new(Node::Apply(Box::new(f), args), *retty)
+            }
+
+            _ => Expression::new(Node::Apply(Box::new(f), args), Type::Unknown),
+        }
+    }
+
+    /// The arguments of a call converted to the types of the parameters, along with the type
+    /// of what the call gives back, once the type parameters of a generic are bound.
+    fn arguments(&mut self, signature: &Signature, explicit: Option<Vec<Type>>, args: Vec<Expression>) -> (Vec<Expression>, Type) {
+        let given: Vec<Type> = args.iter().map(|a| a.ty.clone()).collect();
+
+        let (bindings, _) = types::bind(&signature.generics, explicit, &signature.params, &given);
+
+        let signature = signature.substitute(&bindings);
+
+        let mut converted = Vec::new();
+
+        for (i, a) in args.into_iter().enumerate() {
+            converted.push(match signature.params.get(i) {
+                Some(t) => self.convert(a, t),
+                None    => a,
+            })
+        }
+
+        (converted, signature.retty)
+    }
+
+    /// An operator applied to two operands, with numbers taken to the type they are combined
+    /// in first.
+    fn operation(&mut self, left: Expression, o: Operator, right: Expression) -> Expression {
+        let t = types::operation(&o, &left.ty, &right.ty).unwrap_or(Type::Unknown);
+
+        let (left, right) = match o {
+            Operator::And | Operator::Or => (left, right),
+
+            _ if left.ty.is_numeric() && right.ty.is_numeric() => {
+                let common = left.ty.join(&right.ty);
+
+                (self.convert(left, &common), self.convert(right, &common))
+            }
+
+            _ => (left, right),
+        };
+
+        Expression::new(Node::Binary(Box::new(left), o, Box::new(right)), t)
+    }
+
+    /// An anonymous function, whose type the resolver worked out where it was written.
+    fn lambda(&mut self, args: &[(String, String)], body: &[ast::Statement], position: Position) -> Expression {
+        let (params, retty) = match self.types.get(&position) {
+            Some(&Type::Function(ref p, ref r)) if p.len() == args.len() => (p.clone(), (**r).clone()),
+            _                                                            => (args.iter().map(|&(ref t, _)| self.named(t)).collect(), Type::Unknown),
+        };
+
+        let outer = mem::replace(&mut self.retty, Some(retty.clone()));
+
+        let mut body = self.statements(body);
+
+        self.retty = outer;
+
+        if retty == Type::Void && !terminates(&body) {
+            body.push(Statement::Return(None, position))
+        }
+
+        let params = args.iter().map(|&(_, ref n)| n.clone()).zip(params.iter().cloned()).collect();
+
+        Expression::new(Node::Lambda(params, body), Type::Function(args.iter().map(|_| Type::Unknown).collect(), Box::new(retty)))
+    }
+
+    /// A value converted to the type it is stored as, where the two differ: numbers into each
+    /// other, objects into their bases and collections written out into the collections they
+    /// are stored in.
+    fn convert(&self, value: Expression, to: &Type) -> Expression {
+        match (value.node, to) {
+            (Node::List(e), &Type::List(ref t)) => {
+                let elements = e.into_iter().map(|e| self.convert(e, t)).collect();
+
+                return Expression::new(Node::List(elements), to.clone())
+            }
+
+            (Node::Map(e), &Type::Map(ref k, ref v)) => {
+                let entries = e.into_iter().map(|(a, b)| (self.convert(a, k), self.convert(b, v))).collect();
+
+                return Expression::new(Node::Map(entries), to.clone())
+            }
+
+            (node, _) => {
+                let value = Expression::new(node, value.ty);
+
+                if value.ty == *to || value.ty.is_partial() || to.is_partial() {
+                    return value
+                }
+
+                let converted = match (&value.ty, to) {
+                    (a, b) if a.is_numeric() && b.is_numeric()         => true,
+                    (&Type::Object(ref a, _), &Type::Object(ref b, _)) => !Rc::ptr_eq(a, b) && types::derives(a, b),
+                    _                                                  => false,
+                };
+
+                if converted {
+                    Expression::new(Node::Convert(Box::new(value)), to.clone())
+                } else {
+                    value
+                }
+            }
+        }
+    }
+}
+
+/// The type of a variable or field a scope defines.
+fn variable(scope: &Scope, name: &str) -> Option<Type> {
+    match scope.symbols.borrow().get(name) {
+        Some(&(Symbol::Variable(ref t), _)) => Some(t.clone()),
+        _                                   => None,
+    }
+}
+
+/// Whether a scope is that of a class, rather than of a module or enum.
+fn is_class(scope: &Rc<Scope>) -> bool {
+    let parent = match scope.parent {
+        Some(ref p) => p,
+        None        => return false,
+    };
+
+    let symbols = parent.symbols.borrow();
+
+    match symbols.get(&scope.name) {
+        Some(&(Symbol::Class(ref c), _)) => Rc::ptr_eq(c, scope),
+        _                                => false,
+    }
+}
+
+/// The path of the class declaring a member of an object of class `class`, which may be one
+/// of its bases.
+fn declaring(class: &Rc<Scope>, name: &str) -> Path {
+    scope::owner(class, name).unwrap_or(class.clone()).path()
+}
+
+/// What the type parameters of a class stand for in an object of the given type arguments.
+fn bindings(class: &Scope, arguments: &[Type]) -> Vec<(String, Type)> {
+    class.generics.borrow().iter().cloned().zip(arguments.iter().cloned()).collect()
+}
+
+/// The type the elements of a list or map written out are kept as, widening numbers like
+/// arithmetic does.
+fn common<'a, I: Iterator<Item = &'a Type>>(types: I) -> Type {
+    let mut found: Option<Type> = None;
+
+    for t in types {
+        found = Some(match found.take() {
+            None    => t.clone(),
+            Some(a) => types::unify(&a, t).unwrap_or(a),
+        })
+    }
+
+    found.unwrap_or(Type::Unknown)
+}
+
+/// Whether the statements never run past their end, so no return needs to follow them.
+fn terminates(body: &[Statement]) -> bool {
+    match body.last() {
+        Some(&Statement::Return(_, _))           => true,
+        Some(&Statement::If(_, ref a, ref b, _)) => terminates(a) && terminates(b),
+        _                                        => false,
+    }
+}
+
+/// The name a `name: type` or `name[size]: type` declaration introduces, along with where it
+/// was written.
+fn declaration(ex: &ast::Expression) -> (String, Position) {
+    match *ex {
+        ast::Expression::IndexArray(ref a, _) => declaration(&**a),
+        ref e                                 => (ast::written(e), located(e)),
+    }
+}
+
+/// Where the name an expression ends in was written.
+fn located(ex: &ast::Expression) -> Position {
+    match *ex {
+        ast::Expression::Ident(_, p)                                                 => p,
+        ast::Expression::IndexColon(_, ref b) | ast::Expression::IndexDot(_, ref b)  => located(&**b),
+        ast::Expression::IndexArray(ref a, _) | ast::Expression::Generic(ref a, _)   => located(&**a),
+        _                                                                            => (0, 0),
+    }
+}
@@ -0,0 +1,242 @@
+pub mod lower;
+
+use std::fmt;
+
+use super::ast::Position;
+use super::token::Operator;
+
+/// The types of the IR are the ones the resolver gives, with every name in them resolved.
+pub use super::resolver::types::Type;
+
+/// The name of something a program defines along with the modules and classes it is in,
+/// like `geo::Point`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Path(pub Vec<String>);
+
+impl Path {
+    pub fn new(segments: &[&str]) -> Path {
+        Path(segments.iter().map(|s| s.to_string()).collect())
+    }
+
+    /// The last segment, the name the thing was defined with.
+    pub fn name(&self) -> &str {
+        self.0.last().map_or("", |s| s.as_str())
+    }
+
+    /// The path of the module or class this one is in.
+    pub fn parent(&self) -> Path {
+        Path(self.0[.. self.0.len().saturating_sub(1)].to_vec())
+    }
+
+    pub fn child(&self, name: &str) -> Path {
+        let mut segments = self.0.clone();
+
+        segments.push(name.to_owned());
+
+        Path(segments)
+    }
+
+    /// The segments joined the way a target spells nested names, like `geo_Point` in C.
+    pub fn join(&self, separator: &str) -> String {
+        self.0.join(separator)
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.join("::"))
+    }
+}
+
+/// An expression along with the type of the value it produces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub node: Node,
+    pub ty:   Type,
+}
+
+impl Expression {
+    pub fn new(node: Node, ty: Type) -> Expression {
+        Expression {
+            node: node,
+            ty:   ty,
+        }
+    }
+
+    /// The expression without the conversions around it.
+    pub fn unconverted(&self) -> &Expression {
+        match self.node {
+            Node::Convert(ref e) => e.unconverted(),
+            _                    => self,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Text(String),
+
+    /// A parameter or a variable declared in the function, lambda or block in sight.
+    Local(String),
+    /// A variable defined by the program or one of its modules.
+    Global(Path),
+    /// A function named without being called, which is passed around like a lambda.
+    Function(Path),
+    /// The object the method being gone through was called on.
+    This,
+    /// A field of an object, along with the class declaring it.
+    Field(Box<Expression>, Path, String),
+    /// A member of something the program doesn't define, like an object of a library's class.
+    Member(Box<Expression>, String),
+    /// A variant of an enum, along with the values of its fields when it carries any.
+    Variant(Path, String, Vec<Expression>),
+    /// A name the program doesn't define, left as it is written, like `printf`.
+    Foreign(String),
+
+    Binary(Box<Expression>, Operator, Box<Expression>),
+    Unary(Operator, Box<Expression>),
+    /// A value converted to the type of the expression, like an `int` stored in a `float`
+    /// or an object passed as its base class.
+    Convert(Box<Expression>),
+    Index(Box<Expression>, Box<Expression>),
+
+    /// A call to a function the program defines, with the type arguments written for it.
+    Call(Path, Vec<Type>, Vec<Expression>),
+    /// A call to a method of an object, along with the class declaring it.
+    Method(Box<Expression>, Path, String, Vec<Expression>),
+    /// A call to a method of a string, list or map, or of something the program doesn't
+    /// define.
+    Invoke(Box<Expression>, String, Vec<Expression>),
+    /// A call to a function value, like a lambda or a function from a library.
+    Apply(Box<Expression>, Vec<Expression>),
+    /// An object made by its class, with the type arguments written for it.
+    New(Path, Vec<Type>, Vec<Expression>),
+
+    List(Vec<Expression>),
+    Map(Vec<(Expression, Expression)>),
+    /// An anonymous function, with its parameters and body; its type tells what it returns.
+    Lambda(Vec<(String, Type)>, Vec<Statement>),
+    /// The arrays a `name[size]: type` declaration makes, by their sizes from the outermost
+    /// in, each element made the way C++ makes it.
+    Array(Vec<Expression>),
+}
+
+/// An arm of a `match`: the variant it is for, or none for every other one, and the fields
+/// it binds by their place in the variant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arm {
+    pub variant:  Option<String>,
+    pub bindings: Vec<(usize, String, Type)>,
+    pub body:     Vec<Statement>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// A local variable, along with its value, or none for one made the way C++ makes it
+    /// without one.
+    Let(String, Type, Option<Expression>, Position),
+    /// Stores a value, combined with the one already stored by the operator if given.
+    Assign(Expression, Option<Operator>, Expression, Position),
+    Expression(Expression, Position),
+    /// A condition with what is run when it holds and when it doesn't; `else if` chains are
+    /// nested in the second.
+    If(Expression, Vec<Statement>, Vec<Statement>, Position),
+    /// A loop run while its condition holds, with what comes before it and what is run after
+    /// each time through, like `for (int i = a; i < b; i += 1)`.
+    Loop(Option<Box<Statement>>, Expression, Option<Box<Statement>>, Vec<Statement>, Position),
+    /// A loop over the elements of a collection, with the name and type of each.
+    Each(String, Type, Expression, Vec<Statement>, Position),
+    Match(Expression, Vec<Arm>, Position),
+    Break(Position),
+    Continue(Position),
+    Return(Option<Expression>, Position),
+    /// Brings the names of a module into sight for the rest of the function.
+    Use(Path, Position),
+}
+
+impl Statement {
+    pub fn position(&self) -> Position {
+        match *self {
+            Statement::Let(_, _, _, p)        |
+            Statement::Assign(_, _, _, p)     |
+            Statement::Expression(_, p)       |
+            Statement::If(_, _, _, p)         |
+            Statement::Loop(_, _, _, _, p)    |
+            Statement::Each(_, _, _, _, p)    |
+            Statement::Match(_, _, p)         |
+            Statement::Break(p)               |
+            Statement::Continue(p)            |
+            Statement::Return(_, p)           |
+            Statement::Use(_, p)              => p,
+        }
+    }
+}
+
+/// A function, method or constructor; its body is none when it is only declared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub path:        Path,
+    pub generics:    Vec<String>,
+    pub params:      Vec<(String, Type)>,
+    pub retty:       Type,
+    pub body:        Option<Vec<Statement>>,
+    pub constructor: bool,
+    pub position:    Position,
+}
+
+impl Function {
+    pub fn name(&self) -> &str {
+        self.path.name()
+    }
+}
+
+/// A field of a class, along with the value it starts with if one is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name:     String,
+    pub ty:       Type,
+    pub value:    Option<Expression>,
+    pub position: Position,
+}
+
+/// A class, or a `structure`, which is plain: it has no methods and derives from nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    pub path:     Path,
+    pub generics: Vec<String>,
+    pub parent:   Option<Type>,
+    pub plain:    bool,
+    pub fields:   Vec<Field>,
+    pub methods:  Vec<Function>,
+    pub position: Position,
+}
+
+/// A variant of an enum, with the names and types of the fields it carries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name:     String,
+    pub fields:   Vec<(String, Type)>,
+    pub position: Position,
+}
+
+/// What a file defines, the way every backend is handed it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    /// An imported file, or a library when the flag is set.
+    Import(String, bool, Position),
+    /// Brings the names of a module into sight for the rest of the file.
+    Use(Path, Position),
+    /// A variable of the program or of a module, with its value if one is written.
+    Global(Path, Type, Option<Expression>, Position),
+    Function(Function),
+    Module(Path, Vec<Item>, Position),
+    Class(Class),
+    Enum(Path, Vec<Variant>, Position),
+    /// The methods an `implement` block defines for a class, along with the type parameters
+    /// it names for a generic one.
+    Implement(Path, Vec<String>, Vec<Function>, Position),
+}
@@ -0,0 +1,192 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use super::ast::{Expression, Statement, Position};
+use super::diagnostic::{Diagnostic, code};
+
+/// One Helix file of a program.
+pub struct Unit {
+    pub path:    PathBuf,
+    /// The file's path relative to the one the program starts from, without the extension;
+    /// its translation is written under this name.
+    pub name:    String,
+    pub source:  String,
+    pub ast:     Vec<Statement>,
+    /// The units this one imports, as indices into the program's units.
+    pub imports: Vec<usize>,
+}
+
+/// The diagnostics that stopped a file from loading, with the source they point into.
+pub struct Failure {
+    pub diagnostics: Vec<Diagnostic>,
+    pub source:      String,
+}
+
+/// Whether an import without `library` names a Helix file rather than a C++ header.
+pub fn is_module(path: &str) -> bool {
+    match Path::new(path).extension() {
+        Some(e) => e == "helix",
+        None    => true,
+    }
+}
+
+/// The path of an imported Helix file without its extension.
+pub fn module_name(path: &str) -> &str {
+    if path.ends_with(".helix") {
+        &path[.. path.len() - ".helix".len()]
+    } else {
+        path
+    }
+}
+
+/// Loads the program starting at `path` along with every Helix file it imports, each one
+/// parsed through the same front end. Files come after the files they import, so the one
+/// at `path` is last.
+pub fn load(path: &Path) -> Result<Vec<Unit>, Failure> {
+    let source = match read(path) {
+        Ok(s)  => s,
+        Err(e) => return Err(Failure {
+            diagnostics: vec!(Diagnostic::error(code::MODULE, format!("couldn't read `{}`: {}", path.display(), e))),
+            source:      String::new(),
+        }),
+    };
+
+    load_source(path, source)
+}
+
+/// Loads a program like `load` does, taking the source of the file it starts from as given
+/// rather than as it is on disk, like an editor has it.
+pub fn load_source(path: &Path, source: String) -> Result<Vec<Unit>, Failure> {
+    let name = match path.file_stem() {
+        Some(n) => n.to_string_lossy().into_owned(),
+        None    => "main".to_owned(),
+    };
+
+    let mut loader = Loader {
+        units:   Vec::new(),
+        loaded:  Vec::new(),
+        loading: Vec::new(),
+    };
+
+    try!(loader.unit(path.to_owned(), name, source));
+
+    Ok(loader.units)
+}
+
+struct Loader {
+    units:   Vec<Unit>,
+    /// Where each unit's file really is, to tell when two imports name the same file.
+    loaded:  Vec<PathBuf>,
+    /// The files being loaded, each imported by the one before it, along with their names.
+    loading: Vec<(PathBuf, String)>,
+}
+
+impl Loader {
+    fn unit(&mut self, path: PathBuf, name: String, source: String) -> Result<usize, Failure> {
+        let file = path.to_string_lossy().into_owned();
+
+        let ast = match super::parse(&source) {
+            Ok(a)  => a,
+            Err(e) => return Err(Failure {
+                diagnostics: e.into_iter().map(|d| d.in_file(&file)).collect(),
+                source:      source,
+            }),
+        };
+
+        let canonical = path.canonicalize().unwrap_or(path.clone());
+
+        self.loading.push((canonical.clone(), name.clone()));
+
+        let mut imports = Vec::new();
+
+        for s in ast.iter() {
+            if let Statement::Expression(ref e, _) = *s {
+                if let Expression::Import(ref p, false, position) = **e {
+                    if !is_module(p) {
+                        continue
+                    }
+
+                    let i = match self.import(&path, &name, &source, p, position) {
+                        Ok(i)  => i,
+                        Err(f) => {
+                            self.loading.pop();
+
+                            return Err(f)
+                        }
+                    };
+
+                    if !imports.contains(&i) {
+                        imports.push(i)
+                    }
+                }
+            }
+        }
+
+        self.loading.pop();
+
+        self.loaded.push(canonical);
+        self.units.push(Unit {
+            path:    path,
+            name:    name,
+            source:  source,
+            ast:     ast,
+            imports: imports,
+        });
+
+        Ok(self.units.len() - 1)
+    }
+
+    /// Loads the file an import in `from` refers to, which is looked for next to `from`.
+    fn import(&mut self, from: &Path, name: &str, source: &str, import: &str, position: Position) -> Result<usize, Failure> {
+        let module = module_name(import);
+
+        let path = from.parent().unwrap_or(Path::new("")).join(format!("{}.helix", module));
+        let name = Path::new(name).parent().unwrap_or(Path::new("")).join(module).to_string_lossy().into_owned();
+
+        let fail = |d: Diagnostic| Failure {
+            diagnostics: vec!(d.at(position.0, position.1, import.len() as u32).in_file(&from.to_string_lossy())),
+            source:      source.to_owned(),
+        };
+
+        let canonical = match path.canonicalize() {
+            Ok(p)  => p,
+            Err(_) => return Err(fail(
+                    Diagnostic::error(code::MODULE, format!("cannot find module `{}`", import))
+                        .note(format!("no file at `{}`", path.display()))
+                )),
+        };
+
+        if let Some(i) = self.loading.iter().position(|&(ref p, _)| *p == canonical) {
+            let mut cycle: Vec<&str> = self.loading[i ..].iter().map(|&(_, ref n)| n.as_str()).collect();
+
+            cycle.push(&self.loading[i].1);
+
+            return Err(fail(
+                    Diagnostic::error(code::CYCLE, format!("cyclic import of `{}`", import))
+                        .note(format!("the files import each other in a cycle: {}", cycle.join(" -> ")))
+                ))
+        }
+
+        if let Some(i) = self.loaded.iter().position(|p| *p == canonical) {
+            return Ok(i)
+        }
+
+        let code = match read(&path) {
+            Ok(s)  => s,
+            Err(e) => return Err(fail(
+                    Diagnostic::error(code::MODULE, format!("couldn't read module `{}`: {}", import, e))
+                )),
+        };
+
+        self.unit(path, name, code)
+    }
+}
+
+fn read(path: &Path) -> ::std::io::Result<String> {
+    let mut source = String::new();
+
+    try!(try!(File::open(path)).read_to_string(&mut source));
+
+    Ok(source)
+}
@@ -1,5 +1,41 @@
 pub mod token;
+pub mod diagnostic;
 pub mod block_tree;
 pub mod tokenizer;
 pub mod ast;
+pub mod resolver;
+pub mod ir;
+pub mod loader;
 pub mod translater;
+pub mod interpreter;
+pub mod bytecode;
+
+use self::ast::Statement;
+use self::token::Token;
+use self::diagnostic::Diagnostic;
+
+/// Runs source through the block tree and tokenizer, giving the tokens the parser reads with
+/// every indented block as a single token.
+pub fn tokens(source: &str) -> Result<Vec<Token>, Vec<Diagnostic>> {
+    let indents = match block_tree::BlockTree::new(source, 0).collect_indents() {
+        Ok(i) => i,
+        Err(e) => return Err(vec!(e)),
+    };
+
+    let root = block_tree::BlockTree::new(source, 0).make_tree(&indents);
+
+    let branch = try!(tokenizer::Tokenizer::tokenize_branch(&root));
+
+    Ok(tokenizer::flatten_tree(&branch))
+}
+
+/// Runs source through the whole front end: block tree, tokenizer and parser.
+pub fn parse(source: &str) -> Result<Vec<Statement>, Vec<Diagnostic>> {
+    let mut parser = ast::Parser::from(
+             tokenizer::Tokenizer::from(
+                    try!(tokens(source)),
+                ),
+        );
+
+    parser.parse()
+}
@@ -0,0 +1,92 @@
+use std::rc::Rc;
+use std::collections::HashMap;
+
+use super::super::ast::Position;
+
+use super::scope::{Symbol, Scope};
+use super::types::Type;
+
+/// A name written in a file, along with what it refers to.
+#[derive(Clone)]
+pub struct Reference {
+    pub name:       String,
+    pub position:   Position,
+    /// Where the name was defined, unless that is in another file or the compiler itself.
+    pub definition: Option<Position>,
+    pub symbol:     Symbol,
+    /// The module, class or enum defining the name, or none for locals and what is built
+    /// in.
+    pub scope:      Option<Rc<Scope>>,
+}
+
+impl Reference {
+    /// The definition the name refers to, written the way it reads in Helix.
+    pub fn describe(&self) -> String {
+        match self.symbol {
+            Symbol::Variable(ref t) => format!("{}: {}", self.name, t),
+
+            Symbol::Function(ref s) | Symbol::Prototype(ref s) => {
+                let params: Vec<String> = s.params.iter().map(|p| p.to_string()).collect();
+
+                format!("function {}{} ({}) -> {}", self.name, bracketed(&s.generics), params.join(", "), s.retty)
+            }
+
+            Symbol::Class(ref c) => match *c.base.borrow() {
+                Some(ref b) => format!("class {}{} <- {}", c.name, bracketed(&c.generics.borrow()), b.name),
+                None        => format!("class {}{}", c.name, bracketed(&c.generics.borrow())),
+            },
+
+            Symbol::Module(ref m) => format!("module {}", m.name),
+            Symbol::Enum(ref e)   => format!("enum {}", e.name),
+
+            Symbol::Variant(ref s) => match s.params.len() {
+                0 => format!("{}::{}", s.retty, self.name),
+                _ => {
+                    let fields: Vec<String> = s.params.iter().map(|p| p.to_string()).collect();
+
+                    format!("{}::{}({})", s.retty, self.name, fields.join(", "))
+                }
+            },
+
+            Symbol::Builtin       => format!("function {} (built in)", self.name),
+        }
+    }
+}
+
+/// What resolving a file found out about the names in it, for editors to point at and for
+/// the file to be lowered from.
+pub struct Index {
+    pub references: Vec<Reference>,
+    /// The names defined by the file and the files it imports.
+    pub namespace:  Rc<Scope>,
+    /// The types inferred for variables and functions declared without one, and those of
+    /// lambdas, by where they were written.
+    pub types:      HashMap<Position, Type>,
+}
+
+impl Index {
+    /// The name written over a position.
+    pub fn at(&self, position: Position) -> Option<&Reference> {
+        let (line, col) = position;
+
+        self.references.iter().find(|r| {
+            r.position.0 == line && r.position.1 <= col && col < r.position.1 + r.name.len() as u32
+        })
+    }
+
+    /// The last time `name` was written before a position, which is most likely what it still
+    /// refers to there.
+    pub fn before(&self, name: &str, position: Position) -> Option<&Reference> {
+        self.references.iter()
+            .filter(|r| r.name == name && r.position < position)
+            .max_by_key(|r| r.position)
+    }
+}
+
+/// The type parameters of a generic, as they are written after its name.
+fn bracketed(generics: &[String]) -> String {
+    match generics.len() {
+        0 => String::new(),
+        _ => format!("[{}]", generics.join(", ")),
+    }
+}
@@ -0,0 +1,1955 @@
+pub mod scope;
+pub mod types;
+pub mod index;
+
+use std::mem;
+use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+
+use super::ast::{self, Expression, Statement, Position, Arm};
+use super::diagnostic::{Diagnostic, code};
+
+use self::scope::{Symbol, Signature, Scope};
+use self::types::{Type, method, instance};
+use self::index::{Index, Reference};
+
+/// Checks a program before it is translated: names are defined once, used where they are
+/// visible and every value is used as its type allows. The types inferred for `var`
+/// declarations and for functions without a `-> type` are written back into the tree.
+///
+/// Definitions in modules and classes are collected first, so they can be used anywhere in
+/// the program; variables inside functions only exist from their declaration onward. The
+/// definitions of the files in `imports`, which have been resolved already, are visible too.
+pub fn resolve(ast: &mut [Statement], imports: &[&[Statement]]) -> Vec<Diagnostic> {
+    analyze(ast, imports).0
+}
+
+/// Resolves a file like `resolve` does, also handing back every name written in it along
+/// with what the name refers to.
+pub fn analyze(ast: &mut [Statement], imports: &[&[Statement]]) -> (Vec<Diagnostic>, Index) {
+    let mut resolver = Resolver::new();
+
+    resolver.importing = true;
+
+    for i in imports.iter() {
+        resolver.declare(i);
+    }
+
+    // whatever is wrong with the imported files has been reported with them, and positions
+    // only identify functions within one file
+    resolver.importing = false;
+    resolver.diagnostics.clear();
+    resolver.inferred.clear();
+
+    resolver.declare(ast);
+
+    // a function's return type comes from its body, which may call functions further down
+    // the program, so bodies are gone through quietly until the inferred types settle. Every
+    // pass but the last changes the type of some function, and a type only changes so often
+    // unless it keeps growing, like that of a function returning a list of what it returns
+    let declared = resolver.diagnostics.len();
+    let passes   = (resolver.inferred.len() + 1) * WIDENINGS;
+
+    for _ in 0..passes {
+        resolver.changed.clear();
+
+        resolver.resolve(ast);
+        resolver.diagnostics.truncate(declared);
+
+        if resolver.changed.is_empty() {
+            break
+        }
+    }
+
+    for (name, position) in mem::replace(&mut resolver.changed, Vec::new()) {
+        resolver.error(code::TYPE, format!("the return type of `{}` can't be inferred", name), position, name.len())
+            .notes.push("it keeps changing with the types it is inferred from; write it down with `-> type`".to_owned());
+    }
+
+    resolver.recording = true;
+    resolver.resolve(ast);
+
+    annotate(ast, &resolver.types);
+
+    let mut diagnostics = resolver.diagnostics;
+
+    diagnostics.sort_by_key(|d| d.span.as_ref().map(|s| (s.line, s.col)));
+
+    let index = Index {
+        references: resolver.references,
+        namespace:  resolver.namespace,
+        types:      resolver.types,
+    };
+
+    (diagnostics, index)
+}
+
+/// Where names defined by imported files are said to be, since positions don't tell files
+/// apart.
+const IMPORTED: Position = (0, 0);
+
+/// How often the inferred return type of a function can change while its body is gone
+/// through again and again: from nothing to unknown, and then wider through `int`, `long`,
+/// `float` and `double`.
+const WIDENINGS: usize = 5;
+
+struct Resolver {
+    namespace:   Rc<Scope>,
+    this:        Option<Rc<Scope>>,
+    locals:      Vec<HashMap<String, (Symbol, Position)>>,
+    /// The modules pulled in by a `use` in the function being gone through, each with the
+    /// scope of locals it was written in.
+    uses:        Vec<(usize, Rc<Scope>)>,
+    position:    Position,
+    foreign:     bool,
+
+    function:    String,
+    /// The type parameters of the generic class and function being gone through.
+    generics:    Vec<String>,
+    expected:    Option<Type>,
+    returned:    Option<Type>,
+    /// How many scopes of locals lie outside the lambda being gone through; their locals
+    /// are captured by value.
+    captured:    usize,
+    /// How many loops the statement being gone through is in, within its function.
+    loops:       usize,
+
+    types:       HashMap<Position, Type>,
+    inferred:    HashSet<Position>,
+    /// The functions whose inferred return type changed in the last pass over the bodies.
+    changed:     Vec<(String, Position)>,
+
+    importing:   bool,
+    recording:   bool,
+    references:  Vec<Reference>,
+
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    fn new() -> Resolver {
+        Resolver {
+            namespace:   Scope::new("".to_owned(), None),
+            this:        None,
+            locals:      Vec::new(),
+            uses:        Vec::new(),
+            position:    (0, 0),
+            foreign:     false,
+
+            function:    String::new(),
+            generics:    Vec::new(),
+            expected:    None,
+            returned:    None,
+            captured:    0,
+            loops:       0,
+
+            types:       HashMap::new(),
+            inferred:    HashSet::new(),
+            changed:     Vec::new(),
+
+            importing:   false,
+            recording:   false,
+            references:  Vec::new(),
+
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn error(&mut self, code: &'static str, message: String, position: Position, len: usize) -> &mut Diagnostic {
+        let (line, col) = position;
+
+        self.diagnostics.push(Diagnostic::error(code, message).at(line, col, len as u32));
+        self.diagnostics.last_mut().unwrap()
+    }
+
+    /// Reports a name that refers to nothing. Names could just as well come from an imported
+    /// C++ library, which can't be checked, so in programs importing one this only warns.
+    fn undefined(&mut self, message: String, position: Position, len: usize) {
+        let (line, col) = position;
+
+        if self.foreign {
+            let warning = Diagnostic::warning(code::UNDEFINED, message)
+                .at(line, col, len as u32)
+                .note("it may be declared by an imported library, which isn't checked".to_owned());
+
+            self.diagnostics.push(warning)
+        } else {
+            self.error(code::UNDEFINED, message, position, len);
+        }
+    }
+
+    fn duplicate(&mut self, name: &str, position: Position, previous: Position) {
+        let note = match previous {
+            IMPORTED => format!("`{}` is also defined by an imported file", name),
+            p        => format!("`{}` was first defined at {}:{}", name, p.0, p.1),
+        };
+
+        let error = self.error(code::DUPLICATE,
+                               format!("the name `{}` is defined multiple times", name),
+                               position, name.len());
+
+        error.notes.push(note)
+    }
+
+    fn mismatch(&mut self, expected: &Type, found: &Type, at: (Position, usize)) -> &mut Diagnostic {
+        self.error(code::TYPE,
+                   format!("mismatched types: expected `{}`, found `{}`", expected, found),
+                   at.0, at.1)
+    }
+
+    /// Where an expression is reported, falling back on the function it is in.
+    fn locate(&self, ex: &Expression) -> (Position, usize) {
+        span(ex).unwrap_or((self.position, 1))
+    }
+
+    /// Notes that a name written at `position` refers to `entry`, which was found in `scope`
+    /// unless it is a local, on the last pass only.
+    fn reference(&mut self, name: &str, position: Position, entry: &(Symbol, Position), scope: Option<&Rc<Scope>>) {
+        if !self.recording {
+            return
+        }
+
+        self.references.push(Reference {
+            name:       name.to_owned(),
+            position:   position,
+            definition: if entry.1 == IMPORTED { None } else { Some(entry.1) },
+            symbol:     entry.0.clone(),
+            scope:      scope.and_then(|s| scope::owner(s, name)),
+        })
+    }
+
+    /// Notes the definition of a name as a reference to itself, now that its type is known.
+    fn definition(&mut self, scope: &Rc<Scope>, name: &str, position: Position) {
+        if let Some(entry) = scope.find(name) {
+            self.reference(name, position, &entry, Some(scope))
+        }
+    }
+
+    /// Defines a function or its declaration; a declaration may be followed by one definition
+    /// taking the same number of arguments, which then returns the declared type.
+    fn function(&mut self, scope: &Rc<Scope>, name: &str, symbol: Symbol, written: bool, position: Position) {
+        let position = if self.importing { IMPORTED } else { position };
+
+        let previous = match scope.define(name.to_owned(), symbol.clone(), position) {
+            Ok(_)  => {
+                if !written {
+                    self.inferred.insert(position);
+                }
+
+                return
+            }
+
+            Err(p) => p,
+        };
+
+        match (previous, symbol) {
+            ((Symbol::Prototype(a), _), Symbol::Function(b)) |
+            ((Symbol::Prototype(a), _), Symbol::Prototype(b)) if a.params.len() == b.params.len() => {
+                let retty = if written { b.retty } else { a.retty };
+
+                if !written && scope.name != name {
+                    self.types.insert(position, retty.clone());
+                }
+
+                scope.redefine(name.to_owned(), Symbol::Function(Signature {
+                    generics: b.generics,
+                    params:   b.params,
+                    retty:    retty,
+                }), position)
+            }
+
+            ((Symbol::Prototype(a), p), _) => {
+                let note = format!("`{}` was declared at {}:{} taking {}",
+                                   name, p.0, p.1, arguments(a.params.len()));
+
+                let error = self.error(code::DUPLICATE,
+                                       format!("`{}` doesn't match its earlier declaration", name),
+                                       position, name.len());
+
+                error.notes.push(note)
+            }
+
+            ((_, p), _) => self.duplicate(name, position, p),
+        }
+    }
+
+    fn define(&mut self, scope: &Rc<Scope>, name: &str, symbol: Symbol, position: Position) {
+        let position = if self.importing { IMPORTED } else { position };
+
+        if let Err((_, p)) = scope.define(name.to_owned(), symbol, position) {
+            self.duplicate(name, position, p)
+        }
+    }
+
+    fn signature(&mut self, generics: &[String], args: &[(String, String)], retty: &Option<Box<Expression>>) -> Signature {
+        let outer = self.generics.len();
+
+        self.generics.extend(generics.iter().cloned());
+
+        let signature = Signature {
+            generics: generics.to_vec(),
+            params:   args.iter().map(|&(ref t, _)| self.named(t)).collect(),
+            retty:    match *retty {
+                Some(ref t) => self.type_of(&**t),
+                None        => Type::Unknown,
+            },
+        };
+
+        self.generics.truncate(outer);
+
+        signature
+    }
+
+    /// Collects the modules, classes, functions and variables defined by a namespace.
+    fn declare(&mut self, body: &[Statement]) {
+        let namespace = self.namespace.clone();
+
+        for s in body.iter() {
+            match *s {
+                Statement::Declaration(ref n, ref t, _, p) => {
+                    let t = match *t {
+                        Some(ref t) => self.type_of(&**t),
+                        None        => Type::Unknown,
+                    };
+
+                    self.define(&namespace, n, Symbol::Variable(t), p)
+                }
+
+                Statement::Expression(ref e, _) => match **e {
+                    Expression::Function(ref n, ref g, ref a, _, ref t, p) => {
+                        let signature = self.signature(g, a, t);
+
+                        self.function(&namespace, n, Symbol::Function(signature), t.is_some(), p)
+                    }
+
+                    Expression::FunctionDef(ref n, ref g, ref a, ref t, p) => {
+                        let signature = self.signature(g, a, &Some(t.clone()));
+
+                        self.function(&namespace, n, Symbol::Prototype(signature), true, p)
+                    }
+
+                    Expression::Module(ref n, ref c, p) => {
+                        let module = match namespace.member(n) {
+                            Some(Symbol::Module(m)) => m,
+                            _                       => {
+                                let m = Scope::new(n.clone(), Some(namespace.clone()));
+
+                                self.define(&namespace, n, Symbol::Module(m.clone()), p);
+
+                                m
+                            }
+                        };
+
+                        self.namespace = module;
+                        self.declare(c);
+                        self.namespace = namespace.clone();
+                    }
+
+                    Expression::Class(ref n, ref g, ref c, ref b, p) => {
+                        let class = Scope::new(n.clone(), Some(namespace.clone()));
+
+                        *class.generics.borrow_mut() = g.clone();
+
+                        if let Some(ref b) = *b {
+                            // the type arguments given to a generic base don't change its members
+                            let b = match **b {
+                                Expression::Generic(ref b, _) => b,
+                                _                             => b,
+                            };
+
+                            match self.path(&**b) {
+                                Some(Symbol::Class(b)) => *class.base.borrow_mut() = Some(b),
+
+                                _ => if let Some((name, position)) = name_of(&**b) {
+                                    self.undefined(format!("cannot find class `{}` in this scope", name),
+                                                   position, name.len())
+                                },
+                            }
+                        }
+
+                        self.class(class, c, p);
+                    }
+
+                    Expression::Struct(ref n, ref c, p) => {
+                        let class = Scope::new(n.clone(), Some(namespace.clone()));
+
+                        self.class(class, c, p);
+                    }
+
+                    Expression::Enum(ref n, ref v, p) => {
+                        let enumeration = Scope::new(n.clone(), Some(namespace.clone()));
+
+                        self.define(&namespace, n, Symbol::Enum(enumeration.clone()), p);
+
+                        for &(ref name, ref fields, position) in v.iter() {
+                            let signature = Signature {
+                                generics: Vec::new(),
+                                params:   fields.iter().map(|&(ref t, _)| self.named(t)).collect(),
+                                retty:    Type::Enum(enumeration.clone()),
+                            };
+
+                            self.define(&enumeration, name, Symbol::Variant(signature), position)
+                        }
+                    }
+
+                    Expression::Implement(ref n, ref g, ref c, p) => {
+                        let class = match namespace.lookup(n) {
+                            Some(Symbol::Class(c)) => c,
+                            _                      => {
+                                self.error(code::UNDECLARED,
+                                           format!("cannot implement `{}`, no class of that name was declared", n),
+                                           p, n.len());
+
+                                continue
+                            }
+                        };
+
+                        let expected = class.generics.borrow().len();
+
+                        if g.len() != expected {
+                            self.error(code::GENERICS,
+                                       format!("`{}` takes {} but the implementation names {}",
+                                               n, parameters(expected), g.len()),
+                                       p, n.len())
+                                .notes.push(format!("write `implement {}[{}]`", n, class.generics.borrow().join(", ")));
+                        }
+
+                        self.generics = g.clone();
+                        self.methods(&class, c);
+                        self.generics.clear();
+                    }
+
+                    Expression::Use(ref e) => if let Some(m) = self.used(&**e) {
+                        namespace.uses.borrow_mut().push(m)
+                    },
+
+                    Expression::Typed(ref i, ref t) => {
+                        let t = self.type_of(&**t);
+
+                        if let Some((name, position)) = declared(&**i) {
+                            self.define(&namespace, name, Symbol::Variable(array(&**i, t)), position)
+                        }
+                    }
+
+                    Expression::Import(_, true, _) => self.foreign = true,
+
+                    _ => (),
+                },
+
+                _ => (),
+            }
+        }
+    }
+
+    /// The module a `use` pulls in, reporting what isn't one.
+    fn used(&mut self, e: &Expression) -> Option<Rc<Scope>> {
+        match self.path(e) {
+            Some(Symbol::Module(m)) => return Some(m),
+
+            Some(_) => if let Some((name, position)) = name_of(e) {
+                self.error(code::UNDEFINED, format!("`{}` is not a module", name), position, name.len());
+            },
+
+            // the standard library comes with the C++ compiler
+            None => match *e {
+                Expression::Ident(ref n, _) if n == "std" => (),
+
+                _ => if let Some((name, position)) = name_of(e) {
+                    self.undefined(format!("cannot find module `{}` in this scope", name),
+                                   position, name.len())
+                },
+            },
+        }
+
+        None
+    }
+
+    /// Fills the scope of a class with its fields and methods.
+    fn class(&mut self, class: Rc<Scope>, body: &[Statement], position: Position) {
+        let namespace = self.namespace.clone();
+
+        self.define(&namespace, &class.name, Symbol::Class(class.clone()), position);
+
+        self.this     = Some(class.clone());
+        self.generics = class.generics.borrow().clone();
+
+        for s in body.iter() {
+            match *s {
+                Statement::Declaration(ref n, ref t, _, p) => {
+                    let t = match *t {
+                        Some(ref t) => self.type_of(&**t),
+                        None        => Type::Unknown,
+                    };
+
+                    self.define(&class, n, Symbol::Variable(t), p)
+                }
+
+                Statement::Expression(ref e, _) => match **e {
+                    Expression::Typed(ref i, ref t) => {
+                        let t = self.type_of(&**t);
+
+                        if let Some((n, p)) = declared(&**i) {
+                            self.define(&class, n, Symbol::Variable(array(&**i, t)), p)
+                        }
+                    }
+
+                    _ => (),
+                },
+
+                _ => (),
+            }
+        }
+
+        self.methods(&class, body);
+
+        self.generics.clear()
+    }
+
+    /// Defines the methods of a class body or of an `implement` block.
+    fn methods(&mut self, class: &Rc<Scope>, body: &[Statement]) {
+        self.this = Some(class.clone());
+
+        for s in body.iter() {
+            if let Statement::Expression(ref e, _) = *s {
+                match **e {
+                    Expression::Function(ref n, ref g, ref a, _, ref t, p) => {
+                        let mut signature = self.signature(g, a, t);
+
+                        // constructors make an object of their class
+                        if *n == class.name {
+                            signature.retty = instance(class)
+                        }
+
+                        let written = t.is_some() || *n == class.name;
+
+                        self.function(class, n, Symbol::Function(signature), written, p)
+                    }
+
+                    Expression::FunctionDef(ref n, ref g, ref a, ref t, p) => {
+                        let mut signature = self.signature(g, a, &Some(t.clone()));
+
+                        if *n == class.name {
+                            signature.retty = instance(class)
+                        }
+
+                        self.function(class, n, Symbol::Prototype(signature), true, p)
+                    }
+
+                    _ => (),
+                }
+            }
+        }
+
+        self.this = None
+    }
+
+    /// Checks everything a namespace defines.
+    fn resolve(&mut self, body: &[Statement]) {
+        let namespace = self.namespace.clone();
+
+        for s in body.iter() {
+            match *s {
+                Statement::Declaration(ref n, ref t, ref e, p) => {
+                    let t = self.initial(t, &**e, p);
+
+                    update(&namespace, n, t);
+
+                    self.definition(&namespace, n, p)
+                }
+
+                Statement::Expression(ref e, _) => match **e {
+                    Expression::Function(ref n, ref g, ref a, ref c, _, p) => {
+                        self.generics = g.clone();
+                        self.body(namespace.clone(), n, a, c, p);
+                        self.generics.clear();
+
+                        self.definition(&namespace, n, p)
+                    }
+
+                    Expression::Module(ref n, ref c, p) => {
+                        if let Some(Symbol::Module(m)) = namespace.member(n) {
+                            self.namespace = m;
+                            self.resolve(c);
+                            self.namespace = namespace.clone();
+                        }
+
+                        self.definition(&namespace, n, p)
+                    }
+
+                    Expression::Class(ref n, _, ref c, _, p) | Expression::Struct(ref n, ref c, p) => {
+                        if let Some(Symbol::Class(class)) = namespace.member(n) {
+                            let generics = class.generics.borrow().clone();
+
+                            self.members(class, &generics, c)
+                        }
+
+                        self.definition(&namespace, n, p)
+                    }
+
+                    Expression::Implement(ref n, ref g, ref c, p) => {
+                        if let Some(Symbol::Class(class)) = namespace.lookup(n) {
+                            self.members(class, g, c)
+                        }
+
+                        self.definition(&namespace, n, p)
+                    }
+
+                    Expression::Enum(ref n, ref v, p) => {
+                        if let Some(Symbol::Enum(enumeration)) = namespace.member(n) {
+                            for &(ref name, _, position) in v.iter() {
+                                self.definition(&enumeration, name, position)
+                            }
+                        }
+
+                        self.definition(&namespace, n, p)
+                    }
+
+                    Expression::Typed(ref i, _) => {
+                        self.sizes(&**i);
+
+                        if let Some((n, p)) = declared(&**i) {
+                            self.definition(&namespace, n, p)
+                        }
+                    }
+
+                    Expression::Use(ref e) => if let (Some((entry, scope)), Some((n, p))) = (self.entry(&**e), name_of(&**e)) {
+                        self.reference(n, p, &entry, Some(&scope))
+                    },
+
+                    _ => (),
+                },
+
+                _ => (),
+            }
+        }
+    }
+
+    fn members(&mut self, class: Rc<Scope>, generics: &[String], body: &[Statement]) {
+        self.this = Some(class.clone());
+        self.generics = generics.to_vec();
+
+        for s in body.iter() {
+            match *s {
+                Statement::Declaration(ref n, ref t, ref e, p) => {
+                    let t = self.initial(t, &**e, p);
+
+                    update(&class, n, t);
+
+                    self.definition(&class, n, p)
+                }
+
+                Statement::Expression(ref e, _) => match **e {
+                    Expression::Function(ref n, ref g, ref a, ref c, _, p) => {
+                        self.generics.extend(g.iter().cloned());
+                        self.body(class.clone(), n, a, c, p);
+                        self.generics.truncate(generics.len());
+
+                        self.definition(&class, n, p)
+                    }
+
+                    Expression::Typed(ref i, _) => {
+                        self.sizes(&**i);
+
+                        if let Some((n, p)) = declared(&**i) {
+                            self.definition(&class, n, p)
+                        }
+                    }
+
+                    _ => (),
+                },
+
+                _ => (),
+            }
+        }
+
+        self.this = None;
+        self.generics.clear()
+    }
+
+    /// Checks the body of a function defined in `scope`, inferring its return type when it
+    /// has none written down.
+    fn body(&mut self, scope: Rc<Scope>, name: &str, args: &[(String, String)], body: &[Statement], position: Position) {
+        let signature = match scope.symbols.borrow().get(name) {
+            Some(&(Symbol::Function(ref s), _)) => Some(s.clone()),
+            _                                   => None,
+        };
+
+        let mut parameters = HashMap::new();
+
+        for &(ref t, ref n) in args.iter() {
+            let t = self.named(t);
+
+            if parameters.insert(n.clone(), (Symbol::Variable(t), position)).is_some() {
+                self.error(code::DUPLICATE,
+                           format!("the parameter `{}` is defined multiple times", n),
+                           position, 0);
+            }
+        }
+
+        let infer = self.inferred.contains(&position);
+
+        self.function = name.to_owned();
+        self.position = position;
+        self.locals   = vec!(parameters);
+        self.loops    = 0;
+        self.returned = None;
+        self.expected = match signature {
+            _ if infer || scope.name == name => None,
+            Some(ref s)                      => Some(s.retty.clone()),
+            None                             => None,
+        };
+
+        for s in body.iter() {
+            self.statement(s)
+        }
+
+        self.locals.clear();
+        self.uses.clear();
+
+        if !infer {
+            return
+        }
+
+        let retty = self.returned.take().unwrap_or(Type::Void);
+
+        if self.types.get(&position) != Some(&retty) {
+            self.changed.push((name.to_owned(), position));
+            self.types.insert(position, retty.clone());
+        }
+
+        if let Some(&mut (Symbol::Function(ref mut s), _)) = scope.symbols.borrow_mut().get_mut(name) {
+            s.retty = retty
+        }
+    }
+
+    /// Checks the body of an anonymous function and tells its type, inferring what it returns
+    /// when that isn't written down.
+    fn lambda(&mut self, args: &[(String, String)], body: &[Statement], retty: &Option<Box<Expression>>,
+              position: Position) -> Type {
+        let params: Vec<Type> = args.iter().map(|&(ref t, _)| self.named(t)).collect();
+        let declared          = retty.as_ref().map(|t| self.type_of(&**t));
+
+        let mut parameters = HashMap::new();
+
+        for (&(_, ref n), t) in args.iter().zip(params.iter()) {
+            if parameters.insert(n.clone(), (Symbol::Variable(t.clone()), position)).is_some() {
+                self.error(code::DUPLICATE,
+                           format!("the parameter `{}` is defined multiple times", n),
+                           position, 0);
+            }
+        }
+
+        let function = mem::replace(&mut self.function, "lambda".to_owned());
+        let expected = mem::replace(&mut self.expected, declared.clone());
+        let returned = self.returned.take();
+        let captured = mem::replace(&mut self.captured, self.locals.len());
+        let loops    = mem::replace(&mut self.loops, 0);
+
+        self.locals.push(parameters);
+
+        for s in body.iter() {
+            self.statement(s)
+        }
+
+        self.locals.pop();
+
+        let depth = self.locals.len();
+
+        self.uses.retain(|&(d, _)| d <= depth);
+
+        let inferred = mem::replace(&mut self.returned, returned);
+
+        self.function = function;
+        self.expected = expected;
+        self.captured = captured;
+        self.loops    = loops;
+
+        let t = Type::Function(params, Box::new(declared.or(inferred).unwrap_or(Type::Void)));
+
+        self.types.insert(position, t.clone());
+
+        t
+    }
+
+    /// Reports assignments to the locals a lambda captured, which only hold a copy.
+    fn assignable(&mut self, target: &Expression) {
+        let (name, position) = match root(target) {
+            Some(r) => r,
+            None    => return,
+        };
+
+        let scope = self.locals.iter().rposition(|s| s.contains_key(name));
+
+        if let Some(i) = scope {
+            if i < self.captured {
+                self.error(code::ASSIGNMENT,
+                           format!("can't assign to `{}`, which the lambda captured", name),
+                           position, name.len())
+                    .notes.push("lambdas capture a copy of the locals they use".to_owned());
+            }
+        }
+    }
+
+    fn block(&mut self, body: &Statement) {
+        self.locals.push(HashMap::new());
+        self.statement(body);
+        self.locals.pop();
+
+        let depth = self.locals.len();
+
+        self.uses.retain(|&(d, _)| d <= depth);
+    }
+
+    /// The body of a loop, which `break` and `continue` may leave.
+    fn looped(&mut self, body: &Statement) {
+        self.loops += 1;
+        self.block(body);
+        self.loops -= 1;
+    }
+
+    fn local(&mut self, name: &str, t: Type, position: Position) {
+        let previous = self.locals.last_mut().unwrap().insert(name.to_owned(), (Symbol::Variable(t), position));
+
+        if let Some((_, p)) = previous {
+            self.duplicate(name, position, p)
+        }
+    }
+
+    /// Checks the value of a `var` declaration against its type, or infers the type from it.
+    fn initial(&mut self, t: &Option<Box<Expression>>, e: &Expression, position: Position) -> Type {
+        let value = self.value(e);
+
+        match *t {
+            Some(ref t) => {
+                let t = self.type_of(&**t);
+
+                if !t.accepts(&value) {
+                    let at = span(e).unwrap_or((position, 3));
+
+                    self.mismatch(&t, &value, at);
+                }
+
+                t
+            }
+
+            None => {
+                if value == Type::Void {
+                    self.error(code::TYPE,
+                               "can't declare a variable holding `void`".to_owned(),
+                               position, 0);
+                }
+
+                let empty = match *e {
+                    Expression::List(ref e, _) => e.is_empty(),
+                    Expression::Map(ref e, _)  => e.is_empty(),
+                    _                          => false,
+                };
+
+                if empty {
+                    let at = span(e).unwrap_or((position, 1));
+
+                    self.error(code::TYPE, "can't tell what the empty collection holds".to_owned(), at.0, at.1)
+                        .notes.push("write down its type, like `var xs: [int] = []`".to_owned());
+                }
+
+                self.types.insert(position, value.clone());
+
+                value
+            }
+        }
+    }
+
+    fn condition(&mut self, e: &Expression) {
+        let t = self.value(e);
+
+        if !t.is_condition() {
+            let at = self.locate(e);
+
+            self.mismatch(&Type::Bool, &t, at);
+        }
+    }
+
+    fn statement(&mut self, st: &Statement) {
+        match *st {
+            Statement::Expression(ref e, _) => match **e {
+                Expression::Typed(ref i, ref t) => {
+                    self.sizes(&**i);
+
+                    let t = self.type_of(&**t);
+
+                    if let Some((n, p)) = declared(&**i) {
+                        let t = array(&**i, t);
+
+                        self.reference(n, p, &(Symbol::Variable(t.clone()), p), None);
+                        self.local(n, t, p)
+                    }
+                }
+
+                // the names of the module are in sight until the end of the block
+                Expression::Use(ref u) => {
+                    if let (Some((entry, scope)), Some((n, p))) = (self.entry(&**u), name_of(&**u)) {
+                        self.reference(n, p, &entry, Some(&scope))
+                    }
+
+                    if let Some(m) = self.used(&**u) {
+                        let depth = self.locals.len();
+
+                        self.uses.push((depth, m))
+                    }
+                }
+
+                ref e => {
+                    self.expression(e);
+                }
+            },
+
+            Statement::Declaration(ref n, ref t, ref e, p) => {
+                let t = self.initial(t, &**e, p);
+
+                self.reference(n, p, &(Symbol::Variable(t.clone()), p), None);
+                self.local(n, t, p)
+            }
+
+            Statement::Assignment(ref n, ref e, p) => {
+                self.assignable(&**n);
+
+                let target = self.value(&**n);
+                let value  = self.value(&**e);
+
+                if !target.accepts(&value) {
+                    let at = span(&**e).unwrap_or((p, 1));
+
+                    self.mismatch(&target, &value, at);
+                }
+            }
+
+            Statement::CompoundAssignment(ref n, ref o, ref e, p) => {
+                self.assignable(&**n);
+
+                let target = self.value(&**n);
+                let value  = self.value(&**e);
+
+                match types::operation(o, &target, &value) {
+                    Some(ref t) if target.accepts(t) => (),
+
+                    Some(t) => {
+                        let at = span(&**n).unwrap_or((p, 1));
+
+                        self.mismatch(&target, &t, at);
+                    }
+
+                    None => {
+                        let at = span(&**n).unwrap_or((p, 1));
+
+                        self.error(code::OPERATOR,
+                                   format!("cannot apply `{}=` to `{}` and `{}`", o, target, value),
+                                   at.0, at.1);
+                    }
+                }
+            }
+
+            Statement::Block(ref c) => for s in c.iter() {
+                self.statement(s)
+            },
+
+            Statement::If(ref b, ref c, _) => {
+                for &(ref e, ref s) in b.iter() {
+                    self.condition(e);
+                    self.block(s);
+                }
+
+                if let Some(ref c) = *c {
+                    self.block(&**c)
+                }
+            }
+
+            Statement::While(ref e, ref c, _) => {
+                self.condition(&**e);
+                self.looped(&**c);
+            }
+
+            Statement::For(ref i, ref e, ref c, position) => {
+                let collection = self.value(&**e);
+
+                let element = match collection.iterated() {
+                    Some(t) => t,
+
+                    None => {
+                        let at = self.locate(&**e);
+
+                        self.error(code::TYPE, format!("`{}` can't be iterated over", collection), at.0, at.1);
+
+                        Type::Unknown
+                    }
+                };
+
+                self.locals.push(HashMap::new());
+                self.local(i, element, position);
+                self.looped(&**c);
+                self.locals.pop();
+            }
+
+            Statement::ForRange(ref i, ref a, ref b, ref c, position) => {
+                for e in [a, b].iter() {
+                    let t = self.value(&***e);
+
+                    if !t.is_integral() && !t.is_unknown() {
+                        let at = self.locate(&***e);
+
+                        self.mismatch(&Type::Int, &t, at);
+                    }
+                }
+
+                self.locals.push(HashMap::new());
+                self.local(i, Type::Int, position);
+                self.looped(&**c);
+                self.locals.pop();
+            }
+
+            Statement::Match(ref e, ref arms, p) => self.arms(&**e, arms, p),
+
+            Statement::Break(p) if self.loops == 0 => {
+                self.error(code::LOOP, "`break` outside of a loop".to_owned(), p, "break".len());
+            }
+
+            Statement::Continue(p) if self.loops == 0 => {
+                self.error(code::LOOP, "`continue` outside of a loop".to_owned(), p, "continue".len());
+            }
+
+            Statement::Break(_) | Statement::Continue(_) => (),
+        }
+    }
+
+    /// Checks the arms of a `match` against the variants of the enum matched on, and that
+    /// every variant has one.
+    fn arms(&mut self, subject: &Expression, arms: &[Arm], position: Position) {
+        let enumeration = match self.value(subject) {
+            Type::Enum(e)                    => Some(e),
+            Type::Unknown | Type::Foreign(_) => None,
+
+            t => {
+                let at = span(subject).unwrap_or((position, 5));
+
+                self.error(code::TYPE, format!("`{}` can't be matched on", t), at.0, at.1)
+                    .notes.push("only the values of enums can be matched on".to_owned());
+
+                None
+            }
+        };
+
+        let mut matched: Vec<&str> = Vec::new();
+        let mut otherwise = false;
+
+        for &(ref v, ref b, ref body, p) in arms.iter() {
+            if otherwise {
+                self.error(code::MATCH, format!("the arm for `{}` is never reached", v), p, v.len())
+                    .notes.push("the `_` arm before it matches every variant".to_owned());
+            } else if matched.contains(&v.as_str()) {
+                self.error(code::MATCH, format!("`{}` is matched more than once", v), p, v.len());
+            }
+
+            let fields = match enumeration {
+                _ if v == "_" => {
+                    if !b.is_empty() {
+                        self.error(code::MATCH, "the `_` arm has no fields to bind".to_owned(), p, 1);
+                    }
+
+                    otherwise = true;
+
+                    Vec::new()
+                }
+
+                Some(ref e) => match e.entry(v) {
+                    Some((Symbol::Variant(s), d)) => {
+                        self.reference(v, p, &(Symbol::Variant(s.clone()), d), Some(e));
+
+                        if b.len() != s.params.len() {
+                            self.error(code::ARGUMENTS,
+                                       format!("`{}` has {} but {} bound", v, fields(s.params.len()), match b.len() {
+                                           1 => "1 name is".to_owned(),
+                                           n => format!("{} names are", n),
+                                       }),
+                                       p, v.len());
+                        }
+
+                        matched.push(v);
+
+                        s.params
+                    }
+
+                    _ => {
+                        self.error(code::UNDEFINED, format!("no variant named `{}` in `{}`", v, e.name), p, v.len());
+
+                        Vec::new()
+                    }
+                },
+
+                None => Vec::new(),
+            };
+
+            self.locals.push(HashMap::new());
+
+            for (i, n) in b.iter().enumerate().filter(|&(_, n)| n != "_") {
+                self.local(n, fields.get(i).cloned().unwrap_or(Type::Unknown), p)
+            }
+
+            self.block(body);
+            self.locals.pop();
+        }
+
+        if let Some(e) = enumeration {
+            let mut missing: Vec<(Position, String)> = e.symbols.borrow().iter()
+                .filter(|&(n, _)| !matched.contains(&n.as_str()))
+                .map(|(n, &(_, p))| (p, format!("`{}`", n)))
+                .collect();
+
+            missing.sort();
+
+            if !otherwise && !missing.is_empty() {
+                let names: Vec<String> = missing.into_iter().map(|(_, n)| n).collect();
+
+                self.error(code::MATCH, format!("non-exhaustive match, {} not covered", names.join(", ")), position, 5)
+                    .notes.push(format!("add an arm for each variant of `{}`, or a `_` arm", e.name));
+            }
+        }
+    }
+
+    /// Checks the array sizes of a `name[size]: type` declaration.
+    fn sizes(&mut self, ex: &Expression) {
+        if let Expression::IndexArray(ref a, ref s) = *ex {
+            self.sizes(&**a);
+            self.expression(&**s);
+        }
+    }
+
+    /// What a name refers to where it is used, along with where it was defined and the scope
+    /// it was found in, which is none for locals and what is built in.
+    fn lookup(&self, name: &str) -> Option<((Symbol, Position), Option<Rc<Scope>>)> {
+        for scope in self.locals.iter().rev() {
+            if let Some(e) = scope.get(name) {
+                return Some((e.clone(), None))
+            }
+        }
+
+        for &(_, ref m) in self.uses.iter().rev() {
+            if let Some(e) = m.entry(name) {
+                return Some((e, Some(m.clone())))
+            }
+        }
+
+        if let Some(ref c) = self.this {
+            if let Some(e) = c.entry(name) {
+                return Some((e, Some(c.clone())))
+            }
+        }
+
+        match self.namespace.find(name) {
+            Some(e) => Some((e, Some(self.namespace.clone()))),
+            None    => match name {
+                "printf" | "puts" => Some(((Symbol::Builtin, IMPORTED), None)),
+                "void"            => Some(((Symbol::Variable(Type::Void), IMPORTED), None)),
+                _                 => None,
+            },
+        }
+    }
+
+    /// What a module path such as `a::b` refers to, without reporting anything.
+    fn path(&self, ex: &Expression) -> Option<Symbol> {
+        self.entry(ex).map(|((s, _), _)| s)
+    }
+
+    /// What a path refers to like `path` tells, along with where it was defined and the scope
+    /// it was found in.
+    fn entry(&self, ex: &Expression) -> Option<((Symbol, Position), Rc<Scope>)> {
+        match *ex {
+            Expression::Ident(ref n, _) => self.namespace.find(n).map(|e| (e, self.namespace.clone())),
+
+            Expression::IndexColon(ref a, ref b) => match (self.path(&**a), &**b) {
+                (Some(Symbol::Module(m)), &Expression::Ident(ref n, _)) |
+                (Some(Symbol::Class(m)), &Expression::Ident(ref n, _)) |
+                (Some(Symbol::Enum(m)), &Expression::Ident(ref n, _))  => m.entry(n).map(|e| (e, m)),
+                _                                                      => None,
+            },
+
+            _ => None,
+        }
+    }
+
+    /// The type a type name such as a parameter's refers to.
+    fn named(&self, name: &str) -> Type {
+        types::named(&self.namespace, &self.generics, name)
+    }
+
+    /// The type a type expression refers to, reporting names that refer to none and generic
+    /// classes named without their type arguments outside of their own body.
+    fn type_of(&mut self, t: &Expression) -> Type {
+        let kind = self.kind_of(t);
+
+        if let Type::Object(ref c, ref a) = kind {
+            let expected = c.generics.borrow().len();
+            let inside   = self.this.as_ref().map_or(false, |this| Rc::ptr_eq(this, c));
+
+            if a.is_empty() && expected > 0 && !inside {
+                if let Some((name, position)) = name_of(t) {
+                    self.error(code::GENERICS,
+                               format!("`{}` takes {} but {} supplied", name, parameters(expected), supplied(0)),
+                               position, name.len())
+                        .notes.push(format!("write `{}[{}]`", name, c.generics.borrow().join(", ")));
+                }
+            }
+        }
+
+        kind
+    }
+
+    fn kind_of(&mut self, t: &Expression) -> Type {
+        if let Expression::Ident(ref n, _) = *t {
+            if self.generics.contains(n) {
+                return Type::Parameter(n.clone())
+            }
+
+            // within a class its name is the class, not its constructor
+            if let Some(c) = self.this.clone() {
+                if c.name == *n {
+                    return Type::Object(c, Vec::new())
+                }
+            }
+
+            if let Some(t) = Type::primitive(n) {
+                return t
+            }
+        }
+
+        if let Expression::Generic(ref b, ref a) = *t {
+            let arguments: Vec<Type> = a.iter().map(|t| self.type_of(t)).collect();
+
+            return match self.kind_of(&**b) {
+                Type::Object(c, _) => {
+                    let expected = c.generics.borrow().len();
+
+                    if arguments.len() != expected {
+                        if let Some((name, position)) = name_of(&**b) {
+                            self.error(code::GENERICS,
+                                       format!("`{}` takes {} but {} supplied",
+                                               name, parameters(expected), supplied(arguments.len())),
+                                       position, name.len());
+                        }
+                    }
+
+                    Type::Object(c, arguments)
+                }
+
+                Type::Unknown    => Type::Unknown,
+                Type::Foreign(_) => Type::Foreign(ast::written(t)),
+
+                t => {
+                    if let Some((name, position)) = name_of(&**b) {
+                        self.error(code::GENERICS, format!("`{}` takes no type arguments", t), position, name.len());
+                    }
+
+                    t
+                }
+            }
+        }
+
+        match *t {
+            Expression::List(ref e, _) if e.len() == 1 => return Type::List(Box::new(self.type_of(&e[0]))),
+
+            Expression::Map(ref e, _) if e.len() == 1 => {
+                let key = self.type_of(&e[0].0);
+
+                self.key(&key, &e[0].0);
+
+                return Type::Map(Box::new(key), Box::new(self.type_of(&e[0].1)))
+            }
+
+            Expression::FunctionType(ref a, ref r, _) => {
+                let params = a.iter().map(|t| self.type_of(t)).collect();
+
+                return Type::Function(params, Box::new(self.type_of(&**r)))
+            }
+
+            Expression::List(_, p) | Expression::Map(_, p) => {
+                self.error(code::TYPE, "expected a type, like `[int]` or `{string: int}`".to_owned(), p, 1);
+
+                return Type::Unknown
+            }
+
+            _ => (),
+        }
+
+        if let Expression::IndexColon(ref a, ref b) = *t {
+            if let (&Expression::Ident(ref a, _), &Expression::Ident(ref b, _)) = (&**a, &**b) {
+                if let Some(t) = Type::primitive(&format!("{}::{}", a, b)) {
+                    return t
+                }
+            }
+        }
+
+        match self.entry(t) {
+            Some(((Symbol::Class(c), p), scope)) => {
+                if let Some((name, position)) = name_of(t) {
+                    self.reference(name, position, &(Symbol::Class(c.clone()), p), Some(&scope))
+                }
+
+                Type::Object(c, Vec::new())
+            }
+
+            Some(((Symbol::Enum(e), p), scope)) => {
+                if let Some((name, position)) = name_of(t) {
+                    self.reference(name, position, &(Symbol::Enum(e.clone()), p), Some(&scope))
+                }
+
+                Type::Enum(e)
+            }
+
+            Some(_) => {
+                if let Some((name, position)) = name_of(t) {
+                    self.error(code::TYPE, format!("`{}` is not a type", name), position, name.len());
+                }
+
+                Type::Unknown
+            }
+
+            None => {
+                if let Some((name, position)) = name_of(t) {
+                    self.undefined(format!("cannot find type `{}` in this scope", name), position, name.len())
+                }
+
+                Type::Foreign(ast::written(t))
+            }
+        }
+    }
+
+    /// Checks an element of a list or map literal written at `position` against the ones
+    /// before it, widening numbers like arithmetic does.
+    fn element(&mut self, found: &mut Option<Type>, e: &Expression, position: Position) {
+        let t = self.value(e);
+
+        *found = Some(match found.take() {
+            None    => t,
+            Some(a) => match types::unify(&a, &t) {
+                Some(u) => u,
+                None    => {
+                    self.mismatch(&a, &t, span(e).unwrap_or((position, 1)));
+
+                    a
+                }
+            },
+        })
+    }
+
+    /// Reports keys C++ has no hash for.
+    fn key(&mut self, t: &Type, ex: &Expression) {
+        match *t {
+            Type::Object(_, _) | Type::List(_) | Type::Map(_, _) | Type::Array(_) | Type::Void => {
+                let at = self.locate(ex);
+
+                self.error(code::TYPE, format!("`{}` can't be the key of a map", t), at.0, at.1);
+            }
+
+            _ => (),
+        }
+    }
+
+    /// The type of the value an expression produces.
+    fn value(&mut self, ex: &Expression) -> Type {
+        match self.expression(ex) {
+            Some(Symbol::Variable(t)) => t,
+
+            // a function named on its own is passed around like a lambda
+            Some(Symbol::Function(s)) | Some(Symbol::Prototype(s)) if s.generics.is_empty() => {
+                Type::Function(s.params, Box::new(s.retty))
+            }
+
+            // a variant without fields is a value of its own
+            Some(Symbol::Variant(ref s)) if s.params.is_empty() => s.retty.clone(),
+
+            Some(Symbol::Variant(s)) => {
+                if let Some((name, position)) = name_of(ex) {
+                    self.error(code::ARGUMENTS, format!("`{}` carries {} to be given", name, fields(s.params.len())),
+                               position, name.len())
+                        .notes.push(format!("write `{}(...)` to make a `{}`", name, s.retty));
+                }
+
+                Type::Unknown
+            }
+
+            _ => Type::Unknown,
+        }
+    }
+
+    /// Checks the arguments of a call against the parameters of the function called.
+    fn arguments(&mut self, callee: &Expression, signature: &Signature, args: &[Type]) {
+        let name = match name_of(callee) {
+            Some((n, p)) => (n.to_owned(), p),
+            None         => return,
+        };
+
+        if args.len() != signature.params.len() {
+            self.error(code::ARGUMENTS,
+                       format!("`{}` takes {} but {} supplied",
+                               name.0, arguments(signature.params.len()), match args.len() {
+                                   1 => "1 argument was".to_owned(),
+                                   n => format!("{} arguments were", n),
+                               }),
+                       name.1, name.0.len());
+
+            return
+        }
+
+        for (i, (p, a)) in signature.params.iter().zip(args.iter()).enumerate() {
+            if !p.accepts(a) {
+                let error = self.mismatch(p, a, (name.1, name.0.len()));
+
+                error.notes.push(format!("argument {} of `{}` is of type `{}`", i + 1, name.0, p))
+            }
+        }
+    }
+
+    /// What the type parameters of a generic function or class stand for in a call, either
+    /// given in brackets or inferred from the arguments the way C++ deduces them; whatever
+    /// the arguments don't tell is left to C++.
+    fn bindings(&mut self, callee: &Expression, generics: &[String], explicit: Option<Vec<Type>>,
+                params: &[Type], args: &[Type]) -> Vec<(String, Type)> {
+        let name = name_of(callee).map(|(n, p)| (n.to_owned(), p));
+
+        if let (Some(ref explicit), Some((ref name, position))) = (explicit.as_ref(), name.clone()) {
+            if explicit.len() != generics.len() {
+                self.error(code::GENERICS,
+                           format!("`{}` takes {} but {} supplied", name, parameters(generics.len()), supplied(explicit.len())),
+                           position, name.len());
+            }
+        }
+
+        let (bindings, conflicts) = types::bind(generics, explicit, params, args);
+
+        if let Some((name, position)) = name {
+            for (n, first, second) in conflicts {
+                self.error(code::TYPE,
+                           format!("`{}` is given both `{}` and `{}` for `{}`", name, first, second, n),
+                           position, name.len())
+                    .notes.push(format!("write `{}[{}](...)` to choose one", name, first.join(&second)));
+            }
+        }
+
+        bindings
+    }
+
+    /// Checks the names and types used by an expression and tells what it refers to.
+    fn expression(&mut self, ex: &Expression) -> Option<Symbol> {
+        match *ex {
+            Expression::Integer(i) => Some(Symbol::Variable(types::integer(i))),
+            Expression::Float(_)   => Some(Symbol::Variable(Type::Double)),
+            Expression::Boolean(_) => Some(Symbol::Variable(Type::Bool)),
+            Expression::Text(_)    => Some(Symbol::Variable(Type::Text)),
+
+            Expression::List(ref e, p) => {
+                let mut element = None;
+
+                for e in e.iter() {
+                    self.element(&mut element, e, p)
+                }
+
+                Some(Symbol::Variable(Type::List(Box::new(element.unwrap_or(Type::Unknown)))))
+            }
+
+            Expression::Map(ref e, p) => {
+                let (mut key, mut value) = (None, None);
+
+                for &(ref k, ref v) in e.iter() {
+                    self.element(&mut key, k, p);
+                    self.element(&mut value, v, p);
+                }
+
+                let key = key.unwrap_or(Type::Unknown);
+
+                if let Some(&(ref k, _)) = e.first() {
+                    self.key(&key, k)
+                }
+
+                Some(Symbol::Variable(Type::Map(Box::new(key), Box::new(value.unwrap_or(Type::Unknown)))))
+            }
+
+            Expression::Ident(ref n, p) => match self.lookup(n) {
+                Some((entry, scope)) => {
+                    self.reference(n, p, &entry, scope.as_ref());
+
+                    Some(entry.0)
+                }
+
+                None => {
+                    self.undefined(format!("cannot find `{}` in this scope", n), p, n.len());
+
+                    None
+                }
+            },
+
+            Expression::Operation(ref l, ref o, ref r, p) => {
+                let left  = self.value(&**l);
+                let right = self.value(&**r);
+
+                match types::operation(o, &left, &right) {
+                    Some(t) => Some(Symbol::Variable(t)),
+
+                    None => {
+                        self.error(code::OPERATOR,
+                                   format!("cannot apply `{}` to `{}` and `{}`", o, left, right),
+                                   p, o.to_string().len());
+
+                        Some(Symbol::Variable(Type::Unknown))
+                    }
+                }
+            }
+
+            Expression::Unary(ref o, ref e, p) => {
+                let operand = self.value(&**e);
+
+                match types::unary(o, &operand) {
+                    Some(t) => Some(Symbol::Variable(t)),
+
+                    None => {
+                        self.error(code::OPERATOR,
+                                   format!("cannot apply `{}` to `{}`", o, operand),
+                                   p, o.to_string().len());
+
+                        Some(Symbol::Variable(Type::Unknown))
+                    }
+                }
+            }
+
+            Expression::Return(ref e, p) => {
+                let t = self.value(&**e);
+
+                if let Some(expected) = self.expected.clone() {
+                    if !expected.accepts(&t) {
+                        let at    = span(&**e).unwrap_or((p, 6));
+                        let note  = format!("`{}` returns `{}`", self.function, expected);
+                        let error = self.mismatch(&expected, &t, at);
+
+                        error.notes.push(note)
+                    }
+
+                    return None
+                }
+
+                self.returned = match self.returned.take() {
+                    None                                            => Some(t),
+                    Some(ref r) if r.is_unknown()                   => Some(t),
+                    Some(r) if t.is_unknown() || r == t             => Some(r),
+                    Some(ref r) if r.is_numeric() && t.is_numeric() => Some(r.join(&t)),
+
+                    Some(r) => {
+                        let at    = span(&**e).unwrap_or((p, 6));
+                        let note  = format!("`{}` returns `{}` elsewhere", self.function, r);
+                        let error = self.mismatch(&r, &t, at);
+
+                        error.notes.push(note);
+
+                        Some(r)
+                    }
+                };
+
+                None
+            }
+
+            Expression::Call(ref c, ref a) => {
+                // the type arguments of a generic are given in brackets after its name
+                let (callee, explicit) = match **c {
+                    Expression::Generic(ref b, ref t) => (self.expression(&**b), Some(t.iter().map(|t| self.type_of(t)).collect())),
+                    _                                 => (self.expression(&**c), None),
+                };
+
+                // `Pair[B, A](..)` in a method of `Pair` makes another instance of the class
+                let callee = match (callee, self.this.clone(), name_of(&**c)) {
+                    (Some(Symbol::Function(_)), Some(ref this), Some((name, _)))  |
+                    (Some(Symbol::Prototype(_)), Some(ref this), Some((name, _))) if explicit.is_some() && this.name == name => {
+                        Some(Symbol::Class(this.clone()))
+                    }
+
+                    (callee, _, _) => callee,
+                };
+
+                let args: Vec<Type> = a.iter().map(|e| self.value(e)).collect();
+
+                let t = match callee {
+                    Some(Symbol::Function(s)) | Some(Symbol::Prototype(s)) => {
+                        let bindings = self.bindings(&**c, &s.generics, explicit, &s.params, &args);
+                        let s        = s.substitute(&bindings);
+
+                        self.arguments(&**c, &s, &args);
+
+                        s.retty
+                    }
+
+                    Some(Symbol::Class(class)) => {
+                        let constructor = match class.symbols.borrow().get(&class.name) {
+                            Some(&(Symbol::Function(ref s), _)) | Some(&(Symbol::Prototype(ref s), _)) => s.clone(),
+
+                            _ => Signature {
+                                generics: Vec::new(),
+                                params:   Vec::new(),
+                                retty:    Type::Unknown,
+                            },
+                        };
+
+                        let generics = class.generics.borrow().clone();
+                        let inside   = self.this.as_ref().map_or(false, |this| Rc::ptr_eq(this, &class));
+
+                        // C++ can't tell the type arguments of a class template from its constructor
+                        if explicit.is_none() && !generics.is_empty() && !inside {
+                            if let Some((name, position)) = name_of(&**c) {
+                                self.error(code::GENERICS, format!("`{}` is made without its type arguments", name),
+                                           position, name.len())
+                                    .notes.push(format!("write `{}[{}](...)`", name, generics.join(", ")));
+                            }
+                        }
+
+                        let bindings = self.bindings(&**c, &generics, explicit, &constructor.params, &args);
+
+                        self.arguments(&**c, &constructor.substitute(&bindings), &args);
+
+                        instance(&class).substitute(&bindings)
+                    }
+
+                    Some(Symbol::Variant(s)) => {
+                        if s.params.is_empty() {
+                            if let Some((name, position)) = name_of(&**c) {
+                                self.error(code::ARGUMENTS, format!("`{}` carries no fields and isn't called", name),
+                                           position, name.len());
+                            }
+                        } else {
+                            self.arguments(&**c, &s, &args);
+                        }
+
+                        s.retty
+                    }
+
+                    Some(Symbol::Variable(Type::Function(params, retty))) => {
+                        let s = Signature {
+                            generics: Vec::new(),
+                            params:   params,
+                            retty:    *retty,
+                        };
+
+                        self.arguments(&**c, &s, &args);
+
+                        s.retty
+                    }
+
+                    // printf and puts give back a count
+                    Some(Symbol::Builtin) => Type::Int,
+
+                    _ => Type::Unknown,
+                };
+
+                Some(Symbol::Variable(t))
+            }
+
+            Expression::IndexDot(ref a, ref b) => {
+                let t = self.value(&**a);
+
+                let (name, position) = match **b {
+                    Expression::Ident(ref n, p) => (n, p),
+                    _                           => return None,
+                };
+
+                let (class, arguments) = match t {
+                    Type::Object(c, a) => (c, a),
+
+                    // strings have many more methods, which are left to C++
+                    Type::Text => return method(&t, name).map(Symbol::Function),
+
+                    Type::List(_) | Type::Map(_, _) => return match method(&t, name) {
+                        Some(s) => Some(Symbol::Function(s)),
+
+                        None => {
+                            self.error(code::UNDEFINED,
+                                       format!("no method named `{}` on type `{}`", name, t),
+                                       position, name.len());
+
+                            None
+                        }
+                    },
+
+                    ref t if t.is_numeric() || *t == Type::Void => {
+                        self.error(code::UNDEFINED,
+                                   format!("no field or method named `{}` on type `{}`", name, t),
+                                   position, name.len());
+
+                        return None
+                    }
+
+                    _ => return None,
+                };
+
+                // the members of an object of a generic class have the types it was given
+                let bindings: Vec<(String, Type)> = class.generics.borrow().iter().cloned().zip(arguments.into_iter()).collect();
+
+                match class.entry(name) {
+                    Some(entry) => {
+                        self.reference(name, position, &entry, Some(&class));
+
+                        Some(match entry.0 {
+                            Symbol::Variable(t)  => Symbol::Variable(t.substitute(&bindings)),
+                            Symbol::Function(s)  => Symbol::Function(s.substitute(&bindings)),
+                            Symbol::Prototype(s) => Symbol::Prototype(s.substitute(&bindings)),
+                            s                    => s,
+                        })
+                    }
+
+                    None => {
+                        self.error(code::UNDEFINED,
+                                   format!("no field or method named `{}` on class `{}`", name, class.name),
+                                   position, name.len());
+
+                        None
+                    }
+                }
+            }
+
+            Expression::IndexColon(ref a, ref b) => {
+                let scope = match self.expression(&**a) {
+                    Some(Symbol::Module(m)) | Some(Symbol::Class(m)) | Some(Symbol::Enum(m)) => m,
+                    _                                                                        => return None,
+                };
+
+                let (name, position) = match **b {
+                    Expression::Ident(ref n, p) => (n, p),
+                    _                           => return None,
+                };
+
+                match scope.entry(name) {
+                    Some(entry) => {
+                        self.reference(name, position, &entry, Some(&scope));
+
+                        Some(entry.0)
+                    }
+
+                    None => {
+                        self.error(code::UNDEFINED,
+                                   format!("cannot find `{}` in `{}`", name, scope.name),
+                                   position, name.len());
+
+                        None
+                    }
+                }
+            }
+
+            Expression::Generic(ref b, ref t) => {
+                for t in t.iter() {
+                    self.type_of(t);
+                }
+
+                self.expression(&**b)
+            }
+
+            Expression::Lambda(ref a, ref b, ref r, p) => Some(Symbol::Variable(self.lambda(a, b, r, p))),
+
+            Expression::IndexArray(ref a, ref i) => {
+                let base  = self.value(&**a);
+                let index = self.value(&**i);
+
+                // maps are indexed by their keys, everything else by a position
+                let expected = match base {
+                    Type::Map(ref k, _) => (**k).clone(),
+                    _                   => Type::Int,
+                };
+
+                let valid = match expected {
+                    Type::Int => index.is_integral() || index.is_unknown(),
+                    ref k     => k.accepts(&index),
+                };
+
+                if !valid {
+                    let at = span(&**i).or(span(&**a)).unwrap_or((self.position, 1));
+
+                    self.mismatch(&expected, &index, at);
+                }
+
+                let t = match base.indexed() {
+                    Some(t) => t,
+
+                    None => {
+                        let at = self.locate(&**a);
+
+                        self.error(code::TYPE, format!("`{}` can't be indexed", base), at.0, at.1);
+
+                        Type::Unknown
+                    }
+                };
+
+                Some(Symbol::Variable(t))
+            }
+
+            _ => None,
+        }
+    }
+}
+
+/// Sets the type of a variable defined by a namespace or class once its value is known.
+fn update(scope: &Rc<Scope>, name: &str, t: Type) {
+    if let Some(&mut (Symbol::Variable(ref mut v), _)) = scope.symbols.borrow_mut().get_mut(name) {
+        *v = t
+    }
+}
+
+/// Writes the inferred types of declarations and functions into the tree, so every later
+/// stage sees them as if they had been written down. Types that can't be spelled are left
+/// out.
+fn annotate(ast: &mut [Statement], types: &HashMap<Position, Type>) {
+    for s in ast.iter_mut() {
+        match *s {
+            Statement::Declaration(_, ref mut t, _, p) => if t.is_none() {
+                if let Some(inferred) = types.get(&p) {
+                    if inferred.cpp() != "auto" {
+                        *t = Some(Box::new(spelled(inferred, p)))
+                    }
+                }
+            },
+
+            Statement::Expression(ref mut e, _) => match **e {
+                Expression::Function(_, _, _, ref mut c, ref mut t, p) => {
+                    if t.is_none() {
+                        match types.get(&p) {
+                            Some(&Type::Void) | None => (),
+                            Some(inferred)           => *t = Some(Box::new(spelled(inferred, p))),
+                        }
+                    }
+
+                    annotate(c, types)
+                }
+
+                Expression::Module(_, ref mut c, _)       |
+                Expression::Class(_, _, ref mut c, _, _)  |
+                Expression::Struct(_, ref mut c, _)       |
+                Expression::Implement(_, _, ref mut c, _) => annotate(c, types),
+
+                _ => (),
+            },
+
+            Statement::Block(ref mut c) => annotate(c, types),
+
+            Statement::If(ref mut b, ref mut c, _) => {
+                for &mut (_, ref mut s) in b.iter_mut() {
+                    annotate(::std::slice::from_mut(s), types)
+                }
+
+                if let Some(ref mut c) = *c {
+                    annotate(::std::slice::from_mut(&mut **c), types)
+                }
+            }
+
+            Statement::While(_, ref mut c, _)          |
+            Statement::For(_, _, ref mut c, _)         |
+            Statement::ForRange(_, _, _, ref mut c, _) => annotate(::std::slice::from_mut(&mut **c), types),
+
+            Statement::Match(_, ref mut arms, _) => for &mut (_, _, ref mut c, _) in arms.iter_mut() {
+                annotate(::std::slice::from_mut(c), types)
+            },
+
+            _ => (),
+        }
+    }
+}
+
+/// A type written out the way the resolver writes inferred types into the tree.
+fn spelled(t: &Type, position: Position) -> Expression {
+    match *t {
+        Type::Object(ref c, ref a) if !a.is_empty() => Expression::Generic(
+            Box::new(Expression::Ident(Type::Object(c.clone(), Vec::new()).cpp(), position)),
+            a.iter().map(|t| spelled(t, position)).collect(),
+        ),
+
+        Type::List(ref t) => Expression::List(vec!(spelled(t, position)), position),
+
+        Type::Map(ref k, ref v) => Expression::Map(vec!((spelled(k, position), spelled(v, position))), position),
+
+        Type::Function(ref a, ref r) => Expression::FunctionType(
+            a.iter().map(|t| spelled(t, position)).collect(),
+            Box::new(spelled(r, position)),
+            position,
+        ),
+
+        ref t => Expression::Ident(t.cpp(), position),
+    }
+}
+
+/// The type of a variable declared as `name[size]: type`, one array per size.
+fn array(ex: &Expression, t: Type) -> Type {
+    match *ex {
+        Expression::IndexArray(ref a, _) => array(&**a, Type::Array(Box::new(t))),
+        _                                => t,
+    }
+}
+
+fn arguments(n: usize) -> String {
+    match n {
+        1 => "1 argument".to_owned(),
+        n => format!("{} arguments", n),
+    }
+}
+
+fn parameters(n: usize) -> String {
+    match n {
+        1 => "1 type parameter".to_owned(),
+        n => format!("{} type parameters", n),
+    }
+}
+
+fn supplied(n: usize) -> String {
+    match n {
+        1 => "1 type argument was".to_owned(),
+        n => format!("{} type arguments were", n),
+    }
+}
+
+fn fields(n: usize) -> String {
+    match n {
+        1 => "1 field".to_owned(),
+        n => format!("{} fields", n),
+    }
+}
+
+/// The name a callee or member access ends in, along with where it was written.
+fn name_of(ex: &Expression) -> Option<(&str, Position)> {
+    match *ex {
+        Expression::Ident(ref n, p)                                       => Some((n, p)),
+        Expression::IndexDot(_, ref b) | Expression::IndexColon(_, ref b) => name_of(&**b),
+        Expression::Generic(ref b, _)                                     => name_of(&**b),
+        _                                                                 => None,
+    }
+}
+
+/// Where an expression was written and how long the part worth pointing at is.
+fn span(ex: &Expression) -> Option<(Position, usize)> {
+    match *ex {
+        Expression::Ident(ref n, p)                                            => Some((p, n.len())),
+        Expression::Operation(_, ref o, _, p) | Expression::Unary(ref o, _, p) => Some((p, o.to_string().len())),
+        Expression::Return(_, p)                                               => Some((p, 6)),
+        Expression::Call(ref c, _) | Expression::IndexArray(ref c, _)          => span(&**c),
+        Expression::Generic(ref c, _)                                          => span(&**c),
+        Expression::List(_, p) | Expression::Map(_, p)                         => Some((p, 1)),
+        Expression::Lambda(_, _, _, p)                                         => Some((p, 8)),
+        Expression::IndexDot(_, ref b) | Expression::IndexColon(_, ref b)      => span(&**b),
+        _                                                                      => None,
+    }
+}
+
+/// The variable an assignment ends up storing into, like `xs` in `xs[0].size = 1`.
+fn root(ex: &Expression) -> Option<(&str, Position)> {
+    match *ex {
+        Expression::Ident(ref n, p)                                        => Some((n, p)),
+        Expression::IndexArray(ref a, _) | Expression::IndexDot(ref a, _) => root(&**a),
+        _                                                                  => None,
+    }
+}
+
+/// The name a `name: type` or `name[size]: type` declaration introduces.
+fn declared(ex: &Expression) -> Option<(&str, Position)> {
+    match *ex {
+        Expression::Ident(ref n, p)      => Some((n, p)),
+        Expression::IndexArray(ref a, _) => declared(&**a),
+        _                                => None,
+    }
+}
@@ -0,0 +1,201 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use super::super::ast::Position;
+use super::super::ir::Path;
+
+use super::types::Type;
+
+/// What a name refers to, as far as can be told without running the program.
+#[derive(Clone)]
+pub enum Symbol {
+    /// A variable, field or parameter, or the value an expression produces.
+    Variable(Type),
+    Function(Signature),
+    /// A function declared without a body, to be defined later on.
+    Prototype(Signature),
+    Class(Rc<Scope>),
+    Module(Rc<Scope>),
+    /// An enum, whose scope holds its variants.
+    Enum(Rc<Scope>),
+    /// A variant of an enum, which is made like a function is called when it carries fields;
+    /// the signature returns the enum.
+    Variant(Signature),
+    Builtin,
+}
+
+/// The parameter types and return type of a function, along with its type parameters if it
+/// is generic.
+#[derive(Clone)]
+pub struct Signature {
+    pub generics: Vec<String>,
+    pub params:   Vec<Type>,
+    pub retty:    Type,
+}
+
+impl Signature {
+    /// The signature with the type parameters in it replaced by what they stand for; the
+    /// ones left unbound stay generic.
+    pub fn substitute(&self, bindings: &[(String, Type)]) -> Signature {
+        Signature {
+            generics: self.generics.iter().filter(|g| !bindings.iter().any(|&(ref b, _)| b == *g)).cloned().collect(),
+            params:   self.params.iter().map(|t| t.substitute(bindings)).collect(),
+            retty:    self.retty.substitute(bindings),
+        }
+    }
+}
+
+/// The names defined by a module, class or the program itself.
+pub struct Scope {
+    pub name:     String,
+    pub symbols:  RefCell<HashMap<String, (Symbol, Position)>>,
+    pub uses:     RefCell<Vec<Rc<Scope>>>,
+    pub base:     RefCell<Option<Rc<Scope>>>,
+    pub parent:   Option<Rc<Scope>>,
+    /// The type parameters of a generic class.
+    pub generics: RefCell<Vec<String>>,
+}
+
+impl Scope {
+    pub fn new(name: String, parent: Option<Rc<Scope>>) -> Rc<Scope> {
+        Rc::new(Scope {
+            name:     name,
+            symbols:  RefCell::new(HashMap::new()),
+            uses:     RefCell::new(Vec::new()),
+            base:     RefCell::new(None),
+            parent:   parent,
+            generics: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// The name of the scope along with the modules and classes it is nested in, like
+    /// `geo::Point`.
+    pub fn path(&self) -> Path {
+        let mut segments = vec!(self.name.clone());
+        let mut scope    = self.parent.clone();
+
+        while let Some(s) = scope {
+            segments.insert(0, s.name.clone());
+
+            scope = s.parent.clone()
+        }
+
+        Path(segments.into_iter().filter(|s| !s.is_empty()).collect())
+    }
+
+    /// Looks a name up in this scope alone, including base classes and the modules pulled in
+    /// by `use`.
+    pub fn member(&self, name: &str) -> Option<Symbol> {
+        self.entry(name).map(|(s, _)| s)
+    }
+
+    /// Looks a name up like `member` does, along with where it was defined.
+    pub fn entry(&self, name: &str) -> Option<(Symbol, Position)> {
+        if let Some(e) = self.symbols.borrow().get(name) {
+            return Some(e.clone())
+        }
+
+        if let Some(ref b) = *self.base.borrow() {
+            if let Some(e) = b.entry(name) {
+                return Some(e)
+            }
+        }
+
+        for u in self.uses.borrow().iter() {
+            if let Some(e) = u.entry(name) {
+                return Some(e)
+            }
+        }
+
+        None
+    }
+
+    /// Looks a name up in this scope and then in every enclosing one.
+    pub fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.find(name).map(|(s, _)| s)
+    }
+
+    /// Looks a name up like `lookup` does, along with where it was defined.
+    pub fn find(&self, name: &str) -> Option<(Symbol, Position)> {
+        match self.entry(name) {
+            Some(e) => Some(e),
+            None    => match self.parent {
+                Some(ref p) => p.find(name),
+                None        => None,
+            },
+        }
+    }
+
+    /// Every name `member` finds, sorted.
+    pub fn members(&self) -> Vec<(String, Symbol)> {
+        let mut members: Vec<(String, Symbol)> = self.symbols.borrow()
+            .iter()
+            .map(|(n, &(ref s, _))| (n.clone(), s.clone()))
+            .collect();
+
+        let mut inherited = Vec::new();
+
+        if let Some(ref b) = *self.base.borrow() {
+            inherited.extend(b.members())
+        }
+
+        for u in self.uses.borrow().iter() {
+            inherited.extend(u.members())
+        }
+
+        for (n, s) in inherited {
+            if !members.iter().any(|&(ref m, _)| *m == n) {
+                members.push((n, s))
+            }
+        }
+
+        members.sort_by(|a, b| a.0.cmp(&b.0));
+
+        members
+    }
+
+    /// Defines a name, handing back what it already referred to if this scope had it.
+    pub fn define(&self, name: String, symbol: Symbol, position: Position) -> Result<(), (Symbol, Position)> {
+        let previous = self.symbols.borrow().get(&name).cloned();
+
+        match previous {
+            Some(p) => Err(p),
+            None    => {
+                self.symbols.borrow_mut().insert(name, (symbol, position));
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Replaces whatever a name referred to in this scope.
+    pub fn redefine(&self, name: String, symbol: Symbol, position: Position) {
+        self.symbols.borrow_mut().insert(name, (symbol, position));
+    }
+}
+
+/// The scope defining a name `find` finds from `scope`, which may be an enclosing one, a base
+/// class or a module pulled in by `use`.
+pub fn owner(scope: &Rc<Scope>, name: &str) -> Option<Rc<Scope>> {
+    if scope.symbols.borrow().contains_key(name) {
+        return Some(scope.clone())
+    }
+
+    if let Some(ref b) = *scope.base.borrow() {
+        if b.entry(name).is_some() {
+            return owner(b, name)
+        }
+    }
+
+    for u in scope.uses.borrow().iter() {
+        if u.entry(name).is_some() {
+            return owner(u, name)
+        }
+    }
+
+    match scope.parent {
+        Some(ref p) => owner(p, name),
+        None        => None,
+    }
+}
@@ -0,0 +1,626 @@
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+
+use super::super::ast;
+use super::super::token::Operator;
+
+use super::scope::{Symbol, Signature, Scope};
+
+/// The static type of a value.
+#[derive(Clone)]
+pub enum Type {
+    Void,
+    Bool,
+    Char,
+    Int,
+    Long,
+    Float,
+    Double,
+    Text,
+    /// An object of a class, along with the types the parameters of a generic class stand
+    /// for.
+    Object(Rc<Scope>, Vec<Type>),
+    Enum(Rc<Scope>),
+    Array(Box<Type>),
+    /// A `[type]`, which is a `std::vector` in C++.
+    List(Box<Type>),
+    /// A `{key: value}`, which is a `std::unordered_map` in C++.
+    Map(Box<Type>, Box<Type>),
+    /// A function taking the types in the list, like `(int, int) -> int`, which is a
+    /// `std::function` in C++.
+    Function(Vec<Type>, Box<Type>),
+    /// A type parameter of a generic class or function. What it stands for is only known
+    /// once C++ instantiates the template, so it is taken to be compatible like `Unknown` is.
+    Parameter(String),
+    /// A type the program doesn't define, like one from a C++ library, as it is written; it
+    /// is taken to be compatible like `Unknown` is.
+    Foreign(String),
+    /// Anything the checker can't see into, such as what functions from C++ libraries give
+    /// back; it is taken to be compatible with every other type.
+    Unknown,
+}
+
+impl Type {
+    /// The primitive type a name stands for, if it is one.
+    pub fn primitive(name: &str) -> Option<Type> {
+        match name {
+            "void"                                  => Some(Type::Void),
+            "bool"                                  => Some(Type::Bool),
+            "char"                                  => Some(Type::Char),
+            "int" | "short" | "unsigned" | "size_t" => Some(Type::Int),
+            "long"                                  => Some(Type::Long),
+            "float"                                 => Some(Type::Float),
+            "double"                                => Some(Type::Double),
+            "string" | "std::string"                => Some(Type::Text),
+            _                                       => None,
+        }
+    }
+
+    fn rank(&self) -> Option<u8> {
+        match *self {
+            Type::Bool | Type::Char | Type::Int => Some(1),
+            Type::Long                          => Some(2),
+            Type::Float                         => Some(3),
+            Type::Double                        => Some(4),
+            _                                   => None,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.rank().is_some()
+    }
+
+    pub fn is_integral(&self) -> bool {
+        match *self {
+            Type::Bool | Type::Char | Type::Int | Type::Long => true,
+            _                                                => false,
+        }
+    }
+
+    pub fn is_floating(&self) -> bool {
+        *self == Type::Float || *self == Type::Double
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        match *self {
+            Type::Unknown | Type::Parameter(_) | Type::Foreign(_) => true,
+            _                                                     => false,
+        }
+    }
+
+    /// Whether the type is only known in part, like a `Stack[T]` or a `[{unknown}]`.
+    pub fn is_partial(&self) -> bool {
+        match *self {
+            Type::Object(_, ref a)       => a.iter().any(|t| t.is_partial()),
+            Type::Array(ref t)           => t.is_partial(),
+            Type::List(ref t)            => t.is_partial(),
+            Type::Map(ref k, ref v)      => k.is_partial() || v.is_partial(),
+            Type::Function(ref a, ref r) => a.iter().any(|t| t.is_partial()) || r.is_partial(),
+            Type::Parameter(_)           => false,
+            ref t                        => t.is_unknown(),
+        }
+    }
+
+    /// The type of the elements indexing a value of this type gives, or none for a type that
+    /// can't be indexed. Maps are indexed by their keys.
+    pub fn indexed(&self) -> Option<Type> {
+        match *self {
+            Type::Map(_, ref v) => Some((**v).clone()),
+            ref t               => t.iterated(),
+        }
+    }
+
+    /// The type of the elements a `for` goes through in a value of this type, or none for a
+    /// type that can't be iterated over.
+    pub fn iterated(&self) -> Option<Type> {
+        match *self {
+            Type::Array(ref t) | Type::List(ref t) => Some((**t).clone()),
+            Type::Text                             => Some(Type::Char),
+            Type::Unknown | Type::Foreign(_)       => Some(Type::Unknown),
+            _                                      => None,
+        }
+    }
+
+    /// The type with the type parameters in it replaced by what they stand for.
+    pub fn substitute(&self, bindings: &[(String, Type)]) -> Type {
+        match *self {
+            Type::Parameter(ref n) => match bindings.iter().find(|&&(ref b, _)| b == n) {
+                Some(&(_, ref t)) => t.clone(),
+                None              => self.clone(),
+            },
+
+            Type::Object(ref c, ref a) => Type::Object(c.clone(), a.iter().map(|t| t.substitute(bindings)).collect()),
+            Type::Array(ref t)         => Type::Array(Box::new(t.substitute(bindings))),
+            Type::List(ref t)          => Type::List(Box::new(t.substitute(bindings))),
+            Type::Map(ref k, ref v)    => Type::Map(Box::new(k.substitute(bindings)), Box::new(v.substitute(bindings))),
+
+            Type::Function(ref a, ref r) => {
+                Type::Function(a.iter().map(|t| t.substitute(bindings)).collect(), Box::new(r.substitute(bindings)))
+            }
+
+            ref t => t.clone(),
+        }
+    }
+
+    /// Whether a value of type `other` can be stored where this type is expected. Numbers
+    /// convert into each other implicitly, just like in C++, and objects into their bases.
+    pub fn accepts(&self, other: &Type) -> bool {
+        match (self, other) {
+            (a, b) if a.is_unknown() || b.is_unknown() => true,
+
+            (&Type::Object(ref a, ref x), &Type::Object(ref b, ref y)) => {
+                // type arguments are never converted, `Stack[int]` is no `Stack[float]`
+                derives(b, a) && (x.len() != y.len() || x.iter().zip(y.iter()).all(|(x, y)| same(x, y)))
+            }
+
+            (&Type::Enum(ref a), &Type::Enum(ref b))     => Rc::ptr_eq(a, b),
+            (&Type::Array(ref a), &Type::Array(ref b))   => a.accepts(b) && b.accepts(a),
+
+            (&Type::List(_), &Type::List(_))               |
+            (&Type::Map(_, _), &Type::Map(_, _))           |
+            (&Type::Function(_, _), &Type::Function(_, _)) => same(self, other),
+
+            (&Type::Void, &Type::Void) |
+            (&Type::Text, &Type::Text) => true,
+
+            (a, b) => a.is_numeric() && b.is_numeric(),
+        }
+    }
+
+    /// Whether a value of this type can decide an `if`, a `while` or a logical operator.
+    pub fn is_condition(&self) -> bool {
+        self.is_numeric() || self.is_unknown()
+    }
+
+    /// The type both sides of an arithmetic operation are converted to.
+    pub fn join(&self, other: &Type) -> Type {
+        match (self.rank(), other.rank()) {
+            (Some(a), Some(b)) => {
+                let wider = if a >= b { self } else { other };
+
+                match *wider {
+                    Type::Bool | Type::Char => Type::Int,
+                    ref t                   => t.clone(),
+                }
+            }
+
+            _ => Type::Unknown,
+        }
+    }
+
+    /// How the type is spelled in C++, where `auto` stands in for anything unknown.
+    pub fn cpp(&self) -> String {
+        match *self {
+            Type::Object(ref c, ref a) if a.is_empty() => c.path().to_string(),
+
+            Type::Object(ref c, ref a) => {
+                let arguments: Vec<String> = a.iter().map(|t| t.cpp()).collect();
+
+                if arguments.iter().any(|a| a == "auto") {
+                    return "auto".to_owned()
+                }
+
+                format!("{}<{}>", c.path(), arguments.join(", "))
+            }
+
+            Type::List(ref t) => match t.cpp().as_str() {
+                "auto" => "auto".to_owned(),
+                t      => format!("std::vector<{}>", t),
+            },
+
+            Type::Map(ref k, ref v) => match (k.cpp().as_str(), v.cpp().as_str()) {
+                ("auto", _) | (_, "auto") => "auto".to_owned(),
+                (k, v)                    => format!("std::unordered_map<{}, {}>", k, v),
+            },
+
+            Type::Function(ref a, ref r) => {
+                let mut parts: Vec<String> = a.iter().map(|t| t.cpp()).collect();
+
+                parts.push(r.cpp());
+
+                if parts.iter().any(|t| t == "auto") {
+                    return "auto".to_owned()
+                }
+
+                let retty = parts.pop().unwrap_or_default();
+
+                format!("std::function<{}({})>", retty, parts.join(", "))
+            }
+
+            Type::Enum(ref c)              => c.path().to_string(),
+            Type::Foreign(ref t)           => written(t),
+            Type::Array(_) | Type::Unknown => "auto".to_owned(),
+            ref t                          => t.to_string(),
+        }
+    }
+}
+
+impl PartialEq for Type {
+    fn eq(&self, other: &Type) -> bool {
+        match (self, other) {
+            (&Type::Object(ref a, ref x), &Type::Object(ref b, ref y))     => Rc::ptr_eq(a, b) && x == y,
+            (&Type::Enum(ref a), &Type::Enum(ref b))                       => Rc::ptr_eq(a, b),
+            (&Type::Array(ref a), &Type::Array(ref b))                     => a == b,
+            (&Type::List(ref a), &Type::List(ref b))                       => a == b,
+            (&Type::Map(ref a, ref x), &Type::Map(ref b, ref y))           => a == b && x == y,
+            (&Type::Function(ref a, ref x), &Type::Function(ref b, ref y)) => a == b && x == y,
+            (&Type::Parameter(ref a), &Type::Parameter(ref b))             => a == b,
+            (&Type::Foreign(ref a), &Type::Foreign(ref b))                 => a == b,
+            (a, b)                                                         => mem::discriminant(a) == mem::discriminant(b),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::Void             => write!(f, "void"),
+            Type::Bool             => write!(f, "bool"),
+            Type::Char             => write!(f, "char"),
+            Type::Int              => write!(f, "int"),
+            Type::Long             => write!(f, "long"),
+            Type::Float            => write!(f, "float"),
+            Type::Double           => write!(f, "double"),
+            Type::Text             => write!(f, "string"),
+            Type::Enum(ref e)      => write!(f, "{}", e.path()),
+            Type::Array(ref t)     => write!(f, "{}[]", t),
+            Type::List(ref t)      => write!(f, "[{}]", t),
+            Type::Map(ref k, ref v) => write!(f, "{{{}: {}}}", k, v),
+            Type::Parameter(ref n) => write!(f, "{}", n),
+            Type::Foreign(ref n)   => write!(f, "{}", n),
+            Type::Unknown          => write!(f, "{{unknown}}"),
+
+            Type::Function(ref a, ref r) => {
+                let parameters: Vec<String> = a.iter().map(|t| t.to_string()).collect();
+
+                write!(f, "({}) -> {}", parameters.join(", "), r)
+            }
+
+            Type::Object(ref c, ref a) if a.is_empty() => write!(f, "{}", c.path()),
+
+            Type::Object(ref c, ref a) => {
+                let arguments: Vec<String> = a.iter().map(|t| t.to_string()).collect();
+
+                write!(f, "{}[{}]", c.path(), arguments.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// Whether class `a` is class `b` or one of its descendants.
+pub fn derives(a: &Rc<Scope>, b: &Rc<Scope>) -> bool {
+    if Rc::ptr_eq(a, b) {
+        return true
+    }
+
+    match *a.base.borrow() {
+        Some(ref p) => derives(p, b),
+        None        => false,
+    }
+}
+
+/// Whether two types are the same, with anything unknown in them matching every type. What
+/// is held by objects and collections is never converted.
+pub fn same(a: &Type, b: &Type) -> bool {
+    match (a, b) {
+        (a, b) if a.is_unknown() || b.is_unknown() => true,
+
+        (&Type::Object(ref a, ref x), &Type::Object(ref b, ref y)) => {
+            Rc::ptr_eq(a, b) && x.len() == y.len() && x.iter().zip(y.iter()).all(|(x, y)| same(x, y))
+        }
+
+        (&Type::List(ref a), &Type::List(ref b))             => same(a, b),
+        (&Type::Map(ref a, ref x), &Type::Map(ref b, ref y)) => same(a, b) && same(x, y),
+
+        (&Type::Function(ref a, ref x), &Type::Function(ref b, ref y)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same(a, b)) && same(x, y)
+        }
+
+        (a, b) => a == b,
+    }
+}
+
+/// Whether none of the variants of an enum carry fields, which makes its values comparable.
+pub fn is_plain(enumeration: &Scope) -> bool {
+    enumeration.symbols.borrow().values().all(|&(ref s, _)| match *s {
+        Symbol::Variant(ref s) => s.params.is_empty(),
+        _                      => true,
+    })
+}
+
+/// The type of an integer literal, which is a `long` when it doesn't fit an `int` like in C++.
+pub fn integer(i: i64) -> Type {
+    if i >= i32::min_value() as i64 && i <= i32::max_value() as i64 {
+        Type::Int
+    } else {
+        Type::Long
+    }
+}
+
+/// The type the elements of a list or map written out are kept as, given the type of the
+/// ones so far and that of the next one: numbers are widened like arithmetic does, and
+/// anything unknown takes the type of the others. None when the two can't be kept together.
+pub fn unify(a: &Type, b: &Type) -> Option<Type> {
+    match (a, b) {
+        (a, b) if a.is_unknown()                   => Some(b.clone()),
+        (a, b) if b.is_unknown()                   => Some(a.clone()),
+        (a, b) if a.is_numeric() && b.is_numeric() => Some(a.join(b)),
+        (a, b) if same(a, b)                       => Some(a.clone()),
+        _                                          => None,
+    }
+}
+
+/// The type an operator produces from operands of the given types, or `None` when it can't be
+/// applied to them.
+pub fn operation(op: &Operator, a: &Type, b: &Type) -> Option<Type> {
+    if a.is_unknown() || b.is_unknown() {
+        return Some(match *op {
+            Operator::Plus | Operator::Minus | Operator::Mul | Operator::Div | Operator::Mod |
+            Operator::BitAnd | Operator::BitOr | Operator::BitXor | Operator::Shl | Operator::Shr => Type::Unknown,
+            _ => Type::Bool,
+        })
+    }
+
+    match *op {
+        Operator::Plus if *a == Type::Text && *b == Type::Text => Some(Type::Text),
+
+        Operator::Plus | Operator::Minus | Operator::Mul | Operator::Div => {
+            if a.is_numeric() && b.is_numeric() { Some(a.join(b)) } else { None }
+        }
+
+        Operator::Mod | Operator::BitAnd | Operator::BitOr | Operator::BitXor | Operator::Shl | Operator::Shr => {
+            if a.is_integral() && b.is_integral() { Some(a.join(b)) } else { None }
+        }
+
+        Operator::Equal | Operator::NEqual | Operator::Lt | Operator::LtEqual | Operator::Gt | Operator::GtEqual => {
+            match (a, b) {
+                (&Type::Text, &Type::Text) => Some(Type::Bool),
+
+                (&Type::Enum(ref a), &Type::Enum(ref b)) => match *op {
+                    Operator::Equal | Operator::NEqual if Rc::ptr_eq(a, b) && is_plain(a) => Some(Type::Bool),
+                    _                                                                     => None,
+                },
+
+                (a, b) if a.is_numeric() && b.is_numeric() => Some(Type::Bool),
+                _ => None,
+            }
+        }
+
+        Operator::And | Operator::Or => {
+            if a.is_condition() && b.is_condition() { Some(Type::Bool) } else { None }
+        }
+
+        Operator::Not | Operator::BitNot => None,
+    }
+}
+
+/// The type a prefix operator produces from an operand of the given type.
+pub fn unary(op: &Operator, a: &Type) -> Option<Type> {
+    match *op {
+        Operator::Not                       => if a.is_condition() { Some(Type::Bool) } else { None },
+        _ if a.is_unknown()                 => Some(Type::Unknown),
+        Operator::Minus if a.is_numeric()   => Some(a.join(a)),
+        Operator::BitNot if a.is_integral() => Some(a.join(a)),
+        _                                   => None,
+    }
+}
+
+/// The methods of `std::vector`, `std::unordered_map` and `std::string` lists, maps and
+/// strings have.
+pub fn method(t: &Type, name: &str) -> Option<Signature> {
+    let (params, retty) = match (t, name) {
+        (_, "size")  => (vec!(), Type::Int),
+        (_, "clear") => (vec!(), Type::Void),
+
+        (&Type::List(ref t), "push_back") => (vec!((**t).clone()), Type::Void),
+        (&Type::List(_), "pop_back")      => (vec!(), Type::Void),
+        (&Type::List(ref t), "back")      => (vec!(), (**t).clone()),
+
+        (&Type::Map(ref k, _), "count") => (vec!((**k).clone()), Type::Int),
+        (&Type::Map(ref k, _), "erase") => (vec!((**k).clone()), Type::Int),
+
+        (&Type::Text, "length") => (vec!(), Type::Int),
+
+        _ => return None,
+    };
+
+    Some(Signature {
+        generics: Vec::new(),
+        params:   params,
+        retty:    retty,
+    })
+}
+
+/// The type of an object of a class made by its own constructor, which has the class's own
+/// type parameters as its type arguments.
+pub fn instance(class: &Rc<Scope>) -> Type {
+    let arguments = class.generics.borrow().iter().map(|g| Type::Parameter(g.clone())).collect();
+
+    Type::Object(class.clone(), arguments)
+}
+
+/// What the type parameters of a generic function or class stand for in a call, either given
+/// in brackets or told by the types of the arguments the way C++ deduces them; whatever
+/// neither tells is left unknown. The parameters the arguments bind to two different types
+/// are handed back along with both.
+pub fn bind(generics: &[String], explicit: Option<Vec<Type>>, params: &[Type], args: &[Type])
+            -> (Vec<(String, Type)>, Vec<(String, Type, Type)>) {
+    if let Some(explicit) = explicit {
+        return (generics.iter().cloned().zip(explicit.into_iter()).collect(), Vec::new())
+    }
+
+    let mut bindings  = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for (p, a) in params.iter().zip(args.iter()) {
+        conflicts.extend(infer(p, a, &mut bindings))
+    }
+
+    bindings.retain(|&(ref n, _)| generics.contains(n));
+
+    for g in generics.iter() {
+        if !bindings.iter().any(|&(ref n, _)| n == g) {
+            bindings.push((g.clone(), Type::Unknown))
+        }
+    }
+
+    (bindings, conflicts)
+}
+
+/// Binds the type parameters in the type of a parameter to the matching parts of the type of
+/// an argument, handing back a parameter bound to two different types.
+fn infer(param: &Type, arg: &Type, bindings: &mut Vec<(String, Type)>) -> Option<(String, Type, Type)> {
+    match (param, arg) {
+        (_, &Type::Unknown) => None,
+
+        (&Type::Parameter(ref n), a) => {
+            let bound = bindings.iter().find(|&&(ref b, _)| b == n).map(|&(_, ref t)| t.clone());
+
+            match bound {
+                None                                    => bindings.push((n.clone(), a.clone())),
+                Some(ref t) if t == a || t.is_unknown() => (),
+                Some(t)                                 => return Some((n.clone(), t, a.clone())),
+            }
+
+            None
+        }
+
+        (&Type::Array(ref p), &Type::Array(ref a)) |
+        (&Type::List(ref p), &Type::List(ref a))   => infer(p, a, bindings),
+
+        (&Type::Object(ref c, ref p), &Type::Object(ref d, ref a)) if Rc::ptr_eq(c, d) => {
+            for (p, a) in p.iter().zip(a.iter()) {
+                if let Some(conflict) = infer(p, a, bindings) {
+                    return Some(conflict)
+                }
+            }
+
+            None
+        }
+
+        _ => None,
+    }
+}
+
+/// The type a type name such as a parameter's refers to where the names of `scope` are in
+/// sight, with the ones in `generics` standing for type parameters. A name the program
+/// doesn't define is taken to come from a library.
+pub fn named(scope: &Scope, generics: &[String], name: &str) -> Type {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Type::Unknown
+    }
+
+    if generics.iter().any(|g| g == name) {
+        return Type::Parameter(name.to_owned())
+    }
+
+    if let Some(t) = Type::primitive(name) {
+        return t
+    }
+
+    if name.starts_with('[') && name.ends_with(']') {
+        return Type::List(Box::new(named(scope, generics, &name[1 .. name.len() - 1])))
+    }
+
+    // a function type, like `(int, int) -> int`
+    if name.starts_with('(') {
+        let arrow = ast::split(name, " -> ");
+
+        if arrow.len() > 1 && arrow[0].ends_with(')') {
+            let params = ast::split(&arrow[0][1 .. arrow[0].len() - 1], ",").into_iter()
+                                                                            .filter(|p| !p.is_empty())
+                                                                            .map(|p| named(scope, generics, p))
+                                                                            .collect();
+
+            return Type::Function(params, Box::new(named(scope, generics, &name[arrow[0].len() + 4 ..])))
+        }
+    }
+
+    if name.starts_with('{') && name.ends_with('}') {
+        let entry = ast::split(&name[1 .. name.len() - 1], ": ");
+
+        return match entry.len() {
+            2 => Type::Map(Box::new(named(scope, generics, entry[0])), Box::new(named(scope, generics, entry[1]))),
+            _ => Type::Unknown,
+        }
+    }
+
+    // a generic class given its type arguments, like `Stack[int]`
+    if let (Some(i), true) = (name.find('['), name.ends_with(']')) {
+        let arguments = ast::split(&name[i + 1 .. name.len() - 1], ",").into_iter().map(|a| named(scope, generics, a)).collect();
+
+        return match defined(scope, &name[..i]) {
+            Some(Symbol::Class(c)) => Type::Object(c, arguments),
+            _                      => Type::Foreign(name.to_owned()),
+        }
+    }
+
+    match defined(scope, name) {
+        Some(Symbol::Class(c)) => Type::Object(c, Vec::new()),
+        Some(Symbol::Enum(e))  => Type::Enum(e),
+        _                      => Type::Foreign(name.to_owned()),
+    }
+}
+
+/// What a path written out as a string, like `geo::Point`, refers to where the names of
+/// `scope` are in sight.
+pub fn defined(scope: &Scope, name: &str) -> Option<Symbol> {
+    let segments = ast::split(name, "::");
+
+    let mut found = scope.lookup(segments[0]);
+
+    for s in segments[1 ..].iter() {
+        found = match found {
+            Some(Symbol::Module(m)) | Some(Symbol::Class(m)) | Some(Symbol::Enum(m)) => m.member(s),
+            _                                                                        => None,
+        }
+    }
+
+    found
+}
+
+/// A type written out in Helix the way C++ writes it, with the type arguments of generics in
+/// angle brackets.
+fn written(t: &str) -> String {
+    if t.starts_with('[') && t.ends_with(']') {
+        return format!("std::vector<{}>", written(&t[1 .. t.len() - 1]))
+    }
+
+    if t.starts_with('(') {
+        let arrow = ast::split(t, " -> ");
+
+        if arrow.len() > 1 && arrow[0].ends_with(')') {
+            let params: Vec<String> = ast::split(&arrow[0][1 .. arrow[0].len() - 1], ",").into_iter()
+                                                                                         .filter(|p| !p.is_empty())
+                                                                                         .map(written)
+                                                                                         .collect();
+
+            return format!("std::function<{}({})>", written(&t[arrow[0].len() + 4 ..]), params.join(", "))
+        }
+    }
+
+    if t.starts_with('{') && t.ends_with('}') {
+        let entry: Vec<String> = ast::split(&t[1 .. t.len() - 1], ": ").into_iter().map(written).collect();
+
+        return format!("std::unordered_map<{}>", entry.join(", "))
+    }
+
+    match t.find('[') {
+        Some(i) if t.ends_with(']') => {
+            let arguments: Vec<String> = ast::split(&t[i + 1 .. t.len() - 1], ",").into_iter().map(written).collect();
+
+            format!("{}<{}>", &t[..i], arguments.join(", "))
+        }
+
+        _ => t.to_owned(),
+    }
+}
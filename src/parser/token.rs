@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
     Block(Vec<Token>),
@@ -8,6 +10,7 @@ pub enum TokenType {
     Text,
     Ident,
     Assign,
+    CompoundAssign,
 
     LParen,
     RParen,
@@ -30,6 +33,12 @@ pub enum TokenType {
     Construct,
     If,
     Else,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
+    Range,
     Module,
     Import,
     Library,
@@ -40,12 +49,67 @@ pub enum TokenType {
     Use,
     Implement,
     Class,
+    Enum,
+    Match,
 
     Boolean,
     Operator,
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            TokenType::Block(_)  => "indented block",
+            TokenType::Integer   => "integer",
+            TokenType::Float     => "float",
+            TokenType::Text      => "string",
+            TokenType::Ident     => "identifier",
+            TokenType::Assign    => "`=`",
+            TokenType::CompoundAssign => "compound assignment",
+            TokenType::LParen    => "`(`",
+            TokenType::RParen    => "`)`",
+            TokenType::LBrace    => "`{`",
+            TokenType::RBrace    => "`}`",
+            TokenType::LBracket  => "`[`",
+            TokenType::RBracket  => "`]`",
+            TokenType::Nothing   => "`nothing`",
+            TokenType::Arrow     => "`->`",
+            TokenType::NArrow    => "`<-`",
+            TokenType::Colon     => "`:`",
+            TokenType::Comma     => "`,`",
+            TokenType::Period    => "`.`",
+            TokenType::Bang      => "`!`",
+            TokenType::Semicolon => "`;`",
+            TokenType::Construct => "`construct`",
+            TokenType::If        => "`if`",
+            TokenType::Else      => "`else`",
+            TokenType::While     => "`while`",
+            TokenType::For       => "`for`",
+            TokenType::In        => "`in`",
+            TokenType::Break     => "`break`",
+            TokenType::Continue  => "`continue`",
+            TokenType::Range     => "`..`",
+            TokenType::Module    => "`module`",
+            TokenType::Import    => "`import`",
+            TokenType::Library   => "`library`",
+            TokenType::Def       => "`function`",
+            TokenType::Return    => "`return`",
+            TokenType::Let       => "`var`",
+            TokenType::Struct    => "`structure`",
+            TokenType::Use       => "`use`",
+            TokenType::Implement => "`implement`",
+            TokenType::Class     => "`class`",
+            TokenType::Enum      => "`enum`",
+            TokenType::Match     => "`match`",
+            TokenType::Boolean   => "boolean",
+            TokenType::Operator  => "operator",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Operator {
     Mul,
     Div,
@@ -61,6 +125,46 @@ pub enum Operator {
     LtEqual,
     Gt,
     GtEqual,
+
+    And,
+    Or,
+    Not,
+
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    Shl,
+    Shr,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match *self {
+            Operator::Mul     => "*",
+            Operator::Div     => "/",
+            Operator::Mod     => "%",
+            Operator::Plus    => "+",
+            Operator::Minus   => "-",
+            Operator::Equal   => "==",
+            Operator::NEqual  => "!=",
+            Operator::Lt      => "<",
+            Operator::LtEqual => "<=",
+            Operator::Gt      => ">",
+            Operator::GtEqual => ">=",
+            Operator::And     => "and",
+            Operator::Or      => "or",
+            Operator::Not     => "not",
+            Operator::BitAnd  => "&",
+            Operator::BitOr   => "|",
+            Operator::BitXor  => "^",
+            Operator::BitNot  => "~",
+            Operator::Shl     => "<<",
+            Operator::Shr     => ">>",
+        };
+
+        write!(f, "{}", symbol)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -92,4 +196,23 @@ impl Token {
     pub fn get_position(&self) -> (&u32, &u32) {
         (&self.row, &self.col)
     }
+
+    /// The width of the token in the source, for underlining it in diagnostics.
+    pub fn get_width(&self) -> u32 {
+        match self.token_type {
+            TokenType::Block(_) => 1,
+            _ => self.content.chars().count() as u32,
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.token_type {
+            TokenType::Block(_) => write!(f, "{}", self.token_type),
+            TokenType::Text => write!(f, "string \"{}\"", self.content),
+            _ if self.content.len() > 0 => write!(f, "`{}`", self.content),
+            _ => write!(f, "{}", self.token_type),
+        }
+    }
 }
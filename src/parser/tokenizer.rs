@@ -1,11 +1,13 @@
 use super::token::{Token, TokenType, Operator};
 
 use super::block_tree;
+use super::diagnostic::{Diagnostic, code};
 
 #[derive(Debug, Clone)]
 pub struct Tokenizer {
     tokens: Vec<Token>,
     lines: u32,
+    offset: usize,
     start: usize,
     pos: usize,
     top: usize,
@@ -16,6 +18,19 @@ impl<'a> Tokenizer {
         Tokenizer {
             tokens: Vec::new(),
             lines: 0,
+            offset: 0,
+            start: 0,
+            pos: 0,
+            top: 0,
+        }
+    }
+
+    /// Tokenizer for source that starts at the given line and column of a file.
+    pub fn at(row: u32, col: u32) -> Tokenizer {
+        Tokenizer {
+            tokens: Vec::new(),
+            lines: row.saturating_sub(1),
+            offset: col.saturating_sub(1) as usize,
             start: 0,
             pos: 0,
             top: 0,
@@ -26,6 +41,7 @@ impl<'a> Tokenizer {
         Tokenizer {
             tokens: tokens,
             lines: 0,
+            offset: 0,
             start: 0,
             pos: 0,
             top: 0,
@@ -37,22 +53,35 @@ impl<'a> Tokenizer {
     }
 
     fn push(&mut self, token_type: TokenType, line: &str) {
+        let column = self.column(line);
+
         self.tokens
             .push(Token::new(token_type,
                              line[self.start..self.pos].to_string(),
                              self.lines,
-                             self.pos as u32));
+                             column));
 
         self.start = self.pos
     }
 
+    /// The column the token being read starts at, counted in characters; `start` and `pos`
+    /// are byte offsets into the line.
+    fn column(&self, line: &str) -> u32 {
+        (self.offset + line[..self.start].chars().count() + 1) as u32
+    }
+
     fn peek(&self, line: &str, offset: usize) -> char {
-        match line.chars().nth(self.pos + offset) {
+        match line[self.pos..].chars().nth(offset) {
             Some(c) => c,
             None => ' ',
         }
     }
 
+    /// Moves past the character being looked at, however many bytes it takes.
+    fn advance(&mut self, line: &str) {
+        self.pos += self.look(line).len_utf8()
+    }
+
     fn look(&self, line: &str) -> char {
         self.peek(line, 0)
     }
@@ -83,9 +112,14 @@ impl<'a> Tokenizer {
         }
 
         while offset > 0 && !is_op {
-            match operator(&line[self.start..self.pos + offset]) {
-                Some(_) => is_op = true,
-                None => (),
+            let end = self.pos + offset;
+
+            if line.is_char_boundary(end) {
+                let v = &line[self.start..end];
+
+                if operator(v).is_some() || unary(v).is_some() {
+                    is_op = true
+                }
             }
 
             offset -= 1
@@ -116,6 +150,14 @@ impl<'a> Tokenizer {
         false
     }
 
+    pub fn get_top(&self) -> usize {
+        self.top
+    }
+
+    pub fn set_top(&mut self, top: usize) {
+        self.top = top
+    }
+
     pub fn remaining(&self) -> usize {
         self.tokens.len() - self.top
     }
@@ -132,19 +174,28 @@ impl<'a> Tokenizer {
         self.current().get_content().clone()
     }
 
-    pub fn match_current(&self, token: TokenType) -> Result<&Token, String> {
+    pub fn match_current(&self, token: TokenType) -> Result<&Token, Diagnostic> {
         match self.current().get_type() == token {
             true => Ok(self.current()),
-            false => Err(format!("expected {:?} but found {:#?}", token, self.current())),
+            false => Err(self.error(code::EXPECTED,
+                                    format!("expected {}, found {}", token, self.current()))),
         }
     }
 
+    /// An error pointing at the current token.
+    pub fn error(&self, code: &'static str, message: String) -> Diagnostic {
+        let current = self.current();
+        let (row, col) = current.get_position();
+
+        Diagnostic::error(code, message).at(*row, *col, current.get_width())
+    }
+
     fn push_move(&mut self, token: TokenType, line: &str) {
         self.pos += 1;
         self.push(token, line)
     }
 
-    pub fn tokenize(&mut self, source: String) -> Result<(), String> {
+    pub fn tokenize(&mut self, source: String) -> Result<(), Diagnostic> {
         for line in source.lines() {
             self.lines += 1;
             self.start = 0;
@@ -162,7 +213,13 @@ impl<'a> Tokenizer {
                     self.start += 1;
 
                     while self.look(line) != del {
-                        self.pos += 1
+                        if self.pos >= line.len() {
+                            return Err(Diagnostic::error(code::UNTERMINATED,
+                                                         "unterminated string literal".to_owned())
+                                .at(self.lines, self.column(line) - 1, 1))
+                        }
+
+                        self.advance(line)
                     }
 
                     self.push(TokenType::Text, line);
@@ -175,7 +232,7 @@ impl<'a> Tokenizer {
 
                 if identifier(self.look(line)) {
                     while identifier(self.look(line)) || self.look(line).is_digit(10) {
-                        self.pos += 1
+                        self.advance(line)
                     }
 
                     match keyword(&line[self.start..self.pos]) {
@@ -188,12 +245,7 @@ impl<'a> Tokenizer {
 
                 let peek = self.peek(line, 1);
 
-                if c.is_digit(10) || c == '.' && peek.is_digit(10) ||
-                   c == '-' && peek.is_digit(10) {
-                    if c == '-' {
-                        self.pos += 1
-                    }
-
+                if c.is_digit(10) || c == '.' && peek.is_digit(10) {
                     while self.look(line).is_digit(10) {
                         self.pos += 1
                     }
@@ -222,6 +274,20 @@ impl<'a> Tokenizer {
                     continue
                 }
 
+                if "+-*/%".contains(c) && self.peek(line, 1) == '=' {
+                    self.pos += 2;
+                    self.push(TokenType::CompoundAssign, line);
+
+                    continue
+                }
+
+                if c == '.' && self.peek(line, 1) == '.' {
+                    self.pos += 2;
+                    self.push(TokenType::Range, line);
+
+                    continue
+                }
+
                 if c == '<' && self.peek(line, 1) == '-' {
                     self.pos += 2;
                     self.push(TokenType::NArrow, line);
@@ -247,12 +313,9 @@ impl<'a> Tokenizer {
                 match symbol(c) {
                     Some(t) => self.push_move(t, line),
                     None => {
-                        panic!(
-                                "unexpected symbol: {}, ln {} col {}",
-                                &line[self.start .. line.len()],
-                                self.lines,
-                                self.start,
-                            )
+                        return Err(Diagnostic::error(code::SYMBOL,
+                                                     format!("unexpected symbol `{}`", c))
+                            .at(self.lines, self.column(line), 1))
                     }
                 }
             }
@@ -261,29 +324,48 @@ impl<'a> Tokenizer {
         Ok(())
     }
 
-    #[allow(unused_mut, unused_must_use)]
-    pub fn tokenize_branch(branch: &block_tree::Branch<'a>) -> block_tree::Branch<'a> {
+    /// Tokenizes every line of the branch, collecting the errors of all lines that fail.
+    pub fn tokenize_branch(branch: &block_tree::Branch<'a>) -> Result<block_tree::Branch<'a>, Vec<Diagnostic>> {
+        let mut errors = Vec::new();
+
+        let product = Self::tokenize_chunks(branch, &mut errors);
+
+        match errors.len() {
+            0 => Ok(product),
+            _ => Err(errors),
+        }
+    }
+
+    fn tokenize_chunks(branch: &block_tree::Branch<'a>, errors: &mut Vec<Diagnostic>) -> block_tree::Branch<'a> {
         let mut product = block_tree::Branch::new(Vec::new());
 
         for chunk in branch.content.iter() {
             match chunk.get_value() {
                 block_tree::ChunkValue::Text(t) => {
-                    let mut tokenizer = Tokenizer::new();
+                    let (row, col) = chunk.get_position();
+
+                    let mut tokenizer = Tokenizer::at(row, col);
 
-                    tokenizer.tokenize(t.to_string());
+                    if let Err(e) = tokenizer.tokenize(t.to_string()) {
+                        errors.push(e);
+
+                        continue
+                    }
 
                     product.content.push(
-                        block_tree::Chunk::new(
+                        block_tree::Chunk::at(
                             block_tree::ChunkValue::Tokens(
                                     tokenizer.get_tokens().clone(),
-                                )
-                        )
+                                ),
+                            row,
+                            col,
+                        ).with_trivia(chunk.get_trivia().clone())
                     )
                 }
 
                 block_tree::ChunkValue::Block(ref b) => product.content.push(
                         block_tree::Chunk::new(
-                            block_tree::ChunkValue::Block(Self::tokenize_branch(b)),
+                            block_tree::ChunkValue::Block(Self::tokenize_chunks(b, errors)),
                         )
                     ),
 
@@ -302,7 +384,14 @@ pub fn flatten_tree<'b>(branch: &block_tree::Branch<'b>) -> Vec<Token> {
         match chunk.get_value() {
             block_tree::ChunkValue::Tokens(ref t) => flattened.append(&mut t.clone()),
             block_tree::ChunkValue::Block(ref b) => {
-                flattened.push(Token::new(TokenType::Block(flatten_tree(b)), "".to_string(), 0, 0))
+                let tokens = flatten_tree(b);
+
+                let (row, col) = match tokens.first() {
+                    Some(t) => (*t.get_position().0, *t.get_position().1),
+                    None => (0, 0),
+                };
+
+                flattened.push(Token::new(TokenType::Block(tokens), "".to_string(), row, col))
             }
             _ => continue,
         }
@@ -312,14 +401,20 @@ pub fn flatten_tree<'b>(branch: &block_tree::Branch<'b>) -> Vec<Token> {
 }
 
 fn identifier(c: char) -> bool {
-    c.is_alphabetic() || c == '_' || c == '&'
+    c.is_alphabetic() || c == '_'
 }
 
 fn keyword(v: &str) -> Option<TokenType> {
     match v {
         "true" | "false" => Some(TokenType::Boolean),
+        "and" | "or" | "not" => Some(TokenType::Operator),
         "else" => Some(TokenType::Else),
         "if" => Some(TokenType::If),
+        "while" => Some(TokenType::While),
+        "for" => Some(TokenType::For),
+        "in" => Some(TokenType::In),
+        "break" => Some(TokenType::Break),
+        "continue" => Some(TokenType::Continue),
         "module" => Some(TokenType::Module),
         "library" => Some(TokenType::Library),
         "import" => Some(TokenType::Import),
@@ -327,6 +422,8 @@ fn keyword(v: &str) -> Option<TokenType> {
         "return" => Some(TokenType::Return),
         "var" => Some(TokenType::Let),
         "class" => Some(TokenType::Class),
+        "enum" => Some(TokenType::Enum),
+        "match" => Some(TokenType::Match),
         "implement" => Some(TokenType::Implement),
         "structure" => Some(TokenType::Struct),
         "use" => Some(TokenType::Use),
@@ -354,19 +451,49 @@ fn symbol(c: char) -> Option<TokenType> {
     }
 }
 
+/// Binary operators with their precedence; operators with a higher precedence bind tighter.
 pub fn operator(v: &str) -> Option<(Operator, u8)> {
     match v {
-        "*" => Some((Operator::Mul, 1)),
-        "%" => Some((Operator::Mod, 1)),
-        "/" => Some((Operator::Div, 1)),
-        "+" => Some((Operator::Plus, 2)),
-        "-" => Some((Operator::Minus, 2)),
-        "==" => Some((Operator::Equal, 3)),
-        "!=" => Some((Operator::NEqual, 3)),
-        "<" => Some((Operator::Lt, 4)),
-        ">" => Some((Operator::Gt, 4)),
-        "<=" => Some((Operator::LtEqual, 4)),
-        ">=" => Some((Operator::GtEqual, 4)),
+        "or" | "||" => Some((Operator::Or, 1)),
+        "and" | "&&" => Some((Operator::And, 2)),
+        "|" => Some((Operator::BitOr, 3)),
+        "^" => Some((Operator::BitXor, 4)),
+        "&" => Some((Operator::BitAnd, 5)),
+        "==" => Some((Operator::Equal, 6)),
+        "!=" => Some((Operator::NEqual, 6)),
+        "<" => Some((Operator::Lt, 7)),
+        ">" => Some((Operator::Gt, 7)),
+        "<=" => Some((Operator::LtEqual, 7)),
+        ">=" => Some((Operator::GtEqual, 7)),
+        "<<" => Some((Operator::Shl, 8)),
+        ">>" => Some((Operator::Shr, 8)),
+        "+" => Some((Operator::Plus, 9)),
+        "-" => Some((Operator::Minus, 9)),
+        "*" => Some((Operator::Mul, 10)),
+        "%" => Some((Operator::Mod, 10)),
+        "/" => Some((Operator::Div, 10)),
+        _ => None,
+    }
+}
+
+/// The operator applied by a compound assignment such as `+=`.
+pub fn compound(v: &str) -> Option<Operator> {
+    match v {
+        "+=" => Some(Operator::Plus),
+        "-=" => Some(Operator::Minus),
+        "*=" => Some(Operator::Mul),
+        "/=" => Some(Operator::Div),
+        "%=" => Some(Operator::Mod),
+        _ => None,
+    }
+}
+
+/// Prefix operators, which bind tighter than every binary operator.
+pub fn unary(v: &str) -> Option<Operator> {
+    match v {
+        "-" => Some(Operator::Minus),
+        "not" | "!" => Some(Operator::Not),
+        "~" => Some(Operator::BitNot),
         _ => None,
     }
 }
@@ -0,0 +1,1355 @@
+use std::fmt;
+use std::collections::{HashMap, HashSet};
+use std::path;
+
+use super::super::ast::Position;
+use super::super::ir::{Path, Type, Expression, Node, Statement, Arm, Item, Function, Variant};
+use super::super::token::Operator;
+use super::super::diagnostic::{Diagnostic, code};
+use super::super::loader;
+
+use super::{Backend, Target, operator, quoted};
+
+/// What strings are in C: immutable characters, where the ones made by joining others are
+/// never freed. Every header carries it, guarded so a file sees it once.
+const RUNTIME: &'static str = "#ifndef HELIX_RUNTIME_H
+#define HELIX_RUNTIME_H
+#include <stdbool.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef const char* helix_string;
+
+static inline helix_string helix_concat(helix_string a, helix_string b) {
+\tsize_t n = strlen(a), m = strlen(b);
+\tchar* s = malloc(n + m + 1);
+\tmemcpy(s, a, n);
+\tmemcpy(s + n, b, m + 1);
+\treturn s;
+}
+
+static inline int helix_compare(helix_string a, helix_string b) {
+\treturn strcmp(a, b);
+}
+#endif";
+
+/// The C headers the C++ versions of the standard library's are named after, like `cstdio`.
+const HEADERS: [&'static str; 15] = [
+    "assert", "ctype", "errno", "float", "limits", "locale", "math", "setjmp", "signal", "stdarg",
+    "stddef", "stdint", "stdio", "stdlib", "string",
+];
+
+/// The type of a value as far as C is concerned.
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    /// A number, `bool` or `char`, spelled the C way.
+    Scalar(String),
+    Text,
+    /// A struct made from a class or a Helix `struct`, by its C name.
+    Object(String),
+    Enum(String),
+    /// An array of the given size.
+    Array(Box<Kind>, String),
+    /// A type from a C library, spelled as it is written.
+    Foreign(String),
+    Void,
+    /// What a function from a C library gives back.
+    Unknown,
+}
+
+impl Kind {
+    /// How C spells the type, or the type of the elements of an array.
+    fn c(&self) -> String {
+        match *self {
+            Kind::Scalar(ref s) | Kind::Foreign(ref s) => s.clone(),
+            Kind::Object(ref n) | Kind::Enum(ref n)    => n.clone(),
+            Kind::Array(ref t, _)                      => t.c(),
+            Kind::Text                                 => "helix_string".to_owned(),
+            Kind::Void                                 => "void".to_owned(),
+            Kind::Unknown                              => "int".to_owned(),
+        }
+    }
+
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Kind::Text               => write!(f, "string"),
+            Kind::Array(ref t, ref n) => write!(f, "{}[{}]", t, n),
+            Kind::Unknown            => write!(f, "{{unknown}}"),
+            ref k                    => write!(f, "{}", k.c()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Signature {
+    params: Vec<(Kind, String)>,
+    retty:  Kind,
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    parent:      Option<String>,
+    /// Whether it is a Helix `struct`, which has no methods and so no vtable.
+    plain:       bool,
+    fields:      Vec<(String, Kind)>,
+    methods:     Vec<(String, Signature)>,
+    constructor: Option<Signature>,
+    /// The methods given a body by an `implement` block seen so far.
+    implemented: HashSet<String>,
+}
+
+/// An object a member is taken from.
+enum Receiver {
+    /// Anything that can be written as `x.member`.
+    Value(String),
+    /// `self` in a method.
+    Pointer(String),
+    /// An object made by a call, held in a compound literal for its address to be taken.
+    Temporary(String),
+}
+
+/// The C backend. C has no overloading, namespaces or methods, so names are mangled from the
+/// paths the IR resolves them to and methods are called through vtables.
+pub struct C {
+    title:        String,
+    guard:        String,
+    file:         String,
+    includes:     Vec<String>,
+    header:       Vec<String>,
+    source:       Vec<String>,
+    /// The vtables and the functions making objects, which go after everything they use.
+    tail:         Vec<String>,
+
+    classes:      HashMap<String, Class>,
+    /// The arrays of the variables in sight, by how they are spelled in C.
+    arrays:       HashMap<String, Kind>,
+
+    /// The locals in sight, each with how it is spelled in C.
+    locals:       Vec<HashMap<String, String>>,
+    constructing: bool,
+    depth:        usize,
+    position:     Position,
+    error:        Option<Diagnostic>,
+}
+
+impl C {
+    pub fn new(title: String, file: String) -> C {
+        let guard: String = title.chars()
+            .map(|c| if c.is_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect();
+
+        let title = match path::Path::new(&title).file_name() {
+            Some(n) => n.to_string_lossy().into_owned(),
+            None    => title,
+        };
+
+        C {
+            title:        title,
+            guard:        guard,
+            file:         file.replace('\\', "\\\\").replace('"', "\\\""),
+            includes:     Vec::new(),
+            header:       Vec::new(),
+            source:       Vec::new(),
+            tail:         Vec::new(),
+
+            classes:      HashMap::new(),
+            arrays:       HashMap::new(),
+
+            locals:       Vec::new(),
+            constructing: false,
+            depth:        0,
+            position:     (0, 0),
+            error:        None,
+        }
+    }
+
+    /// Keeps the first error; everything after it is still translated, but not written.
+    fn fail(&mut self, code: &'static str, message: String, note: &str) {
+        if self.error.is_none() {
+            let (line, col) = self.position;
+
+            self.error = Some(Diagnostic::error(code, message).at(line, col, 0).note(note.to_owned()))
+        }
+    }
+
+    fn unsupported(&mut self, message: String) {
+        self.fail(code::UNSUPPORTED, message, "generics, lists, maps, lambdas and enums with fields need `--target cpp`")
+    }
+
+    /// The type of a value as C has it.
+    fn kind(&mut self, t: &Type) -> Kind {
+        match *t {
+            Type::Bool   => Kind::Scalar("bool".to_owned()),
+            Type::Char   => Kind::Scalar("char".to_owned()),
+            Type::Int    => Kind::Scalar("int".to_owned()),
+            Type::Long   => Kind::Scalar("long".to_owned()),
+            Type::Float  => Kind::Scalar("float".to_owned()),
+            Type::Double => Kind::Scalar("double".to_owned()),
+            Type::Text   => Kind::Text,
+            Type::Void   => Kind::Void,
+
+            Type::Object(ref c, ref a) if a.is_empty() => Kind::Object(c.path().join("_")),
+            Type::Enum(ref e)                          => Kind::Enum(e.path().join("_")),
+
+            Type::Foreign(ref t) => Kind::Foreign(t.replace("::", "_")),
+            Type::Unknown        => Kind::Unknown,
+
+            ref t => {
+                self.unsupported(format!("C has no type like `{}`", t));
+
+                Kind::Unknown
+            }
+        }
+    }
+
+    /// The type of a variable as C has it, with the sizes of the arrays its declaration
+    /// makes.
+    fn declared(&mut self, t: &Type, value: Option<&Expression>) -> Kind {
+        let sizes = match value {
+            Some(&Expression { node: Node::Array(ref s), .. }) => s,
+            _                                                  => return self.kind(t),
+        };
+
+        let mut element = t;
+
+        for _ in sizes.iter() {
+            if let Type::Array(ref e) = *element {
+                element = e
+            }
+        }
+
+        let mut kind = self.kind(element);
+
+        for s in sizes.iter().rev() {
+            let size = self.expression(s);
+
+            kind = Kind::Array(Box::new(kind), size)
+        }
+
+        kind
+    }
+
+    /// The array an expression names, along with its sizes.
+    fn array(&self, e: &Expression) -> Option<Kind> {
+        let kind = match e.node {
+            Node::Local(ref n)  => self.arrays.get(n).cloned(),
+            Node::Global(ref p) => self.arrays.get(&p.join("_")).cloned(),
+
+            Node::Field(_, ref owner, ref n) => self.classes.get(&owner.join("_"))
+                .and_then(|c| c.fields.iter().find(|&&(ref f, _)| f == n).map(|&(_, ref k)| k.clone())),
+
+            Node::Index(ref a, _) => match self.array(a) {
+                Some(Kind::Array(t, _)) => Some(*t),
+                _                       => None,
+            },
+
+            _ => None,
+        };
+
+        match kind {
+            Some(Kind::Array(t, size)) => Some(Kind::Array(t, size)),
+            _                          => None,
+        }
+    }
+
+    fn signature(&mut self, f: &Function) -> Signature {
+        Signature {
+            params: f.params.iter().map(|&(ref n, ref t)| (self.kind(t), n.clone())).collect(),
+            retty:  self.kind(&f.retty),
+        }
+    }
+
+    /// Makes the classes a file defines known before any of it is written, since a call
+    /// through a vtable needs the layout of every class involved.
+    fn declare(&mut self, items: &[Item]) {
+        for i in items.iter() {
+            match *i {
+                Item::Module(_, ref c, _) => self.declare(c),
+
+                Item::Class(ref c) if c.generics.is_empty() => {
+                    let name = c.path.join("_");
+
+                    let parent = match c.parent.as_ref().map(|p| self.kind(p)) {
+                        Some(Kind::Object(p)) => Some(p),
+                        _                     => None,
+                    };
+
+                    let mut class = Class {
+                        parent:      parent,
+                        plain:       c.plain,
+                        fields:      Vec::new(),
+                        methods:     Vec::new(),
+                        constructor: None,
+                        implemented: HashSet::new(),
+                    };
+
+                    for f in c.fields.iter() {
+                        let kind = self.declared(&f.ty, f.value.as_ref());
+
+                        class.fields.push((f.name.clone(), kind))
+                    }
+
+                    for m in c.methods.iter() {
+                        let signature = self.signature(m);
+
+                        if m.body.is_some() {
+                            class.implemented.insert(m.name().to_owned());
+                        }
+
+                        if m.constructor {
+                            class.constructor = Some(signature)
+                        } else {
+                            class.methods.push((m.name().to_owned(), signature))
+                        }
+                    }
+
+                    self.classes.insert(name, class);
+                }
+
+                Item::Implement(ref p, ref g, ref c, _) if g.is_empty() => {
+                    if let Some(class) = self.classes.get_mut(&p.join("_")) {
+                        for m in c.iter() {
+                            class.implemented.insert(m.name().to_owned());
+                        }
+                    }
+                }
+
+                _ => (),
+            }
+        }
+    }
+
+    /// The classes a class derives from, starting with itself.
+    fn chain(&self, class: &str) -> Vec<String> {
+        let mut chain = vec!(class.to_owned());
+
+        while let Some(p) = self.classes.get(chain.last().unwrap()).and_then(|c| c.parent.clone()) {
+            chain.push(p)
+        }
+
+        chain
+    }
+
+    fn declares(&self, class: &str, method: &str) -> bool {
+        self.classes.get(class).map_or(false, |c| c.methods.iter().any(|&(ref m, _)| m == method))
+    }
+
+    /// The first class to declare a method, whose vtable has the slot for it.
+    fn slot(&self, class: &str, method: &str) -> Option<String> {
+        self.chain(class).into_iter().rev().find(|c| self.declares(c, method))
+    }
+
+    /// The class whose version of a method objects of `class` run.
+    fn implementer(&self, class: &str, method: &str) -> Option<String> {
+        self.chain(class).into_iter().find(|c| self.declares(c, method))
+    }
+
+    fn method(&self, class: &str, method: &str) -> Option<Signature> {
+        self.classes.get(class).and_then(|c| c.methods.iter().find(|&&(ref m, _)| m == method).map(|&(_, ref s)| s.clone()))
+    }
+
+    /// The members leading from an object of class `from` to the part of it that is a
+    /// `to`, like `base.base`.
+    fn upcast(&self, from: &str, to: &str) -> Option<String> {
+        self.chain(from).iter().position(|c| c == to).map(|n| vec!("base"; n).join("."))
+    }
+
+    /// The C line of `text` at the current depth.
+    fn line(&self, out: &mut Vec<String>, text: String) {
+        out.push(format!("{}{}", "\t".repeat(self.depth), text))
+    }
+
+    fn local(&mut self, name: &str, spelled: String) {
+        self.locals.last_mut().unwrap().insert(name.to_owned(), spelled);
+    }
+
+    /// The C declaration of a variable or parameter of a type, dimensions and all.
+    fn declarator(name: &str, kind: &Kind) -> String {
+        let mut dimensions = String::new();
+        let mut element = kind;
+
+        while let Kind::Array(ref t, ref size) = *element {
+            dimensions.push_str(&format!("[{}]", size));
+            element = &**t
+        }
+
+        format!("{} {}{}", element.c(), name, dimensions)
+    }
+
+    /// The statement giving whatever needs it the value C++ would construct it with.
+    fn initialize(target: &str, kind: &Kind, level: usize) -> Option<String> {
+        match *kind {
+            Kind::Text          => Some(format!("{} = \"\";", target)),
+            Kind::Object(ref c) => Some(format!("{} = {}_new();", target, c)),
+
+            Kind::Array(ref t, ref size) => {
+                let i = format!("i{}_", level);
+
+                C::initialize(&format!("{}[{}]", target, i), &**t, level + 1)
+                    .map(|s| format!("for (int {0} = 0; {0} < {1}; {0}++) {2}", i, size, s))
+            }
+
+            _ => None,
+        }
+    }
+
+    /// The head of a function, with `self` first for a method of `class`.
+    fn prototype(name: &str, signature: &Signature, class: Option<&str>) -> String {
+        let mut params: Vec<String> = class.iter().map(|c| format!("{}* self", c)).collect();
+
+        params.extend(signature.params.iter().map(|&(ref k, ref n)| C::declarator(n, k)));
+
+        if params.is_empty() {
+            params.push("void".to_owned())
+        }
+
+        format!("{} {}({})", signature.retty.c(), name, params.join(", "))
+    }
+
+    /// Writes what a file defines.
+    fn emit(&mut self, items: &[Item]) {
+        for i in items.iter() {
+            match *i {
+                Item::Import(ref p, l, position) => {
+                    self.position = position;
+                    self.include(p, l)
+                }
+
+                // every name is already resolved to what it refers to
+                Item::Use(_, _) => (),
+
+                Item::Global(ref p, ref t, ref e, position) => {
+                    self.position = position;
+                    self.variable(p, t, e.as_ref())
+                }
+
+                Item::Function(ref f) => {
+                    self.position = f.position;
+
+                    if !f.generics.is_empty() {
+                        self.unsupported(format!("`{}` is generic, which C can't express", f.name()));
+
+                        continue
+                    }
+
+                    self.function(f, None)
+                }
+
+                Item::Module(_, ref c, _) => self.emit(c),
+
+                Item::Class(ref c) => {
+                    self.position = c.position;
+
+                    if !c.generics.is_empty() {
+                        self.unsupported(format!("`{}` is generic, which C can't express", c.path.name()));
+
+                        continue
+                    }
+
+                    let name = c.path.join("_");
+
+                    self.structure(&name);
+                    self.implementation(&name, &c.methods)
+                }
+
+                Item::Enum(ref p, ref v, position) => {
+                    self.position = position;
+                    self.enumeration(p, v)
+                }
+
+                Item::Implement(ref p, ref g, ref c, position) => {
+                    self.position = position;
+
+                    if !g.is_empty() {
+                        self.unsupported(format!("`{}` is generic, which C can't express", p.name()));
+
+                        continue
+                    }
+
+                    self.implementation(&p.join("_"), c)
+                }
+            }
+        }
+    }
+
+    fn include(&mut self, path: &str, library: bool) {
+        let header = if library {
+            let name = if path.starts_with('c') { &path[1..] } else { path };
+
+            if path.ends_with(".h") {
+                format!("<{}>", path)
+            } else if HEADERS.contains(&name) {
+                format!("<{}.h>", name)
+            } else {
+                self.fail(code::UNSUPPORTED, format!("`{}` is a C++ library with no C counterpart", path),
+                          "C libraries are imported by the name of their header, like `import \"stdio.h\" library`");
+
+                return
+            }
+        } else if loader::is_module(path) {
+            format!("\"{}.h\"", loader::module_name(path))
+        } else {
+            format!("\"{}\"", path)
+        };
+
+        if !self.includes.contains(&header) {
+            self.includes.push(header)
+        }
+    }
+
+    /// A variable of a module, or of the file itself.
+    fn variable(&mut self, path: &Path, t: &Type, e: Option<&Expression>) {
+        let c    = path.join("_");
+        let kind = self.declared(t, e);
+
+        match kind {
+            Kind::Unknown => return self.fail(code::TYPE, format!("the type of `{}` isn't known", path.name()),
+                                              "write it down, like `var x: int = f()`"),
+
+            Kind::Object(_) => return self.unsupported(format!("`{}` is an object, which C can't make before `main` runs", path.name())),
+
+            _ => (),
+        }
+
+        let declaration = C::declarator(&c, &kind);
+
+        self.header.push(format!("extern {};", declaration));
+
+        // arrays are made by their declarations alone
+        let value = match kind {
+            Kind::Array(_, _) => {
+                self.arrays.insert(c.clone(), kind.clone());
+
+                None
+            }
+
+            _ => e.map(|e| self.expression(e)),
+        };
+
+        self.source.push(match (value, &kind) {
+            (Some(v), _)          => format!("{} = {};", declaration, v),
+            (None, &Kind::Text)   => format!("{} = \"\";", declaration),
+            (None, _)             => format!("{};", declaration),
+        })
+    }
+
+    fn enumeration(&mut self, path: &Path, variants: &[Variant]) {
+        let c = path.join("_");
+
+        if variants.iter().any(|v| !v.fields.is_empty()) {
+            return self.unsupported(format!("the variants of `{}` carry fields, which C can't express", path.name()))
+        }
+
+        let names: Vec<String> = variants.iter().map(|v| format!("{}_{}", c, v.name)).collect();
+
+        self.header.push(format!("typedef enum {{ {} }} {};", names.join(", "), c))
+    }
+
+    /// The struct of a class, with a vtable when it has methods, and the functions making
+    /// its objects.
+    fn structure(&mut self, name: &str) {
+        let class = match self.classes.get(name) {
+            Some(c) => c.clone(),
+            None    => return,
+        };
+
+        let depth  = self.chain(name).len() - 1;
+        let root   = vec!("base."; depth).concat();
+        let table  = vec!(".base"; depth).concat();
+
+        let mut header = vec!(format!("typedef struct {0} {0};", name));
+
+        if !class.plain {
+            let mut slots = vec!(match class.parent {
+                Some(ref p) => format!("\tstruct {}_vtable base;", p),
+                None        => "\tconst char* name;".to_owned(),
+            });
+
+            for &(ref m, ref s) in class.methods.iter() {
+                if self.slot(name, m).as_ref().map(|s| &s[..]) == Some(name) {
+                    slots.push(format!("\t{};", C::prototype(&format!("(*{})", m), s, Some(name))))
+                }
+            }
+
+            header.push(format!("struct {}_vtable {{\n{}\n}};", name, slots.join("\n")));
+        }
+
+        let mut members = Vec::new();
+
+        match class.parent {
+            Some(ref p)            => members.push(format!("\t{} base;", p)),
+            None if !class.plain   => members.push(format!("\tconst struct {}_vtable* vtable;", name)),
+            None                   => (),
+        }
+
+        for &(ref f, ref k) in class.fields.iter() {
+            members.push(format!("\t{};", C::declarator(f, k)))
+        }
+
+        // C has no empty structs
+        if members.is_empty() {
+            members.push("\tchar empty_;".to_owned())
+        }
+
+        header.push(format!("struct {} {{\n{}\n}};", name, members.join("\n")));
+
+        let constructor = class.constructor.clone().unwrap_or(Signature {
+            params: Vec::new(),
+            retty:  Kind::Void,
+        });
+
+        let make = C::prototype(&format!("{}_new", name), &Signature {
+            params: constructor.params,
+            retty:  Kind::Object(name.to_owned()),
+        }, None);
+
+        if !class.plain {
+            header.push(format!("extern const struct {0}_vtable {0}_methods;", name));
+        }
+
+        header.push(format!("void {0}_init({0}* self);", name));
+        header.push(format!("{};", make));
+
+        if !class.plain {
+            header.push(format!("{0} {0}_sliced({0} value);", name));
+        }
+
+        for &(ref m, ref s) in class.methods.iter() {
+            header.push(format!("{};", C::prototype(&format!("{}_{}", name, m), s, Some(name))));
+
+            // an override goes into the vtable of the class it overrides through a function
+            // taking that class
+            let slot = match self.slot(name, m) {
+                Some(ref slot) if slot != name => slot.clone(),
+                _                              => continue,
+            };
+
+            let signature = self.method(&slot, m).unwrap();
+            let thunk     = C::prototype(&format!("{}_{}_as_{}", name, m, slot), &signature, Some(&slot));
+
+            header.push(format!("{};", thunk));
+
+            if class.implemented.contains(m) {
+                let args: Vec<String> = signature.params.iter().map(|&(_, ref n)| n.clone()).collect();
+                let call = format!("{}_{}(({}*)self{})", name, m, name,
+                                   args.iter().map(|a| format!(", {}", a)).collect::<String>());
+
+                self.tail.push(match signature.retty {
+                    Kind::Void => format!("{} {{\n\t{};\n}}", thunk, call),
+                    _          => format!("{} {{\n\treturn {};\n}}", thunk, call),
+                })
+            }
+        }
+
+        self.header.push(header.join("\n"));
+
+        let mut init = vec!(format!("void {0}_init({0}* self) {{", name), "\tmemset(self, 0, sizeof *self);".to_owned());
+
+        if let Some(ref p) = class.parent {
+            init.push(format!("\t{}_init(&self->base);", p))
+        }
+
+        if !class.plain {
+            self.tail.push(format!("const struct {0}_vtable {0}_methods = {1};", name, self.table(name, name)));
+
+            init.push(format!("\tself->{}vtable = &{}_methods{};", root, name, table));
+        }
+
+        for &(ref f, ref k) in class.fields.iter() {
+            if let Some(s) = C::initialize(&format!("self->{}", f), k, 0) {
+                init.push(format!("\t{}", s))
+            }
+        }
+
+        init.push("}".to_owned());
+
+        self.tail.push(init.join("\n"));
+
+        if class.constructor.is_none() {
+            self.tail.push(format!("{} {{\n\t{1} self_;\n\t{1}_init(&self_);\n\treturn self_;\n}}", make, name))
+        }
+
+        if !class.plain {
+            self.tail.push(format!(
+                "{0} {0}_sliced({0} value) {{\n\tvalue.{1}vtable = &{0}_methods{2};\n\treturn value;\n}}",
+                name, root, table,
+            ))
+        }
+    }
+
+    /// The initializer of the vtable of `class`, from the part of it laid out by `level`
+    /// up. A method declared but given no body here is left out as `NULL`.
+    fn table(&self, class: &str, level: &str) -> String {
+        let record = &self.classes[level];
+
+        let mut entries = vec!(match record.parent {
+            Some(ref p) => self.table(class, p),
+            None        => format!("\"{}\"", class),
+        });
+
+        for &(ref m, _) in record.methods.iter() {
+            if self.slot(level, m).as_ref().map(|s| &s[..]) != Some(level) {
+                continue
+            }
+
+            let implementer = self.implementer(class, m).unwrap();
+
+            entries.push(if !self.classes[&implementer].implemented.contains(m) {
+                "NULL".to_owned()
+            } else if implementer == level {
+                format!("{}_{}", level, m)
+            } else {
+                format!("{}_{}_as_{}", implementer, m, level)
+            })
+        }
+
+        format!("{{ {} }}", entries.join(", "))
+    }
+
+    /// Writes the methods and constructor of a class given a body.
+    fn implementation(&mut self, class: &str, methods: &[Function]) {
+        for m in methods.iter() {
+            self.position = m.position;
+
+            if m.body.is_none() {
+                continue
+            }
+
+            if !m.generics.is_empty() {
+                self.unsupported(format!("`{}` is generic, which C can't express", m.name()));
+            } else if m.constructor {
+                self.constructor(class, m)
+            } else {
+                self.function(m, Some(class))
+            }
+        }
+    }
+
+    /// Writes the body of a function, or of a method of `class`; only functions get a
+    /// prototype here, as methods are declared along with their class.
+    fn function(&mut self, f: &Function, class: Option<&str>) {
+        let signature = self.signature(f);
+        let name      = f.path.join("_");
+
+        let head = if class.is_none() && name == "main" && f.params.is_empty() {
+            "int main(void)".to_owned()
+        } else {
+            C::prototype(&name, &signature, class)
+        };
+
+        if class.is_none() && name != "main" {
+            self.header.push(format!("{};", head))
+        }
+
+        if let Some(ref body) = f.body {
+            let lines = self.body(head, None, &signature, body);
+
+            self.source.push(lines)
+        }
+    }
+
+    fn constructor(&mut self, class: &str, f: &Function) {
+        let signature = self.signature(f);
+
+        let head = C::prototype(&format!("{}_new", class), &Signature {
+            params: signature.params.clone(),
+            retty:  Kind::Object(class.to_owned()),
+        }, None);
+
+        if let Some(ref body) = f.body {
+            let lines = self.body(head, Some(class), &signature, body);
+
+            self.source.push(lines)
+        }
+    }
+
+    /// The lines of a function; a constructor, which is given its class, makes the object it
+    /// gives back first.
+    fn body(&mut self, head: String, constructing: Option<&str>, signature: &Signature, body: &[Statement]) -> String {
+        let scope = signature.params.iter().map(|&(_, ref n)| (n.clone(), n.clone())).collect();
+
+        self.locals       = vec!(scope);
+        self.constructing = constructing.is_some();
+        self.depth        = 1;
+
+        let mut lines = vec!(format!("#line {}", self.position.0), format!("{} {{", head));
+
+        if let Some(c) = constructing {
+            lines.push(format!("\t{} self_;", c));
+            lines.push(format!("\t{}* self = &self_;", c));
+            lines.push(format!("\t{}_init(self);", c));
+        }
+
+        for s in body.iter() {
+            self.statement(s, &mut lines)
+        }
+
+        lines.push("}".to_owned());
+
+        self.locals.clear();
+        self.constructing = false;
+        self.depth        = 0;
+
+        lines.join("\n")
+    }
+
+    /// Translates the statements of a block nested in another, with a local of its own if
+    /// given one.
+    fn nested(&mut self, body: &[Statement], out: &mut Vec<String>, local: Option<(&str, String)>) {
+        self.locals.push(HashMap::new());
+        self.depth += 1;
+
+        if let Some((n, s)) = local {
+            self.local(n, s)
+        }
+
+        for s in body.iter() {
+            self.statement(s, out)
+        }
+
+        self.depth -= 1;
+        self.locals.pop();
+    }
+
+    /// A declaration or assignment as C writes it, without the `;` after it, or none for
+    /// one that takes more than that.
+    fn simple(&mut self, st: &Statement) -> Option<String> {
+        match *st {
+            Statement::Let(_, _, Some(Expression { node: Node::Array(_), .. }), _) => None,
+
+            Statement::Let(ref n, ref t, Some(ref e), _) => {
+                let kind = self.kind(t);
+
+                if kind == Kind::Unknown || kind == Kind::Void {
+                    self.fail(code::TYPE, format!("the type of `{}` isn't known", n),
+                              "write it down, like `var x: int = f()`");
+                }
+
+                let code = self.expression(e);
+
+                self.local(n, n.clone());
+                self.arrays.remove(n);
+
+                Some(format!("{} = {}", C::declarator(n, &kind), code))
+            }
+
+            Statement::Assign(ref n, None, ref e, _) => {
+                let target = self.expression(n);
+                let code   = self.expression(e);
+
+                Some(format!("{} = {}", target, code))
+            }
+
+            Statement::Assign(ref n, Some(ref o), ref e, _) => {
+                let target = self.expression(n);
+                let code   = self.expression(e);
+
+                Some(match (&n.ty, o) {
+                    (&Type::Text, &Operator::Plus) => format!("{0} = helix_concat({0}, {1})", target, code),
+                    _                              => format!("{} {}= {}", target, operator(o), code),
+                })
+            }
+
+            _ => None,
+        }
+    }
+
+    fn statement(&mut self, st: &Statement, out: &mut Vec<String>) {
+        self.position = st.position();
+
+        out.push(format!("#line {}", self.position.0));
+
+        if let Some(s) = self.simple(st) {
+            return self.line(out, format!("{};", s))
+        }
+
+        match *st {
+            Statement::Let(ref n, ref t, ref e, _) => {
+                let k           = self.declared(t, e.as_ref());
+                let declaration = C::declarator(n, &k);
+
+                match k {
+                    Kind::Array(_, _) => self.arrays.insert(n.clone(), k.clone()),
+                    _                 => self.arrays.remove(n),
+                };
+
+                match (C::initialize(n, &k, 0), &k) {
+                    (Some(ref s), &Kind::Array(_, _)) => {
+                        self.line(out, format!("{};", declaration));
+                        self.line(out, s.clone());
+                    }
+
+                    (Some(s), _) => self.line(out, format!("{} = {}", declaration, &s[n.len() + 3 ..])),
+                    (None, _)    => self.line(out, format!("{};", declaration)),
+                }
+
+                self.local(n, n.clone())
+            }
+
+            Statement::Expression(ref e, _) => {
+                let code = self.expression(e);
+
+                self.line(out, format!("{};", code))
+            }
+
+            Statement::If(ref e, ref c, ref otherwise, _) => {
+                let condition = self.expression(e);
+
+                self.line(out, format!("if ({}) {{", condition));
+                self.nested(c, out, None);
+
+                let mut otherwise = otherwise;
+
+                // an `if` alone in the `else` of another is an `else if`
+                while let [Statement::If(ref e, ref c, ref rest, _)] = otherwise[..] {
+                    let condition = self.expression(e);
+
+                    self.line(out, format!("}} else if ({}) {{", condition));
+                    self.nested(c, out, None);
+
+                    otherwise = rest
+                }
+
+                if !otherwise.is_empty() {
+                    self.line(out, "} else {".to_owned());
+                    self.nested(otherwise, out, None);
+                }
+
+                self.line(out, "}".to_owned())
+            }
+
+            Statement::Loop(None, ref e, None, ref c, _) => {
+                let condition = self.expression(e);
+
+                self.line(out, format!("while ({}) {{", condition));
+                self.nested(c, out, None);
+                self.line(out, "}".to_owned())
+            }
+
+            // what comes before the loop is a local of the loop alone
+            Statement::Loop(ref i, ref e, ref s, ref c, _) => {
+                self.locals.push(HashMap::new());
+
+                let init      = i.as_ref().and_then(|i| self.simple(i)).unwrap_or_default();
+                let condition = self.expression(e);
+                let step      = s.as_ref().and_then(|s| self.simple(s)).unwrap_or_default();
+
+                self.line(out, format!("for ({}; {}; {}) {{", init, condition, step));
+                self.nested(c, out, None);
+                self.line(out, "}".to_owned());
+
+                self.locals.pop();
+            }
+
+            // the element is reached through a pointer, so assigning to it changes the array
+            // like the reference C++ iterates with does
+            // each element is a copy, like the interpreter binds it, but for arrays, which C
+            // can't copy and are gone through in place
+            Statement::Each(ref i, ref t, ref e, ref c, _) => {
+                let code    = self.expression(e);
+                let pointer = format!("{}_", i);
+
+                let element = match self.array(e).unwrap_or_else(|| self.kind(&e.ty)) {
+                    Kind::Array(t, size) => {
+                        let declared = match *t {
+                            Kind::Array(_, _) => C::declarator(&format!("(*{})", pointer), &t),
+                            ref t             => format!("{}* {}", t.c(), pointer),
+                        };
+
+                        self.line(out, format!("for ({0} = {1}; {2} < {1} + {3}; {2}++) {{", declared, code, pointer, size));
+
+                        *t
+                    }
+
+                    Kind::Text => {
+                        self.line(out, format!("for (const char* {0} = {1}; *{0}; {0}++) {{", pointer, code));
+
+                        self.kind(t)
+                    }
+
+                    k => return self.unsupported(format!("C can't go through the elements of `{}`", k)),
+                };
+
+                match element {
+                    Kind::Array(_, _) => self.nested(c, out, Some((i, format!("(*{})", pointer)))),
+
+                    element => {
+                        self.depth += 1;
+                        self.line(out, format!("{} = *{};", C::declarator(i, &element), pointer));
+                        self.depth -= 1;
+
+                        self.nested(c, out, Some((i, i.clone())))
+                    }
+                }
+
+                self.line(out, "}".to_owned())
+            }
+
+            Statement::Match(ref e, ref arms, _) => self.arms(e, arms, out),
+
+            Statement::Break(_)    => self.line(out, "break;".to_owned()),
+            Statement::Continue(_) => self.line(out, "continue;".to_owned()),
+
+            Statement::Return(_, _) if self.constructing => self.line(out, "return self_;".to_owned()),
+
+            Statement::Return(Some(ref e), _) => {
+                let code = self.expression(e);
+
+                self.line(out, format!("return {};", code))
+            }
+
+            Statement::Return(None, _) => self.line(out, "return;".to_owned()),
+
+            // every name is already resolved to what it refers to
+            Statement::Use(_, _) => (),
+
+            // written by `simple`
+            Statement::Assign(_, _, _, _) => (),
+        }
+    }
+
+    /// A `match` on a plain enum, as a cascade of tests on a copy of the value.
+    fn arms(&mut self, subject: &Expression, arms: &[Arm], out: &mut Vec<String>) {
+        let code = self.expression(subject);
+
+        let enumeration = match self.kind(&subject.ty) {
+            Kind::Enum(e) => e,
+            k             => return self.unsupported(format!("C can't match on `{}`", k)),
+        };
+
+        self.line(out, "{".to_owned());
+        self.depth += 1;
+        self.line(out, format!("{} match_ = {};", enumeration, code));
+
+        for (j, a) in arms.iter().enumerate() {
+            // the checker makes sure every variant has an arm, so the last one needs no test
+            let test = match a.variant {
+                Some(ref v) if j + 1 < arms.len() => format!("if (match_ == {}_{}) ", enumeration, v),
+                _                                 => "".to_owned(),
+            };
+
+            self.line(out, format!("{}{}{{", if j > 0 { "} else " } else { "" }, test));
+            self.nested(&a.body, out, None);
+        }
+
+        self.line(out, "}".to_owned());
+        self.depth -= 1;
+        self.line(out, "}".to_owned())
+    }
+
+    /// The C of an expression.
+    fn expression(&mut self, ex: &Expression) -> String {
+        match ex.node {
+            Node::Integer(i)   => i.to_string(),
+            Node::Float(f)     => format!("{:?}", f),
+            Node::Boolean(b)   => b.to_string(),
+            Node::Text(ref t)  => quoted(t, Target::C),
+
+            Node::Local(ref n) => {
+                for scope in self.locals.iter().rev() {
+                    if let Some(c) = scope.get(n) {
+                        return c.clone()
+                    }
+                }
+
+                n.clone()
+            }
+
+            Node::Global(ref p) | Node::Function(ref p) => p.join("_"),
+            Node::Foreign(ref n)                        => n.replace("::", "_"),
+            Node::This                                  => "(*self)".to_owned(),
+
+            Node::Field(ref o, ref owner, ref n) => {
+                let receiver = self.receiver(o);
+
+                let path = match o.ty {
+                    Type::Object(ref c, _) => self.upcast(&c.path().join("_"), &owner.join("_")).unwrap_or_default(),
+                    _                      => String::new(),
+                };
+
+                member(&receiver, &join(&path, n))
+            }
+
+            Node::Member(ref o, ref n) => {
+                let code = self.expression(o);
+
+                format!("{}.{}", code, n)
+            }
+
+            Node::Variant(ref e, ref v, ref a) => {
+                if !a.is_empty() {
+                    self.unsupported(format!("the variants of `{}` carry fields, which C can't express", e.name()))
+                }
+
+                format!("{}_{}", e.join("_"), v)
+            }
+
+            Node::Binary(ref l, ref o, ref r) => {
+                let left  = self.expression(l);
+                let right = self.expression(r);
+
+                if l.ty == Type::Text || r.ty == Type::Text {
+                    return match *o {
+                        Operator::Plus => format!("helix_concat({}, {})", left, right),
+
+                        Operator::Equal | Operator::NEqual | Operator::Lt | Operator::LtEqual |
+                        Operator::Gt | Operator::GtEqual => format!("(helix_compare({}, {}) {} 0)", left, right, operator(o)),
+
+                        _ => format!("({} {} {})", left, operator(o), right),
+                    }
+                }
+
+                format!("({} {} {})", left, operator(o), right)
+            }
+
+            Node::Unary(ref o, ref e) => {
+                let code = self.expression(e);
+
+                format!("({}{})", operator(o), code)
+            }
+
+            // C converts numbers on its own; objects are cut down to their base class like
+            // C++ does
+            Node::Convert(ref e) => {
+                let code = self.expression(e);
+
+                if let (&Type::Object(ref f, _), &Type::Object(ref t, _)) = (&e.ty, &ex.ty) {
+                    let (f, t) = (f.path().join("_"), t.path().join("_"));
+
+                    if let Some(path) = self.upcast(&f, &t) {
+                        return format!("{}_sliced({}.{})", t, code, path)
+                    }
+                }
+
+                code
+            }
+
+            Node::Index(ref a, ref i) => {
+                let base  = self.expression(a);
+                let index = self.expression(i);
+
+                format!("{}[{}]", base, index)
+            }
+
+            Node::Call(ref p, ref g, ref a) | Node::New(ref p, ref g, ref a) => {
+                if !g.is_empty() {
+                    self.unsupported(format!("`{}` is generic, which C can't express", p.name()));
+
+                    return String::new()
+                }
+
+                let arguments = self.arguments(a);
+
+                match ex.node {
+                    Node::New(_, _, _) => format!("{}_new({})", p.join("_"), arguments.join(", ")),
+                    _                  => format!("{}({})", p.join("_"), arguments.join(", ")),
+                }
+            }
+
+            Node::Method(ref o, _, ref m, ref a) => match o.ty {
+                Type::Object(ref c, _) => {
+                    let receiver = self.receiver(o);
+
+                    self.dispatch(receiver, &c.path().join("_"), m, a)
+                }
+
+                _ => String::new(),
+            },
+
+            Node::Invoke(ref o, ref m, ref a) => {
+                let receiver = self.receiver(o);
+
+                match self.kind(&o.ty) {
+                    Kind::Text if (m == "size" || m == "length") && a.is_empty() => {
+                        format!("(int)strlen({})", member(&receiver, ""))
+                    }
+
+                    Kind::Object(ref c) => {
+                        let message = format!("no method named `{}` on `{}`", m, c);
+
+                        self.fail(code::UNDEFINED, message, "");
+
+                        String::new()
+                    }
+
+                    k => {
+                        self.unsupported(format!("C can't call `{}` on `{}`", m, k));
+
+                        String::new()
+                    }
+                }
+            }
+
+            Node::Apply(ref f, ref a) => {
+                let function  = self.expression(f);
+                let arguments = self.arguments(a);
+
+                format!("{}({})", function, arguments.join(", "))
+            }
+
+            // arrays are made by their declarations
+            Node::Array(_) => String::new(),
+
+            Node::List(_) | Node::Map(_) => {
+                self.unsupported("C has no lists or maps".to_owned());
+
+                String::new()
+            }
+
+            Node::Lambda(_, _) => {
+                self.unsupported("C has no lambdas".to_owned());
+
+                String::new()
+            }
+        }
+    }
+
+    fn arguments(&mut self, args: &[Expression]) -> Vec<String> {
+        args.iter().map(|a| self.expression(a)).collect()
+    }
+
+    /// The object a member is taken from: `self` in a method, a temporary for an object made
+    /// by a call, whose address is taken, or anything else as it is.
+    fn receiver(&mut self, object: &Expression) -> Receiver {
+        let code = self.expression(object);
+
+        match object.node {
+            Node::This => Receiver::Pointer("self".to_owned()),
+
+            Node::Call(_, _, _) | Node::New(_, _, _) | Node::Method(_, _, _, _) | Node::Apply(_, _) => {
+                let kind = self.kind(&object.ty);
+
+                Receiver::Temporary(format!("(({}[]){{ {} }})", kind.c(), code))
+            }
+
+            _ => Receiver::Value(code),
+        }
+    }
+
+    /// Calls a method through the vtable of the object, or straight to the version its class
+    /// runs when the object is a temporary only reached once.
+    fn dispatch(&mut self, receiver: Receiver, class: &str, name: &str, args: &[Expression]) -> String {
+        let (slot, implementer) = match (self.slot(class, name), self.implementer(class, name)) {
+            (Some(s), Some(i)) => (s, i),
+
+            _ => {
+                if self.error.is_none() {
+                    let (line, col) = self.position;
+
+                    self.error = Some(Diagnostic::error(code::UNDEFINED, format!("no method named `{}` on `{}`", name, class))
+                        .at(line, col, 0))
+                }
+
+                return String::new()
+            }
+        };
+
+        let arguments = self.arguments(args);
+
+        let (function, this) = match receiver {
+            Receiver::Temporary(_) => {
+                let path = self.upcast(class, &implementer).unwrap_or_default();
+
+                (format!("{}_{}", implementer, name), address(&receiver, &path))
+            }
+
+            _ => {
+                let chain  = self.chain(class);
+                let vtable = member(&receiver, &join(&vec!("base"; chain.len() - 1).join("."), "vtable"));
+                let path   = self.upcast(class, &slot).unwrap_or_default();
+
+                let table = if chain.last() == Some(&slot) {
+                    vtable
+                } else {
+                    format!("((const struct {}_vtable*){})", slot, vtable)
+                };
+
+                (format!("{}->{}", table, name), address(&receiver, &path))
+            }
+        };
+
+        let mut all = vec!(this);
+
+        all.extend(arguments);
+
+        format!("{}({})", function, all.join(", "))
+    }
+}
+
+impl Backend for C {
+    fn import(&mut self, items: &[Item]) {
+        // mistakes in an imported file are reported when it is translated itself
+        let error = self.error.take();
+
+        self.declare(items);
+
+        self.error = error;
+    }
+
+    fn make_environment(&mut self, items: Vec<Item>) -> Result<(), Diagnostic> {
+        self.declare(&items);
+        self.emit(&items);
+
+        match self.error.take() {
+            Some(e) => Err(e),
+            None    => Ok(()),
+        }
+    }
+
+    fn translate(&mut self) -> (String, String) {
+        let mut source = format!("#include \"{}.h\"\n#line 1 \"{}\"\n", self.title, self.file);
+
+        for s in self.source.iter().chain(self.tail.iter()) {
+            source.push_str(&format!("\n{}\n", s))
+        }
+
+        let mut header = format!("#ifndef {0}_H\n#define {0}_H\n", self.guard);
+
+        for i in self.includes.iter() {
+            header.push_str(&format!("#include {}\n", i))
+        }
+
+        header.push_str(&format!("{}\n", RUNTIME));
+
+        for h in self.header.iter() {
+            header.push_str(&format!("\n{}\n", h))
+        }
+
+        header.push_str("\n#endif\n");
+
+        (source, header)
+    }
+
+    fn extensions(&self) -> (&'static str, &'static str) {
+        ("c", "h")
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    match path {
+        "" => name.to_owned(),
+        p  => format!("{}.{}", p, name),
+    }
+}
+
+/// A member of an object, like `x.base.count` or `self->count`; an empty path is the
+/// object itself.
+fn member(receiver: &Receiver, path: &str) -> String {
+    match (receiver, path) {
+        (&Receiver::Value(ref v), "")                                      => v.clone(),
+        (&Receiver::Value(ref v), p)                                       => format!("{}.{}", v, p),
+        (&Receiver::Pointer(ref p), "") | (&Receiver::Temporary(ref p), "") => format!("(*{})", p),
+        (&Receiver::Pointer(ref p), m) | (&Receiver::Temporary(ref p), m)   => format!("{}->{}", p, m),
+    }
+}
+
+/// The address of a member of an object, which a method takes as `self`.
+fn address(receiver: &Receiver, path: &str) -> String {
+    match (receiver, path) {
+        (&Receiver::Pointer(ref p), "") | (&Receiver::Temporary(ref p), "") => p.clone(),
+        (r, p)                                                             => format!("&{}", member(r, p)),
+    }
+}
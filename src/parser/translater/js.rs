@@ -0,0 +1,1275 @@
+use std::mem;
+use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+
+use super::super::ast::Position;
+use super::super::ir::{Path, Type, Expression, Node, Statement, Arm, Item, Function, Class, Variant};
+use super::super::token::Operator;
+use super::super::diagnostic::{Diagnostic, code};
+use super::super::loader;
+
+use super::{Backend, Target, operator, quoted};
+
+/// What every module needs to behave like the C++ does: `printf` and `puts` writing to
+/// standard output, or to the console in a browser, maps giving the value C++ would put in
+/// for a key that isn't there, and values copied where C++ copies them.
+const RUNTIME: &'static str = "let helix_pending = \"\";
+
+function helix_write(text) {
+\tif (typeof process !== \"undefined\" && process.stdout) {
+\t\tprocess.stdout.write(text);
+\t\treturn;
+\t}
+
+\tconst lines = (helix_pending + text).split(\"\\n\");
+
+\thelix_pending = lines.pop();
+
+\tfor (const line of lines) {
+\t\tconsole.log(line);
+\t}
+}
+
+function helix_format(flags, width, precision, conversion, value) {
+\tlet text;
+
+\tswitch (conversion) {
+\t\tcase \"d\": case \"i\": case \"u\": text = String(Math.trunc(value)); break;
+\t\tcase \"f\": case \"F\": text = Number(value).toFixed(precision === undefined ? 6 : Number(precision)); break;
+\t\tcase \"e\": case \"E\":
+\t\t\ttext = Number(value).toExponential(precision === undefined ? 6 : Number(precision)).replace(/e([+-])(\\d)$/, \"e$10$2\");
+\t\t\tbreak;
+\t\tcase \"g\": case \"G\": text = String(Number(value)); break;
+\t\tcase \"x\": case \"X\": text = (value >>> 0).toString(16); break;
+\t\tcase \"o\": text = (value >>> 0).toString(8); break;
+\t\tcase \"c\": text = typeof value === \"number\" ? String.fromCharCode(value) : String(value); break;
+\t\tdefault: text = String(value);
+\t}
+
+\tif (conversion === \"X\" || conversion === \"E\" || conversion === \"G\") {
+\t\ttext = text.toUpperCase();
+\t}
+
+\tconst numeric = \"diufFeEgG\".includes(conversion);
+
+\tif (numeric && flags.includes(\"+\") && !text.startsWith(\"-\")) {
+\t\ttext = \"+\" + text;
+\t}
+
+\tconst pad = Number(width || 0) - text.length;
+
+\tif (pad <= 0) {
+\t\treturn text;
+\t} else if (flags.includes(\"-\")) {
+\t\treturn text + \" \".repeat(pad);
+\t} else if (numeric && flags.includes(\"0\")) {
+\t\tconst sign = /^[+-]/.test(text) ? text[0] : \"\";
+\t\treturn sign + \"0\".repeat(pad) + text.slice(sign.length);
+\t}
+
+\treturn \" \".repeat(pad) + text;
+}
+
+function printf(format, ...values) {
+\tlet next = 0;
+
+\tconst text = String(format).replace(/%([-+ 0#]*)(\\d+)?(?:\\.(\\d+))?(?:hh|h|ll|l|z)?([diufFeEgGxXocs%])/g,
+\t\t(_, flags, width, precision, conversion) =>
+\t\t\tconversion === \"%\" ? \"%\" : helix_format(flags, width, precision, conversion, values[next++]));
+
+\thelix_write(text);
+
+\treturn text.length;
+}
+
+function puts(text) {
+\thelix_write(String(text) + \"\\n\");
+
+\treturn 0;
+}
+
+function helix_entry(map, key, fallback) {
+\tif (!map.has(key)) {
+\t\tmap.set(key, fallback);
+\t}
+
+\treturn map.get(key);
+}
+
+function helix_copy(value) {
+\tif (Array.isArray(value)) {
+\t\treturn value.map(helix_copy);
+\t} else if (value instanceof Map) {
+\t\treturn new Map([...value].map(([k, v]) => [k, helix_copy(v)]));
+\t} else if (value !== null && typeof value === \"object\" && typeof value.helix_clone === \"function\") {
+\t\treturn value.helix_clone();
+\t}
+
+\treturn value;
+}";
+
+/// Finds a name used but not defined by the program in the modules libraries are mapped to,
+/// or among the globals of JavaScript itself.
+const LIBRARIES: &'static str = "function helix_library(name, ...modules) {
+\tfor (const library of modules) {
+\t\tif (name in library) {
+\t\t\treturn library[name];
+\t\t}
+\t}
+
+\treturn globalThis[name];
+}";
+
+/// The libraries of C and C++ whose functions the runtime stands in for, or that JavaScript
+/// has no use for, so importing them needs no module.
+const STANDARD: [&'static str; 28] = [
+    "iostream", "string", "vector", "map", "unordered_map", "functional", "tuple", "algorithm",
+    "memory", "utility", "cassert", "cctype", "cerrno", "cfloat", "climits", "cmath", "cstdarg",
+    "cstddef", "cstdint", "cstdio", "cstdlib", "cstring", "assert.h", "ctype.h", "math.h",
+    "stdint.h", "stdio.h", "stdlib.h",
+];
+
+/// The JavaScript backend, writing each file as an ES module. Modules become objects made
+/// by a closure, and the methods of `implement` blocks are merged into the ES class of the class they
+/// implement. JavaScript has a single kind of number, so the types the IR gives values are
+/// used to divide and convert integers the way C++ does.
+pub struct JavaScript {
+    file:            String,
+    imports:         Vec<String>,
+    source:          Vec<String>,
+
+    /// The modules imported libraries are mapped to, by the name of the library.
+    libraries:       HashMap<String, String>,
+    /// The modules of the libraries imported so far.
+    loaded:          Vec<String>,
+    /// The names used but defined nowhere in the program, which come from the libraries.
+    foreign:         Vec<String>,
+    /// The names each imported Helix file exports, in the order they were imported.
+    imported:        Vec<Vec<String>>,
+    /// The Helix files imported by the file so far.
+    modules_used:    Vec<String>,
+
+    /// Everything the program defines, for telling how it is spelled from a module.
+    symbols:         HashSet<Path>,
+    enums:           HashMap<Path, Vec<Variant>>,
+    /// The functions of the `implement` blocks of the file, by the class they are for.
+    implementations: HashMap<Path, Vec<Function>>,
+    /// The classes the file defines, which get their methods merged into them.
+    defined:         HashSet<Path>,
+    namespace:       Path,
+    importing:       bool,
+
+    /// The locals in sight, each with how it is spelled in JavaScript.
+    locals:          Vec<HashMap<String, String>>,
+    depth:           usize,
+    position:        Position,
+    error:           Option<Diagnostic>,
+}
+
+impl JavaScript {
+    pub fn new(_title: String, file: String) -> JavaScript {
+        JavaScript {
+            file:            file,
+            imports:         Vec::new(),
+            source:          Vec::new(),
+
+            libraries:       HashMap::new(),
+            loaded:          Vec::new(),
+            foreign:         Vec::new(),
+            imported:        Vec::new(),
+            modules_used:    Vec::new(),
+
+            symbols:         HashSet::new(),
+            enums:           HashMap::new(),
+            implementations: HashMap::new(),
+            defined:         HashSet::new(),
+            namespace:       Path(Vec::new()),
+            importing:       false,
+
+            locals:          Vec::new(),
+            depth:           0,
+            position:        (0, 0),
+            error:           None,
+        }
+    }
+
+    /// Keeps the first error; everything after it is still translated, but not written.
+    fn fail(&mut self, code: &'static str, message: String, note: &str) {
+        if self.error.is_none() {
+            let (line, col) = self.position;
+
+            self.error = Some(Diagnostic::error(code, message).at(line, col, 0).note(note.to_owned()))
+        }
+    }
+
+    fn unsupported(&mut self, message: String) {
+        self.fail(code::UNSUPPORTED, message, "this needs `--target cpp`")
+    }
+
+    /// What a name on its own refers to from the module being gone through, looking
+    /// outwards, since the names of the modules around are in sight of the closures making
+    /// them.
+    fn visible(&self, name: &str) -> Option<Path> {
+        for i in (0 .. self.namespace.0.len() + 1).rev() {
+            let path = Path(self.namespace.0[..i].to_vec()).child(name);
+
+            if self.symbols.contains(&path) {
+                return Some(path)
+            }
+        }
+
+        None
+    }
+
+    /// How the thing at a path is spelled from the module being gone through: bare when its
+    /// name refers to it from there, and through the objects of its modules otherwise.
+    fn spell(&self, path: &Path) -> String {
+        match self.visible(path.name()) {
+            Some(ref p) if p == path => path.name().to_owned(),
+            _                        => path.join("."),
+        }
+    }
+
+    /// The value C++ would construct a variable of a type with.
+    fn default(&mut self, t: &Type) -> String {
+        match *t {
+            Type::Int | Type::Long | Type::Float | Type::Double => "0".to_owned(),
+            Type::Bool                                          => "false".to_owned(),
+            Type::Text | Type::Char                             => "\"\"".to_owned(),
+            Type::List(_) | Type::Array(_)                      => "[]".to_owned(),
+            Type::Map(_, _)                                     => "new Map()".to_owned(),
+            Type::Object(ref c, _)                              => format!("new {}()", self.spell(&c.path())),
+            Type::Function(_, _)                                => "null".to_owned(),
+
+            Type::Enum(ref e) => {
+                let e     = e.path();
+                let first = self.enums.get(&e).and_then(|v| v.first().cloned());
+
+                match first {
+                    Some(ref v) if v.fields.is_empty() => format!("{}.{}", self.spell(&e), v.name),
+
+                    Some(v) => {
+                        let values: Vec<String> = v.fields.iter().map(|&(_, ref t)| self.default(t)).collect();
+
+                        format!("{}.{}({})", self.spell(&e), v.name, values.join(", "))
+                    }
+
+                    None => "undefined".to_owned(),
+                }
+            }
+
+            _ => "undefined".to_owned(),
+        }
+    }
+
+    /// Arrays of the given sizes, from the outermost in, each element made the way C++ makes
+    /// it.
+    fn array(&mut self, sizes: &[Expression], t: &Type) -> String {
+        match (sizes.split_first(), t) {
+            (Some((s, rest)), &Type::Array(ref e)) => {
+                let size  = self.expression(s);
+                let value = self.array(rest, e);
+
+                format!("Array.from({{ length: {} }}, () => {})", size, value)
+            }
+
+            _ => self.default(t),
+        }
+    }
+
+    /// Makes the names a file defines known before any of it is written, since how they are
+    /// spelled depends on where they are defined. The `implement` blocks of the file itself
+    /// are kept, so their methods can go into their class.
+    fn declare(&mut self, items: &[Item]) {
+        for i in items.iter() {
+            match *i {
+                Item::Global(ref p, _, _, _) => {
+                    self.symbols.insert(p.clone());
+                }
+
+                Item::Function(ref f) => {
+                    self.symbols.insert(f.path.clone());
+                }
+
+                Item::Module(ref p, ref c, _) => {
+                    self.symbols.insert(p.clone());
+                    self.declare(c)
+                }
+
+                Item::Class(ref c) => {
+                    self.symbols.insert(c.path.clone());
+
+                    if !self.importing {
+                        self.defined.insert(c.path.clone());
+                    }
+                }
+
+                Item::Enum(ref p, ref v, _) => {
+                    self.symbols.insert(p.clone());
+                    self.enums.insert(p.clone(), v.clone());
+                }
+
+                Item::Implement(ref p, _, ref c, _) if !self.importing => {
+                    self.implementations.entry(p.clone()).or_insert_with(Vec::new).extend(c.iter().cloned())
+                }
+
+                _ => (),
+            }
+        }
+    }
+
+    /// Converts a value to the type it is stored as, cutting the fraction off a number put
+    /// into an integer like C++ does.
+    fn coerce(&self, code: String, from: &Type, to: &Type) -> String {
+        match *to {
+            Type::Int | Type::Long if from.is_floating() => format!("Math.trunc({})", code),
+            _                                            => code,
+        }
+    }
+
+    /// The line of `text` at the current depth.
+    fn line(&self, out: &mut Vec<String>, text: String) {
+        out.push(format!("{}{}", "\t".repeat(self.depth), text))
+    }
+
+    fn local(&mut self, name: &str, spelled: String) {
+        self.locals.last_mut().unwrap().insert(name.to_owned(), spelled);
+    }
+
+    /// Writes the definitions of the file, or of a module in it, and gives back the names a
+    /// module hands out as members of its object; variables are handed out through
+    /// accessors, so assigning them is seen from outside.
+    fn emit(&mut self, items: &[Item], out: &mut Vec<String>) -> Vec<String> {
+        let export = if self.namespace.0.is_empty() { "export " } else { "" };
+
+        let mut members = Vec::new();
+
+        for i in items.iter() {
+            match *i {
+                Item::Import(ref p, l, position) => {
+                    self.position = position;
+                    self.include(p, l)
+                }
+
+                // every name is already resolved to what it refers to
+                Item::Use(_, _) => (),
+
+                Item::Global(ref p, ref t, ref e, position) => {
+                    self.position = position;
+
+                    self.locals = vec!(HashMap::new());
+
+                    let value = match *e {
+                        Some(ref e) => self.expression(e),
+                        None        => self.default(t),
+                    };
+
+                    self.locals.clear();
+
+                    out.push(format!("{}let {} = {};", export, p.name(), value));
+
+                    members.push(accessors(p.name()))
+                }
+
+                Item::Function(ref f) => {
+                    self.position = f.position;
+
+                    let body = match f.body {
+                        Some(ref b) => b,
+                        None        => continue,
+                    };
+
+                    let head = format!("{}function {}", export, f.name());
+                    let code = self.body(head, &f.params, body);
+
+                    out.push(code);
+
+                    members.push(f.name().to_owned())
+                }
+
+                Item::Module(ref p, ref c, _) => {
+                    let mut chunks = Vec::new();
+
+                    let namespace = mem::replace(&mut self.namespace, p.clone());
+
+                    let names = self.emit(c, &mut chunks);
+
+                    self.namespace = namespace;
+
+                    let mut code = format!("{}const {} = (() => {{\n", export, p.name());
+
+                    for c in chunks.iter() {
+                        code.push_str(&format!("{}\n\n", indent(c)))
+                    }
+
+                    code.push_str(&format!("\treturn {{ {} }};\n}})();", names.join(", ")));
+
+                    out.push(code);
+
+                    members.push(p.name().to_owned())
+                }
+
+                Item::Class(ref c) => {
+                    self.position = c.position;
+
+                    let code = self.structure(c, export);
+
+                    out.push(code);
+
+                    members.push(c.path.name().to_owned())
+                }
+
+                Item::Enum(ref p, ref v, position) => {
+                    self.position = position;
+
+                    let code = self.enumeration(p.name(), v, export);
+
+                    out.push(code);
+
+                    members.push(p.name().to_owned())
+                }
+
+                Item::Implement(ref p, _, ref c, position) => {
+                    self.position = position;
+
+                    if let Some(code) = self.prototypes(p, c) {
+                        out.push(code)
+                    }
+                }
+            }
+        }
+
+        members
+    }
+
+    fn include(&mut self, path: &str, library: bool) {
+        if !library {
+            if !loader::is_module(path) || self.modules_used.iter().any(|m| m == path) {
+                return
+            }
+
+            let names = self.imported.get(self.modules_used.len()).cloned().unwrap_or_default();
+
+            let module = format!("./{}.mjs", loader::module_name(path));
+
+            self.modules_used.push(path.to_owned());
+
+            self.imports.push(match names.len() {
+                0 => format!("import \"{}\";", module),
+                _ => format!("import {{ {} }} from \"{}\";", names.join(", "), module),
+            });
+
+            return
+        }
+
+        match self.libraries.get(path).cloned() {
+            Some(module) => {
+                if !self.loaded.contains(&module) {
+                    self.imports.push(format!("import * as library_{} from \"{}\";", self.loaded.len(), module));
+                    self.loaded.push(module)
+                }
+            }
+
+            None if STANDARD.contains(&path) => (),
+
+            None => self.fail(code::UNSUPPORTED, format!("there's no JavaScript module for the library `{}`", path),
+                              &format!("map it to one, like `--library {}=./{}.mjs`", path, path)),
+        }
+    }
+
+    fn enumeration(&mut self, name: &str, variants: &[Variant], export: &str) -> String {
+        let mut values = Vec::new();
+
+        for v in variants.iter() {
+            if v.fields.is_empty() {
+                values.push(format!("\t{0}: Object.freeze({{ tag: \"{0}\" }}),", v.name));
+
+                continue
+            }
+
+            let names: Vec<&str> = v.fields.iter().map(|&(ref f, _)| f.as_str()).collect();
+
+            values.push(format!("\t{0}: ({1}) => Object.freeze({{ tag: \"{0}\", fields: [{1}] }}),", v.name, names.join(", ")));
+        }
+
+        format!("{}const {} = Object.freeze({{\n{}\n}});", export, name, values.join("\n"))
+    }
+
+    /// The ES class of a class or `struct`, with the fields C++ would construct set up in
+    /// its constructor and the methods of the file's `implement` blocks in its body.
+    fn structure(&mut self, class: &Class, export: &str) -> String {
+        let name = class.path.name();
+
+        let parent = match class.parent {
+            Some(Type::Object(ref p, _)) => Some(p.path()),
+            _                            => None,
+        };
+
+        let mut head = format!("{}class {}", export, name);
+
+        if let Some(ref p) = parent {
+            head.push_str(&format!(" extends {}", self.spell(p)))
+        }
+
+        let implemented = self.implementations.get(&class.path).cloned().unwrap_or_default();
+
+        let mut methods     = Vec::new();
+        let mut constructor = (Vec::new(), Vec::new());
+
+        for f in class.methods.iter().chain(implemented.iter()) {
+            self.position = f.position;
+
+            let body = match f.body {
+                Some(ref b) => b,
+                None        => continue,
+            };
+
+            if f.constructor {
+                constructor = (f.params.clone(), body.clone())
+            } else {
+                methods.push(self.body(f.name().to_owned(), &f.params, body))
+            }
+        }
+
+        let constructor = self.body("constructor".to_owned(), &constructor.0, &constructor.1);
+
+        // the fields are set up before the body of the constructor runs, like the members of
+        // a C++ object are
+        let mut lines = Vec::new();
+
+        if parent.is_some() {
+            lines.push("\tsuper();".to_owned())
+        }
+
+        self.locals = vec!(HashMap::new());
+
+        for f in class.fields.iter() {
+            let value = match f.value {
+                Some(ref e) => self.copied(e),
+                None        => self.default(&f.ty),
+            };
+
+            lines.push(format!("\tthis.{} = {};", f.name, value))
+        }
+
+        self.locals.clear();
+
+        let mut rest = constructor.lines().map(|l| l.to_owned());
+
+        let mut constructor: Vec<String> = rest.next().into_iter().collect();
+
+        constructor.extend(lines);
+        constructor.extend(rest);
+
+        let mut members = vec!(indent(&constructor.join("\n")));
+
+        for m in methods.iter() {
+            members.push(indent(m))
+        }
+
+        members.push(indent(&JavaScript::clone(name, class, parent.is_some())));
+
+        format!("{} {{\n{}\n}}", head, members.join("\n\n"))
+    }
+
+    /// The method copying an object field by field, like the copy constructor of a C++ class.
+    /// A derived class passes the copy it makes to the method of its parent, and the method
+    /// of a parent called on its own cuts a derived object down to the parent.
+    fn clone(name: &str, class: &Class, derived: bool) -> String {
+        let mut lines = vec!(format!("helix_clone(copy = Object.create({}.prototype)) {{", name));
+
+        if derived {
+            lines.push("\tsuper.helix_clone(copy);".to_owned())
+        }
+
+        for f in class.fields.iter() {
+            match shared(&f.ty) {
+                true  => lines.push(format!("\tcopy.{0} = helix_copy(this.{0});", f.name)),
+                false => lines.push(format!("\tcopy.{0} = this.{0};", f.name)),
+            }
+        }
+
+        lines.push("\treturn copy;".to_owned());
+        lines.push("}".to_owned());
+
+        lines.join("\n")
+    }
+
+    /// The methods of an `implement` block for a class defined elsewhere, which are put on
+    /// its prototype; a class of the file has them merged into it already.
+    fn prototypes(&mut self, class: &Path, body: &[Function]) -> Option<String> {
+        if self.defined.contains(class) {
+            return None
+        }
+
+        let spelled = self.spell(class);
+
+        let mut methods = Vec::new();
+
+        for f in body.iter() {
+            self.position = f.position;
+
+            if f.constructor {
+                self.unsupported(format!("the constructor of `{}` has to be implemented in the file defining it", class));
+
+                continue
+            }
+
+            if let Some(ref b) = f.body {
+                let head = format!("{}.prototype.{} = function", spelled, f.name());
+
+                methods.push(format!("{};", self.body(head, &f.params, b)))
+            }
+        }
+
+        Some(methods.join("\n\n"))
+    }
+
+    /// Writes a function, method or constructor, whose first line is `head`.
+    fn body(&mut self, head: String, params: &[(String, Type)], body: &[Statement]) -> String {
+        self.locals = vec!(params.iter().map(|&(ref n, _)| (n.clone(), n.clone())).collect());
+        self.depth  = 1;
+
+        let names: Vec<&str> = params.iter().map(|&(ref n, _)| n.as_str()).collect();
+
+        let mut lines = vec!(format!("{}({}) {{", head, names.join(", ")));
+
+        for s in body.iter() {
+            self.statement(s, &mut lines)
+        }
+
+        lines.push("}".to_owned());
+
+        self.locals.clear();
+        self.depth = 0;
+
+        lines.join("\n")
+    }
+
+    /// Translates the statements of a block nested in another, with a local of its own if
+    /// given one.
+    fn nested(&mut self, body: &[Statement], out: &mut Vec<String>, local: Option<(&str, String)>) {
+        self.locals.push(HashMap::new());
+        self.depth += 1;
+
+        if let Some((n, s)) = local {
+            self.local(n, s)
+        }
+
+        for s in body.iter() {
+            self.statement(s, out)
+        }
+
+        self.depth -= 1;
+        self.locals.pop();
+    }
+
+    /// A declaration or assignment as JavaScript writes it, without the `;` after it, or
+    /// none for any other statement.
+    fn simple(&mut self, st: &Statement) -> Option<String> {
+        match *st {
+            Statement::Let(ref n, ref t, ref e, _) => {
+                let value = match *e {
+                    Some(ref e) => self.copied(e),
+                    None        => self.default(t),
+                };
+
+                self.local(n, n.clone());
+
+                Some(format!("let {} = {}", n, value))
+            }
+
+            Statement::Assign(ref n, None, ref e, _) => {
+                let code = self.copied(e);
+
+                if let Some((map, key)) = self.entry(n) {
+                    return Some(format!("{}.set({}, {})", map, key, code))
+                }
+
+                let target = self.expression(n);
+
+                Some(format!("{} = {}", target, code))
+            }
+
+            // the value is combined the way the operator combines its operands, and the
+            // result cut back to an integer if that is what is stored
+            Statement::Assign(ref n, Some(ref o), ref e, _) => {
+                let right    = self.expression(e);
+                let combined = n.ty.join(&e.ty);
+
+                if let Some((map, key)) = self.entry(n) {
+                    let fallback = self.default(&n.ty);
+                    let current  = format!("helix_entry({}, {}, {})", map, key, fallback);
+                    let code     = JavaScript::operation(o, current, right, &combined);
+
+                    return Some(format!("{}.set({}, {})", map, key, self.coerce(code, &combined, &n.ty)))
+                }
+
+                let target = self.expression(n);
+
+                let simple = match *o {
+                    Operator::Div if n.ty.is_integral() => false,
+                    _                                   => !(n.ty.is_integral() && e.ty.is_floating()),
+                };
+
+                if simple {
+                    return Some(format!("{} {}= {}", target, operator(o), right))
+                }
+
+                let code = JavaScript::operation(o, target.clone(), right, &combined);
+
+                Some(format!("{} = {}", target, self.coerce(code, &combined, &n.ty)))
+            }
+
+            _ => None,
+        }
+    }
+
+    fn statement(&mut self, st: &Statement, out: &mut Vec<String>) {
+        self.position = st.position();
+
+        if let Some(s) = self.simple(st) {
+            return self.line(out, format!("{};", s))
+        }
+
+        match *st {
+            Statement::Expression(ref e, _) => {
+                let code = self.expression(e);
+
+                self.line(out, format!("{};", code))
+            }
+
+            Statement::If(ref e, ref c, ref otherwise, _) => {
+                let condition = self.expression(e);
+
+                self.line(out, format!("if ({}) {{", condition));
+                self.nested(c, out, None);
+
+                let mut otherwise = otherwise;
+
+                // an `if` alone in the `else` of another is an `else if`
+                while let [Statement::If(ref e, ref c, ref rest, _)] = otherwise[..] {
+                    let condition = self.expression(e);
+
+                    self.line(out, format!("}} else if ({}) {{", condition));
+                    self.nested(c, out, None);
+
+                    otherwise = rest
+                }
+
+                if !otherwise.is_empty() {
+                    self.line(out, "} else {".to_owned());
+                    self.nested(otherwise, out, None);
+                }
+
+                self.line(out, "}".to_owned())
+            }
+
+            Statement::Loop(None, ref e, None, ref c, _) => {
+                let condition = self.expression(e);
+
+                self.line(out, format!("while ({}) {{", condition));
+                self.nested(c, out, None);
+                self.line(out, "}".to_owned())
+            }
+
+            // what comes before the loop is a local of the loop alone
+            Statement::Loop(ref i, ref e, ref s, ref c, _) => {
+                self.locals.push(HashMap::new());
+
+                let init      = i.as_ref().and_then(|i| self.simple(i)).unwrap_or_default();
+                let condition = self.expression(e);
+                let step      = s.as_ref().and_then(|s| self.simple(s)).unwrap_or_default();
+
+                self.line(out, format!("for ({}; {}; {}) {{", init, condition, step));
+                self.nested(c, out, None);
+                self.line(out, "}".to_owned());
+
+                self.locals.pop();
+            }
+
+            // the element is reached through the list, so assigning to it changes the list
+            // like the reference C++ iterates with does
+            // each element is a copy, like C++ binds it with `auto`
+            Statement::Each(ref i, ref t, ref e, ref c, _) => {
+                let code = self.expression(e);
+
+                let element = match e.ty {
+                    Type::List(_) | Type::Array(_) => {
+                        let items = format!("items{}_", self.depth);
+                        let index = format!("i{}_", self.depth);
+
+                        self.line(out, format!("for (let {0} = 0, {1} = {2}; {0} < {1}.length; {0}++) {{", index, items, code));
+
+                        format!("{}[{}]", items, index)
+                    }
+
+                    _ => {
+                        let element = format!("{}_", i);
+
+                        self.line(out, format!("for (const {} of {}) {{", element, code));
+
+                        element
+                    }
+                };
+
+                let element = match shared(t) {
+                    true  => format!("helix_copy({})", element),
+                    false => element,
+                };
+
+                self.depth += 1;
+                self.line(out, format!("let {} = {};", i, element));
+                self.depth -= 1;
+
+                self.nested(c, out, Some((i, i.clone())));
+                self.line(out, "}".to_owned())
+            }
+
+            Statement::Match(ref e, ref arms, _) => self.arms(e, arms, out),
+
+            Statement::Break(_)    => self.line(out, "break;".to_owned()),
+            Statement::Continue(_) => self.line(out, "continue;".to_owned()),
+
+            Statement::Return(Some(ref e), _) => {
+                let code = self.copied(e);
+
+                self.line(out, format!("return {};", code))
+            }
+
+            Statement::Return(None, _) => self.line(out, "return;".to_owned()),
+
+            // every name is already resolved to what it refers to
+            Statement::Use(_, _) => (),
+
+            // written by `simple`
+            Statement::Let(_, _, _, _) | Statement::Assign(_, _, _, _) => (),
+        }
+    }
+
+    /// A `match` as a cascade of tests on the tag of the value, with the fields of the
+    /// variant taken apart for the arm.
+    fn arms(&mut self, subject: &Expression, arms: &[Arm], out: &mut Vec<String>) {
+        let code = self.expression(subject);
+
+        self.line(out, "{".to_owned());
+        self.depth += 1;
+        self.line(out, format!("const match_ = {};", code));
+
+        for (j, a) in arms.iter().enumerate() {
+            // the checker makes sure every variant has an arm, so the last one needs no test
+            let test = match a.variant {
+                Some(ref v) if j + 1 < arms.len() => format!("if (match_.tag === \"{}\") ", v),
+                _                                 => "".to_owned(),
+            };
+
+            self.line(out, format!("{}{}{{", if j > 0 { "} else " } else { "" }, test));
+
+            self.locals.push(HashMap::new());
+            self.depth += 1;
+
+            if !a.bindings.is_empty() {
+                let mut names = vec!(""; a.bindings.iter().map(|&(i, _, _)| i + 1).max().unwrap_or(0));
+
+                for &(i, ref b, _) in a.bindings.iter() {
+                    names[i] = b
+                }
+
+                self.line(out, format!("const [{}] = match_.fields;", names.join(", ")));
+
+                for &(_, ref b, _) in a.bindings.iter() {
+                    self.local(b, b.clone())
+                }
+            }
+
+            for s in a.body.iter() {
+                self.statement(s, out)
+            }
+
+            self.depth -= 1;
+            self.locals.pop();
+        }
+
+        self.line(out, "}".to_owned());
+        self.depth -= 1;
+        self.line(out, "}".to_owned())
+    }
+
+    /// The map and key of an index into a map, which is assigned with `set`.
+    fn entry(&mut self, target: &Expression) -> Option<(String, String)> {
+        if let Node::Index(ref a, ref i) = target.node {
+            if let Type::Map(_, _) = a.ty {
+                let map = self.expression(a);
+                let key = self.expression(i);
+
+                return Some((map, key))
+            }
+        }
+
+        None
+    }
+
+    /// The JavaScript of an operation on two values giving a value of type `t`, keeping
+    /// integer division integral.
+    fn operation(o: &Operator, left: String, right: String, t: &Type) -> String {
+        match *o {
+            // C++ cuts the fraction off towards zero, which `%` already agrees with
+            Operator::Div if t.is_integral() => format!("Math.trunc({} / {})", left, right),
+            Operator::Equal                  => format!("({} === {})", left, right),
+            Operator::NEqual                 => format!("({} !== {})", left, right),
+            _                                => format!("({} {} {})", left, operator(o), right),
+        }
+    }
+
+    /// The JavaScript of an expression.
+    fn expression(&mut self, ex: &Expression) -> String {
+        match ex.node {
+            Node::Integer(i)   => i.to_string(),
+            Node::Float(f)     => format!("{:?}", f),
+            Node::Boolean(b)   => b.to_string(),
+            Node::Text(ref t)  => quoted(t, Target::Js),
+
+            Node::Local(ref n) => {
+                for scope in self.locals.iter().rev() {
+                    if let Some(j) = scope.get(n) {
+                        return j.clone()
+                    }
+                }
+
+                n.clone()
+            }
+
+            Node::Global(ref p) | Node::Function(ref p) => self.spell(p),
+            Node::This                                  => "this".to_owned(),
+
+            Node::Foreign(ref n) => {
+                let root = n.split("::").next().unwrap_or("").to_owned();
+
+                self.foreign(&root);
+
+                n.replace("::", ".")
+            }
+
+            Node::Field(ref o, _, ref n) | Node::Member(ref o, ref n) => {
+                let code = self.expression(o);
+
+                format!("{}.{}", code, n)
+            }
+
+            Node::Variant(ref e, ref v, ref a) => {
+                let enumeration = self.spell(e);
+
+                match a.len() {
+                    0 => format!("{}.{}", enumeration, v),
+                    _ => format!("{}.{}({})", enumeration, v, self.arguments(a)),
+                }
+            }
+
+            Node::Binary(ref l, ref o, ref r) => {
+                let left  = self.expression(l);
+                let right = self.expression(r);
+
+                JavaScript::operation(o, left, right, &ex.ty)
+            }
+
+            Node::Unary(ref o, ref e) => {
+                let code = self.expression(e);
+
+                format!("({}{})", operator(o), code)
+            }
+
+            // an object converted to a class it derives from is cut down to it, like C++ does
+            Node::Convert(ref e) => match (&e.ty, &ex.ty) {
+                (&Type::Object(ref from, _), &Type::Object(ref to, _)) if !Rc::ptr_eq(from, to) => {
+                    let code = self.expression(e);
+
+                    format!("{}.prototype.helix_clone.call({})", self.spell(&to.path()), code)
+                }
+
+                _ => {
+                    let code = self.expression(e);
+
+                    self.coerce(code, &e.ty, &ex.ty)
+                }
+            },
+
+            Node::Index(ref a, ref i) => {
+                let base  = self.expression(a);
+                let index = self.expression(i);
+
+                match a.ty {
+                    Type::Map(_, ref v) => {
+                        let fallback = self.default(v);
+
+                        format!("helix_entry({}, {}, {})", base, index, fallback)
+                    }
+
+                    _ => format!("{}[{}]", base, index),
+                }
+            }
+
+            // the type arguments of generics are gone in JavaScript
+            Node::Call(ref p, _, ref a) => format!("{}({})", self.spell(p), self.arguments(a)),
+            Node::New(ref p, _, ref a)  => format!("new {}({})", self.spell(p), self.arguments(a)),
+
+            Node::Method(ref o, _, ref m, ref a) => {
+                let code = self.expression(o);
+
+                format!("{}.{}({})", code, m, self.arguments(a))
+            }
+
+            Node::Invoke(ref o, ref m, ref a) => self.member(o, m, a),
+
+            Node::Apply(ref f, ref a) => {
+                let function = self.expression(f);
+
+                format!("{}({})", function, self.arguments(a))
+            }
+
+            Node::Array(ref s) => self.array(s, &ex.ty),
+
+            Node::List(ref l) => format!("[{}]", self.arguments(l)),
+
+            Node::Map(ref m) => {
+                let mut entries = Vec::new();
+
+                for &(ref k, ref v) in m.iter() {
+                    let key   = self.copied(k);
+                    let value = self.copied(v);
+
+                    entries.push(format!("[{}, {}]", key, value))
+                }
+
+                format!("new Map([{}])", entries.join(", "))
+            }
+
+            // like `[=]`, every local in sight is copied as the lambda is made, by a function
+            // taking them that makes it
+            Node::Lambda(ref a, ref c) => {
+                let mut captured: Vec<(String, String)> = Vec::new();
+
+                for scope in self.locals.iter() {
+                    for (n, s) in scope.iter() {
+                        match captured.iter().position(|&(ref m, _)| m == n) {
+                            Some(i) => captured[i].1 = s.clone(),
+                            None    => captured.push((n.clone(), s.clone())),
+                        }
+                    }
+                }
+
+                captured.sort();
+
+                self.locals.push(captured.iter().map(|&(ref n, _)| (n.clone(), n.clone())).collect());
+                self.locals.push(a.iter().map(|&(ref n, _)| (n.clone(), n.clone())).collect());
+                self.depth += 1;
+
+                let mut lines = Vec::new();
+
+                for s in c.iter() {
+                    self.statement(s, &mut lines)
+                }
+
+                self.depth -= 1;
+                self.locals.pop();
+                self.locals.pop();
+
+                let params: Vec<&str> = a.iter().map(|&(ref n, _)| n.as_str()).collect();
+                let lambda            = format!("({}) => {{\n{}\n{}}}", params.join(", "), lines.join("\n"), "\t".repeat(self.depth));
+
+                if captured.is_empty() {
+                    return format!("({})", lambda)
+                }
+
+                let names: Vec<&str>    = captured.iter().map(|&(ref n, _)| n.as_str()).collect();
+                let copies: Vec<String> = captured.iter().map(|&(_, ref s)| format!("helix_copy({})", s)).collect();
+
+                format!("(({}) => {})({})", names.join(", "), lambda, copies.join(", "))
+            }
+        }
+    }
+
+    /// A name used but defined nowhere in the program, which is looked for in the modules of
+    /// the libraries mapped.
+    fn foreign(&mut self, name: &str) {
+        if self.loaded.is_empty() || name == "printf" || name == "puts" || self.visible(name).is_some() {
+            return
+        }
+
+        if !self.foreign.iter().any(|f| f == name) {
+            self.foreign.push(name.to_owned())
+        }
+    }
+
+    /// The arguments of a call, copied since they are passed by value.
+    fn arguments(&mut self, args: &[Expression]) -> String {
+        let arguments: Vec<String> = args.iter().map(|a| self.copied(a)).collect();
+
+        arguments.join(", ")
+    }
+
+    /// An expression whose value is stored or passed on, copied if it is an object, list or
+    /// map held somewhere else, which C++ would copy instead of sharing.
+    fn copied(&mut self, ex: &Expression) -> String {
+        let code = self.expression(ex);
+
+        let held = match ex.node {
+            Node::Local(_) | Node::Global(_) | Node::This | Node::Field(_, _, _) | Node::Member(_, _) | Node::Index(_, _) => true,
+            _                                                                                             => false,
+        };
+
+        match held && shared(&ex.ty) {
+            true  => format!("helix_copy({})", code),
+            false => code,
+        }
+    }
+
+    /// The JavaScript counterpart of a method of a C++ string, vector or map, or a call to
+    /// a method of something the program doesn't define.
+    fn member(&mut self, object: &Expression, m: &str, args: &[Expression]) -> String {
+        let code      = self.expression(object);
+        let arguments = self.arguments(args);
+
+        let list = match object.ty {
+            Type::List(_) | Type::Array(_) => true,
+            _                                 => false,
+        };
+
+        let map = match object.ty {
+            Type::Map(_, _) => true,
+            _               => false,
+        };
+
+        let text = object.ty == Type::Text || object.ty == Type::Char;
+
+        match (m, args.len()) {
+            ("size", 0) | ("length", 0) if text => format!("{}.length", code),
+            ("size", 0) if list                 => format!("{}.length", code),
+            ("push_back", 1) if list            => format!("{}.push({})", code, arguments),
+            ("pop_back", 0) if list             => format!("{}.pop()", code),
+            ("back", 0) if list                 => format!("{}.at(-1)", code),
+            ("clear", 0) if list                => format!("{}.splice(0)", code),
+            ("size", 0) if map                  => format!("{}.size", code),
+            ("clear", 0) if map                 => format!("{}.clear()", code),
+            ("count", 1) if map                 => format!("({}.has({}) ? 1 : 0)", code, arguments),
+            ("erase", 1) if map                 => format!("({}.delete({}) ? 1 : 0)", code, arguments),
+            _                                   => format!("{}.{}({})", code, m, arguments),
+        }
+    }
+
+    /// The names the file exports, which files importing it import.
+    fn exports(items: &[Item]) -> Vec<String> {
+        let mut names = Vec::new();
+
+        for i in items.iter() {
+            match *i {
+                Item::Global(ref p, _, _, _) | Item::Module(ref p, _, _) | Item::Enum(ref p, _, _) => {
+                    names.push(p.name().to_owned())
+                }
+
+                Item::Function(ref f) if f.body.is_some() => names.push(f.name().to_owned()),
+                Item::Class(ref c)                        => names.push(c.path.name().to_owned()),
+
+                _ => (),
+            }
+        }
+
+        names.retain(|n| n != "main");
+        names
+    }
+}
+
+impl Backend for JavaScript {
+    fn import(&mut self, items: &[Item]) {
+        // mistakes in an imported file are reported when it is translated itself
+        let error = self.error.take();
+
+        self.importing = true;
+        self.declare(items);
+        self.importing = false;
+
+        self.imported.push(JavaScript::exports(items));
+
+        self.error = error;
+    }
+
+    fn library(&mut self, name: &str, module: &str) {
+        self.libraries.insert(name.to_owned(), module.to_owned());
+    }
+
+    fn make_environment(&mut self, items: Vec<Item>) -> Result<(), Diagnostic> {
+        self.declare(&items);
+
+        let mut source = Vec::new();
+
+        self.emit(&items, &mut source);
+
+        self.source = source;
+
+        // a program runs from the `main` of the file run by Node
+        if items.iter().any(|i| if let Item::Function(ref f) = *i { f.path.0 == ["main"] } else { false }) {
+            self.source.push("if (typeof process !== \"undefined\" && import.meta.filename === process.argv[1]) {\n\
+                              \tprocess.exitCode = main() ?? 0;\n}".to_owned())
+        }
+
+        match self.error.take() {
+            Some(e) => Err(e),
+            None    => Ok(()),
+        }
+    }
+
+    fn translate(&mut self) -> (String, String) {
+        let mut source = format!("// translated from {}\n", self.file);
+
+        for i in self.imports.iter() {
+            source.push_str(&format!("{}\n", i))
+        }
+
+        source.push_str(&format!("\n{}\n", RUNTIME));
+
+        if !self.loaded.is_empty() {
+            source.push_str(&format!("\n{}\n", LIBRARIES));
+
+            let modules: Vec<String> = (0 .. self.loaded.len()).map(|i| format!("library_{}", i)).collect();
+
+            for f in self.foreign.iter() {
+                source.push_str(&format!("\nconst {0} = helix_library(\"{0}\", {1});", f, modules.join(", ")))
+            }
+
+            if !self.foreign.is_empty() {
+                source.push('\n')
+            }
+        }
+
+        for s in self.source.iter() {
+            source.push_str(&format!("\n{}\n", s))
+        }
+
+        (source, String::new())
+    }
+
+    fn extensions(&self) -> (&'static str, &'static str) {
+        ("mjs", "")
+    }
+}
+
+/// The member of a module's object handing out a variable of it.
+fn accessors(name: &str) -> String {
+    format!("get {0}() {{ return {0}; }}, set {0}(value) {{ {0} = value; }}", name)
+}
+
+/// Whether values of a type are shared between the places holding them in JavaScript, and
+/// have to be copied to be held the way C++ holds them.
+fn shared(t: &Type) -> bool {
+    match *t {
+        Type::Object(_, _) | Type::List(_) | Type::Array(_) | Type::Map(_, _) | Type::Parameter(_) => true,
+        _                                                                                         => false,
+    }
+}
+
+/// Code moved one level in, as the body of a module or class.
+fn indent(code: &str) -> String {
+    code.lines()
+        .map(|l| if l.is_empty() { String::new() } else { format!("\t{}", l) })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
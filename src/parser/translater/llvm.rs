@@ -0,0 +1,1169 @@
+use std::collections::HashMap;
+
+use super::super::ast::Position;
+use super::super::ir::{Path, Type, Expression, Node, Statement, Item, Function};
+use super::super::token::Operator;
+use super::super::diagnostic::{Diagnostic, code};
+use super::super::interpreter::builtin::unescape;
+
+use super::Backend;
+
+/// The type of a value as far as LLVM is concerned. Pointers are typed, like the IR of
+/// LLVM 14 and earlier has them.
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Bool,
+    Char,
+    Int,
+    Long,
+    Float,
+    Double,
+    /// A Helix `struct`, by its path.
+    Struct(String),
+    /// A string literal, which can only be handed on to functions taking one.
+    Text,
+    Void,
+}
+
+impl Kind {
+    /// How LLVM spells the type.
+    fn ir(&self) -> String {
+        match *self {
+            Kind::Bool          => "i1".to_owned(),
+            Kind::Char          => "i8".to_owned(),
+            Kind::Int           => "i32".to_owned(),
+            Kind::Long          => "i64".to_owned(),
+            Kind::Float         => "float".to_owned(),
+            Kind::Double        => "double".to_owned(),
+            Kind::Struct(ref s) => format!("%{}", s.replace("::", ".")),
+            Kind::Text          => "i8*".to_owned(),
+            Kind::Void          => "void".to_owned(),
+        }
+    }
+
+    fn is_float(&self) -> bool {
+        *self == Kind::Float || *self == Kind::Double
+    }
+
+    fn is_integral(&self) -> bool {
+        match *self {
+            Kind::Bool | Kind::Char | Kind::Int | Kind::Long => true,
+            _                                                => false,
+        }
+    }
+
+    fn bits(&self) -> u32 {
+        match *self {
+            Kind::Bool => 1,
+            Kind::Char => 8,
+            Kind::Long => 64,
+            _          => 32,
+        }
+    }
+
+    /// The wider of two integral types, which is what C++ promotes an operand to.
+    fn max(&self, other: &Kind) -> Kind {
+        if self.bits() >= other.bits() { self.clone() } else { other.clone() }
+    }
+
+    /// The value a variable of the type starts out with.
+    fn zero(&self) -> String {
+        match *self {
+            Kind::Bool                  => "false".to_owned(),
+            Kind::Float | Kind::Double  => "0.0".to_owned(),
+            Kind::Struct(_)             => "zeroinitializer".to_owned(),
+            Kind::Text                  => "null".to_owned(),
+            _                           => "0".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Signature {
+    params: Vec<(Kind, String)>,
+    retty:  Kind,
+}
+
+/// What an LLVM name stands for, and whether the file being translated defines it.
+#[derive(Debug, Clone)]
+enum Entry {
+    Function(Signature, bool),
+    Struct,
+    Variable(Kind, bool),
+}
+
+/// The LLVM backend, writing textual IR for the typed subset of Helix: integers, floats,
+/// bools, functions, `if`, loops and structs. Every local lives in an `alloca` of the entry
+/// block of its function, which `mem2reg` turns into registers.
+pub struct Llvm {
+    file:       String,
+    types:      Vec<String>,
+    globals:    Vec<String>,
+    functions:  Vec<String>,
+    /// The functions and variables used but defined elsewhere, declared once each.
+    externals:  Vec<String>,
+    strings:    usize,
+
+    symbols:    HashMap<String, Entry>,
+    structs:    HashMap<String, Vec<(String, Kind)>>,
+    /// The structs in the order they were declared, so their types are written in it.
+    order:      Vec<String>,
+    importing:  bool,
+
+    /// The `alloca`s and instructions of the function being written.
+    allocas:    Vec<String>,
+    code:       Vec<String>,
+    /// The locals in sight, each with the register pointing at it and its type.
+    locals:     Vec<HashMap<String, (String, Kind)>>,
+    /// The labels `continue` and `break` go to in the loops around.
+    loops:      Vec<(String, String)>,
+    retty:      Kind,
+    registers:  usize,
+    labels:     usize,
+    /// The label of the block being written, and whether it has been ended by a branch or
+    /// return.
+    block:      String,
+    terminated: bool,
+
+    position:   Position,
+    error:      Option<Diagnostic>,
+}
+
+impl Llvm {
+    pub fn new(_title: String, file: String) -> Llvm {
+        Llvm {
+            file:       file,
+            types:      Vec::new(),
+            globals:    Vec::new(),
+            functions:  Vec::new(),
+            externals:  Vec::new(),
+            strings:    0,
+
+            symbols:    HashMap::new(),
+            structs:    HashMap::new(),
+            order:      Vec::new(),
+            importing:  false,
+
+            allocas:    Vec::new(),
+            code:       Vec::new(),
+            locals:     Vec::new(),
+            loops:      Vec::new(),
+            retty:      Kind::Void,
+            registers:  0,
+            labels:     0,
+            block:      String::new(),
+            terminated: false,
+
+            position:   (0, 0),
+            error:      None,
+        }
+    }
+
+    /// Keeps the first error; everything after it is still translated, but not written.
+    fn fail(&mut self, code: &'static str, message: String, note: &str) {
+        if self.error.is_none() {
+            let (line, col) = self.position;
+
+            self.error = Some(Diagnostic::error(code, message).at(line, col, 0).note(note.to_owned()))
+        }
+    }
+
+    fn unsupported(&mut self, message: String) {
+        self.fail(code::UNSUPPORTED, message,
+                  "the LLVM target covers ints, floats, bools, functions, `if`, loops and structs")
+    }
+
+    /// The LLVM name of something the program defines, with the modules it is in.
+    fn mangle(path: &Path) -> String {
+        path.join(".")
+    }
+
+    /// The type LLVM gives the values of a type.
+    fn kind(&mut self, t: &Type) -> Kind {
+        match *t {
+            Type::Bool   => Kind::Bool,
+            Type::Char   => Kind::Char,
+            Type::Int    => Kind::Int,
+            Type::Long   => Kind::Long,
+            Type::Float  => Kind::Float,
+            Type::Double => Kind::Double,
+            Type::Text   => Kind::Text,
+            Type::Void   => Kind::Void,
+
+            Type::Object(ref p, ref a) if a.is_empty() => {
+                let l = Llvm::mangle(&p.path());
+
+                match self.symbols.get(&l) {
+                    Some(&Entry::Struct) => Kind::Struct(l),
+
+                    _ => {
+                        self.unsupported(format!("LLVM has no type like `{}`", t));
+
+                        Kind::Int
+                    }
+                }
+            }
+
+            Type::Array(_) => {
+                self.unsupported("LLVM arrays aren't supported".to_owned());
+
+                Kind::Int
+            }
+
+            _ => {
+                self.unsupported(format!("LLVM has no type like `{}`", t));
+
+                Kind::Int
+            }
+        }
+    }
+
+    fn signature(&mut self, f: &Function) -> Signature {
+        Signature {
+            params: f.params.iter().map(|&(ref n, ref t)| (self.kind(t), n.clone())).collect(),
+            retty:  self.kind(&f.retty),
+        }
+    }
+
+    /// Makes the names a file defines known before any of it is written, since LLVM needs
+    /// the types of what is called.
+    fn declare(&mut self, items: &[Item]) {
+        let local = !self.importing;
+
+        for item in items.iter() {
+            match *item {
+                Item::Global(ref p, ref t, _, position) => {
+                    self.position = position;
+
+                    let kind = self.kind(t);
+
+                    self.symbols.insert(Llvm::mangle(p), Entry::Variable(kind, local));
+                }
+
+                Item::Function(ref f) if f.generics.is_empty() => {
+                    self.position = f.position;
+
+                    let s = self.signature(f);
+
+                    // a bodiless function is one of a C library
+                    let defined = local && f.body.is_some();
+
+                    self.symbols.insert(Llvm::mangle(&f.path), Entry::Function(s, defined));
+                }
+
+                Item::Module(_, ref c, _) => self.declare(c),
+
+                Item::Class(ref c) if c.plain => {
+                    self.position = c.position;
+
+                    let l = Llvm::mangle(&c.path);
+
+                    self.symbols.insert(l.clone(), Entry::Struct);
+
+                    let fields = c.fields.iter().map(|f| (f.name.clone(), self.kind(&f.ty))).collect();
+
+                    self.structs.insert(l.clone(), fields);
+                    self.order.push(l);
+                }
+
+                _ => (),
+            }
+        }
+    }
+
+    /// Declares something defined elsewhere the first time it is used.
+    fn external(&mut self, declaration: String) {
+        if !self.externals.contains(&declaration) {
+            self.externals.push(declaration)
+        }
+    }
+
+    fn register(&mut self) -> String {
+        self.registers += 1;
+
+        format!("%t{}", self.registers)
+    }
+
+    fn label(&mut self) -> String {
+        self.labels += 1;
+
+        format!("L{}", self.labels)
+    }
+
+    /// Writes an instruction into the block being written, starting a block nothing
+    /// branches to when the last one has ended, like the code after a `return`.
+    fn instruction(&mut self, text: String) {
+        if self.terminated {
+            let l = self.label();
+
+            self.start(l)
+        }
+
+        self.code.push(format!("\t{}", text))
+    }
+
+    /// Writes the instruction giving a value to a new register, and gives the register.
+    fn value(&mut self, text: String) -> String {
+        let r = self.register();
+
+        self.instruction(format!("{} = {}", r, text));
+
+        r
+    }
+
+    /// Ends the block being written with a branch or return.
+    fn terminate(&mut self, text: String) {
+        self.instruction(text);
+
+        self.terminated = true
+    }
+
+    /// Starts a block, falling into it from the one before when that hasn't ended.
+    fn start(&mut self, label: String) {
+        if !self.terminated && !self.block.is_empty() {
+            self.code.push(format!("\tbr label %{}", label))
+        }
+
+        self.code.push(format!("{}:", label));
+
+        self.block      = label;
+        self.terminated = false
+    }
+
+    fn local(&mut self, name: &str, kind: Kind) -> String {
+        self.registers += 1;
+
+        let pointer = format!("%{}.{}", name, self.registers);
+
+        self.allocas.push(format!("\t{} = alloca {}", pointer, kind.ir()));
+        self.locals.last_mut().unwrap().insert(name.to_owned(), (pointer.clone(), kind));
+
+        pointer
+    }
+
+    /// Converts a value to another type the way C++ does implicitly.
+    fn convert(&mut self, value: String, from: &Kind, to: &Kind) -> String {
+        if from == to {
+            return value
+        }
+
+        let instruction = match (from, to) {
+            (_, &Kind::Bool) if from.is_integral() => {
+                return self.value(format!("icmp ne {} {}, 0", from.ir(), value))
+            }
+
+            (_, &Kind::Bool) if from.is_float() => {
+                return self.value(format!("fcmp une {} {}, 0.0", from.ir(), value))
+            }
+
+            (&Kind::Bool, _) if to.is_integral() => "zext",
+            (&Kind::Bool, _) if to.is_float()    => "uitofp",
+
+            _ if from.is_integral() && to.is_integral() => {
+                if from.bits() < to.bits() { "sext" } else { "trunc" }
+            }
+
+            _ if from.is_integral() && to.is_float() => "sitofp",
+            _ if from.is_float() && to.is_integral() => "fptosi",
+
+            (&Kind::Float, &Kind::Double) => "fpext",
+            (&Kind::Double, &Kind::Float) => "fptrunc",
+
+            _ => {
+                self.fail(code::TYPE, format!("there's no converting `{}` to `{}`", from.ir(), to.ir()),
+                          "only numbers and bools convert to each other");
+
+                return to.zero()
+            }
+        };
+
+        self.value(format!("{} {} {} to {}", instruction, from.ir(), value, to.ir()))
+    }
+
+    /// A value as a condition to branch on.
+    fn condition(&mut self, ex: &Expression) -> String {
+        let (value, kind) = self.expression(ex);
+
+        self.convert(value, &kind, &Kind::Bool)
+    }
+
+    /// Writes the files of a program.
+    fn emit(&mut self, items: &[Item]) {
+        for item in items.iter() {
+            match *item {
+                // C headers give nothing LLVM can use; their functions are declared with
+                // bodiless `function`s, apart from `printf` and `puts`
+                Item::Import(_, _, _) | Item::Use(_, _) => (),
+
+                Item::Global(ref p, _, ref e, position) => {
+                    self.position = position;
+
+                    self.variable(p, e.as_ref())
+                }
+
+                Item::Function(ref f) => {
+                    self.position = f.position;
+
+                    if !f.generics.is_empty() {
+                        self.unsupported(format!("`{}` is generic, which LLVM can't express", f.name()));
+
+                        continue
+                    }
+
+                    let name = Llvm::mangle(&f.path);
+
+                    match (&f.body, self.symbols.get(&name).cloned()) {
+                        (&Some(ref body), Some(Entry::Function(s, _))) => self.function(&name, &s, body),
+
+                        (&None, Some(Entry::Function(s, _))) => {
+                            self.external(format!("declare {} @{}({})", s.retty.ir(), name, types(&s.params)))
+                        }
+
+                        _ => (),
+                    }
+                }
+
+                Item::Module(_, ref c, _) => self.emit(c),
+
+                Item::Class(ref c) if c.plain => {
+                    let name   = Llvm::mangle(&c.path);
+                    let fields = self.structs.get(&name).cloned().unwrap_or_default();
+
+                    let types: Vec<String> = fields.iter().map(|&(_, ref k)| k.ir()).collect();
+
+                    self.types.push(format!("%{} = type {{ {} }}", name, types.join(", ")))
+                }
+
+                Item::Class(ref c) => {
+                    self.position = c.position;
+
+                    self.unsupported(format!("`{}` needs classes or enums, which LLVM isn't given", c.path.name()))
+                }
+
+                Item::Enum(ref p, _, position) | Item::Implement(ref p, _, _, position) => {
+                    self.position = position;
+
+                    self.unsupported(format!("`{}` needs classes or enums, which LLVM isn't given", p.name()))
+                }
+            }
+        }
+    }
+
+    /// A variable of a module, or of the file itself, which starts out with a constant.
+    fn variable(&mut self, path: &Path, e: Option<&Expression>) {
+        let l = Llvm::mangle(path);
+
+        let kind = match self.symbols.get(&l) {
+            Some(&Entry::Variable(ref k, _)) => k.clone(),
+            _                                => return,
+        };
+
+        let value = match e {
+            Some(e) => match constant(e.unconverted(), &kind) {
+                Some(v) => v,
+                None    => return self.unsupported(format!("`{}` has to start out with a constant", path.name())),
+            },
+
+            None => kind.zero(),
+        };
+
+        self.globals.push(format!("@{} = global {} {}", l, kind.ir(), value))
+    }
+
+    fn function(&mut self, name: &str, signature: &Signature, body: &[Statement]) {
+        let retty = signature.retty.clone();
+
+        let params: Vec<String> = signature.params.iter()
+            .map(|&(ref k, ref n)| format!("{} %{}.arg", k.ir(), n))
+            .collect();
+
+        self.retty      = retty.clone();
+        self.locals     = vec!(HashMap::new());
+        self.allocas    = Vec::new();
+        self.code       = Vec::new();
+        self.registers  = 0;
+        self.labels     = 0;
+        self.block      = String::new();
+        self.terminated = false;
+
+        self.start("entry".to_owned());
+
+        for &(ref k, ref n) in signature.params.iter() {
+            let pointer = self.local(n, k.clone());
+
+            self.instruction(format!("store {0} %{1}.arg, {0}* {2}", k.ir(), n, pointer))
+        }
+
+        for s in body.iter() {
+            self.statement(s)
+        }
+
+        // a function giving back a value can still fall off its end, which gives back 0
+        if !self.terminated {
+            match retty {
+                Kind::Void => self.terminate("ret void".to_owned()),
+                ref k      => self.terminate(format!("ret {} {}", k.ir(), k.zero())),
+            }
+        }
+
+        let mut lines = vec!(format!("define {} @{}({}) {{", retty.ir(), name, params.join(", ")));
+
+        lines.push(self.code[0].clone());
+        lines.extend(self.allocas.drain(..));
+        lines.extend(self.code.drain(1 ..));
+        lines.push("}".to_owned());
+
+        self.locals.clear();
+
+        self.functions.push(lines.join("\n"))
+    }
+
+    /// Translates the statements nested in another, with locals of their own.
+    fn nested(&mut self, body: &[Statement]) {
+        self.locals.push(HashMap::new());
+
+        for s in body.iter() {
+            self.statement(s)
+        }
+
+        self.locals.pop();
+    }
+
+    fn statement(&mut self, st: &Statement) {
+        self.position = st.position();
+
+        match *st {
+            Statement::Return(None, _) => match self.retty.clone() {
+                Kind::Void => self.terminate("ret void".to_owned()),
+                ref k      => self.terminate(format!("ret {} {}", k.ir(), k.zero())),
+            },
+
+            Statement::Return(Some(ref v), _) => {
+                let retty = self.retty.clone();
+
+                let (value, kind) = self.expression(v);
+
+                if retty == Kind::Void {
+                    return self.terminate("ret void".to_owned())
+                }
+
+                let value = self.convert(value, &kind, &retty);
+
+                self.terminate(format!("ret {} {}", retty.ir(), value))
+            }
+
+            Statement::Let(ref n, ref t, None, _) => {
+                let kind    = self.kind(t);
+                let pointer = self.local(n, kind.clone());
+
+                self.instruction(format!("store {0} {1}, {0}* {2}", kind.ir(), kind.zero(), pointer))
+            }
+
+            Statement::Let(ref n, ref t, Some(ref e), _) => {
+                let (value, found) = self.expression(e);
+
+                let kind    = self.kind(t);
+                let value   = self.convert(value, &found, &kind);
+                let pointer = self.local(n, kind.clone());
+
+                self.instruction(format!("store {0} {1}, {0}* {2}", kind.ir(), value, pointer))
+            }
+
+            Statement::Assign(ref n, None, ref e, _) => {
+                let (value, found) = self.expression(e);
+
+                if let Some((pointer, kind)) = self.place(n) {
+                    let value = self.convert(value, &found, &kind);
+
+                    self.instruction(format!("store {0} {1}, {0}* {2}", kind.ir(), value, pointer))
+                }
+            }
+
+            Statement::Assign(ref n, Some(ref o), ref e, _) => {
+                let right = self.expression(e);
+
+                if let Some((pointer, kind)) = self.place(n) {
+                    let current = self.value(format!("load {0}, {0}* {1}", kind.ir(), pointer));
+
+                    let (value, found) = self.operation(o, (current, kind.clone()), right);
+                    let value          = self.convert(value, &found, &kind);
+
+                    self.instruction(format!("store {0} {1}, {0}* {2}", kind.ir(), value, pointer))
+                }
+            }
+
+            Statement::Expression(ref e, _) => {
+                self.expression(e);
+            }
+
+            Statement::If(ref e, ref then, ref otherwise, _) => {
+                let condition = self.condition(e);
+
+                let yes = self.label();
+                let no  = self.label();
+                let end = self.label();
+
+                self.terminate(format!("br i1 {}, label %{}, label %{}", condition, yes, no));
+
+                self.start(yes);
+                self.nested(then);
+
+                if !self.terminated {
+                    self.terminate(format!("br label %{}", end))
+                }
+
+                self.start(no);
+                self.nested(otherwise);
+                self.start(end)
+            }
+
+            Statement::Loop(ref init, ref e, ref step, ref c, _) => {
+                self.locals.push(HashMap::new());
+
+                if let Some(ref init) = *init {
+                    self.statement(init)
+                }
+
+                let test = self.label();
+                let body = self.label();
+                let next = match *step {
+                    Some(_) => self.label(),
+                    None    => test.clone(),
+                };
+                let end  = self.label();
+
+                self.start(test.clone());
+
+                let condition = self.condition(e);
+
+                self.terminate(format!("br i1 {}, label %{}, label %{}", condition, body, end));
+
+                self.start(body);
+
+                self.loops.push((next.clone(), end.clone()));
+                self.nested(c);
+                self.loops.pop();
+
+                if let Some(ref step) = *step {
+                    self.start(next);
+                    self.statement(step);
+                }
+
+                if !self.terminated {
+                    self.terminate(format!("br label %{}", test))
+                }
+
+                self.locals.pop();
+
+                self.start(end)
+            }
+
+            Statement::Each(_, _, _, _, _) => self.unsupported("LLVM has nothing to go through with `for ... in`".to_owned()),
+            Statement::Match(_, _, _)      => self.unsupported("LLVM isn't given enums to match on".to_owned()),
+
+            // the names a `use` brings in are resolved already
+            Statement::Use(_, _) => (),
+
+            Statement::Break(_) => match self.loops.last().cloned() {
+                Some((_, end)) => self.terminate(format!("br label %{}", end)),
+                None           => (),
+            },
+
+            Statement::Continue(_) => match self.loops.last().cloned() {
+                Some((next, _)) => self.terminate(format!("br label %{}", next)),
+                None            => (),
+            },
+        }
+    }
+
+    /// The register pointing at something assigned to, like a local, a global or a field of
+    /// either, along with its type.
+    fn place(&mut self, ex: &Expression) -> Option<(String, Kind)> {
+        match ex.node {
+            Node::Local(ref n) => {
+                for scope in self.locals.iter().rev() {
+                    if let Some(&(ref p, ref k)) = scope.get(n) {
+                        return Some((p.clone(), k.clone()))
+                    }
+                }
+
+                self.fail(code::UNDEFINED, format!("cannot find `{}`", n), "LLVM only knows the variables of the program");
+
+                None
+            }
+
+            Node::Global(ref p) => {
+                let l = Llvm::mangle(p);
+
+                match self.symbols.get(&l).cloned() {
+                    Some(Entry::Variable(k, local)) => {
+                        if !local {
+                            self.external(format!("@{} = external global {}", l, k.ir()))
+                        }
+
+                        Some((format!("@{}", l), k))
+                    }
+
+                    _ => {
+                        self.fail(code::UNDEFINED, format!("cannot find `{}`", p), "LLVM only knows the variables of the program");
+
+                        None
+                    }
+                }
+            }
+
+            Node::Field(ref a, _, ref name) => {
+                let (pointer, kind) = self.place(a)?;
+                let (index, field)  = self.field(&kind, name)?;
+
+                let p = self.value(format!("getelementptr inbounds {0}, {0}* {1}, i32 0, i32 {2}", kind.ir(), pointer, index));
+
+                Some((p, field))
+            }
+
+            Node::Foreign(ref n) => {
+                self.fail(code::UNDEFINED, format!("cannot find `{}`", n), "LLVM only knows the variables of the program");
+
+                None
+            }
+
+            _ => {
+                self.unsupported("only variables and their fields can be assigned to".to_owned());
+
+                None
+            }
+        }
+    }
+
+    /// The position and type of a field of a struct.
+    fn field(&mut self, kind: &Kind, name: &str) -> Option<(usize, Kind)> {
+        let fields = match *kind {
+            Kind::Struct(ref s) => self.structs.get(s).cloned().unwrap_or_default(),
+            _                   => Vec::new(),
+        };
+
+        match fields.iter().position(|&(ref f, _)| f == name) {
+            Some(i) => Some((i, fields[i].1.clone())),
+
+            None => {
+                self.fail(code::UNDEFINED, format!("no field named `{}` on `{}`", name, kind.ir()),
+                          "only structs have fields");
+
+                None
+            }
+        }
+    }
+
+    /// The instruction of an operation on two values, brought to a common type first like
+    /// C++ does.
+    fn operation(&mut self, o: &Operator, left: (String, Kind), right: (String, Kind)) -> (String, Kind) {
+        let (left, a)  = left;
+        let (right, b) = right;
+
+        match *o {
+            Operator::And | Operator::Or => return self.logical(o, (left, a), (right, b)),
+            _                            => (),
+        }
+
+        if !(a.is_integral() || a.is_float()) || !(b.is_integral() || b.is_float()) {
+            self.fail(code::TYPE, format!("`{}` can't be applied to `{}` and `{}`", super::operator(o), a.ir(), b.ir()),
+                      "LLVM only does arithmetic on numbers and bools");
+
+            return (a.zero(), a)
+        }
+
+        let common = if a == Kind::Double || b == Kind::Double {
+            Kind::Double
+        } else if a == Kind::Float || b == Kind::Float {
+            Kind::Float
+        } else if a == Kind::Long || b == Kind::Long {
+            Kind::Long
+        } else {
+            Kind::Int
+        };
+
+        let left  = self.convert(left, &a, &common);
+        let right = self.convert(right, &b, &common);
+
+        let float = common.is_float();
+
+        match *o {
+            Operator::BitAnd | Operator::BitOr | Operator::BitXor | Operator::Shl | Operator::Shr if float => {
+                self.fail(code::OPERATOR, format!("`{}` takes integers", super::operator(o)), "convert the operands to `int`");
+
+                return (common.zero(), common)
+            }
+
+            _ => (),
+        }
+
+        let (instruction, result) = match *o {
+            Operator::Plus    => (if float { "fadd" } else { "add" }, common.clone()),
+            Operator::Minus   => (if float { "fsub" } else { "sub" }, common.clone()),
+            Operator::Mul     => (if float { "fmul" } else { "mul" }, common.clone()),
+            // C++ divides integers towards zero, like `sdiv` and `srem` do
+            Operator::Div     => (if float { "fdiv" } else { "sdiv" }, common.clone()),
+            Operator::Mod     => (if float { "frem" } else { "srem" }, common.clone()),
+            Operator::BitAnd  => ("and", common.clone()),
+            Operator::BitOr   => ("or", common.clone()),
+            Operator::BitXor  => ("xor", common.clone()),
+            Operator::Shl     => ("shl", common.clone()),
+            Operator::Shr     => ("ashr", common.clone()),
+            Operator::Equal   => (if float { "fcmp oeq" } else { "icmp eq" }, Kind::Bool),
+            Operator::NEqual  => (if float { "fcmp une" } else { "icmp ne" }, Kind::Bool),
+            Operator::Lt      => (if float { "fcmp olt" } else { "icmp slt" }, Kind::Bool),
+            Operator::LtEqual => (if float { "fcmp ole" } else { "icmp sle" }, Kind::Bool),
+            Operator::Gt      => (if float { "fcmp ogt" } else { "icmp sgt" }, Kind::Bool),
+            Operator::GtEqual => (if float { "fcmp oge" } else { "icmp sge" }, Kind::Bool),
+            _                 => unreachable!(),
+        };
+
+        (self.value(format!("{} {} {}, {}", instruction, common.ir(), left, right)), result)
+    }
+
+    /// `&&` and `||` of two values already worked out.
+    fn logical(&mut self, o: &Operator, left: (String, Kind), right: (String, Kind)) -> (String, Kind) {
+        let left  = self.convert(left.0, &left.1, &Kind::Bool);
+        let right = self.convert(right.0, &right.1, &Kind::Bool);
+
+        let instruction = match *o {
+            Operator::And => "and",
+            _             => "or",
+        };
+
+        (self.value(format!("{} i1 {}, {}", instruction, left, right)), Kind::Bool)
+    }
+
+    /// `&&` and `||` whose right side is only evaluated when the left one doesn't decide.
+    fn short_circuit(&mut self, o: &Operator, l: &Expression, r: &Expression) -> (String, Kind) {
+        let left = self.condition(l);
+        let from = self.block.clone();
+
+        let rest = self.label();
+        let end  = self.label();
+
+        match *o {
+            Operator::And => self.terminate(format!("br i1 {}, label %{}, label %{}", left, rest, end)),
+            _             => self.terminate(format!("br i1 {}, label %{}, label %{}", left, end, rest)),
+        }
+
+        self.start(rest);
+
+        let right = self.condition(r);
+        let after = self.block.clone();
+
+        self.terminate(format!("br label %{}", end));
+        self.start(end);
+
+        let decided = match *o {
+            Operator::And => "false",
+            _             => "true",
+        };
+
+        (self.value(format!("phi i1 [ {}, %{} ], [ {}, %{} ]", decided, from, right, after)), Kind::Bool)
+    }
+
+    /// The register or constant holding the value of an expression, along with its type.
+    fn expression(&mut self, ex: &Expression) -> (String, Kind) {
+        match ex.node {
+            Node::Integer(i) => match ex.ty {
+                Type::Long => (i.to_string(), Kind::Long),
+                _          => (i.to_string(), Kind::Int),
+            },
+
+            Node::Float(f)     => (float(f), Kind::Double),
+            Node::Boolean(b)   => (b.to_string(), Kind::Bool),
+            Node::Text(ref t)  => (self.string(t), Kind::Text),
+
+            Node::Local(_) | Node::Global(_) | Node::Foreign(_) => match self.place(ex) {
+                Some((pointer, kind)) => (self.value(format!("load {0}, {0}* {1}", kind.ir(), pointer)), kind),
+                None                  => (Kind::Int.zero(), Kind::Int),
+            },
+
+            Node::Field(ref a, _, ref name) => {
+                let (value, kind) = self.expression(a);
+
+                match self.field(&kind, name) {
+                    Some((index, field)) => (self.value(format!("extractvalue {} {}, {}", kind.ir(), value, index)), field),
+                    None                 => (Kind::Int.zero(), Kind::Int),
+                }
+            }
+
+            Node::Binary(ref l, ref o, ref r) => match *o {
+                Operator::And | Operator::Or => self.short_circuit(o, l, r),
+
+                _ => {
+                    let left  = self.expression(l);
+                    let right = self.expression(r);
+
+                    self.operation(o, left, right)
+                }
+            },
+
+            Node::Unary(ref o, ref e) => {
+                let (value, kind) = self.expression(e);
+
+                match *o {
+                    Operator::Not => {
+                        let value = self.convert(value, &kind, &Kind::Bool);
+
+                        (self.value(format!("xor i1 {}, true", value)), Kind::Bool)
+                    }
+
+                    Operator::Minus if kind.is_float() => (self.value(format!("fneg {} {}", kind.ir(), value)), kind),
+
+                    Operator::Minus => {
+                        let value = self.convert(value, &kind, &Kind::Int.max(&kind));
+                        let kind  = Kind::Int.max(&kind);
+
+                        (self.value(format!("sub {} 0, {}", kind.ir(), value)), kind)
+                    }
+
+                    _ => {
+                        let value = self.convert(value, &kind, &Kind::Int.max(&kind));
+                        let kind  = Kind::Int.max(&kind);
+
+                        (self.value(format!("xor {} {}, -1", kind.ir(), value)), kind)
+                    }
+                }
+            }
+
+            Node::Convert(ref e) => {
+                let (value, kind) = self.expression(e);
+                let to            = self.kind(&ex.ty);
+
+                (self.convert(value, &kind, &to), to)
+            }
+
+            Node::Call(ref p, _, ref a) => self.call(p, a),
+
+            Node::Apply(ref f, ref a) => match f.node {
+                Node::Foreign(ref n) if n == "printf" || n == "puts" => self.print(n, a),
+
+                Node::Foreign(ref n) => {
+                    self.fail(code::UNDEFINED, format!("cannot find the function `{}`", n),
+                              "functions of C libraries are declared with a bodiless `function`, like `function abs(n: int) -> int`");
+
+                    (Kind::Int.zero(), Kind::Int)
+                }
+
+                _ => {
+                    self.unsupported("LLVM only calls functions by name".to_owned());
+
+                    (Kind::Int.zero(), Kind::Int)
+                }
+            },
+
+            Node::Array(_) => {
+                self.unsupported("LLVM arrays aren't supported".to_owned());
+
+                (Kind::Int.zero(), Kind::Int)
+            }
+
+            _ => {
+                self.unsupported("this can't be translated to LLVM".to_owned());
+
+                (Kind::Int.zero(), Kind::Int)
+            }
+        }
+    }
+
+    /// A string literal as a constant of the module, giving a pointer to its first character.
+    fn string(&mut self, text: &str) -> String {
+        let bytes = unescape(text).into_bytes();
+
+        let mut encoded = String::new();
+
+        for b in bytes.iter() {
+            if *b >= 32 && *b < 127 && *b != b'"' && *b != b'\\' {
+                encoded.push(*b as char)
+            } else {
+                encoded.push_str(&format!("\\{:02X}", b))
+            }
+        }
+
+        let name = format!("@.str.{}", self.strings);
+        let size = bytes.len() + 1;
+
+        self.strings += 1;
+
+        self.globals.push(format!("{} = private unnamed_addr constant [{} x i8] c\"{}\\00\"", name, size, encoded));
+
+        format!("getelementptr inbounds ([{0} x i8], [{0} x i8]* {1}, i64 0, i64 0)", size, name)
+    }
+
+    fn arguments(&mut self, args: &[Expression], params: &[(Kind, String)]) -> Vec<String> {
+        let mut arguments = Vec::new();
+
+        for (i, a) in args.iter().enumerate() {
+            let (value, kind) = self.expression(a);
+
+            arguments.push(match params.get(i) {
+                Some(&(ref p, _)) => {
+                    let value = self.convert(value, &kind, p);
+
+                    format!("{} {}", p.ir(), value)
+                }
+
+                // what goes into the `...` of `printf` is promoted like C does
+                None => match kind {
+                    Kind::Bool | Kind::Char => {
+                        let value = self.convert(value, &kind, &Kind::Int);
+
+                        format!("i32 {}", value)
+                    }
+
+                    Kind::Float => {
+                        let value = self.convert(value, &kind, &Kind::Double);
+
+                        format!("double {}", value)
+                    }
+
+                    k => format!("{} {}", k.ir(), value),
+                },
+            })
+        }
+
+        arguments
+    }
+
+    fn call(&mut self, path: &Path, args: &[Expression]) -> (String, Kind) {
+        let l = Llvm::mangle(path);
+
+        match self.symbols.get(&l).cloned() {
+            Some(Entry::Function(s, local)) => {
+                if !local {
+                    self.external(format!("declare {} @{}({})", s.retty.ir(), l, types(&s.params)))
+                }
+
+                if args.len() != s.params.len() {
+                    self.fail(code::ARGUMENTS, format!("`{}` takes {} arguments", path, s.params.len()), "");
+                }
+
+                let arguments = self.arguments(args, &s.params);
+                let call      = format!("call {} @{}({})", s.retty.ir(), l, arguments.join(", "));
+
+                match s.retty {
+                    Kind::Void => {
+                        self.instruction(call);
+
+                        (String::new(), Kind::Void)
+                    }
+
+                    k => (self.value(call), k),
+                }
+            }
+
+            _ => {
+                self.unsupported(format!("`{}` can't be called from LLVM", path));
+
+                (Kind::Int.zero(), Kind::Int)
+            }
+        }
+    }
+
+    /// A call to `printf` or `puts`, the functions of C that need no declaring.
+    fn print(&mut self, name: &str, args: &[Expression]) -> (String, Kind) {
+        let (declaration, head) = if name == "printf" {
+            ("declare i32 @printf(i8*, ...)", "i32 (i8*, ...) @printf")
+        } else {
+            ("declare i32 @puts(i8*)", "i32 @puts")
+        };
+
+        self.external(declaration.to_owned());
+
+        let arguments = self.arguments(args, &[(Kind::Text, String::new())]);
+
+        (self.value(format!("call {}({})", head, arguments.join(", "))), Kind::Int)
+    }
+}
+
+impl Backend for Llvm {
+    fn import(&mut self, items: &[Item]) {
+        // mistakes in an imported file are reported when it is translated itself
+        let error = self.error.take();
+
+        self.importing = true;
+        self.declare(items);
+        self.importing = false;
+
+        // the structs of imported files are laid out the same in every file using them
+        let imported: Vec<String> = self.order.drain(..).collect();
+
+        for s in imported.iter() {
+            let fields: Vec<String> = self.structs[s].iter().map(|&(_, ref k)| k.ir()).collect();
+
+            self.types.push(format!("%{} = type {{ {} }}", s, fields.join(", ")))
+        }
+
+        self.error = error;
+    }
+
+    fn make_environment(&mut self, items: Vec<Item>) -> Result<(), Diagnostic> {
+        self.declare(&items);
+        self.emit(&items);
+
+        match self.error.take() {
+            Some(e) => Err(e),
+            None    => Ok(()),
+        }
+    }
+
+    fn translate(&mut self) -> (String, String) {
+        let mut source = format!("; translated from {}\nsource_filename = \"{}\"\n", self.file,
+                                 self.file.replace('\\', "\\5C").replace('"', "\\22"));
+
+        for group in [&self.types, &self.globals, &self.externals].iter() {
+            if group.is_empty() {
+                continue
+            }
+
+            source.push('\n');
+
+            for l in group.iter() {
+                source.push_str(&format!("{}\n", l))
+            }
+        }
+
+        for f in self.functions.iter() {
+            source.push_str(&format!("\n{}\n", f))
+        }
+
+        // LLVM needs no headers, as every file declares what it uses from the others
+        (source, String::new())
+    }
+
+    fn extensions(&self) -> (&'static str, &'static str) {
+        ("ll", "")
+    }
+}
+
+/// The types of the parameters of a function, as its declaration lists them.
+fn types(params: &[(Kind, String)]) -> String {
+    params.iter().map(|&(ref k, _)| k.ir()).collect::<Vec<String>>().join(", ")
+}
+
+/// A literal as a constant of the given type.
+fn constant(e: &Expression, kind: &Kind) -> Option<String> {
+    match (&e.node, kind) {
+        (&Node::Integer(i), k) if k.is_integral() => Some(i.to_string()),
+        (&Node::Integer(i), k) if k.is_float()    => Some(constant_float(i as f64, k)),
+        (&Node::Float(f), k) if k.is_float()      => Some(constant_float(f, k)),
+        (&Node::Boolean(b), &Kind::Bool)          => Some(b.to_string()),
+        _                                         => None,
+    }
+}
+
+fn constant_float(f: f64, kind: &Kind) -> String {
+    match *kind {
+        // a `float` is written as the `double` it rounds to
+        Kind::Float => float(f as f32 as f64),
+        _           => float(f),
+    }
+}
+
+/// A floating point constant, written in hexadecimal as LLVM takes every one it can't print
+/// exactly in decimal.
+fn float(f: f64) -> String {
+    format!("0x{:016X}", f.to_bits())
+}
@@ -1,8 +1,19 @@
-use super::ast::{
-        Expression, Statement
-    };
+pub mod c;
+pub mod js;
+pub mod llvm;
+
+use std::path::Path;
+
+use super::ast::Statement;
+use super::ir::{Type, Expression, Node, Item, Function};
+use super::ir::lower::Lowering;
+use super::ir;
+use super::resolver::index::Index;
 
 use super::token::Operator;
+use super::diagnostic::Diagnostic;
+use super::loader;
+use super::interpreter::builtin::unescape;
 
 #[derive(Debug, Clone)]
 pub enum CElement {
@@ -13,8 +24,14 @@ pub enum CElement {
 
     Ident(String),
 
-    If(Box<CElement>, Box<CElement>),
-    IfElse(Box<CElement>, Box<CElement>, Box<CElement>),
+    If(Vec<(CElement, CElement)>, Option<Box<CElement>>),
+
+    /// A loop with what comes before it and what is run after each time through, which is a
+    /// `while` without either.
+    Loop(Option<Box<CElement>>, Box<CElement>, Option<Box<CElement>>, Box<CElement>),
+    For(String, Box<CElement>, Box<CElement>),
+    Break,
+    Continue,
 
     Block(Box<Vec<CElement>>),
 
@@ -27,36 +44,69 @@ pub enum CElement {
     IndexDot(Box<CElement>, Box<CElement>),
     IndexColon(Box<CElement>, Box<CElement>),
     IndexArray(Box<CElement>, Box<CElement>),
+    Generic(Box<CElement>, Vec<CElement>),
+    List(Vec<CElement>),
+    Map(Vec<(CElement, CElement)>),
+
+    /// A generic function, class or implementation along with its type parameters.
+    Template(Vec<String>, Box<CElement>),
 
     Include(String),
     Module(String, Box<Vec<CElement>>),
     Struct(String, Box<Vec<CElement>>),
+    /// An enum along with its variants and the `(type, name)` fields each carries.
+    Enum(String, Vec<(String, Vec<(String, String)>)>),
+    Match(Box<CElement>, Vec<(String, Vec<String>, CElement)>),
     Typed(Box<CElement>, Box<CElement>),
-    Declaration(String, Box<CElement>),
-    Assignment(String, Box<CElement>),
+    Declaration(String, String, Box<CElement>),
+    Assignment(Box<CElement>, Box<CElement>),
+    CompoundAssignment(Box<CElement>, String, Box<CElement>),
 
     Use(Box<CElement>),
 
     Operation(Box<CElement>, String, Box<CElement>),
+    Unary(String, Box<CElement>),
     Function(String, Vec<(String, String)>, Box<Vec<CElement>>, Option<String>),
+    /// A method the classes deriving from its own can override, called on the class of the
+    /// object like the interpreter calls it.
+    Virtual(Box<CElement>),
+    /// A lambda capturing copies of the locals around it, with its return type when one is
+    /// written down.
+    Lambda(Vec<(String, String)>, Box<Vec<CElement>>, Option<String>),
 
-    Return(Box<CElement>),
+    Return(Option<Box<CElement>>),
+
+    /// An element along with the line of the Helix source it was translated from.
+    Line(u32, Box<CElement>),
 }
 
 #[derive(Debug, Clone)]
 pub struct Environment {
     title:   String,
+    file:    String,
     imports: Vec<String>,
     header:  Vec<String>,
     pub global: Vec<CElement>,
 }
 
 impl<'a> Environment {
-    pub fn new(title: String) -> Environment {
+    /// Starts the translation of one file; `title` is the path its `.cpp` and `.hpp` are
+    /// written to, without the extension, and `file` the Helix file it is translated from.
+    pub fn new(title: String, file: String) -> Environment {
+        let guard: String = title.chars()
+            .map(|c| if c.is_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect();
+
+        let title = match Path::new(&title).file_name() {
+            Some(n) => n.to_string_lossy().into_owned(),
+            None    => title,
+        };
+
         Environment {
-            title:   title.clone(),
+            title:   title,
+            file:    file.replace('\\', "\\\\").replace('"', "\\\""),
             header:  vec!(
-                format!("#ifndef {0}\n#define {0}", title),
+                format!("#ifndef {0}_HPP\n#define {0}_HPP", guard),
             ),
             imports: Vec::new(),
             global:  Vec::new(),
@@ -72,15 +122,26 @@ impl<'a> Environment {
                 )
         }
 
+        // the directives of every element name only the line, so the file is named once
+        let origin = format!("#line 1 \"{}\"", self.file);
+
         self.header.insert(1, imports);
+        self.header.insert(2, origin.clone());
 
-        format!("#include \"{}.hpp\"\n", self.title)
+        format!("#include \"{}.hpp\"\n{}\n", self.title, origin)
     }
 
     pub fn class(&mut self, c: CElement) {
         self.header.push(translate_element(&c))
     }
 
+    /// Declares a function or module in the header, so files importing this one can use it.
+    pub fn declare(&mut self, c: &CElement) {
+        if let Some(p) = prototype(c) {
+            self.header.push(p)
+        }
+    }
+
     pub fn header(&self) -> String {
         let mut header = "".to_string();
 
@@ -110,34 +171,154 @@ impl<'a> Environment {
     }
 }
 
-#[derive(Debug, Clone)]
+/// The languages a program can be translated into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Cpp,
+    C,
+    Js,
+    Llvm,
+}
+
+impl Target {
+    /// The target named on the command line, like `c` or `cpp`.
+    pub fn named(name: &str) -> Option<Target> {
+        match name {
+            "cpp" | "c++" => Some(Target::Cpp),
+            "c"           => Some(Target::C),
+            "js"          => Some(Target::Js),
+            "llvm"        => Some(Target::Llvm),
+            _             => None,
+        }
+    }
+}
+
+/// A language the files of a program are translated into, one file at a time, from what
+/// the files are lowered into.
+pub trait Backend {
+    /// Makes the definitions of a Helix file imported by the one translated known.
+    fn import(&mut self, _items: &[Item]) {}
+
+    /// Maps a library imported with `import ... library` to the module standing for it, for
+    /// targets loading libraries as modules.
+    fn library(&mut self, _name: &str, _module: &str) {}
+
+    fn make_environment(&mut self, items: Vec<Item>) -> Result<(), Diagnostic>;
+
+    /// The source and the header the file is translated into; targets without headers give an
+    /// empty one, which isn't written.
+    fn translate(&mut self) -> (String, String);
+
+    /// The extensions of the source and the header, like `cpp` and `hpp`.
+    fn extensions(&self) -> (&'static str, &'static str);
+}
+
+/// Translates one Helix file through the backend of its target, lowering it and the files it
+/// imports first.
 pub struct Translater {
-    environment: Environment,
+    backend: Box<Backend>,
 }
 
 impl Translater {
-    pub fn new(title: String) -> Translater {
+    pub fn new(title: String, file: String) -> Translater {
+        Translater::to(Target::Cpp, title, file)
+    }
+
+    pub fn to(target: Target, title: String, file: String) -> Translater {
         Translater {
-            environment: Environment::new(title),
+            backend: match target {
+                Target::Cpp  => Box::new(Cpp::new(title, file)),
+                Target::C    => Box::new(c::C::new(title, file)),
+                Target::Js   => Box::new(js::JavaScript::new(title, file)),
+                Target::Llvm => Box::new(llvm::Llvm::new(title, file)),
+            },
+        }
+    }
+
+    /// Makes the definitions of an imported file known, from the index its resolution made.
+    pub fn import(&mut self, ast: &[Statement], index: &Index) {
+        let items = Lowering::new(index).lower(ast).unwrap_or_else(|_| Vec::new());
+
+        self.backend.import(&items)
+    }
+
+    pub fn library(&mut self, name: &str, module: &str) {
+        self.backend.library(name, module)
+    }
+
+    pub fn make_environment(&mut self, ast: Vec<Statement>, index: &Index) -> Result<(), Diagnostic> {
+        let items = try!(Lowering::new(index).lower(&ast));
+
+        self.backend.make_environment(items)
+    }
+
+    pub fn translate(&mut self) -> (String, String) {
+        self.backend.translate()
+    }
+
+    pub fn extensions(&self) -> (&'static str, &'static str) {
+        self.backend.extensions()
+    }
+}
+
+/// The C++ backend, which keeps the classes, namespaces and templates of a program.
+#[derive(Debug, Clone)]
+pub struct Cpp {
+    environment: Environment,
+}
+
+impl Cpp {
+    pub fn new(title: String, file: String) -> Cpp {
+        Cpp {
+            environment: Environment::new(title, file),
         }
     }
 
-    pub fn make_environment(&mut self, ast: Vec<Statement>) {
-        for s in ast.iter() {
-            if let Some(c) = statement(s) {
-                match c {
-                    CElement::Function(_, _, _, _)
-                    | CElement::Implement(_, _)
-                    | CElement::Module(_, _) => self.environment.global.push(c),
-                    CElement::Include(i)     => self.environment.import(i),
-                    CElement::Class(_, _, _)    => self.environment.class(c),
-                    _ => continue,
+    pub fn get_environment(&self) -> &Environment {
+        &self.environment
+    }
+}
+
+impl Backend for Cpp {
+    fn make_environment(&mut self, items: Vec<Item>) -> Result<(), Diagnostic> {
+        for i in items.iter() {
+            let c = item(i);
+
+            self.environment.declare(&c);
+
+            match *unlocated(&c) {
+                CElement::Function(_, _, _, _)
+                | CElement::Implement(_, _)
+                | CElement::Declaration(_, _, _)
+                | CElement::Typed(_, _) => self.environment.global.push(c.clone()),
+                // the classes of a module are in the header along with its prototypes
+                CElement::Module(_, _) => self.environment.global.push(definitions(&c)),
+                CElement::Include(ref i) => self.environment.import(i.clone()),
+                CElement::Class(_, _, _) | CElement::Struct(_, _) | CElement::Enum(_, _) => self.environment.class(c.clone()),
+                // templates are instantiated by the files using them, so they are defined
+                // in the header as a whole
+                CElement::Template(_, _) => self.environment.class(c.clone()),
+                // the names a file pulls in are used by its classes too
+                CElement::Use(_) => self.environment.class(c.clone()),
+                _ => continue,
+            }
+
+            // the fields of variants are kept in tuples
+            if tagged(&c) && !self.environment.imports.iter().any(|i| i == "<tuple>") {
+                self.environment.import("<tuple>".to_owned())
+            }
+
+            for h in containers(&c) {
+                if !self.environment.imports.iter().any(|i| i == h) {
+                    self.environment.import(h.to_owned())
                 }
             }
         }
+
+        Ok(())
     }
 
-    pub fn translate(&mut self) -> (String, String) {
+    fn translate(&mut self) -> (String, String) {
         let mut source = "".to_string();
 
         source.push_str(&self.environment.translate_imports());
@@ -146,21 +327,23 @@ impl Translater {
         (source, self.environment.header())
     }
 
-    pub fn get_environment(&self) -> &Environment {
-        &self.environment
+    fn extensions(&self) -> (&'static str, &'static str) {
+        ("cpp", "hpp")
     }
 }
 
 pub fn translate_element(ce: &CElement) -> String {
     return match *ce {
         CElement::Integer(ref i)  => i.to_string(),
-        CElement::Float(ref i)    => i.to_string(),
+        CElement::Float(ref i)    => format!("{:?}", i),
         CElement::Boolean(ref i)  => i.to_string(),
         CElement::Ident(ref i)    => i.to_string(),
-        CElement::Text(ref i)     => format!("\"{}\"", i.to_string()),
+        CElement::Text(ref i)     => quoted(i, Target::Cpp),
 
-        CElement::Return(ref e) => format!("return {};\n", translate_element(&**e)),
+        CElement::Return(Some(ref e)) => format!("return {};\n", translate_element(&**e)),
+        CElement::Return(None)        => "return;\n".to_string(),
         CElement::Use(ref e)    => format!("using namespace {};\n", translate_element(&**e)),
+        CElement::Virtual(ref e)  => format!("virtual {}", translate_element(&**e)),
 
         CElement::FunctionDef(ref n, ref a, ref t) => {
             let mut args = "".to_string();
@@ -202,24 +385,32 @@ pub fn translate_element(ce: &CElement) -> String {
                 }
             },
 
-        CElement::Implement(ref n, ref c) => {
+        CElement::Implement(ref n, ref c) => implementation(n, c, ""),
 
-                let mut implementation = "".to_string();
+        CElement::Template(ref g, ref c) => {
+                let template = format!("template <{}>\n", g.iter().map(|g| format!("typename {}", g)).collect::<Vec<String>>().join(", "));
 
-                for e in c.iter() {
-                    match e {
-                        &CElement::Function(ref n1, ref a, ref c, ref t) => implementation.push_str(
-                            &translate_element(
-                                &CElement::Function(format!("{}::{}", n, n1), a.clone(), c.clone(), t.clone()),
-                            )
-                        ),
-                        _ => panic!("Unexpected impementation of: {:?}", e),
-                    }
+                // every method of a generic class is a template of its own
+                match **c {
+                    CElement::Implement(ref n, ref c) => implementation(n, c, &template),
+                    ref c                             => format!("{}{}", template, translate_element(c)),
                 }
-
-                format!("\n{}\n", implementation)
             },
 
+        CElement::Generic(ref a, ref b) => format!(
+                "{}<{}>",
+                translate_element(&**a),
+                b.iter().map(translate_element).collect::<Vec<String>>().join(", "),
+            ),
+
+        // the declared types of lists and maps say what the braces make
+        CElement::List(ref e) => format!("{{{}}}", e.iter().map(translate_element).collect::<Vec<String>>().join(", ")),
+
+        CElement::Map(ref e) => format!(
+                "{{{}}}",
+                e.iter().map(|&(ref k, ref v)| format!("{{{}, {}}}", translate_element(k), translate_element(v))).collect::<Vec<String>>().join(", "),
+            ),
+
         CElement::IndexDot(ref a, ref b)   => format!("{}.{}", translate_element(&**a), translate_element(&**b)),
         CElement::IndexColon(ref a, ref b) => format!("{}::{}", translate_element(&**a), translate_element(&**b)),
         CElement::IndexArray(ref a, ref b) => format!("{}[{}]", translate_element(&**a), translate_element(&**b)),
@@ -229,7 +420,7 @@ pub fn translate_element(ce: &CElement) -> String {
 
                 for e in c.iter() {
                     block.push_str(
-                            &translate_element(&e)
+                            &format!("{};\n", translate_element(&e))
                         )
                 }
 
@@ -239,17 +430,47 @@ pub fn translate_element(ce: &CElement) -> String {
                     )
             },
 
-        CElement::If(ref e, ref c) => format!(
-            "if({}) {{{}}}", translate_element(&e), translate_element(&c),
+        CElement::If(ref b, ref c1) => {
+                let mut cascade = "".to_string();
+
+                for &(ref e, ref c) in b.iter() {
+                    if cascade.len() > 0 {
+                        cascade.push_str(" else ")
+                    }
+
+                    cascade.push_str(
+                            &format!("if({}) {{{}}}", translate_element(&e), translate_element(&c))
+                        )
+                }
+
+                if let Some(ref c1) = *c1 {
+                    cascade.push_str(
+                            &format!(" else {{{}}}", translate_element(&c1))
+                        )
+                }
+
+                cascade
+            },
+
+        CElement::Loop(None, ref e, None, ref c) => format!(
+            "while({}) {{{}}}", translate_element(&e), translate_element(&c),
         ),
 
-        CElement::IfElse(ref e, ref c, ref c1) => format!(
-            "if({}) {{{}}} else {{{}}}", 
+        CElement::Loop(ref i, ref e, ref s, ref c) => format!(
+            "for({}; {}; {}) {{{}}}",
+            i.as_ref().map_or(String::new(), |i| translate_element(unlocated(i)).trim_end_matches(';').to_string()),
             translate_element(&e),
+            s.as_ref().map_or(String::new(), |s| translate_element(unlocated(s))),
             translate_element(&c),
-            translate_element(&c1),
         ),
 
+        CElement::For(ref i, ref e, ref c) => format!(
+            "for(auto {} : {}) {{{}}}", i, translate_element(&e), translate_element(&c),
+        ),
+
+        CElement::Break    => "break".to_string(),
+        CElement::Continue => "continue".to_string(),
+
         CElement::Call(ref c, ref e) => {
                 let mut args = "".to_string();
 
@@ -313,18 +534,43 @@ pub fn translate_element(ce: &CElement) -> String {
                 }
             },
 
+        CElement::Lambda(ref a, ref c, ref t) => {
+                let mut body = "".to_string();
+
+                for e in c.iter() {
+                    body.push_str(
+                            &format!("{};\n", translate_element(&e))
+                        );
+                }
+
+                let args: Vec<String> = a.iter().map(|&(ref t, ref n)| format!("{} {}", t, n)).collect();
+
+                let retty = match *t {
+                    Some(ref t) => format!(" -> {}", t),
+                    None        => "".to_string(),
+                };
+
+                format!("[=]({}){} {{\n\t{}}}", args.join(", "), retty, body)
+            },
+
         CElement::Operation(ref l, ref o, ref r) => format!(
                 "({} {} {})",
+                translate_element(l),
+                o.clone(),
                 translate_element(r),
+            ),
+
+        CElement::Unary(ref o, ref e) => format!(
+                "({}{})",
                 o.clone(),
-                translate_element(l),
+                translate_element(e),
             ),
 
-        CElement::Declaration(ref i, ref r) => {
+        CElement::Declaration(ref i, ref t, ref r) => {
                 let mut line = "".to_string();
 
                 line.push_str(
-                        &format!("{} {} = {};", type_of(&**r), i, translate_element(r))
+                        &format!("{} {} = {};", t, i, translate_element(r))
                     );
 
                 line
@@ -334,7 +580,17 @@ pub fn translate_element(ce: &CElement) -> String {
                 let mut line = "".to_string();
 
                 line.push_str(
-                        &format!("{} = {};", i, translate_element(r))
+                        &format!("{} = {}", translate_element(i), translate_element(r))
+                    );
+
+                line
+            },
+
+        CElement::CompoundAssignment(ref i, ref o, ref r) => {
+                let mut line = "".to_string();
+
+                line.push_str(
+                        &format!("{} {}= {}", translate_element(i), o, translate_element(r))
                     );
 
                 line
@@ -382,279 +638,564 @@ pub fn translate_element(ce: &CElement) -> String {
                     )
             },
 
-        CElement::Include(ref n) => format!("#include {}\n", n),
-    }
-}
+        CElement::Enum(ref n, ref v) => {
+                let names: Vec<&str> = v.iter().map(|&(ref n, _)| n.as_str()).collect();
 
-pub fn expression(ex: &Expression) -> CElement {
-    match *ex {
-        Expression::Integer(ref i)                 => CElement::Integer(i.clone()),
-        Expression::Float(ref f)                   => CElement::Float(f.clone()),
-        Expression::Text(ref f)                    => CElement::Text(f.clone()),
-        Expression::Boolean(ref f)                 => CElement::Boolean(f.clone()),
-        Expression::Ident(ref f)                   => CElement::Ident(f.clone()),
-        Expression::Return(ref e)                  => CElement::Return(Box::new(expression(&**e))),
-        Expression::Use(ref e)                     => CElement::Use(Box::new(expression(&**e))),
-
-        Expression::FunctionDef(ref n, ref a, ref t) => {
-            let mut retty = expression(&**t);
-
-            retty = match &retty {
-                &CElement::Ident(ref t) => if &t.clone() == n {
-                    CElement::Ident("".to_owned())
-                } else {
-                    CElement::Ident(t.clone())
-                },
-                c => c.clone(),
-            };
+                // `helix_tag` gives the variant of a value, whichever way its enum is kept
+                if v.iter().all(|&(_, ref f)| f.is_empty()) {
+                    return format!(
+                        "enum class {0} {{ {1} }};\ninline {0} helix_tag({0} v) {{ return v; }}",
+                        n, names.join(", "),
+                    )
+                }
 
-            CElement::FunctionDef(n.clone(), a.clone(), Box::new(retty))
-        },
+                // variants carrying fields keep them in a member of their own, next to a tag
+                // telling which one is in use; the others convert from a plain enumerator
+                let mut members = "".to_string();
+                let mut plain: Vec<String> = Vec::new();
 
-        Expression::Class(ref n, ref c, ref p)       => {
-                let mut statement_stack: Vec<CElement> = Vec::new();
+                for (i, &(ref name, ref fields)) in v.iter().enumerate() {
+                    if fields.is_empty() {
+                        plain.push(format!("{} = {}", name, i));
 
-                for s in c.iter() {
-                    if let Some(c) = statement(s) {
-                        statement_stack.push(c)
+                        continue
                     }
+
+                    let types: Vec<&str>    = fields.iter().map(|&(ref t, _)| t.as_str()).collect();
+                    let args: Vec<String>   = fields.iter().map(|&(ref t, ref f)| format!("{} {}", t, f)).collect();
+                    let values: Vec<&str>   = fields.iter().map(|&(_, ref f)| f.as_str()).collect();
+
+                    members.push_str(&format!(
+                        "\tstd::tuple<{1}> {0}_;\n\tstatic {3} {0}({2}) {{ {3} v(Tag::{0}); v.{0}_ = std::make_tuple({4}); return v; }}\n",
+                        name, types.join(", "), args.join(", "), n, values.join(", "),
+                    ))
                 }
 
-                let parent = match *p {
-                    Some(ref p) => Some(Box::new(expression(&*p))),
-                    None        => None,
+                let conversion = if plain.is_empty() {
+                    "".to_string()
+                } else {
+                    format!("\tenum Plain {{ {} }};\n\t{}(Plain v) : tag(static_cast<Tag>(v)) {{}}\n", plain.join(", "), n)
                 };
 
-                CElement::Class(
-                    n.clone(),
-                    Box::new(statement_stack),
-                    parent,
+                format!(
+                    "struct {0} {{\n\tenum class Tag {{ {1} }};\n\tTag tag;\n\texplicit {0}(Tag t = Tag::{2}) : tag(t) {{}}\n{3}{4}}};\ninline {0}::Tag helix_tag(const {0}& v) {{ return v.tag; }}",
+                    n, names.join(", "), names[0], conversion, members,
                 )
             },
 
-        Expression::Implement(ref n, ref c)            => {
-                let mut statement_stack: Vec<CElement> = Vec::new();
+        CElement::Match(ref e, ref a) => {
+                let mut cascade = "".to_string();
+
+                for (j, &(ref v, ref b, ref c)) in a.iter().enumerate() {
+                    if cascade.len() > 0 {
+                        cascade.push_str(" else ")
+                    }
+
+                    let mut bindings = "".to_string();
+
+                    for (i, n) in b.iter().enumerate().filter(|&(_, n)| n != "_") {
+                        bindings.push_str(&format!("auto& {} = std::get<{}>(match_.{}_);\n", n, i, v))
+                    }
+
+                    // the checker makes sure every variant has an arm, so the last one needs
+                    // no test
+                    if v != "_" && j + 1 < a.len() {
+                        cascade.push_str(&format!("if(helix_tag(match_) == decltype(helix_tag(match_))::{}) ", v))
+                    }
+
+                    cascade.push_str(&format!("{{{}{}}}", bindings, translate_element(c)));
 
-                for s in c.iter() {
-                    if let Some(c) = statement(s) {
-                        statement_stack.push(c)
+                    if v == "_" {
+                        break
                     }
                 }
 
-                CElement::Implement(
-                    n.clone(),
-                    Box::new(statement_stack),
-                )
+                format!("{{auto&& match_ = {};\n{}}}", translate_element(e), cascade)
             },
 
-        Expression::Typed(ref r, ref f)            => CElement::Typed(
-                Box::new(expression(&**r)), Box::new(expression(&**f)),
-            ),
+        CElement::Include(ref n) => format!("#include {}\n", n),
 
-        Expression::IndexDot(ref a, ref b)         => CElement::IndexDot(
-                Box::new(expression(&**a)),
-                Box::new(expression(&**b)),
-            ),
+        CElement::Line(ref l, ref e) => format!("\n#line {}\n{}", l, translate_element(e)),
+    }
+}
 
-        Expression::IndexArray(ref a, ref b)       => CElement::IndexArray(
-                Box::new(expression(&**a)),
-                Box::new(expression(&**b)),
-            ),
+/// The methods of an `implement` block, named after their class and each preceded by the
+/// `template` line of a generic class.
+fn implementation(n: &str, c: &[CElement], template: &str) -> String {
+    let mut implementation = "".to_string();
 
-        Expression::IndexColon(ref a, ref b)       => CElement::IndexColon(
-                Box::new(expression(&**a)),
-                Box::new(expression(&**b)),
-            ),
+    for e in c.iter() {
+        if let CElement::Line(ref l, _) = *e {
+            implementation.push_str(&format!("\n#line {}\n", l))
+        }
 
-        Expression::Call(ref e, ref c)             => {
-                let mut expression_stack: Vec<CElement> = Vec::new();
+        let (generics, function) = match *unlocated(e) {
+            CElement::Template(ref g, ref f) => (Some(g), &**f),
+            ref f                            => (None, f),
+        };
 
-                for s in c.iter() {
-                    expression_stack.push(expression(s))
-                }
+        match *function {
+            CElement::Function(ref n1, ref a, ref c, ref t) => {
+                let method = CElement::Function(format!("{}::{}", n, n1), a.clone(), c.clone(), t.clone());
 
-                CElement::Call(
-                    Box::new(expression(&**e)),
-                    Box::new(expression_stack),
-                )
-            },
+                implementation.push_str(template);
+                implementation.push_str(&translate_element(&match generics {
+                    Some(g) => CElement::Template(g.clone(), Box::new(method)),
+                    None    => method,
+                }))
+            }
 
-        Expression::Import(ref p, ref l) => if *l {
+            _ => continue,
+        }
+    }
+
+    format!("\n{}\n", implementation)
+}
+
+/// Translates what a file defines, keeping the line it was written on for a `#line`
+/// directive.
+pub fn item(it: &Item) -> CElement {
+    let (line, c) = match *it {
+        Item::Import(ref p, library, (line, _)) => (line, if library {
                 CElement::Include(
                     format!("<{}>", p)
                 )
+            } else if loader::is_module(p) {
+                CElement::Include(
+                    format!("\"{}.hpp\"", loader::module_name(p))
+                )
             } else {
                 CElement::Include(
                     format!("\"{}\"", p)
                 )
-            },
+            }),
 
-        Expression::Module(ref n, ref c) => {
-                let mut statement_stack: Vec<CElement> = Vec::new();
+        Item::Use(ref p, (line, _)) => (line, CElement::Use(Box::new(CElement::Ident(p.to_string())))),
 
-                for s in c.iter() {
-                    if let Some(c) = statement(s) {
-                        statement_stack.push(c)
-                    }
-                }
+        Item::Global(ref p, ref t, ref e, (line, _)) => (line, declaration(p.name(), t, e.as_ref())),
 
-                CElement::Module(
-                    n.clone(),
-                    Box::new(statement_stack),
-                )
-            },
+        Item::Function(ref f) => (f.position.0, template(&f.generics, function(f))),
 
-        Expression::Struct(ref n, ref c) => {
-                let mut statement_stack: Vec<CElement> = Vec::new();
+        Item::Module(ref p, ref c, (line, _)) => (line, CElement::Module(
+                p.name().to_owned(),
+                Box::new(c.iter().map(item).collect()),
+            )),
 
-                for s in c.iter() {
-                    if let Some(c) = statement(s) {
-                        statement_stack.push(c)
-                    }
-                }
+        Item::Class(ref c) => {
+            let mut members: Vec<CElement> = Vec::new();
+
+            for f in c.fields.iter() {
+                members.push(CElement::Line(f.position.0, Box::new(declaration(&f.name, &f.ty, f.value.as_ref()))))
+            }
+
+            // templates can't be virtual, so generic methods are bound by the type they are
+            // called on
+            for m in c.methods.iter() {
+                let method = match m.generics.len() {
+                    0 if !m.constructor => CElement::Virtual(Box::new(function(m))),
+                    _                   => template(&m.generics, function(m)),
+                };
+
+                members.push(CElement::Line(m.position.0, Box::new(method)))
+            }
 
-                CElement::Struct(
-                    n.clone(),
-                    Box::new(statement_stack),
+            let class = if c.plain {
+                CElement::Struct(c.path.name().to_owned(), Box::new(members))
+            } else {
+                CElement::Class(
+                    c.path.name().to_owned(),
+                    Box::new(members),
+                    c.parent.as_ref().map(|p| Box::new(CElement::Ident(p.cpp()))),
                 )
-            },
+            };
 
-        Expression::Function(ref n, ref a, ref c, ref t)  => {
-                let mut statement_stack: Vec<CElement> = Vec::new();
+            (c.position.0, template(&c.generics, class))
+        }
 
-                let mut retty = match *t {
-                    Some(ref t) => Some(translate_element(&expression(&*t))),
-                    None    => None,
-                };
+        Item::Enum(ref p, ref v, (line, _)) => (line, CElement::Enum(
+                p.name().to_owned(),
+                v.iter().map(|v| (v.name.clone(), v.fields.iter().map(|&(ref f, ref t)| (t.cpp(), f.clone())).collect())).collect(),
+            )),
 
-                for s in c.iter() {
-                    if let Some(c) = statement(s) {
+        Item::Implement(ref p, ref g, ref c, (line, _)) => {
+            // the methods of a generic class belong to its instances, like `Stack<T>`
+            let class = match g.len() {
+                0 => p.to_string(),
+                _ => format!("{}<{}>", p, g.join(", ")),
+            };
 
-                        if let None = retty {
-                            let expr = get_return(&c);
-                            if let Some(e) = expr {
-                                retty = Some(type_of(&e).to_string())
-                            }
-                        }
+            let methods = c.iter().map(|f| CElement::Line(f.position.0, Box::new(template(&f.generics, function(f))))).collect();
 
-                        statement_stack.push(c)
-                    }
-                }
+            (line, template(g, CElement::Implement(class, Box::new(methods))))
+        }
+    };
 
-                retty = match retty {
-                    Some(ref t) => {
-                        if &&t == &&n {
-                            Some("".to_owned())
-                        } else {
-                            Some(t.clone())
-                        }
-                    },
+    CElement::Line(line, Box::new(c))
+}
 
-                    None => None,
-                };
+/// A function, or its declaration when it has no body. Constructors are written without a
+/// return type.
+fn function(f: &Function) -> CElement {
+    let params = f.params.iter().map(|&(ref n, ref t)| (t.cpp(), n.clone())).collect();
 
-                CElement::Function(
-                    n.clone(),
-                    a.clone(),
-                    Box::new(statement_stack),
-                    retty,
-                )
-            },
+    let retty = if f.constructor {
+        "".to_owned()
+    } else {
+        f.retty.cpp()
+    };
+
+    match f.body {
+        Some(ref b) => CElement::Function(f.name().to_owned(), params, Box::new(b.iter().map(statement).collect()), Some(retty)),
+        None        => CElement::FunctionDef(f.name().to_owned(), params, Box::new(CElement::Ident(retty))),
+    }
+}
 
-        Expression::Operation(ref l, ref o, ref r) => CElement::Operation(
+/// A variable along with its value, or the way C++ makes it without one; arrays have their
+/// sizes after the name.
+fn declaration(name: &str, t: &Type, value: Option<&Expression>) -> CElement {
+    if let Some(&Expression { node: Node::Array(ref sizes), .. }) = value {
+        let mut name = name.to_owned();
+        let mut t    = t;
+
+        for s in sizes.iter() {
+            name.push_str(&format!("[{}]", translate_element(&expression(s))));
+
+            if let Type::Array(ref e) = *t {
+                t = e
+            }
+        }
+
+        return CElement::Typed(Box::new(CElement::Ident(name)), Box::new(CElement::Ident(t.cpp())))
+    }
+
+    match value {
+        Some(v) => CElement::Declaration(name.to_owned(), t.cpp(), Box::new(expression(v))),
+        None    => CElement::Typed(Box::new(CElement::Ident(name.to_owned())), Box::new(CElement::Ident(t.cpp()))),
+    }
+}
+
+pub fn expression(ex: &Expression) -> CElement {
+    match ex.node {
+        Node::Integer(ref i)  => CElement::Integer(i.clone()),
+        Node::Float(ref f)    => CElement::Float(f.clone()),
+        Node::Text(ref f)     => CElement::Text(f.clone()),
+        Node::Boolean(ref f)  => CElement::Boolean(f.clone()),
+
+        Node::Local(ref n) | Node::Foreign(ref n) => CElement::Ident(n.clone()),
+
+        // names are written out from the outermost namespace, which finds them anywhere
+        Node::Global(ref p) | Node::Function(ref p) => CElement::Ident(p.to_string()),
+
+        Node::This => CElement::Ident("(*this)".to_owned()),
+
+        Node::Field(ref o, _, ref n) | Node::Member(ref o, ref n) => member(o, n),
+
+        Node::Variant(ref e, ref v, ref a) => {
+            let variant = CElement::IndexColon(
+                Box::new(CElement::Ident(e.to_string())),
+                Box::new(CElement::Ident(v.clone())),
+            );
+
+            match a.len() {
+                0 => variant,
+                _ => CElement::Call(Box::new(variant), Box::new(a.iter().map(expression).collect())),
+            }
+        }
+
+        // string literals are arrays of `char` to C++, and two of them can't be added
+        Node::Binary(ref l, Operator::Plus, ref r) if literal(l) && literal(r) => CElement::Operation(
+                Box::new(CElement::Call(Box::new(CElement::Ident("std::string".to_owned())), Box::new(vec!(expression(l))))),
+                operator(&Operator::Plus).to_string(),
+                Box::new(expression(r)),
+            ),
+
+        Node::Binary(ref l, ref o, ref r) => CElement::Operation(
                 Box::new(expression(l)),
                 operator(o).to_string(),
                 Box::new(expression(r)),
             ),
+
+        Node::Unary(ref o, ref e) => CElement::Unary(
+                operator(o).to_string(),
+                Box::new(expression(e)),
+            ),
+
+        // C++ converts values on its own
+        Node::Convert(ref e) => expression(e),
+
+        Node::Index(ref a, ref b) => CElement::IndexArray(
+                Box::new(expression(&**a)),
+                Box::new(expression(&**b)),
+            ),
+
+        Node::Call(ref p, ref g, ref a) | Node::New(ref p, ref g, ref a) => CElement::Call(
+                Box::new(generic(CElement::Ident(p.to_string()), g)),
+                Box::new(a.iter().map(expression).collect()),
+            ),
+
+        Node::Method(ref o, _, ref n, ref a) | Node::Invoke(ref o, ref n, ref a) => CElement::Call(
+                Box::new(member(o, n)),
+                Box::new(a.iter().map(expression).collect()),
+            ),
+
+        Node::Apply(ref f, ref a) => CElement::Call(
+                Box::new(expression(f)),
+                Box::new(a.iter().map(expression).collect()),
+            ),
+
+        // arrays are made by their declarations
+        Node::Array(_) => CElement::Ident("{}".to_owned()),
+
+        Node::List(ref e) => CElement::List(e.iter().map(expression).collect()),
+        Node::Map(ref e)  => CElement::Map(e.iter().map(|&(ref k, ref v)| (expression(k), expression(v))).collect()),
+
+        Node::Lambda(ref a, ref c) => CElement::Lambda(
+                a.iter().map(|&(ref n, ref t)| (t.cpp(), n.clone())).collect(),
+                Box::new(c.iter().map(statement).collect()),
+                match ex.ty {
+                    Type::Function(_, ref r) if !r.is_partial() => Some(r.cpp()),
+                    _                                           => None,
+                },
+            ),
+    }
+}
+
+/// Whether an expression is a string literal.
+fn literal(ex: &Expression) -> bool {
+    match ex.unconverted().node {
+        Node::Text(_) => true,
+        _             => false,
+    }
+}
+
+/// A member of an object; the members of the object a method is called on are named on
+/// their own.
+fn member(object: &Expression, name: &str) -> CElement {
+    match object.node {
+        Node::This => CElement::Ident(name.to_owned()),
+        _          => CElement::IndexDot(Box::new(expression(object)), Box::new(CElement::Ident(name.to_owned()))),
+    }
+}
+
+/// A function or class given the types its parameters stand for, if any are written.
+fn generic(ce: CElement, arguments: &[Type]) -> CElement {
+    match arguments.len() {
+        0 => ce,
+        _ => CElement::Generic(Box::new(ce), arguments.iter().map(|t| CElement::Ident(t.cpp())).collect()),
+    }
+}
+
+/// Translates a statement, keeping the line it was written on for a `#line` directive.
+pub fn statement(st: &ir::Statement) -> CElement {
+    CElement::Line(st.position().0, Box::new(translate_statement(st)))
+}
+
+/// The element a statement is translated into, without where it came from.
+pub fn unlocated(ce: &CElement) -> &CElement {
+    match *ce {
+        CElement::Line(_, ref c) => unlocated(&**c),
+        ref c                    => c,
     }
 }
 
-pub fn statement(st: &Statement) -> Option<CElement> {
+/// The standard headers the C++ an element is translated into needs for its lists, maps and
+/// function types.
+fn containers(ce: &CElement) -> Vec<&'static str> {
+    let translated = translate_element(ce);
+
+    [("std::vector<", "<vector>"), ("std::unordered_map<", "<unordered_map>"), ("std::function<", "<functional>"),
+     ("std::string(", "<string>")].iter()
+        .filter(|&&(t, _)| translated.contains(t))
+        .map(|&(_, h)| h)
+        .collect()
+}
+
+/// Makes an element generic if it has type parameters.
+fn template(generics: &[String], ce: CElement) -> CElement {
+    match generics.len() {
+        0 => ce,
+        _ => CElement::Template(generics.to_vec(), Box::new(ce)),
+    }
+}
+
+fn block(body: &[ir::Statement]) -> CElement {
+    CElement::Block(Box::new(body.iter().map(statement).collect()))
+}
+
+fn translate_statement(st: &ir::Statement) -> CElement {
     match *st {
-        Statement::Expression(ref e) => Some(expression(&**e)),
+        ir::Statement::Expression(ref e, _) => expression(e),
 
-        Statement::If(ref e, ref c)  => Some(
-                CElement::If(
-                        Box::new(expression(&**e)),
-                        Box::new(statement(&**c).unwrap()),
-                    )
+        ir::Statement::Let(ref n, ref t, ref e, _) => declaration(n, t, e.as_ref()),
+
+        ir::Statement::Assign(ref n, None, ref r, _) => CElement::Assignment(
+                Box::new(expression(n)),
+                Box::new(expression(r)),
             ),
 
-        Statement::IfElse(ref e, ref c, ref c1)  => Some(
-                CElement::IfElse(
-                        Box::new(expression(&**e)),
-                        Box::new(statement(&**c).unwrap()),
-                        Box::new(statement(&**c1).unwrap()),
-                    )
+        ir::Statement::Assign(ref n, Some(ref o), ref r, _) => CElement::CompoundAssignment(
+                Box::new(expression(n)),
+                operator(o).to_string(),
+                Box::new(expression(r)),
             ),
 
-        Statement::Block(ref c) => {
-            let mut statement_stack: Vec<CElement> = Vec::new();
+        // an `if` alone in the `else` of another is an `else if`
+        ir::Statement::If(ref e, ref c, ref otherwise, _) => {
+            let mut branches  = vec!((expression(e), block(c)));
+            let mut otherwise = otherwise;
 
-            for s in c.iter() {
-                if let Some(c) = statement(s) {
-                    statement_stack.push(c)
-                }
+            while let [ir::Statement::If(ref e, ref c, ref rest, _)] = otherwise[..] {
+                branches.push((expression(e), block(c)));
+
+                otherwise = rest
             }
 
-            Some(
-                CElement::Block(
-                        Box::new(statement_stack)
-                    )
-            )
+            let otherwise = match otherwise.len() {
+                0 => None,
+                _ => Some(Box::new(block(otherwise))),
+            };
+
+            CElement::If(branches, otherwise)
         }
 
-        Statement::Assignment(ref n, ref r) => Some(
-                CElement::Assignment(
-                        n.clone(),
-                        Box::new(expression(&**r)),
-                    ),
+        ir::Statement::Loop(ref i, ref e, ref s, ref c, _) => CElement::Loop(
+                i.as_ref().map(|i| Box::new(translate_statement(i))),
+                Box::new(expression(e)),
+                s.as_ref().map(|s| Box::new(translate_statement(s))),
+                Box::new(block(c)),
+            ),
+
+        ir::Statement::Each(ref i, _, ref e, ref c, _) => CElement::For(
+                i.clone(),
+                Box::new(expression(e)),
+                Box::new(block(c)),
+            ),
+
+        ir::Statement::Match(ref e, ref a, _) => CElement::Match(
+                Box::new(expression(e)),
+                a.iter().map(|a| {
+                    // the fields are bound by where they are in the variant
+                    let mut bindings = vec!("_".to_owned(); a.bindings.iter().map(|&(i, _, _)| i + 1).max().unwrap_or(0));
+
+                    for &(i, ref n, _) in a.bindings.iter() {
+                        bindings[i] = n.clone()
+                    }
+
+                    (a.variant.clone().unwrap_or("_".to_owned()), bindings, block(&a.body))
+                }).collect(),
             ),
 
-        Statement::Declaration(ref n, ref r) => Some(
-                CElement::Declaration(
-                        n.clone(),
-                        Box::new(expression(&**r)),
-                    ),
+        ir::Statement::Break(_)    => CElement::Break,
+        ir::Statement::Continue(_) => CElement::Continue,
+
+        ir::Statement::Return(ref e, _) => CElement::Return(e.as_ref().map(|e| Box::new(expression(e)))),
+
+        ir::Statement::Use(ref p, _) => CElement::Use(Box::new(CElement::Ident(p.to_string()))),
+    }
+}
+
+/// Whether an element is defined in the header as a whole: classes, enums and templates,
+/// which every file using them needs, and the names pulled in for them.
+fn whole(ce: &CElement) -> bool {
+    match *unlocated(ce) {
+        CElement::Class(_, _, _)
+        | CElement::Struct(_, _)
+        | CElement::Enum(_, _)
+        | CElement::Template(_, _)
+        | CElement::Use(_) => true,
+        _                  => false,
+    }
+}
+
+/// A module without what its header defines, which is what goes in the source.
+fn definitions(ce: &CElement) -> CElement {
+    match *ce {
+        CElement::Line(l, ref c)    => CElement::Line(l, Box::new(definitions(c))),
+        CElement::Module(ref n, ref c) => CElement::Module(
+                n.clone(),
+                Box::new(c.iter().filter(|e| !whole(e)).map(definitions).collect()),
             ),
+        ref c => c.clone(),
     }
 }
 
-fn type_of(element: &CElement) -> &str {
-    match *element {
-        CElement::Text(_)    => "string",
-        CElement::Boolean(_) => "bool",
-        CElement::Integer(_) => "int",
-        CElement::Float(_)   => "float",
-        _                    => "auto",
+/// Whether an element is or holds an enum with variants that carry fields.
+fn tagged(ce: &CElement) -> bool {
+    match *unlocated(ce) {
+        CElement::Enum(_, ref v)   => v.iter().any(|&(_, ref f)| !f.is_empty()),
+        CElement::Module(_, ref c) => c.iter().any(tagged),
+        _                          => false,
     }
 }
 
-fn get_return(st: &CElement) -> Option<CElement> {
-    match *st {
-        CElement::Return(ref e) => Some(*e.clone()),
-        CElement::If(_, ref c)  => get_return(c),
-        CElement::IfElse(_, ref c, ref c1)  => {
-                match get_return(c) {
-                    Some(e) => Some(e),
-                    None    => get_return(c1),
-                }
-            },
-        CElement::Block(ref c) => {
-                for e in c.iter() {
-                    return match get_return(&e) {
-                        Some(e) => Some(e),
-                        None    => continue
-                    }
-                }
+/// The declaration of a function or global, or of what a module defines, as it goes in a
+/// header, after a `#line` directive for where it was written.
+fn prototype(ce: &CElement) -> Option<String> {
+    let declaration = match *unlocated(ce) {
+        CElement::Function(ref n, _, _, _) if n == "main" => None,
 
-                None
-            },
+        CElement::Function(ref n, ref a, _, ref t) => {
+            let retty = match *t {
+                Some(ref t) => t.clone(),
+                None        => "void".to_owned(),
+            };
+
+            Some(translate_element(&CElement::FunctionDef(n.clone(), a.clone(), Box::new(CElement::Ident(retty)))))
+        },
+
+        // a global is defined by the source, and only declared where it is used
+        CElement::Declaration(ref i, ref t, _) if t != "auto" => Some(format!("extern {} {};", t, i)),
+
+        CElement::Typed(ref i, ref t) => match translate_element(t).as_str() {
+            "auto" => None,
+            t      => Some(format!("extern {} {};", t, translate_element(i))),
+        },
+
+        CElement::Module(ref n, ref c) => {
+            let declarations: Vec<String> = c.iter()
+                .filter_map(|e| if whole(e) { Some(translate_element(e)) } else { prototype(e) })
+                .collect();
+
+            Some(format!("namespace {} {{\n\t{}\n}}", n, declarations.join("\n").replace('\n', "\n\t")))
+        },
 
         _ => None,
+    };
+
+    match *ce {
+        CElement::Line(ref l, _) => declaration.map(|d| format!("#line {}\n{}", l, d)),
+        _                        => declaration,
     }
 }
 
+/// A string literal as the target writes it, its escapes resolved the way the interpreter
+/// resolves them and written again, so quotes and backslashes in it stay in the string.
+fn quoted(text: &str, target: Target) -> String {
+    let mut out = String::from("\"");
+
+    for c in unescape(text).chars() {
+        match c {
+            '"'  => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+
+            // octal escapes take at most three digits, so the characters after them can't
+            // be read as part of them, but JavaScript modules don't allow them
+            c if (c as u32) < 32 || c as u32 == 127 => match target {
+                Target::Js => out.push_str(&format!("\\x{:02x}", c as u32)),
+                _          => out.push_str(&format!("\\{:03o}", c as u32)),
+            },
+
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
 fn operator<'a>(v: &Operator) -> &'a str {
     match *v {
         Operator::Mul     => "*",
@@ -668,5 +1209,14 @@ fn operator<'a>(v: &Operator) -> &'a str {
         Operator::Gt      => ">",
         Operator::LtEqual => "<=",
         Operator::GtEqual => ">=",
+        Operator::And     => "&&",
+        Operator::Or      => "||",
+        Operator::Not     => "!",
+        Operator::BitAnd  => "&",
+        Operator::BitOr   => "|",
+        Operator::BitXor  => "^",
+        Operator::BitNot  => "~",
+        Operator::Shl     => "<<",
+        Operator::Shr     => ">>",
     }
 }
\ No newline at end of file
@@ -0,0 +1,253 @@
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use parser;
+use parser::ast::Statement;
+use parser::token::{Token, TokenType};
+use parser::diagnostic::{Diagnostic, Severity};
+use parser::interpreter::Interpreter;
+use parser::interpreter::value::Value;
+use parser::translater;
+use parser::ir::lower::Lowering;
+
+const HELP: &'static str = "\
+entries are run as they are typed, blocks end with an empty line
+
+:ast <code>          show the syntax tree of code
+:tokens <code>       show the tokens of code
+:cpp <code>          show the C++ code is translated into
+:load <file.helix>   load the definitions of a file and the files it imports
+:reset               forget everything defined so far
+:help                show this message
+:quit                leave";
+
+/// An interactive session running entries with the interpreter, which keeps whatever they
+/// define for the entries after them.
+pub struct Repl {
+    interpreter: Interpreter,
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        Repl {
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    pub fn run(&mut self) {
+        while let Some(entry) = read() {
+            let (command, rest) = match entry.trim().starts_with(':') {
+                true  => {
+                    let entry = entry.trim_left();
+
+                    match entry.find(char::is_whitespace) {
+                        Some(i) => (&entry[.. i], entry[i ..].trim()),
+                        None    => (entry.trim_right(), ""),
+                    }
+                }
+
+                false => ("", entry.as_str()),
+            };
+
+            match command {
+                ""             => self.evaluate(rest),
+                ":ast"         => ast(rest),
+                ":tokens"      => tokens(rest),
+                ":cpp"         => cpp(rest),
+                ":load"        => self.load(rest),
+                ":reset"       => self.interpreter = Interpreter::new(),
+                ":help"        => println!("{}", HELP),
+                ":quit" | ":q" => break,
+
+                c => writeln!(io::stderr(), "unknown command `{}`, see :help", c).unwrap(),
+            }
+        }
+
+        println!("=> bye bb <3")
+    }
+
+    fn evaluate(&mut self, source: &str) {
+        let ast = match parse(source) {
+            Some(a) => a,
+            None    => return,
+        };
+
+        for s in ast.iter() {
+            match self.interpreter.interact(s) {
+                Ok(Some(Value::Void)) | Ok(None) => (),
+                Ok(Some(v))                      => println!("=> {}", v),
+
+                Err(e) => {
+                    writeln!(io::stderr(), "runtime error: {}", e).unwrap();
+
+                    return
+                }
+            }
+        }
+    }
+
+    fn load(&mut self, path: &str) {
+        let mut units = match parser::loader::load(Path::new(path)) {
+            Ok(u)  => u,
+            Err(f) => return report(&f.diagnostics, &f.source),
+        };
+
+        for i in 0 .. units.len() {
+            let (loaded, rest) = units.split_at_mut(i);
+            let unit = &mut rest[0];
+
+            let imports: Vec<&[Statement]> = unit.imports.iter().map(|&j| &loaded[j].ast[..]).collect();
+
+            let errors: Vec<Diagnostic> = parser::resolver::resolve(&mut unit.ast, &imports)
+                .into_iter()
+                .filter(|d| d.severity == Severity::Error)
+                .map(|d| d.in_file(&unit.path.to_string_lossy()))
+                .collect();
+
+            if !errors.is_empty() {
+                return report(&errors, &unit.source)
+            }
+        }
+
+        for unit in units.iter() {
+            if let Err(e) = self.interpreter.load(&unit.ast) {
+                return writeln!(io::stderr(), "runtime error: {}", e).unwrap()
+            }
+        }
+
+        println!("=> loaded {}", path)
+    }
+}
+
+/// Reads one entry, which goes on until an empty line when it starts a block. Gives `None`
+/// once the input has ended.
+fn read() -> Option<String> {
+    let mut entry = String::new();
+
+    loop {
+        print!("{}", if entry.is_empty() { ">>> " } else { "... " });
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => return if entry.is_empty() { None } else { Some(entry) },
+            Ok(_)          => (),
+        }
+
+        if line.trim().is_empty() {
+            if entry.is_empty() {
+                continue
+            }
+
+            return Some(entry)
+        }
+
+        entry.push_str(&line);
+
+        if !opens_block(&entry) {
+            return Some(entry)
+        }
+    }
+}
+
+/// Whether an entry begins with something followed by an indented block.
+fn opens_block(entry: &str) -> bool {
+    let mut words = entry.split_whitespace();
+
+    let mut first = words.next();
+
+    // commands take the code after them
+    if let Some(c) = first {
+        if c.starts_with(':') {
+            first = words.next()
+        }
+    }
+
+    match first {
+        Some("function") | Some("class") | Some("structure") | Some("module") | Some("implement") |
+        Some("enum") | Some("if") | Some("while") | Some("for") | Some("match") => true,
+        _                                                                        => false,
+    }
+}
+
+fn report(diagnostics: &[Diagnostic], source: &str) {
+    for d in diagnostics.iter() {
+        writeln!(io::stderr(), "{}", d.render(source)).unwrap();
+    }
+}
+
+fn parse(source: &str) -> Option<Vec<Statement>> {
+    match parser::parse(source) {
+        Ok(a)  => Some(a),
+        Err(e) => {
+            let errors: Vec<Diagnostic> = e.into_iter().map(|d| d.in_file("<repl>")).collect();
+
+            report(&errors, source);
+
+            None
+        }
+    }
+}
+
+fn ast(source: &str) {
+    if let Some(ast) = parse(source) {
+        for s in ast.iter() {
+            println!("{:#?}", s)
+        }
+    }
+}
+
+fn tokens(source: &str) {
+    match parser::tokens(source) {
+        Ok(t)  => show(&t, 0),
+        Err(e) => {
+            let errors: Vec<Diagnostic> = e.into_iter().map(|d| d.in_file("<repl>")).collect();
+
+            report(&errors, source)
+        }
+    }
+}
+
+/// Lists tokens with where they were written, indenting the contents of blocks.
+fn show(tokens: &[Token], depth: usize) {
+    for t in tokens.iter() {
+        let (row, col) = t.get_position();
+
+        match t.get_type() {
+            TokenType::Block(ref b) => {
+                println!("{}{}:{} {}", "  ".repeat(depth), row, col, t);
+
+                show(b, depth + 1)
+            }
+
+            ref k => println!("{}{}:{} {} ({})", "  ".repeat(depth), row, col, t, k),
+        }
+    }
+}
+
+fn cpp(source: &str) {
+    let mut ast = match parse(source) {
+        Some(a) => a,
+        None    => return,
+    };
+
+    // only for the types it writes down; names from earlier entries are unknown to it
+    let (_, index) = parser::resolver::analyze(&mut ast, &[]);
+
+    let mut lowering = Lowering::new(&index);
+
+    for s in ast.iter() {
+        let elements = match lowering.item(s) {
+            Ok(Some(i)) => vec!(translater::item(&i)),
+            Ok(None)    => lowering.statement(s).iter().map(translater::statement).collect(),
+
+            Err(e) => return report(&[e.in_file("<repl>")], source),
+        };
+
+        for c in elements.iter() {
+            println!("{}", translater::translate_element(translater::unlocated(c)).trim())
+        }
+    }
+}
@@ -0,0 +1,360 @@
+extern crate helix_lang;
+
+mod common;
+
+use std::process::Command;
+
+use helix_lang::parser;
+use helix_lang::parser::bytecode::{self, file, Op};
+use helix_lang::parser::bytecode::compiler::Compiler;
+use helix_lang::parser::bytecode::vm::Vm;
+use helix_lang::parser::interpreter::Interpreter;
+use helix_lang::parser::ir::Item;
+use helix_lang::parser::ir::lower::Lowering;
+
+use common::Scratch;
+
+fn lower(source: &str) -> Vec<Item> {
+    let mut ast = parser::parse(source).unwrap();
+
+    let (_, index) = parser::resolver::analyze(&mut ast, &[]);
+
+    Lowering::new(&index).lower(&ast).unwrap()
+}
+
+fn compile(source: &str) -> bytecode::Program {
+    let mut compiler = Compiler::new();
+
+    compiler.load(&lower(source));
+
+    match compiler.compile() {
+        Ok(p)       => p,
+        Err((_, d)) => panic!("{}", d.render(source)),
+    }
+}
+
+/// Runs a program on the VM and on the interpreter, which have to agree, and gives what
+/// `main` returned.
+fn run(source: &str) -> String {
+    let compiled = Vm::new(compile(source)).run();
+
+    let mut interpreter = Interpreter::new();
+
+    interpreter.load(&parser::parse(source).unwrap()).unwrap();
+
+    let interpreted = interpreter.run();
+
+    let (compiled, interpreted) = match (compiled, interpreted) {
+        (Ok(a), Ok(b))   => (a.to_string(), b.to_string()),
+        (Err(a), Err(b)) => (format!("error: {}", a), format!("error: {}", b)),
+        (a, b)           => panic!("the VM gave {:?} and the interpreter {:?}", a, b),
+    };
+
+    assert_eq!(compiled, interpreted);
+
+    compiled
+}
+
+#[test]
+fn arithmetic_and_control_flow() {
+    let source = "
+function fib (n: int) -> int
+  if n < 2
+    return n
+  return fib(n - 1) + fib(n - 2)
+
+function main
+  var total = 0
+  for i in 0 .. 10
+    if i == 3
+      continue
+    if i > 7 && total > 0
+      break
+    total += i
+  var k = 0
+  while true
+    k += 1
+    if k >= 5 || total < 0
+      break
+  var f: int = 7 / 2.0
+  return total * 1000 + k * 100 + fib(10) + f
+";
+
+    assert_eq!(run(source), "25558");
+}
+
+#[test]
+fn classes_modules_and_lambdas() {
+    let source = "
+module shapes
+  var made = 0
+
+  class Shape
+    name: string
+    function area -> float
+      return 0.0
+
+  class Square <- Shape
+    side: float
+    function Square (s: float)
+      side = s
+      name = \"square\"
+      made += 1
+    function area -> float
+      return side * side
+
+function apply (f: (int) -> int, x: int) -> int
+  return f(x)
+
+function main
+  use shapes
+  var s = Square(1.5)
+  var t = s
+  t.side = 2.0
+  var offset = 10
+  var add = function (n: int) -> int: n + offset
+  offset = 0
+  var sizes = [1, 2, 3]
+  var names = {\"a\": 1}
+  names[\"b\"] = apply(add, sizes[2])
+  if s.name != \"square\"
+    return -1
+  var area: int = s.area() * 4 + t.area()
+  return area * 10000 + names[\"b\"] * 10 + shapes::made
+";
+
+    let mut compiler = Compiler::new();
+
+    compiler.load(&lower(source));
+
+    // `use` inside a function is left to the interpreter
+    assert_eq!(compiler.compile().unwrap_err().1.code, "E0020");
+
+    let source = source.replace("  use shapes\n", "").replace("Square(", "shapes::Square(");
+
+    assert_eq!(run(&source), "130131");
+}
+
+#[test]
+fn objects_are_sliced_to_their_base_classes() {
+    let source = "
+class animal
+  legs: int
+  function speak -> int
+    return legs
+
+class bird <- animal
+  function bird
+    legs = 2
+  function speak -> int
+    return 8
+
+function call (a: animal) -> int
+  return a.speak()
+
+function main
+  var b = bird()
+  var a: animal = b
+  return call(b) * 100 + a.speak() * 10 + b.speak()
+";
+
+    assert_eq!(run(source), "228");
+}
+
+#[test]
+fn loops_go_through_copies_of_the_elements() {
+    let source = "
+class box
+  n: int
+
+function main
+  var xs = [1, 2, 3]
+  for x in xs
+    x += 10
+  b: box
+  b.n = 5
+  var bs = [b]
+  for o in bs
+    o.n = 7
+  return xs[0] * 10 + bs[0].n
+";
+
+    assert_eq!(run(source), "15");
+}
+
+#[test]
+fn arrays_of_arrays_are_laid_out_like_cpp() {
+    let source = "
+function main
+  grid[3][2]: int
+  grid[2][1] = 5
+  var n = 0
+  for row in grid
+    n = n * 10 + row.size()
+  return grid[2][1] + n
+";
+
+    assert_eq!(run(source), "227");
+}
+
+#[test]
+fn enums_and_match() {
+    let source = "
+enum Shape
+  Circle(r: float)
+  Rect(w: float, h: float)
+  Empty
+
+function area (s: Shape) -> float
+  match s
+    Circle(r)
+      return 3.0 * r * r
+    Rect(w, h)
+      return w * h
+    _
+      return 0.0
+
+function main
+  var shapes = [Shape::Circle(1.0), Shape::Rect(2.0, 3.0), Shape::Empty]
+  var total = 0.0
+  for s in shapes
+    total += area(s)
+  return total
+";
+
+    assert_eq!(run(source), "9");
+}
+
+#[test]
+fn runtime_errors_match_the_interpreter() {
+    let source = "
+function half (n: int) -> int
+  return n / 2
+
+function main
+  return half(1, 2)
+";
+
+    assert!(run(source).starts_with("error: "));
+}
+
+#[test]
+fn parameters_are_converted_on_the_line_of_their_function() {
+    let source = "
+function greet
+  puts(\"hi\")
+
+function half (x: float) -> float
+  return x / 2
+";
+
+    let program = compile(source);
+
+    let half = program.functions.iter().find(|f| f.name == "half").unwrap();
+
+    assert!(match half.chunk.code[1] { Op::Convert(_) => true, _ => false });
+    assert_eq!(half.chunk.lines[0], 5);
+}
+
+#[test]
+fn programs_survive_a_round_trip_through_hxc() {
+    let source = "
+function main
+  var words = [\"a\", \"b\"]
+  var n = 0
+  for w in words
+    n += 1
+  return n + 0.5
+";
+
+    let program = compile(source);
+    let bytes   = file::write(&program);
+    let read    = file::read(&bytes).unwrap();
+
+    assert_eq!(bytecode::disassemble(&program), bytecode::disassemble(&read));
+    assert_eq!(Vm::new(read).run().unwrap().to_string(), "2.5");
+
+    assert!(file::read(&bytes[.. bytes.len() - 1]).is_err());
+    assert!(file::read(b"helix").is_err());
+}
+
+/// Whether a program is turned down when it is read back after being damaged.
+fn rejected<F: Fn(&mut bytecode::Program)>(source: &str, damage: F) -> bool {
+    let mut program = compile(source);
+
+    damage(&mut program);
+
+    file::read(&file::write(&program)).is_err()
+}
+
+#[test]
+fn damaged_files_are_rejected() {
+    let source = "
+function half (x: int) -> int
+  return x / 2
+
+function main
+  return half(4)
+";
+
+    assert!(!rejected(source, |_| ()));
+
+    // a value taken off an empty stack
+    assert!(rejected(source, |p| {
+        p.functions[0].chunk.code.insert(0, Op::Pop);
+        p.functions[0].chunk.lines.insert(0, 2);
+    }));
+
+    // more locals than a function could ever set
+    assert!(rejected(source, |p| p.functions[0].locals = u32::max_value()));
+
+    // a call with fewer arguments than the function has parameters
+    assert!(rejected(source, |p| for op in p.functions[1].chunk.code.iter_mut() {
+        if let Op::CallFunction(_, ref mut n) = *op {
+            *n = 0;
+        }
+    }));
+
+    // a jump past the last instruction
+    assert!(rejected(source, |p| {
+        let end = p.functions[0].chunk.code.len() as u32 + 1;
+
+        p.functions[0].chunk.code.insert(0, Op::Jump(end));
+        p.functions[0].chunk.lines.insert(0, 2);
+    }));
+}
+
+#[test]
+fn compiled_files_run_from_the_command_line() {
+    let scratch = Scratch::new("bytecode", "exit");
+
+    let source   = scratch.write("exit.helix", "function main\n  puts(\"hi\")\n  return 3\n");
+    let compiled = scratch.path.join("exit.hxc");
+
+    let helix = env!("CARGO_BIN_EXE_helix-lang");
+
+    let status = Command::new(helix).arg("compile").arg(&source).arg(&compiled).status().unwrap();
+
+    assert!(status.success());
+
+    let output = Command::new(helix).arg("run").arg(&compiled).output().unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+    assert_eq!(output.status.code(), Some(3));
+
+    let listing = Command::new(helix).arg("disasm").arg(&compiled).output().unwrap();
+
+    assert!(String::from_utf8_lossy(&listing.stdout).contains("CALL"));
+}
+
+#[test]
+fn endless_recursion_is_stopped() {
+    let scratch = Scratch::new("bytecode", "recursion");
+
+    let source = scratch.write("recursion.helix", "function f (n: int) -> int\n  return f(n + 1)\n\nfunction main\n  return f(0)\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helix-lang")).arg("run").arg(&source).output().unwrap();
+
+    assert!(String::from_utf8_lossy(&output.stderr).contains("calls nested more than"));
+    assert_eq!(output.status.code(), Some(1));
+}
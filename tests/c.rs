@@ -0,0 +1,170 @@
+extern crate helix_lang;
+
+mod common;
+
+use std::process::Command;
+
+use helix_lang::parser::translater::Target;
+
+use common::Scratch;
+
+/// Translates a program to C and builds it with `gcc`, giving its code along with what it
+/// printed and the status it exited with, or nothing when there's no `gcc`.
+fn gcc(name: &str, source: &str) -> Option<(String, String, i32)> {
+    let (code, header) = common::translate(Target::C, name, source);
+
+    let scratch = Scratch::new("c", name);
+    let path    = scratch.write(&format!("{}.c", name), &code);
+    let binary  = scratch.path.join(name);
+
+    scratch.write(&format!("{}.h", name), &header);
+
+    let built = match Command::new("gcc").arg("-std=c99").arg(&path).arg("-o").arg(&binary).output() {
+        Ok(o)  => o,
+        Err(_) => return None,
+    };
+
+    assert!(built.status.success(), "{}\n{}", String::from_utf8_lossy(&built.stderr), code);
+
+    let (out, status) = common::printed(Command::new(&binary).output().unwrap());
+
+    Some((code, out, status))
+}
+
+#[test]
+fn modules_become_prefixed_names() {
+    let source = "
+module geo
+  var scale = 2
+
+  function grow (n: int) -> int
+    return n * scale
+
+  module inner
+    function twice (n: int) -> int
+      return grow(n) + grow(n)
+
+function main
+  geo::scale += 1
+  printf(\"%d\\n\", geo::inner::twice(3))
+  return geo::scale
+";
+
+    if let Some((code, out, status)) = gcc("modules", source) {
+        assert!(code.contains("int geo_scale = 2;"));
+        assert!(code.contains("int geo_inner_twice(int n)"));
+
+        assert_eq!(out, "18\n");
+        assert_eq!(status, 3);
+    }
+}
+
+#[test]
+fn methods_are_dispatched_through_vtables() {
+    let source = "
+class animal
+  legs: int
+  function speak -> int
+  function describe -> int
+
+class bird <- animal
+  wings: int
+  function bird -> bird
+  function speak -> int
+
+implement animal
+  function speak -> int
+    return legs
+
+  function describe -> int
+    return speak() * 10
+
+implement bird
+  function bird -> bird
+    legs = 2
+    wings = 2
+
+  function speak -> int
+    return legs + wings
+
+function main
+  a: animal
+  a.legs = 4
+  b: bird
+  printf(\"%d %d\\n\", a.describe(), b.describe())
+  return b.speak()
+";
+
+    if let Some((code, out, status)) = gcc("classes", source) {
+        assert!(code.contains("self->vtable->speak(self)"));
+
+        assert_eq!(out, "40 40\n");
+        assert_eq!(status, 4);
+    }
+}
+
+#[test]
+fn objects_are_cut_down_to_their_base_classes() {
+    let source = "
+class animal
+  legs: int
+  function speak -> int
+    return legs
+
+class bird <- animal
+  function bird
+    legs = 2
+  function speak -> int
+    return 8
+
+function call (a: animal) -> int
+  return a.speak()
+
+function main
+  var b = bird()
+  var a: animal = b
+  return call(b) * 100 + a.speak() * 10 + b.speak()
+";
+
+    if let Some((_, out, status)) = gcc("slicing", source) {
+        assert_eq!(out, "");
+        assert_eq!(status, 228);
+    }
+}
+
+#[test]
+fn strings_are_joined_by_the_runtime() {
+    let source = "
+function greet (name: string) -> string
+  return \"hi \" + name + \"!\"
+
+function main
+  var s = greet(\"bob\")
+  puts(s)
+  if s == \"hi bob!\"
+    puts(\"same\")
+  return 0
+";
+
+    if let Some((code, out, status)) = gcc("strings", source) {
+        assert!(code.contains("helix_concat(helix_concat(\"hi \", name), \"!\")"));
+
+        assert_eq!(out, "hi bob!\nsame\n");
+        assert_eq!(status, 0);
+    }
+}
+
+#[test]
+fn quotes_and_backslashes_stay_in_strings() {
+    let source = "
+function main
+  puts('say \"hi\"')
+  puts('a\\\\b\\tc')
+  return 0
+";
+
+    if let Some((_, out, status)) = gcc("quotes", source) {
+        assert_eq!(out, "say \"hi\"\na\\b\tc\n");
+        assert_eq!(status, 0);
+    }
+}
@@ -0,0 +1,72 @@
+//! What the tests running translated programs share: checking and translating a program,
+//! and a directory to build it in.
+
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Output;
+
+use helix_lang::parser;
+use helix_lang::parser::diagnostic::Severity;
+use helix_lang::parser::translater::{Translater, Target};
+
+/// Translates a program after making sure it resolves, giving its code and its header.
+pub fn translate(target: Target, name: &str, source: &str) -> (String, String) {
+    let mut ast = parser::parse(source).unwrap();
+
+    let (diagnostics, index) = parser::resolver::analyze(&mut ast, &[]);
+
+    let errors: Vec<_> = diagnostics
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .collect();
+
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let mut translater = Translater::to(target, name.to_owned(), format!("{}.helix", name));
+
+    translater.make_environment(ast, &index).unwrap();
+    translater.translate()
+}
+
+/// A directory of its own for one test, removed with what's in it when the test is done.
+pub struct Scratch {
+    pub path: PathBuf,
+}
+
+impl Scratch {
+    pub fn new(backend: &str, name: &str) -> Scratch {
+        let path = env::temp_dir().join(format!("helix-{}-{}-{}", backend, name, std::process::id()));
+
+        fs::create_dir_all(&path).unwrap();
+
+        Scratch {
+            path: path,
+        }
+    }
+
+    /// Writes a file into the directory, giving where it went.
+    pub fn write(&self, file: &str, content: &str) -> PathBuf {
+        let path = self.path.join(file);
+
+        fs::write(&path, content).unwrap();
+
+        path
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// What a program that ran printed and the status it exited with, making sure it didn't
+/// complain.
+pub fn printed(output: Output) -> (String, i32) {
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    (String::from_utf8(output.stdout).unwrap(), output.status.code().unwrap())
+}
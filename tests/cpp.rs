@@ -0,0 +1,181 @@
+extern crate helix_lang;
+
+mod common;
+
+use std::process::Command;
+
+use helix_lang::parser;
+use helix_lang::parser::translater::{Translater, Target};
+
+use common::Scratch;
+
+/// Translates a program to C++, giving its implementation and its header.
+fn cpp(source: &str) -> (String, String) {
+    let mut ast = parser::parse(source).unwrap();
+
+    let (_, index) = parser::resolver::analyze(&mut ast, &[]);
+
+    let mut translater = Translater::new("prog".to_owned(), "prog.helix".to_owned());
+
+    translater.make_environment(ast, &index).unwrap();
+    translater.translate()
+}
+
+/// Translates a program to C++ and builds it with `g++`, giving what it printed and the
+/// status it exited with, or nothing when there's no `g++`.
+fn gpp(name: &str, source: &str) -> Option<(String, i32)> {
+    let (code, header) = common::translate(Target::Cpp, name, source);
+
+    let scratch = Scratch::new("cpp", name);
+    let path    = scratch.write(&format!("{}.cpp", name), &code);
+    let binary  = scratch.path.join(name);
+
+    scratch.write(&format!("{}.hpp", name), &header);
+
+    let built = match Command::new("g++").arg("-std=c++14").arg(&path).arg("-o").arg(&binary).output() {
+        Ok(o)  => o,
+        Err(_) => return None,
+    };
+
+    assert!(built.status.success(), "{}\n{}\n{}", String::from_utf8_lossy(&built.stderr), header, code);
+
+    Some(common::printed(Command::new(&binary).output().unwrap()))
+}
+
+#[test]
+fn prototypes_keep_their_lines() {
+    let (_, header) = cpp("
+module m
+  function f (a: int) -> int
+    return a
+
+function g (a: int) -> int
+  return m::f(a)
+
+function main
+  return g(0)
+");
+
+    assert!(header.contains("#line 2\nnamespace m {\n\t#line 3\n\tint f (int a);\n}"), "{}", header);
+    assert!(header.contains("#line 6\nint g (int a);"), "{}", header);
+}
+
+#[test]
+fn methods_are_called_on_the_class_of_the_object() {
+    let source = "
+import \"cstdio\" library
+
+class animal
+  legs: int
+  function speak -> int
+  function describe -> int
+
+class bird <- animal
+  wings: int
+  function bird -> bird
+  function speak -> int
+
+implement animal
+  function speak -> int
+    return legs
+
+  function describe -> int
+    return speak() * 10
+
+implement bird
+  function bird -> bird
+    legs = 2
+    wings = 2
+
+  function speak -> int
+    return legs + wings
+
+function main
+  a: animal
+  a.legs = 4
+  b: bird
+  printf(\"%d %d\\n\", a.describe(), b.describe())
+  return b.speak()
+";
+
+    if let Some((out, status)) = gpp("classes", source) {
+        assert_eq!(out, "40 40\n");
+        assert_eq!(status, 4);
+    }
+}
+
+#[test]
+fn loops_go_through_copies_of_the_elements() {
+    let source = "
+class box
+  n: int
+
+function main
+  var xs = [1, 2, 3]
+  for x in xs
+    x += 10
+  b: box
+  b.n = 5
+  var bs = [b]
+  for o in bs
+    o.n = 7
+  return xs[0] * 10 + bs[0].n
+";
+
+    if let Some((out, status)) = gpp("copies", source) {
+        assert_eq!(out, "");
+        assert_eq!(status, 15);
+    }
+}
+
+#[test]
+fn generics_become_templates() {
+    let source = "
+import \"cstdio\" library
+
+class Stack[T]
+  items: [T]
+
+  function push (x: T)
+    items.push_back(x)
+
+  function top -> T
+    return items[items.size() - 1]
+
+  function size -> int
+
+implement Stack[T]
+  function size -> int
+    return items.size()
+
+function max[T] (a: T, b: T) -> T
+  if a > b
+    return a
+  return b
+
+function main
+  s: Stack[int]
+  s.push(4)
+  s.push(9)
+  t: Stack[Stack[float]]
+  t.push(Stack[float]())
+  t.items[0].push(1.5)
+  printf(\"%d %d %.1f\\n\", s.top(), s.size(), t.top().top())
+  return max[int](3, 8) + max(2, 1)
+";
+
+    let (code, header) = cpp(source);
+
+    assert!(header.contains("template <typename T>\nclass Stack {"), "{}", header);
+    assert!(header.contains("template <typename T>\nint Stack<T>::size() {"), "{}", header);
+    assert!(header.contains("template <typename T>\nT max(T a,T b) {"), "{}", header);
+
+    assert!(code.contains("Stack<int> s;"), "{}", code);
+    assert!(code.contains("Stack<Stack<float>> t;"), "{}", code);
+    assert!(code.contains("max<int>(3,8)"), "{}", code);
+
+    if let Some((out, status)) = gpp("generics", source) {
+        assert_eq!(out, "9 2 1.5\n");
+        assert_eq!(status, 10);
+    }
+}
@@ -0,0 +1,100 @@
+extern crate helix_lang;
+
+mod common;
+
+use std::process::{Command, Output};
+
+use common::Scratch;
+
+const PROGRAM: &'static str = "
+function main
+  return 7
+";
+
+/// Runs `helix build` with the arguments given.
+fn build(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_helix-lang")).arg("build").args(arguments).output().unwrap()
+}
+
+/// A compiler standing in for the real one, which prints the arguments it was given and
+/// exits with `status`.
+#[cfg(unix)]
+fn compiler(scratch: &Scratch, status: i32) -> String {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = scratch.write("cc", &format!("#!/bin/sh\necho \"$@\" >&2\nexit {}\n", status));
+
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+#[cfg(unix)]
+fn options_are_handed_to_the_compiler() {
+    let scratch = Scratch::new("driver", "options");
+    let source  = scratch.write("main.helix", PROGRAM);
+    let cc      = compiler(&scratch, 0);
+
+    let destination = scratch.path.join("out").join("main");
+    let destination = destination.to_string_lossy();
+
+    let built = build(&[&format!("--cxx={}", cc), "--std=c++17", "-O", "2", "-I", "inc", "-l", "m",
+                        &source.to_string_lossy(), &destination]);
+
+    assert!(built.status.success());
+    assert_eq!(String::from_utf8_lossy(&built.stderr),
+               format!("-std=c++17 -O2 -Iinc {0}.cpp -o {0} -lm\n", destination));
+
+    let built = build(&["--target=c", &format!("--cxx={}", cc), &source.to_string_lossy(), &destination]);
+
+    assert!(built.status.success());
+    assert_eq!(String::from_utf8_lossy(&built.stderr), format!("-std=c99 -O0 {0}.c -o {0}\n", destination));
+}
+
+#[test]
+#[cfg(unix)]
+fn failures_of_the_compiler_are_passed_on() {
+    let scratch = Scratch::new("driver", "failure");
+    let source  = scratch.write("main.helix", PROGRAM);
+    let cc      = compiler(&scratch, 3);
+
+    let built  = build(&[&format!("--cxx={}", cc), &source.to_string_lossy(), &scratch.path.join("main").to_string_lossy()]);
+    let stderr = String::from_utf8_lossy(&built.stderr);
+
+    assert_eq!(built.status.code(), Some(3));
+    assert!(stderr.ends_with(&format!("error: `{}` failed with exit code 3\n", cc)), "{}", stderr);
+}
+
+#[test]
+fn missing_compilers_are_reported() {
+    let scratch = Scratch::new("driver", "missing");
+    let source  = scratch.write("main.helix", PROGRAM);
+    let cc      = scratch.path.join("nothing").to_string_lossy().into_owned();
+
+    let built  = build(&[&format!("--cxx={}", cc), &source.to_string_lossy(), &scratch.path.join("main").to_string_lossy()]);
+    let stderr = String::from_utf8_lossy(&built.stderr);
+
+    assert_eq!(built.status.code(), Some(1));
+    assert!(stderr.starts_with(&format!("error: couldn't run the compiler `{}`", cc)), "{}", stderr);
+}
+
+#[test]
+fn programs_are_built_where_they_are_asked_for() {
+    if Command::new("g++").arg("--version").output().is_err() {
+        return
+    }
+
+    let scratch     = Scratch::new("driver", "destination");
+    let source      = scratch.write("main.helix", PROGRAM);
+    let destination = scratch.path.join("deep").join("er").join("main");
+
+    let built = build(&["-O", "1", &source.to_string_lossy(), &destination.to_string_lossy()]);
+
+    assert!(built.status.success(), "{}", String::from_utf8_lossy(&built.stderr));
+
+    let (_, status) = common::printed(Command::new(&destination).output().unwrap());
+
+    assert_eq!(status, 7);
+}
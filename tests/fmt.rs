@@ -0,0 +1,107 @@
+extern crate helix_lang;
+
+mod common;
+
+use std::fs;
+use std::process::Command;
+
+use common::Scratch;
+
+/// Writes a file, formats it with `helix fmt` and gives what it became, along with whether
+/// `helix fmt --check` listed it before and after.
+fn format(name: &str, source: &str) -> (String, bool, bool) {
+    let scratch = Scratch::new("fmt", name);
+    let path    = scratch.write(&format!("{}.helix", name), source);
+
+    let helix = env!("CARGO_BIN_EXE_helix-lang");
+
+    let before = Command::new(helix).arg("fmt").arg("--check").arg(&path).output().unwrap();
+
+    assert!(Command::new(helix).arg("fmt").arg(&path).status().unwrap().success());
+
+    let after     = Command::new(helix).arg("fmt").arg("--check").arg(&path).output().unwrap();
+    let formatted = fs::read_to_string(&path).unwrap();
+
+    (formatted, !before.status.success(), !after.status.success())
+}
+
+#[test]
+fn source_is_written_in_the_canonical_style() {
+    let source = "
+function greet(name:string)->string
+    return \"hi \"+name
+
+
+function main
+    var s=greet(\"bob\")
+    puts(s)
+    return 0
+";
+
+    let (formatted, listed, relisted) = format("style", source);
+
+    assert_eq!(formatted, "\
+function greet (name: string) -> string
+  return \"hi \" + name
+
+function main
+  var s = greet(\"bob\")
+  puts(s)
+  return 0
+");
+
+    assert!(listed);
+    assert!(!relisted);
+}
+
+#[test]
+fn comments_are_kept() {
+    let source = "\
+# greets people
+function greet (name: string) -> string
+    # the greeting
+    return \"hi \" + name   # trailing
+
+function main
+    puts(greet(\"bob\"))
+
+    # done
+    return 0
+# the end
+";
+
+    let (formatted, _, _) = format("comments", source);
+
+    assert_eq!(formatted, "\
+# greets people
+function greet (name: string) -> string
+  # the greeting
+  return \"hi \" + name # trailing
+
+function main
+  puts(greet(\"bob\"))
+
+  # done
+  return 0
+# the end
+");
+}
+
+#[test]
+fn formatted_source_is_left_alone() {
+    let source = "\
+# greets people
+function main
+  var xs = [1, 2, 3]
+  for x in xs
+    if x % 2 == 0 and x > 0
+      puts(\"even\")
+
+  return 0
+";
+
+    let (formatted, listed, _) = format("formatted", source);
+
+    assert_eq!(formatted, source);
+    assert!(!listed);
+}
@@ -0,0 +1,101 @@
+extern crate helix_lang;
+
+mod common;
+
+use std::process::Command;
+
+use helix_lang::parser::loader;
+use helix_lang::parser::diagnostic::code;
+
+use common::Scratch;
+
+const CONF: &'static str = "
+module conf
+  var level = 3
+
+  class Point
+    x: int
+    y: int
+
+  enum Shade
+    Light
+    Dark
+
+  function sum (p: Point) -> int
+    return p.x + p.y + level
+";
+
+const MAIN: &'static str = "
+import \"cstdio\" library
+import \"conf\"
+
+var scale = 10
+
+function main
+  p: conf::Point
+  p.x = 1
+  p.y = 2
+  conf::level += 1
+  printf(\"%d\\n\", conf::sum(p) * scale)
+  return conf::level
+";
+
+#[test]
+fn imported_files_come_before_the_files_importing_them() {
+    let scratch = Scratch::new("imports", "order");
+
+    scratch.write("conf.helix", CONF);
+
+    let main  = scratch.write("main.helix", MAIN);
+    let units = loader::load(&main).ok().unwrap();
+
+    let names: Vec<&str> = units.iter().map(|u| u.name.as_str()).collect();
+
+    assert_eq!(names, vec!("conf", "main"));
+    assert_eq!(units[1].imports, vec!(0));
+}
+
+#[test]
+fn files_importing_each_other_are_refused() {
+    let scratch = Scratch::new("imports", "cycle");
+
+    scratch.write("a.helix", "import \"b\"\n\nfunction main\n  return 0\n");
+    scratch.write("b.helix", "import \"c\"\n");
+    scratch.write("c.helix", "import \"a\"\n");
+
+    let failure = loader::load(&scratch.path.join("a.helix")).err().unwrap();
+
+    assert_eq!(failure.diagnostics[0].code, code::CYCLE);
+    assert_eq!(failure.diagnostics[0].notes, vec!("the files import each other in a cycle: a -> b -> c -> a".to_owned()));
+}
+
+#[test]
+fn programs_of_several_files_are_built() {
+    if Command::new("g++").arg("--version").output().is_err() {
+        return
+    }
+
+    let scratch = Scratch::new("imports", "build");
+
+    scratch.write("conf.helix", CONF);
+
+    let main   = scratch.write("main.helix", MAIN);
+    let binary = scratch.path.join("out").join("main");
+
+    let helix = env!("CARGO_BIN_EXE_helix-lang");
+    let built = Command::new(helix).arg("build").arg(&main).arg(&binary).output().unwrap();
+
+    assert!(built.status.success(), "{}", String::from_utf8_lossy(&built.stderr));
+
+    // the header of an imported file stands on its own
+    let header = Command::new("g++").arg("-std=c++14").arg("-fsyntax-only").arg("-x").arg("c++")
+        .arg(scratch.path.join("out").join("conf.hpp"))
+        .output().unwrap();
+
+    assert!(header.status.success(), "{}", String::from_utf8_lossy(&header.stderr));
+
+    let (out, status) = common::printed(Command::new(&binary).output().unwrap());
+
+    assert_eq!(out, "70\n");
+    assert_eq!(status, 4);
+}
@@ -0,0 +1,174 @@
+extern crate helix_lang;
+
+use helix_lang::parser;
+use helix_lang::parser::ir::{Path, Type, Expression, Node, Statement, Item, Function};
+use helix_lang::parser::ir::lower::Lowering;
+
+fn lower(source: &str) -> Vec<Item> {
+    let mut ast = parser::parse(source).unwrap();
+
+    let (_, index) = parser::resolver::analyze(&mut ast, &[]);
+
+    Lowering::new(&index).lower(&ast).unwrap()
+}
+
+/// The function with the given path, looking into modules and `implement` blocks.
+fn function<'a>(items: &'a [Item], path: &Path) -> &'a Function {
+    match find(items, path) {
+        Some(f) => f,
+        None    => panic!("no function `{}` in {:?}", path, items),
+    }
+}
+
+fn find<'a>(items: &'a [Item], path: &Path) -> Option<&'a Function> {
+    for i in items.iter() {
+        let found = match *i {
+            Item::Function(ref f) if f.path == *path => Some(f),
+
+            Item::Module(_, ref c, _)       => find(c, path),
+            Item::Implement(_, _, ref c, _) => c.iter().find(|f| f.path == *path),
+
+            _ => None,
+        };
+
+        if found.is_some() {
+            return found
+        }
+    }
+
+    None
+}
+
+fn body(f: &Function) -> &[Statement] {
+    f.body.as_ref().map(|b| b.as_slice()).unwrap()
+}
+
+#[test]
+fn returns_are_written_out() {
+    let items = lower("
+function greet
+  puts(\"hi\")
+
+function main
+  greet()
+");
+
+    let greet = function(&items, &Path::new(&["greet"]));
+    let main  = function(&items, &Path::new(&["main"]));
+
+    assert_eq!(greet.retty, Type::Void);
+    assert!(match body(greet).last() { Some(&Statement::Return(None, _)) => true, _ => false });
+
+    assert_eq!(main.retty, Type::Int);
+
+    match body(main).last() {
+        Some(&Statement::Return(Some(ref e), _)) => assert_eq!(e.node, Node::Integer(0)),
+        s                                        => panic!("expected a return, found {:?}", s),
+    }
+}
+
+#[test]
+fn names_are_resolved_through_modules_and_use() {
+    let items = lower("
+module tools
+  var base = 5
+  function add (a: int) -> int
+    return a + base
+
+use tools
+
+function main
+  return add(1)
+");
+
+    let add = function(&items, &Path::new(&["tools", "add"]));
+
+    match body(add)[0] {
+        Statement::Return(Some(ref e), _) => match e.node {
+            Node::Binary(_, _, ref r) => assert_eq!(r.node, Node::Global(Path::new(&["tools", "base"]))),
+            ref n                     => panic!("expected an operation, found {:?}", n),
+        },
+        ref s => panic!("expected a return, found {:?}", s),
+    }
+
+    match body(function(&items, &Path::new(&["main"])))[0] {
+        Statement::Return(Some(ref e), _) => {
+            assert_eq!(e.ty, Type::Int);
+
+            match e.node {
+                Node::Call(ref p, _, _) => assert_eq!(*p, Path::new(&["tools", "add"])),
+                ref n                   => panic!("expected a call, found {:?}", n),
+            }
+        }
+        ref s => panic!("expected a return, found {:?}", s),
+    }
+}
+
+#[test]
+fn conversions_are_explicit() {
+    let items = lower("
+function main
+  var a = 7
+  var f: float = a
+  var d = a / 2.0
+  return 0
+");
+
+    let main = function(&items, &Path::new(&["main"]));
+
+    match body(main)[1] {
+        Statement::Let(_, Type::Float, Some(ref e), _) => {
+            assert_eq!(e.ty, Type::Float);
+            assert_eq!(*e.unconverted(), Expression::new(Node::Local("a".to_owned()), Type::Int));
+        }
+        ref s => panic!("expected a float, found {:?}", s),
+    }
+
+    match body(main)[2] {
+        Statement::Let(_, Type::Double, Some(ref e), _) => match e.node {
+            Node::Binary(ref l, _, _) => {
+                assert_eq!(l.ty, Type::Double);
+                assert!(match l.node { Node::Convert(_) => true, _ => false });
+            }
+            ref n => panic!("expected an operation, found {:?}", n),
+        },
+        ref s => panic!("expected a double, found {:?}", s),
+    }
+}
+
+#[test]
+fn methods_and_ranges_are_lowered() {
+    let items = lower("
+class dog
+  age: int
+  function hello (times: int) -> int
+
+implement dog
+  function hello (times: int) -> int
+    var total = 0
+    for i in 0 .. times
+      total += age
+    return total
+");
+
+    let hello = function(&items, &Path::new(&["dog", "hello"]));
+
+    match body(hello)[1] {
+        Statement::Loop(Some(ref init), _, Some(_), ref c, _) => {
+            assert!(match **init { Statement::Let(ref n, Type::Int, Some(_), _) => n == "i", _ => false });
+
+            match c[0] {
+                Statement::Assign(_, Some(_), ref e, _) => match e.node {
+                    Node::Field(ref o, ref owner, ref n) => {
+                        assert_eq!(o.node, Node::This);
+                        assert_eq!(*owner, Path::new(&["dog"]));
+                        assert_eq!(n, "age");
+                    }
+                    ref n => panic!("expected a field, found {:?}", n),
+                },
+                ref s => panic!("expected an assignment, found {:?}", s),
+            }
+        }
+        ref s => panic!("expected a loop, found {:?}", s),
+    }
+}
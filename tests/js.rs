@@ -0,0 +1,231 @@
+extern crate helix_lang;
+
+mod common;
+
+use std::process::Command;
+
+use helix_lang::parser::translater::Target;
+
+use common::Scratch;
+
+/// Translates a program to JavaScript and runs it under `node`, giving what it printed and
+/// the status it exited with, or nothing when there's no `node` to run it with.
+fn node(name: &str, source: &str) -> Option<(String, i32)> {
+    let (code, _) = common::translate(Target::Js, name, source);
+
+    let scratch = Scratch::new("js", name);
+    let path    = scratch.write(&format!("{}.mjs", name), &code);
+
+    match Command::new("node").arg(&path).output() {
+        Ok(o)  => Some(common::printed(o)),
+        Err(_) => None,
+    }
+}
+
+#[test]
+fn integer_division_and_remainder_truncate_like_cpp() {
+    let source = "
+function main
+  var a = -7
+  var b = 2
+  var c = a / b
+  c /= 2
+  var f = a / 2.0
+  printf(\"%d %d %d %d %.1f\\n\", c, a % b, 7 % -2, 7 / -2, f)
+  var n: int = f
+  return n
+";
+
+    if let Some((out, status)) = node("division", source) {
+        assert_eq!(out, "-1 -1 1 -3 -3.5\n");
+        assert_eq!(status, 253);
+    }
+}
+
+#[test]
+fn implementations_merge_into_classes() {
+    let source = "
+class animal
+  legs: int
+  function speak -> int
+
+class bird <- animal
+  wings: int
+  function bird -> bird
+  function speak -> int
+
+implement animal
+  function speak -> int
+    return legs
+
+implement bird
+  function bird -> bird
+    legs = 2
+    wings = 2
+
+  function speak -> int
+    printf(\"%d legs, %d wings\\n\", legs, wings)
+    return legs + wings
+
+function main
+  b: bird
+  return b.speak()
+";
+
+    if let Some((out, status)) = node("classes", source) {
+        assert_eq!(out, "2 legs, 2 wings\n");
+        assert_eq!(status, 4);
+    }
+}
+
+#[test]
+fn modules_are_namespace_objects() {
+    let source = "
+module counter
+  var count = 0
+
+  function bump (by: int) -> int
+    count += by
+    return count
+
+  module inner
+    function twice (n: int) -> int
+      return bump(n) + bump(n)
+
+function main
+  counter::bump(1)
+  counter::inner::twice(2)
+  puts(\"done\")
+  return counter::count
+";
+
+    if let Some((out, status)) = node("modules", source) {
+        assert_eq!(out, "done\n");
+        assert_eq!(status, 5);
+    }
+}
+
+#[test]
+fn matches_take_variants_apart() {
+    let source = "
+enum Shape
+  Empty
+  Square(side: int)
+
+function area (s: Shape) -> int
+  match s
+    Square(side)
+      return side * side
+    _
+      return 0
+
+function main
+  return area(Shape::Square(3)) + area(Shape::Empty)
+";
+
+    if let Some((out, status)) = node("matches", source) {
+        assert_eq!(out, "");
+        assert_eq!(status, 9);
+    }
+}
+
+#[test]
+fn values_are_copied_like_cpp_copies_them() {
+    let source = "
+class point
+  x: int
+  y: int
+
+class segment
+  start: point
+  points: [point]
+
+function bump (p: point) -> int
+  p.x = 100
+  return p.x
+
+function main
+  a: point
+  a.x = 1
+  var b = a
+  b.x = 5
+  bump(a)
+  s: segment
+  s.start = a
+  s.points.push_back(a)
+  a.x = 7
+  var t = s
+  t.start.x = 9
+  t.points[0].x = 9
+  var xs = [1, 2]
+  var ys = xs
+  ys[0] = 9
+  printf(\"%d %d %d %d\\n\", a.x + b.x, s.start.x, s.points[0].x, xs[0])
+  return 0
+";
+
+    if let Some((out, status)) = node("values", source) {
+        assert_eq!(out, "12 1 1 1\n");
+        assert_eq!(status, 0);
+    }
+}
+
+#[test]
+fn objects_are_cut_down_to_their_base_classes() {
+    let source = "
+class animal
+  legs: int
+  function speak -> int
+    return legs
+
+class bird <- animal
+  function bird
+    legs = 2
+  function speak -> int
+    return 8
+
+function call (a: animal) -> int
+  return a.speak()
+
+function main
+  var b = bird()
+  var a: animal = b
+  return call(b) * 100 + a.speak() * 10 + b.speak()
+";
+
+    if let Some((out, status)) = node("slicing", source) {
+        assert_eq!(out, "");
+        assert_eq!(status, 228);
+    }
+}
+
+#[test]
+fn lambdas_copy_the_locals_they_capture() {
+    let source = "
+function main
+  var offset = 10
+  var add = function (n: int) -> int: n + offset
+  offset = 0
+  return add(3)
+";
+
+    if let Some((out, status)) = node("lambdas", source) {
+        assert_eq!(out, "");
+        assert_eq!(status, 13);
+    }
+}
+
+#[test]
+fn quotes_and_backslashes_stay_in_strings() {
+    let source = "
+function main
+  puts('say \"hi\"')
+  puts('a\\\\b\\tc')
+  return 0
+";
+
+    if let Some((out, status)) = node("quotes", source) {
+        assert_eq!(out, "say \"hi\"\na\\b\tc\n");
+        assert_eq!(status, 0);
+    }
+}
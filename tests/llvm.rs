@@ -0,0 +1,139 @@
+extern crate helix_lang;
+
+mod common;
+
+use std::process::Command;
+
+use helix_lang::parser;
+use helix_lang::parser::translater::{Translater, Target};
+
+use common::Scratch;
+
+/// Translates a program to LLVM IR, assembles it with `llc` and links it with `cc`, giving
+/// what it printed and the status it exited with, or nothing when there's no `llc`.
+fn native(name: &str, source: &str) -> Option<(String, i32)> {
+    let (code, _) = common::translate(Target::Llvm, name, source);
+
+    let scratch = Scratch::new("llvm", name);
+    let path    = scratch.write(&format!("{}.ll", name), &code);
+    let object  = scratch.path.join(format!("{}.o", name));
+    let binary  = scratch.path.join(name);
+
+    let assembled = match Command::new("llc").arg("-relocation-model=pic").arg("-filetype=obj")
+                                             .arg(&path).arg("-o").arg(&object).output() {
+        Ok(o)  => o,
+        Err(_) => return None,
+    };
+
+    assert!(assembled.status.success(), "{}\n{}", String::from_utf8_lossy(&assembled.stderr), code);
+
+    let linked = Command::new("cc").arg(&object).arg("-o").arg(&binary).output().unwrap();
+
+    assert!(linked.status.success(), "{}", String::from_utf8_lossy(&linked.stderr));
+
+    Some(common::printed(Command::new(&binary).output().unwrap()))
+}
+
+#[test]
+fn conversions_and_logic_are_explicit() {
+    let source = "
+function loud (n: int) -> bool
+  printf(\"%d \", n)
+  return n > 0
+
+function main
+  var a = 7
+  var f: float = a / 2
+  var d = a / 2.0
+  var n: int = d * 3
+  if loud(0) && loud(1)
+    puts(\"both\")
+  if loud(2) || loud(3)
+    puts(\"either\")
+  printf(\"%.1f %.2f %d\\n\", f, d, n)
+  return n
+";
+
+    if let Some((out, status)) = native("conversions", source) {
+        assert_eq!(out, "0 2 either\n3.0 3.50 10\n");
+        assert_eq!(status, 10);
+    }
+}
+
+#[test]
+fn loops_break_and_continue() {
+    let source = "
+function main
+  var total = 0
+  for i in 0 .. 10
+    if i == 3
+      continue
+    if i > 7 && total > 0
+      break
+    total += i
+  var k = 0
+  while true
+    k += 1
+    if k >= 5 || total < 0
+      break
+  printf(\"%d %d\\n\", total, k)
+  return 0
+";
+
+    if let Some((out, status)) = native("loops", source) {
+        assert_eq!(out, "25 5\n");
+        assert_eq!(status, 0);
+    }
+}
+
+#[test]
+fn structs_and_modules_are_laid_out_natively() {
+    let source = "
+structure Point
+  x: int
+  y: float
+
+module geo
+  var scale = 3
+
+  function dot (a: Point, b: Point) -> float
+    return a.x * b.x + a.y * b.y
+
+function fib (n: int) -> int
+  if n < 2
+    return n
+  return fib(n - 1) + fib(n - 2)
+
+function main
+  p: Point
+  p.x = 2
+  p.y = 1.5
+  geo::scale += 1
+  printf(\"%.2f %d\\n\", geo::dot(p, p), fib(10))
+  puts(\"done\")
+  return geo::scale
+";
+
+    if let Some((out, status)) = native("structs", source) {
+        assert_eq!(out, "6.25 55\ndone\n");
+        assert_eq!(status, 4);
+    }
+}
+
+#[test]
+fn classes_are_left_to_other_targets() {
+    let source = "
+class animal
+  legs: int
+";
+
+    let mut ast = parser::parse(source).unwrap();
+
+    let (_, index) = parser::resolver::analyze(&mut ast, &[]);
+
+    let mut translater = Translater::to(Target::Llvm, "classes".to_owned(), "classes.helix".to_owned());
+
+    let error = translater.make_environment(ast, &index).unwrap_err();
+
+    assert_eq!(error.code, "E0020");
+}
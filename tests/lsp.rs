@@ -0,0 +1,182 @@
+extern crate rustc_serialize;
+
+use std::io::prelude::*;
+use std::process::{Command, Stdio};
+
+use rustc_serialize::json::Json;
+
+const URI: &'static str = "file:///tmp/helix-lsp/main.helix";
+
+const DOCUMENT: &'static str = "module geo
+  function area (w: int, h: int) -> int
+    return w * h
+
+class point
+  x: int
+  y: int
+
+implement point
+  function sum -> int
+    return x + y
+
+function main
+  p: point
+  p.x = geo::area(2, 3)
+  return p.x
+";
+
+/// Sends messages to a server, giving the messages it sent back and the status it exited
+/// with.
+fn exchange(messages: &[String]) -> (Vec<Json>, i32) {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_helix-lang"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    {
+        let input = server.stdin.as_mut().unwrap();
+
+        for m in messages.iter() {
+            write!(input, "Content-Length: {}\r\n\r\n{}", m.len(), m).unwrap();
+        }
+    }
+
+    let output = server.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let mut received = Vec::new();
+    let mut rest     = stdout.as_str();
+
+    while let Some(i) = rest.find("\r\n\r\n") {
+        let length: usize = rest[.. i]["Content-Length: ".len() ..].parse().unwrap();
+        let body          = &rest[i + 4 .. i + 4 + length];
+
+        received.push(Json::from_str(body).unwrap());
+
+        rest = &rest[i + 4 + length ..];
+    }
+
+    (received, output.status.code().unwrap())
+}
+
+/// A request when given an id to answer it by, or a notification otherwise.
+fn message(id: Option<u32>, method: &str, params: &str) -> String {
+    match id {
+        Some(id) => format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"method\":\"{}\",\"params\":{}}}", id, method, params),
+        None     => format!("{{\"jsonrpc\":\"2.0\",\"method\":\"{}\",\"params\":{}}}", method, params),
+    }
+}
+
+/// Opens the document with the text given, asks the requests given about it and shuts the
+/// server down, giving the messages it sent back.
+fn session(text: &str, requests: &[String]) -> Vec<Json> {
+    let mut messages = vec!(message(None, "textDocument/didOpen",
+                                    &format!("{{\"textDocument\":{{\"uri\":\"{}\",\"text\":{}}}}}", URI, Json::String(text.to_owned()))));
+
+    messages.extend(requests.iter().cloned());
+    messages.push(message(Some(0), "shutdown", "null"));
+    messages.push(message(None, "exit", "null"));
+
+    let (received, status) = exchange(&messages);
+
+    assert_eq!(status, 0);
+
+    received
+}
+
+/// A request about the document at a line and character, both counted from 0.
+fn at(id: u32, method: &str, line: u32, character: u32) -> String {
+    message(Some(id), method, &format!("{{\"textDocument\":{{\"uri\":\"{}\"}},\"position\":{{\"line\":{},\"character\":{}}}}}",
+                                       URI, line, character))
+}
+
+/// The answer to the request with an id.
+fn answer(received: &[Json], id: u64) -> Json {
+    let m = received.iter().find(|m| m.find("id").and_then(|i| i.as_u64()) == Some(id)).unwrap();
+
+    match m.find("result") {
+        Some(r) => r.clone(),
+        None    => m.find("error").unwrap().clone(),
+    }
+}
+
+fn start(json: &Json) -> (u64, u64) {
+    let number = |k| json.find_path(&["start", k]).and_then(|n| n.as_u64()).unwrap();
+
+    (number("line"), number("character"))
+}
+
+#[test]
+fn diagnostics_follow_the_changes_to_a_document() {
+    let change = message(None, "textDocument/didChange",
+                         &format!("{{\"textDocument\":{{\"uri\":\"{}\"}},\"contentChanges\":[{{\"text\":{}}}]}}",
+                                  URI, Json::String(DOCUMENT.to_owned())));
+
+    let received = session("function main\n  return nope\n", &[change]);
+
+    let published: Vec<&Json> = received.iter()
+        .filter(|m| m.find("method").and_then(|m| m.as_string()) == Some("textDocument/publishDiagnostics"))
+        .map(|m| m.find_path(&["params", "diagnostics"]).unwrap())
+        .collect();
+
+    assert_eq!(published.len(), 2);
+
+    let first = published[0].as_array().unwrap();
+
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].find("code").unwrap().as_string(), Some("E0010"));
+    assert_eq!(start(first[0].find("range").unwrap()), (1, 9));
+
+    assert!(published[1].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn definitions_and_types_are_found_at_names() {
+    let received = session(DOCUMENT, &[at(1, "textDocument/definition", 14, 13), at(2, "textDocument/hover", 15, 9)]);
+
+    let definition = answer(&received, 1);
+
+    assert_eq!(definition.find("uri").unwrap().as_string(), Some(URI));
+    assert_eq!(start(definition.find("range").unwrap()), (1, 11));
+
+    let hover = answer(&received, 2);
+
+    assert_eq!(hover.find_path(&["contents", "value"]).unwrap().as_string(), Some("```helix\np: point\n```"));
+}
+
+#[test]
+fn documents_are_outlined() {
+    let received = session(DOCUMENT, &[message(Some(1), "textDocument/documentSymbol", &format!("{{\"textDocument\":{{\"uri\":\"{}\"}}}}", URI))]);
+
+    let symbols = answer(&received, 1);
+
+    let names: Vec<(&str, u64, Vec<&str>)> = symbols.as_array().unwrap().iter().map(|s| (
+        s.find("name").unwrap().as_string().unwrap(),
+        s.find("kind").unwrap().as_u64().unwrap(),
+        s.find("children").unwrap().as_array().unwrap().iter().map(|c| c.find("name").unwrap().as_string().unwrap()).collect(),
+    )).collect();
+
+    assert_eq!(names, vec!(("geo", 2, vec!("area")), ("point", 5, vec!()), ("implement point", 5, vec!("sum")), ("main", 12, vec!())));
+}
+
+#[test]
+fn members_are_completed_after_dots_and_colons() {
+    let received = session(DOCUMENT, &[at(1, "textDocument/completion", 14, 4), at(2, "textDocument/completion", 14, 13)]);
+
+    let labels = |id| -> Vec<String> {
+        answer(&received, id).as_array().unwrap().iter().map(|c| c.find("label").unwrap().as_string().unwrap().to_owned()).collect()
+    };
+
+    assert_eq!(labels(1), vec!("sum", "x", "y"));
+    assert_eq!(labels(2), vec!("area"));
+}
+
+#[test]
+fn unknown_methods_and_exits_without_shutting_down_fail() {
+    let (received, status) = exchange(&[message(Some(1), "bogus", "{}"), message(None, "exit", "null")]);
+
+    assert_eq!(answer(&received, 1).find("code").unwrap().as_i64(), Some(-32601));
+    assert_eq!(status, 1);
+}
@@ -0,0 +1,55 @@
+extern crate helix_lang;
+
+use helix_lang::parser;
+use helix_lang::parser::diagnostic::code;
+
+/// The code, line and column of each error parsing a program gives.
+fn errors(source: &str) -> Vec<(&'static str, u32, u32)> {
+    parser::parse(source)
+        .unwrap_err()
+        .into_iter()
+        .map(|d| {
+            let span = d.span.unwrap();
+
+            (d.code, span.line, span.col)
+        })
+        .collect()
+}
+
+#[test]
+fn definitions_need_a_name() {
+    let source = "
+function main
+  var count = 0
+  var inc = function: count += 1
+  return 0
+
+enum
+  Red
+
+structure (a: int)
+  x: int
+
+implement 3
+  function f
+    return 1
+";
+
+    assert_eq!(errors(source), vec!((code::EXPECTED, 4, 21), (code::EXPECTED, 8, 3), (code::EXPECTED, 10, 11), (code::EXPECTED, 13, 11)));
+}
+
+#[test]
+fn statements_of_several_lines_are_skipped_past_their_error() {
+    let source = "
+function main
+  var b = 1
+  b.
+  zoo::
+  return 0
+
+function other
+  return (1
+";
+
+    assert_eq!(errors(source), vec!((code::EXPECTED, 6, 3), (code::EXPECTED, 9, 11)));
+}
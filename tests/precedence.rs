@@ -0,0 +1,110 @@
+extern crate helix_lang;
+
+use helix_lang::parser;
+use helix_lang::parser::ir::{Item, Statement};
+use helix_lang::parser::ir::lower::Lowering;
+use helix_lang::parser::translater::{expression, translate_element};
+
+/// Translates the expression returned by a one-line `main` to C++.
+fn cpp(source: &str) -> String {
+    let mut ast = parser::parse(&format!("function main\n  return {}\n", source)).unwrap();
+
+    let (_, index) = parser::resolver::analyze(&mut ast, &[]);
+
+    let items = Lowering::new(&index).lower(&ast).unwrap();
+
+    let body = match items[0] {
+        Item::Function(ref f) => f.body.clone().unwrap(),
+        ref i                 => panic!("expected a function, found {:?}", i),
+    };
+
+    match body[0] {
+        Statement::Return(Some(ref e), _) => translate_element(&expression(e)),
+        ref s                             => panic!("expected a return, found {:?}", s),
+    }
+}
+
+#[test]
+fn arithmetic_binds_tighter_than_comparison() {
+    assert_eq!(cpp("a + b == 0"), "((a + b) == 0)");
+    assert_eq!(cpp("0 == a + b"), "(0 == (a + b))");
+    assert_eq!(cpp("a * b < c - d"), "((a * b) < (c - d))");
+}
+
+#[test]
+fn multiplication_binds_tighter_than_addition() {
+    assert_eq!(cpp("1 + 2 * 3"), "(1 + (2 * 3))");
+    assert_eq!(cpp("1 * 2 + 3"), "((1 * 2) + 3)");
+    assert_eq!(cpp("a + b % c - d / e"), "((a + (b % c)) - (d / e))");
+}
+
+#[test]
+fn relational_binds_tighter_than_equality() {
+    assert_eq!(cpp("a < b == c > d"), "((a < b) == (c > d))");
+    assert_eq!(cpp("a != b <= c"), "(a != (b <= c))");
+}
+
+#[test]
+fn operators_are_left_associative() {
+    assert_eq!(cpp("a - b - c"), "((a - b) - c)");
+    assert_eq!(cpp("a / b / c"), "((a / b) / c)");
+    assert_eq!(cpp("a - b + c"), "((a - b) + c)");
+    assert_eq!(cpp("a % b * c"), "((a % b) * c)");
+}
+
+#[test]
+fn operands_keep_their_order() {
+    assert_eq!(cpp("10 - 4"), "(10 - 4)");
+    assert_eq!(cpp("x / 2"), "(x / 2)");
+}
+
+#[test]
+fn parentheses_group() {
+    assert_eq!(cpp("(a + b) * c"), "((a + b) * c)");
+    assert_eq!(cpp("a * (b + c)"), "(a * (b + c))");
+    assert_eq!(cpp("a - (b - c)"), "(a - (b - c))");
+    assert_eq!(cpp("((a))"), "a");
+    assert_eq!(cpp("10 + 10 * (10 % 10 * 10)"), "(10 + (10 * ((10 % 10) * 10)))");
+}
+
+#[test]
+fn calls_and_members_inside_operations() {
+    assert_eq!(cpp("fib(n - 1) + fib(n - 2)"), "(fib((n - 1)) + fib((n - 2)))");
+    assert_eq!(cpp("bar::foo(1, 2) * 3"), "(bar::foo(1,2) * 3)");
+    assert_eq!(cpp("bob.age + xs[i + 1]"), "(bob.age + xs[(i + 1)])");
+    assert_eq!(cpp("f(a, b + c)(d)"), "f(a,(b + c))(d)");
+    assert_eq!(cpp("(f)(a * b)"), "f((a * b))");
+}
+
+#[test]
+fn logical_operators_bind_loosest() {
+    assert_eq!(cpp("a or b and c"), "(a || (b && c))");
+    assert_eq!(cpp("a && b || c"), "((a && b) || c)");
+    assert_eq!(cpp("a == 1 and b < 2"), "((a == 1) && (b < 2))");
+}
+
+#[test]
+fn bitwise_operators_follow_cpp_precedence() {
+    assert_eq!(cpp("a | b ^ c & d"), "(a | (b ^ (c & d)))");
+    assert_eq!(cpp("a & b == c"), "(a & (b == c))");
+    assert_eq!(cpp("1 << n + 1"), "(1 << (n + 1))");
+    assert_eq!(cpp("a >> 1 < b"), "((a >> 1) < b)");
+}
+
+#[test]
+fn unary_operators_bind_tightest() {
+    assert_eq!(cpp("-a * b"), "((-a) * b)");
+    assert_eq!(cpp("not a and b"), "((!a) && b)");
+    assert_eq!(cpp("!(a or b)"), "(!(a || b))");
+    assert_eq!(cpp("~x & 255"), "((~x) & 255)");
+    assert_eq!(cpp("- -a"), "(-(-a))");
+    assert_eq!(cpp("a-1"), "(a - 1)");
+    assert_eq!(cpp("f(1, -1)"), "f(1,-1)");
+}
+
+#[test]
+fn string_literals_are_added_as_strings() {
+    assert_eq!(cpp("\"ab\" + \"cd\""), "(std::string(\"ab\") + \"cd\")");
+    assert_eq!(cpp("\"ab\" + \"cd\" + \"ef\""), "((std::string(\"ab\") + \"cd\") + \"ef\")");
+    assert_eq!(cpp("a + \"cd\""), "(a + \"cd\")");
+}
@@ -0,0 +1,108 @@
+extern crate helix_lang;
+
+mod common;
+
+use std::io::prelude::*;
+use std::process::{Command, Stdio};
+
+use common::Scratch;
+
+/// Types the lines given into a session, giving what it printed to standard output and to
+/// standard error.
+fn session(input: &str) -> (String, String) {
+    let mut repl = Command::new(env!("CARGO_BIN_EXE_helix-lang"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    repl.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+
+    let output = repl.wait_with_output().unwrap();
+
+    (String::from_utf8_lossy(&output.stdout).into_owned(), String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+#[test]
+fn enums_and_matches_take_blocks() {
+    let input = "\
+enum Color
+  Red
+  Green
+
+var c = Color::Green
+match c
+  Red
+    puts(\"red\")
+  Green
+    puts(\"green\")
+
+";
+
+    let (out, err) = session(input);
+
+    assert_eq!(err, "");
+    assert!(out.contains("green\n"), "{}", out);
+    assert!(!out.contains("red\n"), "{}", out);
+}
+
+#[test]
+fn blocks_are_read_until_an_empty_line() {
+    let input = "\
+function double (n: int) -> int
+  var twice = n * 2
+  return twice
+
+double(21)
+";
+
+    let (out, err) = session(input);
+
+    assert_eq!(err, "");
+    assert_eq!(out, ">>> ... ... ... >>> => 42\n>>> => bye bb <3\n");
+}
+
+#[test]
+fn definitions_are_kept_until_a_reset() {
+    let (out, err) = session("var x = 3\nx + 1\n:reset\nx\n");
+
+    assert!(out.contains("=> 4\n"), "{}", out);
+    assert!(err.contains("undefined: x"), "{}", err);
+}
+
+#[test]
+fn errors_leave_the_session_running() {
+    let (out, err) = session("f(\nvar y =\n:bogus\n1 + 2\n:quit\n3 + 4\n");
+
+    assert!(err.contains("expected an expression, found end of line"), "{}", err);
+    assert!(err.contains("unknown command `:bogus`"), "{}", err);
+    assert!(out.contains("=> 3\n"), "{}", out);
+    assert!(!out.contains("=> 7"), "{}", out);
+}
+
+#[test]
+fn commands_show_what_entries_become() {
+    let (out, err) = session(":tokens x + 1\n:cpp var y = 2 * 3\n:ast x\n");
+
+    assert_eq!(err, "");
+    assert!(out.contains("1:1 `x` (identifier)\n1:3 `+` (operator)\n1:5 `1` (integer)\n"), "{}", out);
+    assert!(out.contains("int y = (2 * 3);\n"), "{}", out);
+    assert!(out.contains("Ident(\n"), "{}", out);
+}
+
+#[test]
+fn files_are_loaded_with_their_imports() {
+    let scratch = Scratch::new("repl", "load");
+
+    scratch.write("conf.helix", "module conf\n  var level = 3\n");
+
+    let main = scratch.write("main.helix", "import \"conf\"\n\nfunction triple (n: int) -> int\n  return n * conf::level\n");
+
+    let (out, err) = session(&format!(":load {}\ntriple(5)\n", main.display()));
+
+    assert_eq!(err, "");
+    assert!(out.contains(&format!("=> loaded {}\n", main.display())), "{}", out);
+    assert!(out.contains("=> 15\n"), "{}", out);
+}
@@ -0,0 +1,208 @@
+extern crate helix_lang;
+
+use helix_lang::parser;
+use helix_lang::parser::diagnostic::{Severity, code};
+
+/// The errors the resolver reports for a program, each with its code, line and column.
+fn errors(source: &str) -> Vec<(&'static str, u32, u32)> {
+    let mut ast = parser::parse(source).unwrap();
+
+    parser::resolver::resolve(&mut ast, &[])
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| {
+            let span = d.span.unwrap();
+
+            (d.code, span.line, span.col)
+        })
+        .collect()
+}
+
+#[test]
+fn break_and_continue_only_leave_loops() {
+    let source = "
+function main
+  var x = 1
+  if x > 0
+    break
+  while x < 3
+    x += 1
+    if x == 2
+      continue
+  var f = function (n: int) -> int
+    continue
+    return n
+  return 0
+";
+
+    assert_eq!(errors(source), vec!((code::LOOP, 5, 5), (code::LOOP, 11, 5)));
+}
+
+#[test]
+fn return_types_are_inferred_until_they_settle() {
+    let source = "
+function main
+  var a = twice(2)
+  return a
+
+function twice (n: int)
+  return half(n) * 4.0
+
+function half (n: int)
+  return n / 2
+";
+
+    assert!(errors(source).is_empty());
+
+    let source = "
+function nest (n: int)
+  return [nest(n - 1)]
+";
+
+    assert_eq!(errors(source), vec!((code::TYPE, 2, 10)));
+}
+
+#[test]
+fn undefined_names_are_reported() {
+    let source = "
+function main
+  var x = 1
+  puts(y)
+  missing(x)
+  return x
+";
+
+    assert_eq!(errors(source), vec!((code::UNDEFINED, 4, 8), (code::UNDEFINED, 5, 3)));
+}
+
+#[test]
+fn duplicate_definitions_are_reported() {
+    let source = "
+function add (a: int, b: int) -> int
+  return a + b
+
+function add (a: int) -> int
+  return a
+
+function main
+  var x = 1
+  var x = 2
+  return x
+";
+
+    assert_eq!(errors(source), vec!((code::DUPLICATE, 5, 10), (code::DUPLICATE, 10, 7)));
+}
+
+#[test]
+fn calls_take_as_many_arguments_as_there_are_parameters() {
+    let source = "
+function add (a: int, b: int) -> int
+  return a + b
+
+function main
+  add(1)
+  return add(1, 2, 3)
+";
+
+    assert_eq!(errors(source), vec!((code::ARGUMENTS, 6, 3), (code::ARGUMENTS, 7, 10)));
+}
+
+#[test]
+fn implementations_need_a_declared_class() {
+    let source = "
+implement cat
+  function meow
+    puts(\"meow\")
+";
+
+    assert_eq!(errors(source), vec!((code::UNDECLARED, 2, 11)));
+}
+
+#[test]
+fn mismatched_types_are_reported() {
+    let source = "
+function half (n: int) -> int
+  return \"half\"
+
+function main
+  var s: string = 3
+  half(\"two\")
+  return half(4)
+";
+
+    assert_eq!(errors(source), vec!((code::TYPE, 3, 3), (code::TYPE, 6, 7), (code::TYPE, 7, 3)));
+}
+
+#[test]
+fn assignments_are_reported_where_they_are() {
+    let source = "
+class point
+  x: int
+
+function main
+  var s = \"s\"
+  s = 4
+  a: point
+  a.x = \"no\"
+  xs: [int]
+  xs[0] = \"no\"
+  a.x += \"no\"
+  return 0
+";
+
+    assert_eq!(errors(source), vec!((code::TYPE, 7, 3), (code::TYPE, 9, 3), (code::TYPE, 11, 3), (code::OPERATOR, 12, 5)));
+}
+
+#[test]
+fn operators_take_the_types_they_apply_to() {
+    let source = "
+function main
+  var a = \"a\" * 2
+  var b = [1] - 1
+  var c = \"a\" + \"b\"
+  return 0
+";
+
+    assert_eq!(errors(source), vec!((code::OPERATOR, 3, 15), (code::OPERATOR, 4, 15)));
+}
+
+#[test]
+fn matches_cover_every_variant() {
+    let source = "
+enum color
+  red
+  green
+  blue
+
+function name (c: color) -> int
+  match c
+    red
+      return 1
+    green
+      return 2
+
+function main
+  return name(color::red)
+";
+
+    assert_eq!(errors(source), vec!((code::MATCH, 8, 3)));
+}
+
+#[test]
+fn modules_are_used_until_the_end_of_the_block() {
+    let source = "
+module m
+  var z = 2
+  function g -> int
+    return 40
+
+function main
+  if z > 0
+    use m
+    z = g()
+  use m
+  return g() + z
+";
+
+    assert_eq!(errors(source), vec!((code::UNDEFINED, 8, 6)));
+}
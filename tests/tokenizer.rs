@@ -0,0 +1,36 @@
+extern crate helix_lang;
+
+use helix_lang::parser;
+use helix_lang::parser::diagnostic::code;
+
+#[test]
+fn text_outside_ascii_is_read_whole() {
+    let tokens = parser::tokens("var größe = \"é\" == \"日本\"\n").unwrap();
+
+    let contents: Vec<&str> = tokens.iter().map(|t| t.get_content().as_str()).collect();
+
+    assert_eq!(contents, vec!("var", "größe", "=", "é", "==", "日本"));
+
+    // columns count characters, not bytes
+    assert_eq!(*tokens[2].get_position().1, 11);
+    assert_eq!(*tokens[4].get_position().1, 17);
+}
+
+#[test]
+fn symbols_outside_ascii_are_reported() {
+    let errors = parser::tokens("var ü = 3 € 4\n").unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, code::SYMBOL);
+
+    let span = errors[0].span.clone().unwrap();
+
+    assert_eq!((span.line, span.col, span.len), (1, 11, 1));
+}
+
+#[test]
+fn unterminated_text_after_wide_characters_is_reported() {
+    let errors = parser::tokens("puts(\"ünterminated)\n").unwrap_err();
+
+    assert_eq!(errors[0].code, code::UNTERMINATED);
+}